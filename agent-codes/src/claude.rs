@@ -0,0 +1,483 @@
+//! [`Agent`] for the Claude Code CLI.
+//!
+//! Tool approvals only arrive as [`AgentEvent::ApprovalRequest`]s when the
+//! client was spawned with `permission_prompt_tool("stdio")` and
+//! `enable_tool_approval()` was called. Messages from subagents (those with a
+//! `parent_tool_use_id`) are skipped; their work shows up as the parent's
+//! `Task` tool call and result.
+
+use crate::event::{
+    AgentEvent, ApprovalDecision, ApprovalKind, ApprovalRequest, FileChange, FileChangeKind,
+    PendingApproval, ToolCall, ToolResult, TurnCompletion, Usage,
+};
+use crate::{Agent, BoxFuture, Error, Provider, Result};
+use claude_codes::io::ResultMessage;
+use claude_codes::{
+    AsyncClient, ClaudeInput, ClaudeOutput, ContentBlock, ControlRequestPayload,
+    ToolPermissionRequest, ToolResultContent,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+impl Agent for AsyncClient {
+    fn provider(&self) -> Provider {
+        Provider::Claude
+    }
+
+    fn start_session(&mut self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move { Ok(self.session_uuid()?.to_string()) })
+    }
+
+    fn resume_session<'a>(&'a mut self, session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match self.session_uuid() {
+                Ok(session) if session.to_string() == session_id => Ok(()),
+                _ => Err(Error::Unsupported {
+                    provider: Provider::Claude,
+                    operation: "resuming another session in a running client",
+                }),
+            }
+        })
+    }
+
+    fn send_turn<'a>(
+        &'a mut self,
+        session_id: &'a str,
+        input: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let session = Uuid::parse_str(session_id).map_err(|e| {
+                Error::InvalidArgument(format!("Claude session ID {}: {}", session_id, e))
+            })?;
+            self.send(&ClaudeInput::user_message(input, session))
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<AgentEvent>>>> {
+        Box::pin(async move {
+            match self.receive().await {
+                Ok(output) => Ok(Some(events(output))),
+                Err(claude_codes::Error::ConnectionClosed) => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn respond<'a>(
+        &'a mut self,
+        request: &'a ApprovalRequest,
+        decision: ApprovalDecision,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let PendingApproval::Claude {
+                request_id,
+                request,
+            } = &request.pending
+            else {
+                return Err(Error::InvalidArgument(
+                    "approval request did not come from Claude".to_string(),
+                ));
+            };
+            let response = match decision {
+                ApprovalDecision::Approve => request.allow(request_id),
+                ApprovalDecision::ApproveForSession => request
+                    .allow_and_remember_suggestion(request_id)
+                    .unwrap_or_else(|| request.allow(request_id)),
+                ApprovalDecision::Deny => request.deny("Denied by the user", request_id),
+                ApprovalDecision::Cancel => {
+                    request.deny_and_stop("Cancelled by the user", request_id)
+                }
+            };
+            self.send_control_response(response).await?;
+            Ok(())
+        })
+    }
+
+    fn interrupt<'a>(&'a mut self, _session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            AsyncClient::interrupt(self).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Normalize one CLI output.
+fn events(output: ClaudeOutput) -> Vec<AgentEvent> {
+    match output {
+        ClaudeOutput::System(system) if system.is_init() => system
+            .data
+            .get("session_id")
+            .and_then(Value::as_str)
+            .map(|session_id| AgentEvent::SessionStarted {
+                session_id: session_id.to_string(),
+            })
+            .into_iter()
+            .collect(),
+        ClaudeOutput::StreamEvent(stream) if stream.parent_tool_use_id.is_none() => {
+            let event = &stream.event;
+            match (event.text_delta(), event.thinking_delta()) {
+                (Some(text), _) => vec![AgentEvent::TextDelta(text.to_string())],
+                (_, Some(thinking)) => vec![AgentEvent::ReasoningDelta(thinking.to_string())],
+                _ => Vec::new(),
+            }
+        }
+        ClaudeOutput::Assistant(assistant) if assistant.parent_tool_use_id.is_none() => assistant
+            .message
+            .content
+            .into_iter()
+            .flat_map(assistant_block)
+            .collect(),
+        ClaudeOutput::User(user) if user.parent_tool_use_id.is_none() => user
+            .message
+            .content
+            .into_iter()
+            .filter_map(|block| match block {
+                ContentBlock::ToolResult(result) => Some(AgentEvent::ToolResult(ToolResult {
+                    id: result.tool_use_id,
+                    output: result.content.map(tool_output).unwrap_or_default(),
+                    is_error: result.is_error.unwrap_or(false),
+                })),
+                _ => None,
+            })
+            .collect(),
+        ClaudeOutput::Result(result) => usage(&result)
+            .map(AgentEvent::Usage)
+            .into_iter()
+            .chain([AgentEvent::TurnCompleted(completion(&result))])
+            .collect(),
+        ClaudeOutput::ControlRequest(control) => match control.request {
+            ControlRequestPayload::CanUseTool(request) => {
+                vec![AgentEvent::ApprovalRequest(approval(
+                    control.request_id,
+                    request,
+                ))]
+            }
+            _ => Vec::new(),
+        },
+        ClaudeOutput::Error(error) => vec![AgentEvent::Error(error.error.message)],
+        _ => Vec::new(),
+    }
+}
+
+fn assistant_block(block: ContentBlock) -> Vec<AgentEvent> {
+    match block {
+        ContentBlock::Text(text) => vec![AgentEvent::Message(text.text)],
+        ContentBlock::Thinking(thinking) => vec![AgentEvent::Reasoning(thinking.thinking)],
+        ContentBlock::ToolUse(tool) => {
+            let change = file_change(&tool.name, &tool.input);
+            let call = AgentEvent::ToolCall(ToolCall {
+                id: tool.id,
+                name: tool.name,
+                input: tool.input,
+            });
+            std::iter::once(call)
+                .chain(change.map(AgentEvent::FileChange))
+                .collect()
+        }
+        ContentBlock::Image(_) | ContentBlock::ToolResult(_) => Vec::new(),
+    }
+}
+
+/// The file a file-editing tool call touches.
+fn file_change(tool: &str, input: &Value) -> Option<FileChange> {
+    let (field, kind) = match tool {
+        "Write" => ("file_path", None),
+        "Edit" | "MultiEdit" => ("file_path", Some(FileChangeKind::Update)),
+        "NotebookEdit" => ("notebook_path", Some(FileChangeKind::Update)),
+        _ => return None,
+    };
+    let path = input.get(field)?.as_str()?;
+    Some(FileChange {
+        path: path.to_string(),
+        kind,
+    })
+}
+
+fn tool_output(content: ToolResultContent) -> String {
+    match content {
+        ToolResultContent::Text(text) => text,
+        ToolResultContent::Structured(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Session totals across models. The result's own `usage` only covers the
+/// turn, so results without a model breakdown report nothing.
+fn usage(result: &ResultMessage) -> Option<Usage> {
+    if result.model_usage.is_empty() {
+        return None;
+    }
+    Some(
+        result
+            .model_usage
+            .values()
+            .fold(Usage::default(), |total, model| Usage {
+                input_tokens: total.input_tokens
+                    + model.input_tokens
+                    + model.cache_read_input_tokens
+                    + model.cache_creation_input_tokens,
+                output_tokens: total.output_tokens + model.output_tokens,
+                cached_input_tokens: total.cached_input_tokens + model.cache_read_input_tokens,
+            }),
+    )
+}
+
+fn completion(result: &ResultMessage) -> TurnCompletion {
+    let error = match (result.is_error, result.errors.is_empty()) {
+        (false, _) => None,
+        (true, false) => Some(result.errors.join("; ")),
+        (true, true) => result.result.clone(),
+    };
+    TurnCompletion {
+        success: !result.is_error,
+        error,
+    }
+}
+
+fn approval(request_id: String, request: ToolPermissionRequest) -> ApprovalRequest {
+    let kind = match request.tool_name.as_str() {
+        "Bash" => ApprovalKind::Command {
+            command: request.input["command"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            cwd: None,
+        },
+        "Write" | "Edit" | "MultiEdit" | "NotebookEdit" => ApprovalKind::FileChange {
+            changes: request.input.clone(),
+        },
+        _ => ApprovalKind::Tool {
+            name: request.tool_name.clone(),
+            input: request.input.clone(),
+        },
+    };
+    ApprovalRequest {
+        id: request_id.clone(),
+        kind,
+        reason: request.decision_reason.clone(),
+        pending: PendingApproval::Claude {
+            request_id,
+            request,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_codes::StreamTransport;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::task::JoinHandle;
+
+    fn parse(json: &str) -> Vec<AgentEvent> {
+        events(ClaudeOutput::parse_json(json).unwrap())
+    }
+
+    /// A client over a pipe whose CLI side writes `lines`, then collects what the client sends.
+    fn connect(lines: &[&str]) -> (AsyncClient, JoinHandle<Vec<Value>>) {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let lines: Vec<String> = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            for line in lines {
+                writer.write_all(line.as_bytes()).await.unwrap();
+            }
+            let mut sent = Vec::new();
+            let mut reader = BufReader::new(reader).lines();
+            while let Some(line) = reader.next_line().await.unwrap() {
+                sent.push(serde_json::from_str(&line).unwrap());
+            }
+            sent
+        });
+        let client = AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        (client, server)
+    }
+
+    fn approval_request(json: &str) -> ApprovalRequest {
+        match parse(json).pop() {
+            Some(AgentEvent::ApprovalRequest(request)) => request,
+            other => panic!("expected an approval request, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_respond_maps_decisions_to_permission_results() {
+        let request = approval_request(
+            r#"{"type":"control_request","request_id":"req_1","request":{"subtype":"can_use_tool","tool_name":"Bash","input":{"command":"ls"},
+                "permission_suggestions":[{"type":"addRules","rules":[{"toolName":"Bash","ruleContent":"ls"}],"behavior":"allow","destination":"session"}]}}"#,
+        );
+        let (client, server) = connect(&[]);
+        let mut agent: Box<dyn Agent> = Box::new(client);
+        for decision in [
+            ApprovalDecision::Approve,
+            ApprovalDecision::ApproveForSession,
+            ApprovalDecision::Deny,
+            ApprovalDecision::Cancel,
+        ] {
+            agent.respond(&request, decision).await.unwrap();
+        }
+
+        let codex = ApprovalRequest {
+            pending: PendingApproval::Codex(codex_codes::ServerRequest::from_parts(
+                codex_codes::RequestId::Integer(1),
+                "item/commandExecution/requestApproval".to_string(),
+                None,
+            )),
+            ..request.clone()
+        };
+        let err = agent
+            .respond(&codex, ApprovalDecision::Approve)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(_)));
+        drop(agent);
+
+        let sent = server.await.unwrap();
+        let results: Vec<&Value> = sent.iter().map(|m| &m["response"]["response"]).collect();
+        assert_eq!(results.len(), 4);
+        assert!(sent.iter().all(|m| m["response"]["request_id"] == "req_1"));
+        assert_eq!(results[0]["behavior"], "allow");
+        assert!(results[0].get("updatedPermissions").is_none());
+        assert_eq!(results[1]["behavior"], "allow");
+        assert_eq!(results[1]["updatedPermissions"][0]["type"], "addRules");
+        assert_eq!(results[2]["behavior"], "deny");
+        assert!(results[2].get("interrupt").is_none());
+        assert_eq!(results[3]["behavior"], "deny");
+        assert_eq!(results[3]["interrupt"], true);
+    }
+
+    #[tokio::test]
+    async fn test_resume_session_rejects_a_foreign_session() {
+        let session = "6d5b2c3e-0c1b-4b8e-9a3f-2f1e0d9c8b7a";
+        let result = format!(
+            r#"{{"type":"result","subtype":"success","is_error":false,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"session_id":"{0}","uuid":"{0}","total_cost_usd":0.0}}"#,
+            session
+        );
+        let (client, _server) = connect(&[&result]);
+        let mut agent: Box<dyn Agent> = Box::new(client);
+
+        let err = agent.resume_session(session).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Unsupported {
+                provider: Provider::Claude,
+                ..
+            }
+        ));
+
+        let events = agent.next_events().await.unwrap().unwrap();
+        assert!(matches!(events.last(), Some(AgentEvent::TurnCompleted(_))));
+        agent.resume_session(session).await.unwrap();
+        let err = agent
+            .resume_session("0e9d6c1f-5a4b-4c3d-8e2f-1a0b9c8d7e6f")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Unsupported { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_start_session_needs_a_known_session() {
+        let (client, _server) = connect(&[]);
+        let mut agent: Box<dyn Agent> = Box::new(client);
+
+        let err = agent.start_session().await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Claude(claude_codes::Error::SessionNotInitialized)
+        ));
+    }
+
+    #[test]
+    fn test_normalizes_claude_outputs() {
+        let events = parse(
+            r#"{"type":"assistant","session_id":"s1","message":{"id":"m1","role":"assistant","model":"claude","content":[
+                {"type":"thinking","thinking":"Need a file","signature":"sig"},
+                {"type":"text","text":"Writing it now."},
+                {"type":"tool_use","id":"toolu_1","name":"Write","input":{"file_path":"/tmp/a.txt","content":"hi"}}
+            ]}}"#,
+        );
+        assert!(matches!(&events[0], AgentEvent::Reasoning(text) if text == "Need a file"));
+        assert!(matches!(&events[1], AgentEvent::Message(text) if text == "Writing it now."));
+        assert!(matches!(&events[2], AgentEvent::ToolCall(call) if call.name == "Write"));
+        assert!(matches!(
+            &events[3],
+            AgentEvent::FileChange(FileChange { path, kind: None }) if path == "/tmp/a.txt"
+        ));
+
+        let events = parse(
+            r#"{"type":"user","session_id":"08cd4ce5-1ce0-4dd4-8e7c-8b69712c514e","message":{"role":"user","content":[
+                {"type":"tool_result","tool_use_id":"toolu_1","content":"written"}
+            ]}}"#,
+        );
+        assert!(matches!(
+            &events[..],
+            [AgentEvent::ToolResult(ToolResult { id, output, is_error: false })]
+                if id == "toolu_1" && output == "written"
+        ));
+
+        let events = parse(
+            r#"{"type":"control_request","request_id":"req_1","request":{"subtype":"can_use_tool","tool_name":"Bash","input":{"command":"ls"}}}"#,
+        );
+        match &events[..] {
+            [AgentEvent::ApprovalRequest(request)] => {
+                assert_eq!(request.id, "req_1");
+                assert_eq!(
+                    request.kind,
+                    ApprovalKind::Command {
+                        command: "ls".to_string(),
+                        cwd: None
+                    }
+                );
+            }
+            other => panic!("expected an approval request, got {:?}", other),
+        }
+
+        let events = parse(
+            r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"session_id":"s1","total_cost_usd":0.0,
+                "modelUsage":{"sonnet":{"inputTokens":10,"outputTokens":4,"cacheReadInputTokens":2,"cacheCreationInputTokens":3},"haiku":{"inputTokens":5,"outputTokens":1}}}"#,
+        );
+        assert!(matches!(
+            &events[..],
+            [
+                AgentEvent::Usage(Usage {
+                    input_tokens: 20,
+                    output_tokens: 5,
+                    cached_input_tokens: 2
+                }),
+                AgentEvent::TurnCompleted(TurnCompletion {
+                    success: true,
+                    error: None
+                }),
+            ]
+        ));
+
+        // A turn's own usage is not a session total.
+        let events = parse(
+            r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"session_id":"s1","total_cost_usd":0.0,
+                "usage":{"input_tokens":10,"cache_creation_input_tokens":0,"cache_read_input_tokens":2,"output_tokens":4,"server_tool_use":{"web_search_requests":0},"service_tier":"standard"}}"#,
+        );
+        assert!(matches!(&events[..], [AgentEvent::TurnCompleted(_)]));
+    }
+
+    #[test]
+    fn test_skips_subagent_output() {
+        let events = parse(
+            r#"{"type":"user","session_id":"08cd4ce5-1ce0-4dd4-8e7c-8b69712c514e","parent_tool_use_id":"toolu_task","message":{"role":"user","content":[
+                {"type":"tool_result","tool_use_id":"toolu_2","content":"subagent output"}
+            ]}}"#,
+        );
+        assert!(events.is_empty(), "got {:?}", events);
+
+        let events = parse(
+            r#"{"type":"assistant","session_id":"s1","parent_tool_use_id":"toolu_task","message":{"id":"m2","role":"assistant","model":"claude","content":[
+                {"type":"tool_use","id":"toolu_2","name":"Read","input":{"file_path":"/tmp/a.txt"}}
+            ]}}"#,
+        );
+        assert!(events.is_empty(), "got {:?}", events);
+    }
+}
@@ -0,0 +1,466 @@
+//! [`Agent`] for the Codex app-server.
+//!
+//! Approval requests the client's approval handler answers never reach
+//! [`Agent::next_events`]; other server requests that are not approvals are
+//! rejected with a JSON-RPC error.
+
+use crate::event::{
+    AgentEvent, ApprovalDecision, ApprovalKind, ApprovalRequest, FileChange, FileChangeKind,
+    PendingApproval, ToolCall, ToolResult, TurnCompletion, Usage,
+};
+use crate::{Agent, BoxFuture, Error, Provider, Result};
+use codex_codes::{
+    AsyncClient, CommandApprovalDecision, CommandExecutionApprovalResponse, CommandExecutionStatus,
+    FileChangeApprovalDecision, FileChangeApprovalResponse, McpToolCallStatus, PatchChangeKind,
+    ServerNotification, ServerRequest, ThreadItem, ThreadResumeParams, ThreadStartParams,
+    TurnInterruptParams, TurnStartParams, TurnStatus, TypedServerMessage,
+};
+use serde_json::{json, Value};
+
+/// JSON-RPC error code for requests the agent does not handle.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+impl Agent for AsyncClient {
+    fn provider(&self) -> Provider {
+        Provider::Codex
+    }
+
+    fn start_session(&mut self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let thread = self.thread_start(&ThreadStartParams::default()).await?;
+            Ok(thread.thread_id().to_string())
+        })
+    }
+
+    fn resume_session<'a>(&'a mut self, session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.thread_resume(&ThreadResumeParams::new(session_id))
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn send_turn<'a>(
+        &'a mut self,
+        session_id: &'a str,
+        input: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.turn_start(&TurnStartParams::text(session_id, input))
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<AgentEvent>>>> {
+        Box::pin(async move {
+            match self.next_typed_message().await? {
+                None => Ok(None),
+                Some(TypedServerMessage::Notification(notification)) => {
+                    Ok(Some(events(notification)))
+                }
+                Some(TypedServerMessage::Request(ServerRequest::Unknown {
+                    id, method, ..
+                })) => {
+                    let message = format!("unsupported request: {}", method);
+                    self.respond_error(id, METHOD_NOT_FOUND, &message).await?;
+                    Ok(Some(Vec::new()))
+                }
+                Some(TypedServerMessage::Request(request)) => {
+                    Ok(Some(vec![AgentEvent::ApprovalRequest(approval(request))]))
+                }
+            }
+        })
+    }
+
+    fn respond<'a>(
+        &'a mut self,
+        request: &'a ApprovalRequest,
+        decision: ApprovalDecision,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match &request.pending {
+                PendingApproval::Codex(ServerRequest::CommandExecutionApproval { id, .. }) => {
+                    let decision = match decision {
+                        ApprovalDecision::Approve => CommandApprovalDecision::Accept,
+                        ApprovalDecision::ApproveForSession => {
+                            CommandApprovalDecision::AcceptForSession
+                        }
+                        ApprovalDecision::Deny => CommandApprovalDecision::Decline,
+                        ApprovalDecision::Cancel => CommandApprovalDecision::Cancel,
+                    };
+                    let response = CommandExecutionApprovalResponse { decision };
+                    self.respond(id.clone(), &response).await?;
+                }
+                PendingApproval::Codex(ServerRequest::FileChangeApproval { id, .. }) => {
+                    let decision = match decision {
+                        ApprovalDecision::Approve => FileChangeApprovalDecision::Accept,
+                        ApprovalDecision::ApproveForSession => {
+                            FileChangeApprovalDecision::AcceptForSession
+                        }
+                        ApprovalDecision::Deny => FileChangeApprovalDecision::Decline,
+                        ApprovalDecision::Cancel => FileChangeApprovalDecision::Cancel,
+                    };
+                    let response = FileChangeApprovalResponse { decision };
+                    self.respond(id.clone(), &response).await?;
+                }
+                _ => {
+                    return Err(Error::InvalidArgument(
+                        "approval request did not come from Codex".to_string(),
+                    ))
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn interrupt<'a>(&'a mut self, session_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let params = TurnInterruptParams {
+                thread_id: session_id.to_string(),
+            };
+            self.turn_interrupt(&params).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Normalize one app-server notification.
+fn events(notification: ServerNotification) -> Vec<AgentEvent> {
+    match notification {
+        ServerNotification::ThreadStarted(started) => vec![AgentEvent::SessionStarted {
+            session_id: started.thread_id,
+        }],
+        ServerNotification::AgentMessageDelta(delta) => vec![AgentEvent::TextDelta(delta.delta)],
+        ServerNotification::ReasoningDelta(delta) => {
+            vec![AgentEvent::ReasoningDelta(delta.delta)]
+        }
+        ServerNotification::ItemStarted(started) => item_started(started.item),
+        ServerNotification::ItemCompleted(completed) => item_completed(completed.item),
+        ServerNotification::ThreadTokenUsageUpdated(updated) => vec![AgentEvent::Usage(Usage {
+            input_tokens: updated.usage.input_tokens,
+            output_tokens: updated.usage.output_tokens,
+            cached_input_tokens: updated.usage.cached_input_tokens,
+        })],
+        ServerNotification::TurnCompleted(completed) => {
+            vec![AgentEvent::TurnCompleted(TurnCompletion {
+                success: completed.turn.status == TurnStatus::Completed,
+                error: completed.turn.error.map(|error| error.message),
+            })]
+        }
+        ServerNotification::Error(error) => vec![AgentEvent::Error(error.error)],
+        _ => Vec::new(),
+    }
+}
+
+fn item_started(item: ThreadItem) -> Vec<AgentEvent> {
+    let call = match item {
+        ThreadItem::CommandExecution(command) => ToolCall {
+            id: command.id,
+            name: "shell".to_string(),
+            input: json!({ "command": command.command }),
+        },
+        // Named the way Claude names MCP tools.
+        ThreadItem::McpToolCall(call) => ToolCall {
+            id: call.id,
+            name: format!("mcp__{}__{}", call.server, call.tool),
+            input: call.arguments,
+        },
+        ThreadItem::WebSearch(search) => ToolCall {
+            id: search.id,
+            name: "web_search".to_string(),
+            input: json!({ "query": search.query }),
+        },
+        ThreadItem::FileChange(change) => {
+            return change
+                .changes
+                .into_iter()
+                .map(|change| {
+                    AgentEvent::FileChange(FileChange {
+                        path: change.path,
+                        kind: Some(match change.kind {
+                            PatchChangeKind::Add => FileChangeKind::Add,
+                            PatchChangeKind::Delete => FileChangeKind::Delete,
+                            PatchChangeKind::Update => FileChangeKind::Update,
+                        }),
+                    })
+                })
+                .collect()
+        }
+        _ => return Vec::new(),
+    };
+    vec![AgentEvent::ToolCall(call)]
+}
+
+fn item_completed(item: ThreadItem) -> Vec<AgentEvent> {
+    let result = match item {
+        ThreadItem::AgentMessage(message) => return vec![AgentEvent::Message(message.text)],
+        ThreadItem::Reasoning(reasoning) => return vec![AgentEvent::Reasoning(reasoning.text)],
+        ThreadItem::Error(error) => return vec![AgentEvent::Error(error.message)],
+        ThreadItem::CommandExecution(command) => ToolResult {
+            id: command.id,
+            output: command.aggregated_output,
+            is_error: command.status != CommandExecutionStatus::Completed,
+        },
+        ThreadItem::McpToolCall(call) => {
+            let output = match (&call.error, &call.result) {
+                (Some(error), _) => error.message.clone(),
+                (None, Some(result)) => result
+                    .content
+                    .iter()
+                    .filter_map(|part| part.get("text").and_then(Value::as_str))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                (None, None) => String::new(),
+            };
+            ToolResult {
+                id: call.id,
+                output,
+                is_error: call.error.is_some() || call.status == McpToolCallStatus::Failed,
+            }
+        }
+        ThreadItem::WebSearch(search) => ToolResult {
+            id: search.id,
+            output: String::new(),
+            is_error: false,
+        },
+        ThreadItem::FileChange(_) | ThreadItem::TodoList(_) => return Vec::new(),
+    };
+    vec![AgentEvent::ToolResult(result)]
+}
+
+fn approval(request: ServerRequest) -> ApprovalRequest {
+    let (kind, reason) = match &request {
+        ServerRequest::CommandExecutionApproval { params, .. } => (
+            ApprovalKind::Command {
+                command: params.command.clone(),
+                cwd: Some(params.cwd.clone()),
+            },
+            params.reason.clone(),
+        ),
+        ServerRequest::FileChangeApproval { params, .. } => (
+            ApprovalKind::FileChange {
+                changes: params.changes.clone(),
+            },
+            params.reason.clone(),
+        ),
+        ServerRequest::Unknown { method, params, .. } => (
+            ApprovalKind::Tool {
+                name: method.clone(),
+                input: params.clone().unwrap_or(Value::Null),
+            },
+            None,
+        ),
+    };
+    ApprovalRequest {
+        id: request.id().to_string(),
+        kind,
+        reason,
+        pending: PendingApproval::Codex(request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_codes::{RequestId, StreamTransport};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::task::JoinHandle;
+
+    /// A client over a pipe whose server side writes `lines`, then collects what the client sends.
+    fn connect(lines: Vec<Value>) -> (AsyncClient, JoinHandle<Vec<Value>>) {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            for line in lines {
+                writer
+                    .write_all(format!("{}\n", line).as_bytes())
+                    .await
+                    .unwrap();
+            }
+            let mut sent = Vec::new();
+            let mut reader = BufReader::new(reader).lines();
+            while let Some(line) = reader.next_line().await.unwrap() {
+                sent.push(serde_json::from_str(&line).unwrap());
+            }
+            sent
+        });
+        let client = AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn test_respond_maps_decisions_to_approval_responses() {
+        let requests = [
+            approval(ServerRequest::from_parts(
+                RequestId::Integer(1),
+                "item/commandExecution/requestApproval".to_string(),
+                Some(
+                    json!({"threadId": "th_1", "turnId": "t_1", "callId": "c1", "command": "ls", "cwd": "/repo"}),
+                ),
+            )),
+            approval(ServerRequest::from_parts(
+                RequestId::Integer(2),
+                "item/fileChange/requestApproval".to_string(),
+                Some(json!({"threadId": "th_1", "turnId": "t_1", "callId": "c2", "changes": {}})),
+            )),
+        ];
+        let (client, server) = connect(Vec::new());
+        let mut agent: Box<dyn Agent> = Box::new(client);
+        for request in &requests {
+            for decision in [
+                ApprovalDecision::Approve,
+                ApprovalDecision::ApproveForSession,
+                ApprovalDecision::Deny,
+                ApprovalDecision::Cancel,
+            ] {
+                agent.respond(request, decision).await.unwrap();
+            }
+        }
+
+        let claude = ApprovalRequest {
+            pending: PendingApproval::Claude {
+                request_id: "req_1".to_string(),
+                request: claude_codes::ToolPermissionRequest {
+                    tool_name: "Bash".to_string(),
+                    input: json!({"command": "ls"}),
+                    permission_suggestions: vec![],
+                    blocked_path: None,
+                    decision_reason: None,
+                    tool_use_id: None,
+                },
+            },
+            ..requests[0].clone()
+        };
+        let err = agent
+            .respond(&claude, ApprovalDecision::Approve)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArgument(_)));
+        drop(agent);
+
+        let sent: Vec<(Value, Value)> = server
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| (m["id"].clone(), m["result"]["decision"].clone()))
+            .collect();
+        let expected = ["accept", "acceptForSession", "decline", "cancel"];
+        let expected: Vec<(Value, Value)> = [1, 2]
+            .into_iter()
+            .flat_map(|id| expected.map(|decision| (json!(id), json!(decision))))
+            .collect();
+        assert_eq!(sent, expected);
+    }
+
+    #[tokio::test]
+    async fn test_next_events_rejects_unknown_requests() {
+        let (client, server) = connect(vec![
+            json!({"id": 9, "method": "item/tool/requestUserInput", "params": {"threadId": "th_1"}}),
+        ]);
+        let mut agent: Box<dyn Agent> = Box::new(client);
+
+        let events = agent.next_events().await.unwrap().unwrap();
+        assert!(events.is_empty());
+        drop(agent);
+
+        let sent = server.await.unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0]["id"], 9);
+        assert_eq!(sent[0]["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn test_drives_codex_turn() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+            let mut script = [
+                vec![json!({"id": 0, "result": {"thread": {"id": "th_1"}}})],
+                vec![
+                    json!({"id": 1, "result": {}}),
+                    json!({"method": "item/started", "params": {"threadId": "th_1", "turnId": "t_1", "item": {
+                        "type": "commandExecution", "id": "cmd_1", "command": "ls",
+                        "aggregated_output": "", "status": "inProgress"
+                    }}}),
+                    json!({"id": 7, "method": "item/commandExecution/requestApproval", "params": {
+                        "threadId": "th_1", "turnId": "t_1", "callId": "cmd_1", "command": "ls", "cwd": "/repo"
+                    }}),
+                ],
+                vec![
+                    json!({"method": "item/agentMessage/delta", "params": {"threadId": "th_1", "itemId": "msg_1", "delta": "Done"}}),
+                    json!({"method": "thread/tokenUsage/updated", "params": {"threadId": "th_1", "usage": {"inputTokens": 9, "outputTokens": 2}}}),
+                    json!({"method": "turn/completed", "params": {"threadId": "th_1", "turnId": "t_1", "turn": {"id": "t_1", "status": "completed"}}}),
+                ],
+            ];
+            let expected = ["thread/start", "turn/start", ""];
+
+            // Each client line is answered with the next batch of server lines.
+            for (batch, method) in script.iter_mut().zip(expected) {
+                let line = lines.next_line().await.unwrap().unwrap();
+                let message: Value = serde_json::from_str(&line).unwrap();
+                if method.is_empty() {
+                    assert_eq!(message["id"], 7);
+                    assert_eq!(message["result"]["decision"], "acceptForSession");
+                } else {
+                    assert_eq!(message["method"], method);
+                    batch[0]["id"] = message["id"].clone();
+                }
+                for reply in batch.iter() {
+                    writer
+                        .write_all(format!("{}\n", reply).as_bytes())
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let mut agent: Box<dyn Agent> =
+            Box::new(AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap());
+        assert_eq!(agent.provider(), Provider::Codex);
+        let session = agent.start_session().await.unwrap();
+        assert_eq!(session, "th_1");
+        agent.send_turn(&session, "List files").await.unwrap();
+
+        let mut events = Vec::new();
+        while let Some(batch) = agent.next_events().await.unwrap() {
+            for event in batch {
+                if let AgentEvent::ApprovalRequest(request) = &event {
+                    assert_eq!(
+                        request.kind,
+                        ApprovalKind::Command {
+                            command: "ls".to_string(),
+                            cwd: Some("/repo".to_string())
+                        }
+                    );
+                    agent
+                        .respond(request, ApprovalDecision::ApproveForSession)
+                        .await
+                        .unwrap();
+                }
+                events.push(event);
+            }
+        }
+        server.await.unwrap();
+
+        assert!(matches!(&events[0], AgentEvent::ToolCall(call) if call.name == "shell"));
+        assert!(matches!(&events[1], AgentEvent::ApprovalRequest(request) if request.id == "7"));
+        assert!(matches!(&events[2], AgentEvent::TextDelta(text) if text == "Done"));
+        assert!(matches!(
+            &events[3],
+            AgentEvent::Usage(Usage {
+                input_tokens: 9,
+                output_tokens: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            &events[4],
+            AgentEvent::TurnCompleted(TurnCompletion {
+                success: true,
+                error: None
+            })
+        ));
+    }
+}
@@ -0,0 +1,37 @@
+//! Error types for the agent-codes crate.
+//!
+//! Provider errors are wrapped unchanged, so callers that need the details
+//! can still match on [`claude_codes::Error`] or [`codex_codes::Error`].
+
+use crate::Provider;
+use thiserror::Error;
+
+/// All possible errors from [`Agent`](crate::Agent) operations.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The Claude client failed.
+    #[error("Claude: {0}")]
+    Claude(#[from] claude_codes::Error),
+
+    /// The Codex client failed.
+    #[error("Codex: {0}")]
+    Codex(#[from] codex_codes::Error),
+
+    /// The provider cannot do this.
+    ///
+    /// For example, a Claude client cannot switch to another session — its
+    /// session is fixed when the CLI is spawned.
+    #[error("{provider} does not support {operation}")]
+    Unsupported {
+        provider: Provider,
+        operation: &'static str,
+    },
+
+    /// An argument does not fit the provider, such as an approval request
+    /// from the other provider or a session ID that is not a UUID.
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+/// Type alias for `Result<T, Error>`.
+pub type Result<T> = std::result::Result<T, Error>;
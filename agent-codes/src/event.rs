@@ -0,0 +1,148 @@
+//! Provider-neutral events, approvals and usage.
+//!
+//! Both providers' messages are normalized into [`AgentEvent`]s. Streaming
+//! text arrives twice — as [`AgentEvent::TextDelta`]s while the model writes,
+//! and as one [`AgentEvent::Message`] once the message is complete — so
+//! callers can render either without tracking which provider they talk to.
+//! Claude only streams deltas when spawned with `--include-partial-messages`.
+
+use serde_json::Value;
+
+/// One normalized event from an agent.
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    /// The provider reported the session's ID — Claude's session UUID or
+    /// Codex's thread ID. Pass it to [`Agent::resume_session`](crate::Agent::resume_session) later.
+    SessionStarted { session_id: String },
+    /// A chunk of agent text, as it is generated.
+    TextDelta(String),
+    /// A complete agent message.
+    Message(String),
+    /// A chunk of reasoning text, as it is generated.
+    ReasoningDelta(String),
+    /// A complete reasoning block or summary.
+    Reasoning(String),
+    /// The agent started a tool call or shell command.
+    ToolCall(ToolCall),
+    /// A tool call or shell command finished.
+    ToolResult(ToolResult),
+    /// The agent is changing a file.
+    FileChange(FileChange),
+    /// The agent needs permission; answer with [`Agent::respond`](crate::Agent::respond).
+    ApprovalRequest(ApprovalRequest),
+    /// Updated token totals for the session.
+    Usage(Usage),
+    /// The provider reported an error; the turn may still continue.
+    Error(String),
+    /// The turn is over.
+    TurnCompleted(TurnCompletion),
+}
+
+/// A tool call or shell command the agent started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    /// Matches the [`ToolResult::id`] of the call's result.
+    pub id: String,
+    /// The tool's name. Codex shell commands are named `shell` with the
+    /// command line under `input.command`.
+    pub name: String,
+    pub input: Value,
+}
+
+/// The result of a [`ToolCall`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolResult {
+    pub id: String,
+    /// The tool's text output.
+    pub output: String,
+    pub is_error: bool,
+}
+
+/// Kind of change made to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChangeKind {
+    Add,
+    Delete,
+    Update,
+}
+
+/// A file the agent is changing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    /// `None` when the provider does not say — Claude's `Write` tool both
+    /// creates and overwrites files.
+    pub kind: Option<FileChangeKind>,
+}
+
+/// What an [`ApprovalRequest`] asks to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApprovalKind {
+    /// Run a shell command.
+    Command {
+        command: String,
+        cwd: Option<String>,
+    },
+    /// Apply file changes.
+    FileChange { changes: Value },
+    /// Use any other tool.
+    Tool { name: String, input: Value },
+}
+
+/// A request for permission, from [`AgentEvent::ApprovalRequest`].
+#[derive(Debug, Clone)]
+pub struct ApprovalRequest {
+    /// The provider's ID for the request.
+    pub id: String,
+    pub kind: ApprovalKind,
+    /// Why the agent is asking, if it said.
+    pub reason: Option<String>,
+    pub(crate) pending: PendingApproval,
+}
+
+/// The provider-native request, kept to build the response.
+#[derive(Debug, Clone)]
+pub(crate) enum PendingApproval {
+    Claude {
+        request_id: String,
+        request: claude_codes::ToolPermissionRequest,
+    },
+    Codex(codex_codes::ServerRequest),
+}
+
+/// How to answer an [`ApprovalRequest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    /// Allow this request.
+    Approve,
+    /// Allow this request and similar ones for the rest of the session.
+    ///
+    /// Claude remembers the first permission suggestion it offered, if any.
+    ApproveForSession,
+    /// Refuse this request; the agent carries on without it.
+    Deny,
+    /// Refuse this request and stop the turn.
+    Cancel,
+}
+
+/// Token totals for a session.
+///
+/// Claude reports totals across all models at the end of each turn; Codex
+/// reports thread totals as they change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Every input token, including those read from or written to the cache.
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// The part of `input_tokens` served from the provider's cache.
+    pub cached_input_tokens: u64,
+}
+
+/// How a turn ended, from [`AgentEvent::TurnCompleted`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnCompletion {
+    /// Whether the turn finished without an error or interruption.
+    pub success: bool,
+    /// What went wrong, if the provider said.
+    pub error: Option<String>,
+}
@@ -0,0 +1,132 @@
+//! A provider-agnostic interface over the `claude-codes` and `codex-codes` clients.
+//!
+//! Applications that let users pick between Claude Code and Codex can drive
+//! either through the [`Agent`] trait, which is implemented for
+//! [`claude_codes::AsyncClient`] and [`codex_codes::AsyncClient`]. Both
+//! providers' messages are normalized into [`AgentEvent`]s: text and
+//! reasoning (streamed and complete), tool calls and their results, file
+//! changes, approval requests, token usage and turn completion.
+//!
+//! The trait is object-safe, so the provider can be chosen at runtime:
+//!
+//! ```no_run
+//! use agent_codes::{Agent, AgentEvent, ApprovalDecision};
+//!
+//! # async fn example(use_codex: bool) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut agent: Box<dyn Agent> = if use_codex {
+//!     Box::new(codex_codes::AsyncClient::start().await?)
+//! } else {
+//!     let builder = claude_codes::ClaudeCliBuilder::new().permission_prompt_tool("stdio");
+//!     let mut client = claude_codes::AsyncClient::from_builder(builder).await?;
+//!     client.enable_tool_approval().await?;
+//!     Box::new(client)
+//! };
+//!
+//! let session = agent.start_session().await?;
+//! agent.send_turn(&session, "What is 2 + 2?").await?;
+//!
+//! 'turn: while let Some(events) = agent.next_events().await? {
+//!     for event in events {
+//!         match event {
+//!             AgentEvent::TextDelta(text) => print!("{}", text),
+//!             AgentEvent::ApprovalRequest(request) => {
+//!                 agent.respond(&request, ApprovalDecision::Deny).await?;
+//!             }
+//!             AgentEvent::TurnCompleted(_) => break 'turn,
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Sessions
+//!
+//! A session is a Claude session or a Codex thread. One Codex app-server
+//! hosts any number of threads, so Codex turns are addressed by the session
+//! ID. A Claude CLI process runs exactly one session: [`Agent::start_session`]
+//! returns its ID, and resuming a different session means spawning a new
+//! client with `--resume`.
+
+mod claude;
+mod codex;
+pub mod error;
+pub mod event;
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+pub use error::{Error, Result};
+pub use event::{
+    AgentEvent, ApprovalDecision, ApprovalKind, ApprovalRequest, FileChange, FileChangeKind,
+    ToolCall, ToolResult, TurnCompletion, Usage,
+};
+
+/// A boxed future, as returned by [`Agent`] methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Which CLI an [`Agent`] talks to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Provider {
+    Claude,
+    Codex,
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Claude => write!(f, "Claude"),
+            Provider::Codex => write!(f, "Codex"),
+        }
+    }
+}
+
+/// A coding agent session driven the same way whichever CLI runs it.
+pub trait Agent: Send {
+    /// Which CLI this agent talks to.
+    fn provider(&self) -> Provider;
+
+    /// Start a session and return its ID.
+    ///
+    /// Codex starts a new thread. Claude returns the session of its process,
+    /// which is known when the client came from
+    /// [`AsyncClient::from_builder`](claude_codes::AsyncClient::from_builder)
+    /// or `resume_session`, and fails otherwise until the CLI has reported it.
+    fn start_session(&mut self) -> BoxFuture<'_, Result<String>>;
+
+    /// Continue a session started earlier.
+    ///
+    /// Codex resumes the thread from disk. Claude accepts only its own
+    /// session and fails with [`Error::Unsupported`] otherwise.
+    fn resume_session<'a>(&'a mut self, session_id: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Send a user message, starting a turn.
+    ///
+    /// Read the turn's progress with [`next_events`](Self::next_events) until
+    /// [`AgentEvent::TurnCompleted`].
+    fn send_turn<'a>(
+        &'a mut self,
+        session_id: &'a str,
+        input: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Read the next message and normalize it into events.
+    ///
+    /// One message may produce several events, or none. Returns `None` once
+    /// the provider has closed the connection.
+    fn next_events(&mut self) -> BoxFuture<'_, Result<Option<Vec<AgentEvent>>>>;
+
+    /// Answer an [`AgentEvent::ApprovalRequest`].
+    fn respond<'a>(
+        &'a mut self,
+        request: &'a ApprovalRequest,
+        decision: ApprovalDecision,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Stop the turn in progress on `session_id`.
+    ///
+    /// The turn still ends with [`AgentEvent::TurnCompleted`].
+    fn interrupt<'a>(&'a mut self, session_id: &'a str) -> BoxFuture<'a, Result<()>>;
+}
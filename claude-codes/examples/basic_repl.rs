@@ -253,5 +253,8 @@ fn handle_output(output: ClaudeOutput) {
                 evt.rate_limit_info.resets_at
             );
         }
+        ClaudeOutput::StreamEvent(evt) => {
+            debug!("Stream event: {:?}", evt.event);
+        }
     }
 }
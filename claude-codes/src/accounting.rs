@@ -0,0 +1,629 @@
+//! Token and cost accounting with budgets
+//!
+//! A [`UsageLedger`] consumes [`ClaudeOutput`] messages and aggregates token
+//! usage and cost per session, per model and per subagent into a
+//! [`UsageReport`]. Reports are serializable, so totals can be carried across
+//! many runs with [`UsageLedger::from_report`] or combined with
+//! [`UsageReport::merge`].
+//!
+//! Tokens are counted from the `usage` of each assistant message (deduplicated
+//! by message ID, since the CLI repeats it for every content block of a
+//! message). USD comes from `result` messages: `total_cost_usd` per session and
+//! `modelUsage` per model. The CLI does not price subagents separately, so
+//! subagent totals carry tokens only.
+//!
+//! [`Budget`]s fire once when a metric crosses its limit, calling an optional
+//! callback. Budgets marked with [`Budget::interrupt`] also make
+//! `AsyncClient` interrupt the turn in progress when the ledger is attached
+//! with `AsyncClient::set_usage_ledger`. Cost is only known when a result
+//! arrives, so token budgets are the ones that can stop a turn mid-flight.
+//!
+//! # Example
+//!
+//! ```
+//! use claude_codes::accounting::{Budget, UsageLedger};
+//! use claude_codes::ClaudeOutput;
+//!
+//! let mut ledger = UsageLedger::new().budget(Budget::output_tokens(1000).interrupt());
+//!
+//! let output = ClaudeOutput::parse_json(r#"{"type":"assistant","message":{"id":"msg_1","role":"assistant","model":"claude-sonnet-4","content":[],"usage":{"input_tokens":10,"output_tokens":1500}},"session_id":"s1"}"#).unwrap();
+//! let exceeded = ledger.record(&output);
+//!
+//! assert_eq!(exceeded.len(), 1);
+//! assert!(exceeded[0].interrupt);
+//! assert_eq!(ledger.report().models["claude-sonnet-4"].tokens.output_tokens, 1500);
+//! ```
+
+use crate::io::{AssistantUsage, ClaudeOutput, ResultMessage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+// ============================================================================
+// Report types
+// ============================================================================
+
+/// Token counts, broken down the way the API bills them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTotals {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    /// Cache writes with the 5-minute TTL
+    pub ephemeral_5m_input_tokens: u64,
+    /// Cache writes with the 1-hour TTL
+    pub ephemeral_1h_input_tokens: u64,
+    pub web_search_requests: u64,
+}
+
+impl TokenTotals {
+    /// Token counts from an assistant message's usage
+    pub fn from_assistant_usage(usage: &AssistantUsage) -> Self {
+        let cache_creation = usage.cache_creation.as_ref();
+        Self {
+            input_tokens: u64::from(usage.input_tokens),
+            output_tokens: u64::from(usage.output_tokens),
+            cache_read_input_tokens: u64::from(usage.cache_read_input_tokens),
+            cache_creation_input_tokens: u64::from(usage.cache_creation_input_tokens),
+            ephemeral_5m_input_tokens: cache_creation
+                .map_or(0, |c| u64::from(c.ephemeral_5m_input_tokens)),
+            ephemeral_1h_input_tokens: cache_creation
+                .map_or(0, |c| u64::from(c.ephemeral_1h_input_tokens)),
+            web_search_requests: 0,
+        }
+    }
+
+    /// All tokens: input, output, cache reads and cache writes
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_read_input_tokens
+            + self.cache_creation_input_tokens
+    }
+
+    /// Field-wise `self - other`, clamped at zero
+    fn saturating_sub(&self, other: &Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens.saturating_sub(other.input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(other.output_tokens),
+            cache_read_input_tokens: self
+                .cache_read_input_tokens
+                .saturating_sub(other.cache_read_input_tokens),
+            cache_creation_input_tokens: self
+                .cache_creation_input_tokens
+                .saturating_sub(other.cache_creation_input_tokens),
+            ephemeral_5m_input_tokens: self
+                .ephemeral_5m_input_tokens
+                .saturating_sub(other.ephemeral_5m_input_tokens),
+            ephemeral_1h_input_tokens: self
+                .ephemeral_1h_input_tokens
+                .saturating_sub(other.ephemeral_1h_input_tokens),
+            web_search_requests: self
+                .web_search_requests
+                .saturating_sub(other.web_search_requests),
+        }
+    }
+
+    /// Field-wise maximum
+    fn max(&self, other: &Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens.max(other.input_tokens),
+            output_tokens: self.output_tokens.max(other.output_tokens),
+            cache_read_input_tokens: self
+                .cache_read_input_tokens
+                .max(other.cache_read_input_tokens),
+            cache_creation_input_tokens: self
+                .cache_creation_input_tokens
+                .max(other.cache_creation_input_tokens),
+            ephemeral_5m_input_tokens: self
+                .ephemeral_5m_input_tokens
+                .max(other.ephemeral_5m_input_tokens),
+            ephemeral_1h_input_tokens: self
+                .ephemeral_1h_input_tokens
+                .max(other.ephemeral_1h_input_tokens),
+            web_search_requests: self.web_search_requests.max(other.web_search_requests),
+        }
+    }
+}
+
+impl std::ops::AddAssign for TokenTotals {
+    fn add_assign(&mut self, other: Self) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.ephemeral_5m_input_tokens += other.ephemeral_5m_input_tokens;
+        self.ephemeral_1h_input_tokens += other.ephemeral_1h_input_tokens;
+        self.web_search_requests += other.web_search_requests;
+    }
+}
+
+/// Tokens and cost for one slice of a report
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub tokens: TokenTotals,
+    /// Cost in USD (zero where the CLI does not report it)
+    pub cost_usd: f64,
+}
+
+impl std::ops::AddAssign<&UsageSummary> for UsageSummary {
+    fn add_assign(&mut self, other: &UsageSummary) {
+        self.tokens += other.tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+/// Aggregated usage across sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Totals across everything recorded
+    pub total: UsageSummary,
+    /// Per session ID
+    pub sessions: BTreeMap<String, UsageSummary>,
+    /// Per model
+    pub models: BTreeMap<String, UsageSummary>,
+    /// Per subagent, keyed by the `tool_use_id` of the Task call that started it
+    pub subagents: BTreeMap<String, UsageSummary>,
+}
+
+impl UsageReport {
+    /// Add another report's totals into this one
+    pub fn merge(&mut self, other: &UsageReport) {
+        self.total += &other.total;
+        for (key, summary) in &other.sessions {
+            *self.sessions.entry(key.clone()).or_default() += summary;
+        }
+        for (key, summary) in &other.models {
+            *self.models.entry(key.clone()).or_default() += summary;
+        }
+        for (key, summary) in &other.subagents {
+            *self.subagents.entry(key.clone()).or_default() += summary;
+        }
+    }
+}
+
+// ============================================================================
+// Budgets
+// ============================================================================
+
+/// What a [`Budget`] limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetMetric {
+    /// Total cost in USD
+    CostUsd,
+    /// All tokens, including cache reads and writes
+    TotalTokens,
+    /// Output tokens
+    OutputTokens,
+}
+
+impl BudgetMetric {
+    fn observe(&self, summary: &UsageSummary) -> f64 {
+        match self {
+            BudgetMetric::CostUsd => summary.cost_usd,
+            BudgetMetric::TotalTokens => summary.tokens.total_tokens() as f64,
+            BudgetMetric::OutputTokens => summary.tokens.output_tokens as f64,
+        }
+    }
+}
+
+impl fmt::Display for BudgetMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BudgetMetric::CostUsd => "cost_usd",
+            BudgetMetric::TotalTokens => "total_tokens",
+            BudgetMetric::OutputTokens => "output_tokens",
+        })
+    }
+}
+
+/// A budget that was crossed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetExceeded {
+    pub metric: BudgetMetric,
+    pub limit: f64,
+    /// The value that crossed the limit
+    pub observed: f64,
+    /// Whether the budget asks for the turn in progress to be interrupted
+    pub interrupt: bool,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} budget exceeded: {} > {}",
+            self.metric, self.observed, self.limit
+        )
+    }
+}
+
+type BudgetCallback = Arc<dyn Fn(&BudgetExceeded) + Send + Sync>;
+
+/// A limit on the ledger's total usage
+#[derive(Clone)]
+pub struct Budget {
+    metric: BudgetMetric,
+    limit: f64,
+    interrupt: bool,
+    callback: Option<BudgetCallback>,
+    fired: bool,
+}
+
+impl Budget {
+    fn new(metric: BudgetMetric, limit: f64) -> Self {
+        Self {
+            metric,
+            limit,
+            interrupt: false,
+            callback: None,
+            fired: false,
+        }
+    }
+
+    /// Limit total cost in USD
+    pub fn cost_usd(limit: f64) -> Self {
+        Self::new(BudgetMetric::CostUsd, limit)
+    }
+
+    /// Limit all tokens, including cache reads and writes
+    pub fn total_tokens(limit: u64) -> Self {
+        Self::new(BudgetMetric::TotalTokens, limit as f64)
+    }
+
+    /// Limit output tokens
+    pub fn output_tokens(limit: u64) -> Self {
+        Self::new(BudgetMetric::OutputTokens, limit as f64)
+    }
+
+    /// Interrupt the turn in progress when the budget is exceeded
+    pub fn interrupt(mut self) -> Self {
+        self.interrupt = true;
+        self
+    }
+
+    /// Call `callback` when the budget is exceeded
+    pub fn on_exceeded<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&BudgetExceeded) + Send + Sync + 'static,
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// The metric this budget limits
+    pub fn metric(&self) -> BudgetMetric {
+        self.metric
+    }
+
+    /// The limit
+    pub fn limit(&self) -> f64 {
+        self.limit
+    }
+
+    /// Whether the budget has already fired
+    pub fn is_exceeded(&self) -> bool {
+        self.fired
+    }
+
+    fn check(&mut self, total: &UsageSummary) -> Option<BudgetExceeded> {
+        if self.fired {
+            return None;
+        }
+        let observed = self.metric.observe(total);
+        if observed <= self.limit {
+            return None;
+        }
+        self.fired = true;
+        let exceeded = BudgetExceeded {
+            metric: self.metric,
+            limit: self.limit,
+            observed,
+            interrupt: self.interrupt,
+        };
+        if let Some(callback) = &self.callback {
+            callback(&exceeded);
+        }
+        Some(exceeded)
+    }
+}
+
+impl fmt::Debug for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Budget")
+            .field("metric", &self.metric)
+            .field("limit", &self.limit)
+            .field("interrupt", &self.interrupt)
+            .field("fired", &self.fired)
+            .finish()
+    }
+}
+
+// ============================================================================
+// Ledger
+// ============================================================================
+
+/// Aggregates usage from [`ClaudeOutput`] messages and enforces [`Budget`]s
+#[derive(Debug, Clone, Default)]
+pub struct UsageLedger {
+    report: UsageReport,
+    budgets: Vec<Budget>,
+    /// Tokens already counted per assistant message ID
+    counted: HashMap<String, TokenTotals>,
+    /// Latest cumulative cost per session, and per (session, model)
+    session_costs: HashMap<String, f64>,
+    model_costs: HashMap<(String, String), f64>,
+}
+
+impl UsageLedger {
+    /// Create an empty ledger
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continue aggregating on top of a report from earlier runs
+    pub fn from_report(report: UsageReport) -> Self {
+        Self {
+            report,
+            ..Self::default()
+        }
+    }
+
+    /// Add a budget
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budgets.push(budget);
+        self
+    }
+
+    /// Add a budget to an existing ledger
+    pub fn add_budget(&mut self, budget: Budget) {
+        self.budgets.push(budget);
+    }
+
+    /// The budgets, in the order they were added
+    pub fn budgets(&self) -> &[Budget] {
+        &self.budgets
+    }
+
+    /// Re-arm budgets that have fired
+    pub fn reset_budgets(&mut self) {
+        for budget in &mut self.budgets {
+            budget.fired = false;
+        }
+    }
+
+    /// The aggregated report
+    pub fn report(&self) -> &UsageReport {
+        &self.report
+    }
+
+    /// Consume the ledger, returning its report
+    pub fn into_report(self) -> UsageReport {
+        self.report
+    }
+
+    /// Record an output. Returns the budgets it pushed over their limits.
+    pub fn record(&mut self, output: &ClaudeOutput) -> Vec<BudgetExceeded> {
+        match output {
+            ClaudeOutput::Assistant(msg) => {
+                let Some(usage) = &msg.message.usage else {
+                    return Vec::new();
+                };
+                let tokens = TokenTotals::from_assistant_usage(usage);
+                let delta = match self.counted.get(&msg.message.id) {
+                    Some(counted) => {
+                        let delta = tokens.saturating_sub(counted);
+                        let merged = counted.max(&tokens);
+                        self.counted.insert(msg.message.id.clone(), merged);
+                        delta
+                    }
+                    None => {
+                        self.counted.insert(msg.message.id.clone(), tokens);
+                        tokens
+                    }
+                };
+                self.add_tokens(
+                    &msg.session_id,
+                    Some(&msg.message.model),
+                    msg.parent_tool_use_id.as_deref(),
+                    delta,
+                );
+            }
+            ClaudeOutput::Result(result) => self.record_result(result),
+            _ => return Vec::new(),
+        }
+        self.check_budgets()
+    }
+
+    fn add_tokens(
+        &mut self,
+        session_id: &str,
+        model: Option<&str>,
+        subagent: Option<&str>,
+        tokens: TokenTotals,
+    ) {
+        self.report.total.tokens += tokens;
+        self.report
+            .sessions
+            .entry(session_id.to_string())
+            .or_default()
+            .tokens += tokens;
+        if let Some(model) = model {
+            self.report
+                .models
+                .entry(model.to_string())
+                .or_default()
+                .tokens += tokens;
+        }
+        if let Some(subagent) = subagent {
+            self.report
+                .subagents
+                .entry(subagent.to_string())
+                .or_default()
+                .tokens += tokens;
+        }
+    }
+
+    fn record_result(&mut self, result: &ResultMessage) {
+        let session_id = &result.session_id;
+
+        // Web searches are only reported per query, on the result
+        if let Some(usage) = &result.usage {
+            let searches = TokenTotals {
+                web_search_requests: u64::from(usage.server_tool_use.web_search_requests),
+                ..TokenTotals::default()
+            };
+            self.add_tokens(session_id, None, None, searches);
+        }
+
+        // Costs are cumulative for the session; apply the increase since the last result
+        let previous = self
+            .session_costs
+            .insert(session_id.clone(), result.total_cost_usd)
+            .unwrap_or(0.0);
+        let delta = (result.total_cost_usd - previous).max(0.0);
+        self.report.total.cost_usd += delta;
+        self.report
+            .sessions
+            .entry(session_id.clone())
+            .or_default()
+            .cost_usd += delta;
+
+        for (model, usage) in &result.model_usage {
+            let key = (session_id.clone(), model.clone());
+            let previous = self.model_costs.insert(key, usage.cost_usd).unwrap_or(0.0);
+            self.report
+                .models
+                .entry(model.clone())
+                .or_default()
+                .cost_usd += (usage.cost_usd - previous).max(0.0);
+        }
+    }
+
+    fn check_budgets(&mut self) -> Vec<BudgetExceeded> {
+        let total = &self.report.total;
+        self.budgets
+            .iter_mut()
+            .filter_map(|budget| budget.check(total))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn assistant(
+        id: &str,
+        model: &str,
+        parent: Option<&str>,
+        usage: serde_json::Value,
+    ) -> ClaudeOutput {
+        serde_json::from_value(json!({
+            "type": "assistant",
+            "message": {"id": id, "role": "assistant", "model": model, "content": [], "usage": usage},
+            "session_id": "s1",
+            "parent_tool_use_id": parent
+        }))
+        .unwrap()
+    }
+
+    fn result(session: &str, cost: f64, model_cost: f64, searches: u32) -> ClaudeOutput {
+        serde_json::from_value(json!({
+            "type": "result", "subtype": "success", "is_error": false,
+            "duration_ms": 1, "duration_api_ms": 1, "num_turns": 1,
+            "session_id": session, "total_cost_usd": cost,
+            "usage": {
+                "input_tokens": 0, "output_tokens": 0,
+                "cache_creation_input_tokens": 0, "cache_read_input_tokens": 0,
+                "server_tool_use": {"web_search_requests": searches}, "service_tier": "standard"
+            },
+            "modelUsage": {"claude-sonnet-4": {"costUSD": model_cost}}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_assistant_usage_deduplicated_by_message_id() {
+        let mut ledger = UsageLedger::new();
+        let usage = json!({
+            "input_tokens": 10, "output_tokens": 5,
+            "cache_read_input_tokens": 100, "cache_creation_input_tokens": 50,
+            "cache_creation": {"ephemeral_5m_input_tokens": 20, "ephemeral_1h_input_tokens": 30}
+        });
+        ledger.record(&assistant("msg_1", "claude-sonnet-4", None, usage.clone()));
+        ledger.record(&assistant("msg_1", "claude-sonnet-4", None, usage));
+        ledger.record(&assistant(
+            "msg_2",
+            "claude-haiku-4",
+            Some("toolu_task"),
+            json!({"input_tokens": 1, "output_tokens": 2}),
+        ));
+
+        let report = ledger.report();
+        assert_eq!(report.total.tokens.input_tokens, 11);
+        assert_eq!(report.total.tokens.ephemeral_1h_input_tokens, 30);
+        assert_eq!(report.models["claude-sonnet-4"].tokens.total_tokens(), 165);
+        assert_eq!(report.subagents["toolu_task"].tokens.output_tokens, 2);
+        assert_eq!(report.sessions["s1"].tokens.output_tokens, 7);
+    }
+
+    #[test]
+    fn test_result_costs_are_cumulative_per_session() {
+        let mut ledger = UsageLedger::new();
+        ledger.record(&result("s1", 0.10, 0.10, 1));
+        ledger.record(&result("s1", 0.25, 0.25, 2));
+        ledger.record(&result("s2", 0.05, 0.05, 0));
+
+        let report = ledger.report();
+        assert!((report.total.cost_usd - 0.30).abs() < 1e-9);
+        assert!((report.sessions["s1"].cost_usd - 0.25).abs() < 1e-9);
+        assert!((report.models["claude-sonnet-4"].cost_usd - 0.30).abs() < 1e-9);
+        assert_eq!(report.total.tokens.web_search_requests, 3);
+    }
+
+    #[test]
+    fn test_budgets_fire_once_with_callback() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut ledger = UsageLedger::new()
+            .budget(Budget::cost_usd(0.2).on_exceeded(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }))
+            .budget(Budget::total_tokens(1_000_000).interrupt());
+
+        assert!(ledger.record(&result("s1", 0.1, 0.1, 0)).is_empty());
+        let exceeded = ledger.record(&result("s1", 0.3, 0.3, 0));
+        assert_eq!(exceeded.len(), 1);
+        assert_eq!(exceeded[0].metric, BudgetMetric::CostUsd);
+        assert!(!exceeded[0].interrupt);
+        assert!(ledger.record(&result("s1", 0.4, 0.4, 0)).is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(ledger.budgets()[0].is_exceeded());
+
+        ledger.reset_budgets();
+        assert_eq!(ledger.record(&result("s1", 0.5, 0.5, 0)).len(), 1);
+    }
+
+    #[test]
+    fn test_report_roundtrip_and_merge() {
+        let mut ledger = UsageLedger::new();
+        ledger.record(&result("s1", 0.1, 0.1, 1));
+        let saved = serde_json::to_string(ledger.report()).unwrap();
+
+        let restored: UsageReport = serde_json::from_str(&saved).unwrap();
+        let mut ledger = UsageLedger::from_report(restored.clone());
+        ledger.record(&result("s2", 0.2, 0.2, 0));
+        assert!((ledger.report().total.cost_usd - 0.3).abs() < 1e-9);
+
+        let mut merged = restored.clone();
+        merged.merge(&restored);
+        assert_eq!(merged.total.tokens.web_search_requests, 2);
+        assert!((merged.sessions["s1"].cost_usd - 0.2).abs() < 1e-9);
+    }
+}
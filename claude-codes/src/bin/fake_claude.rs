@@ -0,0 +1,60 @@
+//! A fake Claude CLI that replays a scripted conversation
+//!
+//! Reads the script path from the `FAKE_CLAUDE_SCRIPT` environment variable and
+//! replays it over stdin/stdout. Command-line arguments are accepted and ignored,
+//! except `--session-id`, which is substituted for `"$session_id"` placeholders.
+//!
+//! See [`claude_codes::testing`] for the script format.
+
+use claude_codes::testing::{MockScript, SCRIPT_ENV_VAR};
+use std::io::{BufReader, Write};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--version") {
+        println!("{} (Fake Claude Code)", env!("CARGO_PKG_VERSION"));
+        return ExitCode::SUCCESS;
+    }
+
+    let session_id = args
+        .iter()
+        .position(|arg| arg == "--session-id")
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+        .unwrap_or_else(|| "00000000-0000-0000-0000-000000000000".to_string());
+
+    let Some(path) = std::env::var_os(SCRIPT_ENV_VAR) else {
+        eprintln!("fake-claude: {} is not set", SCRIPT_ENV_VAR);
+        return ExitCode::from(2);
+    };
+
+    let script = match MockScript::load(&path) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!(
+                "fake-claude: failed to load {}: {}",
+                path.to_string_lossy(),
+                e
+            );
+            return ExitCode::from(2);
+        }
+    };
+
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let stderr = std::io::stderr();
+    match script.run(
+        &session_id,
+        BufReader::new(stdin.lock()),
+        stdout.lock(),
+        stderr.lock(),
+    ) {
+        Ok(code) => ExitCode::from(code.clamp(0, 255) as u8),
+        Err(e) => {
+            let _ = writeln!(std::io::stderr(), "fake-claude: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -394,6 +394,7 @@ pub struct ClaudeCliBuilder {
     add_dir: Vec<PathBuf>,
     ide: bool,
     strict_mcp_config: bool,
+    include_partial_messages: bool,
     session_id: Option<Uuid>,
     oauth_token: Option<String>,
     api_key: Option<String>,
@@ -401,6 +402,8 @@ pub struct ClaudeCliBuilder {
     permission_prompt_tool: Option<String>,
     /// Allow spawning inside another Claude Code session by unsetting CLAUDECODE env var
     allow_recursion: bool,
+    /// Extra environment variables for the child process
+    env: Vec<(String, String)>,
 }
 
 impl Default for ClaudeCliBuilder {
@@ -431,11 +434,13 @@ impl ClaudeCliBuilder {
             add_dir: Vec::new(),
             ide: false,
             strict_mcp_config: false,
+            include_partial_messages: false,
             session_id: None,
             oauth_token: None,
             api_key: None,
             permission_prompt_tool: None,
             allow_recursion: false,
+            env: Vec::new(),
         }
     }
 
@@ -566,12 +571,35 @@ impl ClaudeCliBuilder {
         self
     }
 
+    /// Emit `stream_event` partial message chunks as they arrive
+    ///
+    /// See [`crate::PartialMessageAccumulator`] for rebuilding messages from them.
+    pub fn include_partial_messages(mut self, include: bool) -> Self {
+        self.include_partial_messages = include;
+        self
+    }
+
     /// Set a specific session ID (must be a UUID)
     pub fn session_id(mut self, id: Uuid) -> Self {
         self.session_id = Some(id);
         self
     }
 
+    /// Fix the ID of the session the CLI will run, if it is known before spawning
+    ///
+    /// New sessions get their `--session-id` chosen here rather than in
+    /// `build_args`; resumed sessions keep theirs. `--continue` picks up the
+    /// most recent session, which is only known once the CLI reports it.
+    pub(crate) fn pin_session_id(&mut self) -> Option<Uuid> {
+        if self.continue_conversation {
+            return None;
+        }
+        match &self.resume {
+            Some(resume) => Uuid::parse_str(resume).ok(),
+            None => Some(*self.session_id.get_or_insert_with(Uuid::new_v4)),
+        }
+    }
+
     /// Set OAuth token for authentication (must start with "sk-ant-oat")
     pub fn oauth_token<S: Into<String>>(mut self, token: S) -> Self {
         let token_str = token.into();
@@ -592,6 +620,12 @@ impl ClaudeCliBuilder {
         self
     }
 
+    /// Set an environment variable for the Claude process
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
     /// Enable bidirectional tool permission protocol via stdio
     ///
     /// When enabled, Claude CLI will send permission requests via stdout
@@ -707,6 +741,10 @@ impl ClaudeCliBuilder {
             args.push("--strict-mcp-config".to_string());
         }
 
+        if self.include_partial_messages {
+            args.push("--include-partial-messages".to_string());
+        }
+
         if let Some(ref tool) = self.permission_prompt_tool {
             args.push("--permission-prompt-tool".to_string());
             args.push(tool.clone());
@@ -763,6 +801,8 @@ impl ClaudeCliBuilder {
             cmd.env("ANTHROPIC_API_KEY", key);
         }
 
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+
         let child = cmd.spawn().map_err(Error::Io)?;
 
         Ok(child)
@@ -790,6 +830,8 @@ impl ClaudeCliBuilder {
             cmd.env("ANTHROPIC_API_KEY", key);
         }
 
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+
         cmd
     }
 
@@ -821,6 +863,8 @@ impl ClaudeCliBuilder {
             cmd.env("ANTHROPIC_API_KEY", key);
         }
 
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+
         cmd.spawn()
     }
 }
@@ -842,6 +886,17 @@ mod tests {
         assert!(args.contains(&"--input-format".to_string()));
     }
 
+    #[test]
+    fn test_include_partial_messages() {
+        let args = ClaudeCliBuilder::new().build_args();
+        assert!(!args.contains(&"--include-partial-messages".to_string()));
+
+        let args = ClaudeCliBuilder::new()
+            .include_partial_messages(true)
+            .build_args();
+        assert!(args.contains(&"--include-partial-messages".to_string()));
+    }
+
     #[test]
     fn test_with_prompt() {
         let builder = ClaudeCliBuilder::new().prompt("Hello, Claude!");
@@ -969,6 +1024,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pin_session_id_matches_the_spawned_session() {
+        let mut builder = ClaudeCliBuilder::new();
+        let session = builder.pin_session_id().unwrap();
+        let args = builder.build_args();
+        let position = args.iter().position(|arg| arg == "--session-id").unwrap();
+        assert_eq!(args[position + 1], session.to_string());
+
+        let resumed = Uuid::new_v4();
+        let mut builder = ClaudeCliBuilder::new().resume(Some(resumed.to_string()));
+        assert_eq!(builder.pin_session_id(), Some(resumed));
+
+        let mut builder = ClaudeCliBuilder::new().continue_conversation(true);
+        assert_eq!(builder.pin_session_id(), None);
+    }
+
     #[test]
     fn test_session_id_not_present_with_continue() {
         // When continuing a session, --session-id should NOT be added
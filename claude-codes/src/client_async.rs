@@ -1,57 +1,184 @@
 //! Asynchronous client for Claude communication
 
+use crate::accounting::UsageLedger;
 use crate::cli::ClaudeCliBuilder;
-use crate::error::{Error, Result};
+use crate::error::{Error, ExitReport, Result, STDERR_TAIL_LINES};
+use crate::governor::{GovernorEvent, RateLimitGovernor};
+use crate::hooks::HookRegistry;
 use crate::io::{
-    ClaudeInput, ClaudeOutput, ContentBlock, ControlRequestMessage, ControlResponse,
-    ControlResponseMessage,
+    ClaudeInput, ClaudeOutput, ContentBlock, ControlRequestMessage, ControlRequestPayload,
+    ControlResponse, ControlResponseMessage, ControlResponsePayload, InitializeRequest,
+    PermissionModeName, SetModelRequest, SetPermissionModeRequest,
 };
+use crate::mcp_server::McpServerRegistry;
+use crate::policy::ApprovalPolicy;
 use crate::protocol::Protocol;
+use crate::stderr::{StderrBuffer, StderrPump};
+use crate::transcript::{Direction, TranscriptRecorder, TRANSCRIPT_PROVIDER};
+use crate::transport::{
+    ProcessTransport, Transport, TransportReader, TransportWriter, STDERR_DRAIN_TIMEOUT,
+};
+use codes_core::limits::ReadLimits;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufReader as AsyncBufReader};
-use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout};
+use tokio::process::Child;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 /// Asynchronous client for communicating with Claude
+///
+/// The client speaks the JSON Lines protocol over a [`Transport`]. By default
+/// that is a spawned `claude` process ([`ProcessTransport`]), but any
+/// [`AsyncRead`](tokio::io::AsyncRead)/[`AsyncWrite`](tokio::io::AsyncWrite)
+/// pair can be used via [`AsyncClient::from_transport`].
 pub struct AsyncClient {
-    child: Child,
-    stdin: ChildStdin,
-    stdout: BufReader<ChildStdout>,
-    stderr: Option<BufReader<ChildStderr>>,
+    transport: Box<dyn Transport>,
+    /// Write side, shared with every [`ClientWriter`]
+    outbound: Arc<Outbound>,
+    reader: BufReader<TransportReader>,
     session_uuid: Option<Uuid>,
     /// Whether tool approval protocol has been initialized
     tool_approval_enabled: bool,
+    /// Outputs that arrived while waiting for a control response
+    buffered: VecDeque<ClaudeOutput>,
+    /// Hook callbacks answered automatically by `receive()`
+    hooks: Option<HookRegistry>,
+    /// In-process MCP servers answered automatically by `receive()`
+    mcp_servers: Option<McpServerRegistry>,
+    /// Tool approval policy applied to `can_use_tool` requests by `receive()`
+    approval_policy: Option<ApprovalPolicy>,
+    /// Wire-level transcript of every line sent and received
+    recorder: Option<TranscriptRecorder>,
+    /// Usage ledger fed every output returned by `receive()`
+    usage_ledger: Option<UsageLedger>,
+    /// Rate-limit governor fed every output returned by `receive()`
+    rate_limit_governor: Option<RateLimitGovernor>,
+    /// Recent stderr lines, once a pump has been attached
+    stderr: Option<StderrBuffer>,
+    /// Longest wait for the next output in `receive()`
+    idle_timeout: Option<Duration>,
+    /// Longest wait for the response to a control request
+    request_timeout: Option<Duration>,
+    /// Bytes of a partially read line, kept across reads that time out
+    line_buf: Vec<u8>,
 }
 
 /// Buffer size for reading Claude's stdout (10MB).
 const STDOUT_BUFFER_SIZE: usize = 10 * 1024 * 1024;
 
+/// How long to wait for the process to exit once its stdout has closed
+const EXIT_WAIT: Duration = Duration::from_secs(2);
+
+/// How long a cancelled turn may take to wind down after its interrupt
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The write side of a client, shared with its [`ClientWriter`]s
+struct Outbound {
+    writer: tokio::sync::Mutex<TransportWriter>,
+    /// Control requests sent by a `ClientWriter`, answered from the read side
+    pending: Mutex<HashMap<String, oneshot::Sender<ControlResponsePayload>>>,
+    /// Set once nothing will read responses for `pending` anymore
+    closed: AtomicBool,
+    /// Whether a user message was sent and its result has not arrived yet
+    turn_active: AtomicBool,
+}
+
+impl Outbound {
+    fn new(writer: TransportWriter) -> Self {
+        Self {
+            writer: tokio::sync::Mutex::new(writer),
+            pending: Mutex::default(),
+            closed: AtomicBool::new(false),
+            turn_active: AtomicBool::new(false),
+        }
+    }
+
+    async fn write_line(&self, json_line: &str) -> Result<()> {
+        let mut writer = self.writer.lock().await;
+        writer
+            .write_all(json_line.as_bytes())
+            .await
+            .map_err(Error::Io)?;
+        writer.flush().await.map_err(Error::Io)
+    }
+
+    fn note_sent(&self, input: &ClaudeInput) {
+        if matches!(input, ClaudeInput::User(_)) {
+            self.turn_active.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn lock_pending(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<String, oneshot::Sender<ControlResponsePayload>>> {
+        // A panic while holding the lock cannot leave the map inconsistent
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Fail every waiting control request with `ConnectionClosed`
+    fn fail_pending(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.lock_pending().clear();
+    }
+}
+
+fn record(recorder: &Option<TranscriptRecorder>, direction: Direction, line: &str) {
+    if let Some(recorder) = recorder {
+        if let Err(e) = recorder.record(direction, line) {
+            warn!("[TRANSCRIPT] Failed to record line: {}", e);
+        }
+    }
+}
+
+/// Turn a control response into the result of its request
+fn control_result(subtype: &str, response: ControlResponsePayload) -> Result<Option<Value>> {
+    match response {
+        ControlResponsePayload::Success { response, .. } => Ok(response),
+        ControlResponsePayload::Error { error, .. } => Err(Error::Protocol(format!(
+            "Control request {} failed: {}",
+            subtype, error
+        ))),
+    }
+}
+
 impl AsyncClient {
     /// Create a new async client from a tokio Child process
-    pub fn new(mut child: Child) -> Result<Self> {
-        let stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| Error::Io(std::io::Error::other("Failed to get stdin handle")))?;
-
-        let stdout = BufReader::with_capacity(
-            STDOUT_BUFFER_SIZE,
-            child
-                .stdout
-                .take()
-                .ok_or_else(|| Error::Io(std::io::Error::other("Failed to get stdout handle")))?,
-        );
+    pub fn new(child: Child) -> Result<Self> {
+        Self::from_transport(ProcessTransport::new(child))
+    }
 
-        let stderr = child.stderr.take().map(BufReader::new);
+    /// Create a new async client over an arbitrary [`Transport`]
+    ///
+    /// Use [`StreamTransport`](crate::transport::StreamTransport) to run the
+    /// protocol over a socket, pipe or other byte stream.
+    pub fn from_transport<T: Transport + 'static>(mut transport: T) -> Result<Self> {
+        let (reader, writer) = transport.connect().map_err(Error::Io)?;
 
         Ok(Self {
-            child,
-            stdin,
-            stdout,
-            stderr,
+            transport: Box::new(transport),
+            outbound: Arc::new(Outbound::new(writer)),
+            reader: BufReader::with_capacity(STDOUT_BUFFER_SIZE, reader),
             session_uuid: None,
             tool_approval_enabled: false,
+            buffered: VecDeque::new(),
+            hooks: None,
+            mcp_servers: None,
+            approval_policy: None,
+            recorder: None,
+            usage_ledger: None,
+            rate_limit_governor: None,
+            stderr: None,
+            idle_timeout: None,
+            request_timeout: None,
+            line_buf: Vec::new(),
         })
     }
 
@@ -68,17 +195,27 @@ impl AsyncClient {
 
     /// Create a client with a specific model
     pub async fn with_model(model: &str) -> Result<Self> {
-        let child = ClaudeCliBuilder::new().model(model).spawn().await?;
+        let mut builder = ClaudeCliBuilder::new().model(model);
+        let session_uuid = builder.pin_session_id();
+        let child = builder.spawn().await?;
 
         info!("Started Claude process with model: {}", model);
-        Self::new(child)
+        let mut client = Self::new(child)?;
+        client.session_uuid = session_uuid;
+        Ok(client)
     }
 
     /// Create a client from a custom builder
-    pub async fn from_builder(builder: ClaudeCliBuilder) -> Result<Self> {
+    ///
+    /// The session UUID is known from the start unless the builder continues
+    /// the most recent conversation
+    pub async fn from_builder(mut builder: ClaudeCliBuilder) -> Result<Self> {
+        let session_uuid = builder.pin_session_id();
         let child = builder.spawn().await?;
         info!("Started Claude process from custom builder");
-        Self::new(child)
+        let mut client = Self::new(child)?;
+        client.session_uuid = session_uuid;
+        Ok(client)
     }
 
     /// Resume a previous session by UUID
@@ -127,15 +264,43 @@ impl AsyncClient {
         text: &str,
         session_id: Uuid,
     ) -> Result<Vec<ClaudeOutput>> {
+        self.wait_for_rate_limit().await;
+
         // Send the query
         let input = ClaudeInput::user_message(text, session_id);
         self.send(&input).await?;
 
-        // Collect responses until we get a Result message
+        self.collect_turn(ReadLimits::idle(self.idle_timeout)).await
+    }
+
+    /// Send a query and collect all responses, stopping early if `cancel` fires
+    ///
+    /// On cancellation the turn is interrupted and drained, so the client is
+    /// ready for the next query, and [`Error::Cancelled`] is returned.
+    pub async fn query_cancellable(
+        &mut self,
+        text: &str,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<ClaudeOutput>> {
+        self.wait_for_rate_limit().await;
+
+        let input = ClaudeInput::user_message(text, Uuid::new_v4());
+        self.send(&input).await?;
+
+        self.collect_turn(ReadLimits {
+            idle: self.idle_timeout,
+            cancel: Some(cancel.clone()),
+            ..ReadLimits::default()
+        })
+        .await
+    }
+
+    /// Collect responses until a Result message
+    async fn collect_turn(&mut self, limits: ReadLimits) -> Result<Vec<ClaudeOutput>> {
         let mut responses = Vec::new();
 
         loop {
-            let output = self.receive().await?;
+            let output = self.receive_within(limits.clone()).await?;
             let is_result = matches!(&output, ClaudeOutput::Result(_));
             responses.push(output);
 
@@ -147,6 +312,32 @@ impl AsyncClient {
         Ok(responses)
     }
 
+    /// Send a query, resending it while the turn fails with a retryable error
+    ///
+    /// Without a [rate-limit governor](Self::set_rate_limit_governor) this is
+    /// the same as [`query`](Self::query). With one, a turn that fails with
+    /// an overloaded or rate-limit error is sent again after the governor's
+    /// backoff, up to its [`max_retries`](crate::governor::BackoffPolicy::max_retries).
+    /// Returns the outputs of the last attempt.
+    pub async fn query_with_retry(&mut self, text: &str) -> Result<Vec<ClaudeOutput>> {
+        let session_id = Uuid::new_v4();
+        if let Some(governor) = self.rate_limit_governor.as_mut() {
+            governor.clear_retry();
+        }
+        loop {
+            let responses = self.query_with_session(text, session_id).await?;
+            match &self.rate_limit_governor {
+                Some(governor) if governor.should_retry() => {
+                    info!(
+                        "[RATE_LIMIT] Retrying turn (attempt {})",
+                        governor.attempts()
+                    );
+                }
+                _ => return Ok(responses),
+            }
+        }
+    }
+
     /// Send a query and return an async iterator over responses
     /// Returns a stream that yields ClaudeOutput until Result message is received
     pub async fn query_stream(&mut self, text: &str) -> Result<ResponseStream<'_>> {
@@ -160,14 +351,18 @@ impl AsyncClient {
         text: &str,
         session_id: Uuid,
     ) -> Result<ResponseStream<'_>> {
+        self.wait_for_rate_limit().await;
+
         // Send the query first
         let input = ClaudeInput::user_message(text, session_id);
         self.send(&input).await?;
 
         // Return a stream that will read responses
+        let limits = ReadLimits::idle(self.idle_timeout);
         Ok(ResponseStream {
             client: self,
             finished: false,
+            limits,
         })
     }
 
@@ -175,14 +370,100 @@ impl AsyncClient {
     pub async fn send(&mut self, input: &ClaudeInput) -> Result<()> {
         let json_line = Protocol::serialize(input)?;
         debug!("[OUTGOING] Sending JSON to Claude: {}", json_line.trim());
+        self.write_line(&json_line).await?;
+        self.outbound.note_sent(input);
+        Ok(())
+    }
 
-        self.stdin
-            .write_all(json_line.as_bytes())
-            .await
-            .map_err(Error::Io)?;
+    /// Get a handle for sending from other tasks while this client receives
+    ///
+    /// The handle is cheap to clone and works concurrently with
+    /// [`receive`](Self::receive): answer a `can_use_tool` request from
+    /// another task, push a follow-up message while a turn streams, or
+    /// interrupt it. Responses to the handle's control requests are picked up
+    /// by whatever is reading from this client, so keep receiving (or use
+    /// [`spawn_reader`](Self::spawn_reader)) while it waits.
+    ///
+    /// The handle uses the transcript recorder and request timeout in effect
+    /// when it is created.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use claude_codes::{AsyncClient, ClaudeInput, ClaudeOutput, ControlRequestPayload};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut client = AsyncClient::with_defaults().await?;
+    /// client.enable_tool_approval().await?;
+    /// let writer = client.writer();
+    /// let (mut outputs, _reader) = client.spawn_reader(64);
+    ///
+    /// writer.send(&ClaudeInput::user_message("List the files", uuid::Uuid::new_v4())).await?;
+    /// while let Some(output) = outputs.recv().await {
+    ///     match output? {
+    ///         ClaudeOutput::ControlRequest(req) => {
+    ///             if let ControlRequestPayload::CanUseTool(perm) = &req.request {
+    ///                 let writer = writer.clone();
+    ///                 let response = perm.allow(&req.request_id);
+    ///                 tokio::spawn(async move { writer.send_control_response(response).await });
+    ///             }
+    ///         }
+    ///         ClaudeOutput::Result(_) => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn writer(&self) -> ClientWriter {
+        ClientWriter {
+            outbound: self.outbound.clone(),
+            recorder: self.recorder.clone(),
+            request_timeout: self.request_timeout,
+        }
+    }
 
-        self.stdin.flush().await.map_err(Error::Io)?;
-        Ok(())
+    /// Receive on a background task, fanning outputs out on a channel
+    ///
+    /// Pair with [`writer`](Self::writer) for an actor-style session: one
+    /// task consumes outputs from a channel holding up to `capacity` of them,
+    /// while any number of writers send inputs and control responses. Hooks,
+    /// MCP servers and the approval policy are still answered by the reader
+    /// task.
+    ///
+    /// The task stops once the stream ends, or at the next output after the
+    /// receiver is dropped, and hands the client back through its
+    /// [`JoinHandle`], e.g. to [`close`](Self::close) it. Must be called from
+    /// within a Tokio runtime.
+    pub fn spawn_reader(
+        mut self,
+        capacity: usize,
+    ) -> (mpsc::Receiver<Result<ClaudeOutput>>, JoinHandle<Self>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        let handle = tokio::spawn(async move {
+            loop {
+                let output = self.receive().await;
+                let ended = matches!(
+                    output.as_ref().map_err(Error::root),
+                    Err(Error::ConnectionClosed | Error::ProcessCrashed(_) | Error::Io(_))
+                );
+                if tx.send(output).await.is_err() {
+                    debug!("[CLIENT] Output receiver dropped, stopping reader task");
+                    break;
+                }
+                if ended {
+                    break;
+                }
+            }
+            self
+        });
+        (rx, handle)
+    }
+
+    /// Write one serialized line to the transport, recording it if enabled
+    async fn write_line(&mut self, json_line: &str) -> Result<()> {
+        record(&self.recorder, Direction::Sent, json_line);
+        self.outbound.write_line(json_line).await
     }
 
     /// Receive a single response from Claude.
@@ -195,36 +476,247 @@ impl AsyncClient {
     ///
     /// In a `tokio::select!` loop with other async operations, ensure `receive()`
     /// is given priority or called frequently. For high-throughput scenarios,
-    /// consider spawning a dedicated task to drain stdout into an unbounded channel.
+    /// use [`spawn_reader`](Self::spawn_reader) to drain stdout on a dedicated task.
     ///
     /// # Returns
     ///
     /// - `Ok(ClaudeOutput)` - A parsed message from Claude
-    /// - `Err(Error::ConnectionClosed)` - Claude process has exited
+    /// - `Err(Error::ConnectionClosed)` - Claude process has exited normally
+    /// - `Err(Error::ProcessCrashed)` - Claude process exited with an error or signal
     /// - `Err(Error::Deserialization)` - Failed to parse the message
+    /// - `Err(Error::Timeout)` - Nothing arrived within the [idle timeout](Self::set_idle_timeout)
+    /// - `Err(Error::WithStderr)` - Any other failure, with the last lines from
+    ///   [captured stderr](Self::capture_stderr)
+    ///
+    /// Control requests the client can answer on its own (such as hook
+    /// callbacks registered with [`enable_hooks`](Self::enable_hooks)) are
+    /// answered here and never returned.
     pub async fn receive(&mut self) -> Result<ClaudeOutput> {
-        let mut line = String::new();
+        self.receive_within(ReadLimits::idle(self.idle_timeout))
+            .await
+    }
 
+    /// Receive the next output, failing with [`Error::Timeout`] if nothing arrives within `timeout`
+    ///
+    /// Overrides the client's [idle timeout](Self::set_idle_timeout) for this
+    /// call. A timeout leaves the turn running; receive again to keep waiting.
+    pub async fn receive_timeout(&mut self, timeout: Duration) -> Result<ClaudeOutput> {
+        self.receive_within(ReadLimits::idle(Some(timeout))).await
+    }
+
+    /// Receive the next output, stopping early if `cancel` fires
+    ///
+    /// On cancellation the turn in progress is interrupted and drained up to
+    /// its result, so the client is ready for the next query, and
+    /// [`Error::Cancelled`] is returned.
+    pub async fn receive_cancellable(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<ClaudeOutput> {
+        self.receive_within(ReadLimits {
+            idle: self.idle_timeout,
+            cancel: Some(cancel.clone()),
+            ..ReadLimits::default()
+        })
+        .await
+    }
+
+    /// Receive within `limits`, winding down the turn if cancelled
+    async fn receive_within(&mut self, limits: ReadLimits) -> Result<ClaudeOutput> {
+        match self.receive_limited(&limits).await {
+            Err(Error::Cancelled) => {
+                self.cancel_turn().await;
+                Err(Error::Cancelled)
+            }
+            other => other,
+        }
+    }
+
+    async fn receive_limited(&mut self, limits: &ReadLimits) -> Result<ClaudeOutput> {
         loop {
-            line.clear();
-            let bytes_read = self.stdout.read_line(&mut line).await.map_err(Error::Io)?;
+            if limits.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let output = match self.buffered.pop_front() {
+                Some(output) => output,
+                None => self.read_output(limits).await?,
+            };
+            if let Some(response) = self.auto_response(&output) {
+                self.send_control_response(response).await?;
+                continue;
+            }
+            if output.as_result().is_some() {
+                self.outbound.turn_active.store(false, Ordering::SeqCst);
+            }
+            self.account(&output).await?;
+            self.govern(&output);
+            return Ok(output);
+        }
+    }
+
+    /// Interrupt the turn in progress and drain it up to its result
+    async fn cancel_turn(&mut self) {
+        if !self.outbound.turn_active.load(Ordering::SeqCst) {
+            return;
+        }
+        info!("[CLIENT] Cancelled, interrupting current turn");
+        let limits = ReadLimits::within(Some(CANCEL_TIMEOUT));
+        if let Err(e) = self
+            .control_request(ControlRequestPayload::Interrupt, &limits)
+            .await
+        {
+            warn!("[CLIENT] Interrupt after cancellation failed: {}", e);
+            return;
+        }
+        while self.outbound.turn_active.load(Ordering::SeqCst) {
+            if let Err(e) = self.receive_limited(&limits).await {
+                warn!("[CLIENT] Failed to drain cancelled turn: {}", e);
+                return;
+            }
+        }
+    }
+
+    /// Feed an output to the usage ledger, interrupting the turn if a budget asks to
+    async fn account(&mut self, output: &ClaudeOutput) -> Result<()> {
+        let Some(ledger) = self.usage_ledger.as_mut() else {
+            return Ok(());
+        };
+        let exceeded = ledger.record(output);
+        for budget in &exceeded {
+            warn!("[ACCOUNTING] {}", budget);
+        }
+        // A result means the turn is already over
+        if output.as_result().is_none() && exceeded.iter().any(|b| b.interrupt) {
+            match self.interrupt().await {
+                Err(Error::Protocol(message)) => {
+                    warn!("[ACCOUNTING] Interrupt was rejected: {}", message)
+                }
+                other => other?,
+            }
+        }
+        Ok(())
+    }
 
-            if bytes_read == 0 {
-                return Err(Error::ConnectionClosed);
+    /// Feed an output to the rate-limit governor
+    fn govern(&mut self, output: &ClaudeOutput) {
+        let Some(governor) = self.rate_limit_governor.as_mut() else {
+            return;
+        };
+        for event in governor.observe(output) {
+            match event {
+                GovernorEvent::Recovered { .. } | GovernorEvent::OverageEnded { .. } => {
+                    info!("[RATE_LIMIT] {}", event)
+                }
+                _ => warn!("[RATE_LIMIT] {}", event),
             }
+        }
+    }
 
+    /// Wait until the rate-limit governor allows new queries
+    ///
+    /// Returns immediately without a governor or when nothing is paused.
+    /// Called by the `query*` methods before sending.
+    pub async fn wait_for_rate_limit(&mut self) {
+        let Some(wait) = self
+            .rate_limit_governor
+            .as_ref()
+            .and_then(RateLimitGovernor::wait_time)
+        else {
+            return;
+        };
+        info!("[RATE_LIMIT] Pausing {:?} before the next query", wait);
+        tokio::time::sleep(wait).await;
+    }
+
+    /// Build the response for a control request the client handles itself
+    fn auto_response(&mut self, output: &ClaudeOutput) -> Option<ControlResponse> {
+        let ClaudeOutput::ControlRequest(request) = output else {
+            return None;
+        };
+        match &request.request {
+            ControlRequestPayload::HookCallback(callback) => {
+                let hooks = self.hooks.as_ref()?;
+                debug!(
+                    "[HOOKS] Dispatching {} for request {}",
+                    callback.callback_id, request.request_id
+                );
+                match hooks.dispatch(callback)? {
+                    Ok(output) => Some(crate::hooks::output_response(&request.request_id, &output)),
+                    Err(error) => {
+                        warn!("[HOOKS] {} (request {})", error, request.request_id);
+                        Some(error.to_response(&request.request_id))
+                    }
+                }
+            }
+            ControlRequestPayload::CanUseTool(tool_request) => {
+                let policy = self.approval_policy.as_mut()?;
+                let response = policy.decide(&request.request_id, tool_request);
+                if response.is_some() {
+                    debug!(
+                        "[TOOL_APPROVAL] Policy decided {} for request {}",
+                        tool_request.tool_name, request.request_id
+                    );
+                }
+                response
+            }
+            ControlRequestPayload::McpMessage(message) => {
+                let servers = self.mcp_servers.as_ref()?;
+                debug!(
+                    "[MCP] Answering message for server {} (request {})",
+                    message.server_name, request.request_id
+                );
+                servers.respond(&request.request_id, message)
+            }
+            _ => None,
+        }
+    }
+
+    /// Read and parse the next output line, attaching captured stderr to failures
+    async fn read_output(&mut self, limits: &ReadLimits) -> Result<ClaudeOutput> {
+        let result = self.read_output_line(limits).await;
+        result.map_err(|error| match &self.stderr {
+            Some(buffer) => error.with_stderr(buffer.tail(STDERR_TAIL_LINES)),
+            None => error,
+        })
+    }
+
+    /// Read and parse the next output line from the transport
+    async fn read_output_line(&mut self, limits: &ReadLimits) -> Result<ClaudeOutput> {
+        loop {
+            // `read_until` keeps partial lines in `line_buf` if the wait is cut short
+            let bytes_read = limits
+                .guard(self.reader.read_until(b'\n', &mut self.line_buf))
+                .await?;
+
+            if bytes_read == 0 && self.line_buf.is_empty() {
+                return Err(self.closed_error().await);
+            }
+
+            let line = String::from_utf8(std::mem::take(&mut self.line_buf))
+                .map_err(|e| Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
             let trimmed = line.trim();
             if trimmed.is_empty() {
                 continue;
             }
 
             debug!("[INCOMING] Received JSON from Claude: {}", trimmed);
+            record(&self.recorder, Direction::Received, trimmed);
 
             // Use the parse_json_tolerant method which handles ANSI escape codes
             match ClaudeOutput::parse_json_tolerant(trimmed) {
                 Ok(output) => {
                     debug!("[INCOMING] Parsed output type: {}", output.message_type());
 
+                    if let ClaudeOutput::ControlResponse(resp) = &output {
+                        let request_id = resp.response.request_id();
+                        let waiter = self.outbound.lock_pending().remove(request_id);
+                        if let Some(waiter) = waiter {
+                            debug!("[CLIENT] Forwarding control response for {}", request_id);
+                            let _ = waiter.send(resp.response.clone());
+                            continue;
+                        }
+                    }
+
                     // Capture UUID from first response if not already set
                     if self.session_uuid.is_none() {
                         if let ClaudeOutput::Assistant(ref msg) = output {
@@ -258,28 +750,152 @@ impl AsyncClient {
 
     /// Check if the Claude process is still running
     pub fn is_alive(&mut self) -> bool {
-        self.child.try_wait().ok().flatten().is_none()
+        self.transport.is_alive()
     }
 
-    /// Gracefully shutdown the client
+    /// Tell a crash from a normal end of stream once stdout has closed
+    async fn closed_error(&mut self) -> Error {
+        self.outbound.fail_pending();
+        match self.transport.exit_report(EXIT_WAIT).await {
+            Some(mut report) if !report.success() => {
+                self.attach_stderr(&mut report).await;
+                error!("Claude process crashed: {}", report);
+                Error::ProcessCrashed(report)
+            }
+            _ => Error::ConnectionClosed,
+        }
+    }
+
+    /// Fill an exit report's stderr tail from the pump, if one is attached
+    async fn attach_stderr(&mut self, report: &mut ExitReport) {
+        let Some(buffer) = &self.stderr else {
+            return;
+        };
+        if report.stderr_tail.is_empty() {
+            // The pipe closes with the process; give the pump a moment to catch up
+            let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, buffer.closed()).await;
+            report.stderr_tail = buffer.tail(STDERR_TAIL_LINES);
+        }
+    }
+
+    /// Close stdin and wait for the CLI to exit, killing it after `grace`
+    ///
+    /// Closing stdin lets the CLI finish up and flush its session file before
+    /// exiting. Returns how the process exited, or `None` for transports that
+    /// cannot tell (such as [`StreamTransport`](crate::transport::StreamTransport)).
+    pub async fn close(mut self, grace: Duration) -> Result<Option<ExitReport>> {
+        info!("Closing Claude session...");
+        {
+            let mut writer = self.outbound.writer.lock().await;
+            if let Err(e) = writer.shutdown().await {
+                debug!("Failed to shut down stdin: {}", e);
+            }
+            // Dropping the writer closes the CLI's stdin, even if writer handles remain
+            *writer = Box::new(tokio::io::sink());
+        }
+        let mut report = self.transport.close(grace).await.map_err(Error::Io)?;
+        if let Some(report) = &mut report {
+            self.attach_stderr(report).await;
+            info!("Claude process exited: {}", report);
+        }
+        Ok(report)
+    }
+
+    /// Kill the Claude process and wait for it to exit
+    ///
+    /// Use [`close`](Self::close) to let the CLI exit on its own first.
     pub async fn shutdown(mut self) -> Result<()> {
         info!("Shutting down Claude process...");
-        self.child.kill().await.map_err(Error::Io)?;
+        self.transport.shutdown().await.map_err(Error::Io)?;
         Ok(())
     }
 
-    /// Get the process ID
+    /// Get the process ID, if the transport is a local process
     pub fn pid(&self) -> Option<u32> {
-        self.child.id()
+        self.transport.pid()
     }
 
     /// Take the stderr reader (can only be called once)
-    pub fn take_stderr(&mut self) -> Option<BufReader<ChildStderr>> {
-        self.stderr.take()
+    ///
+    /// The CLI blocks once the pipe fills up, so the caller must keep reading
+    /// it. Use [`capture_stderr`](Self::capture_stderr) to have it drained in
+    /// the background instead.
+    pub fn take_stderr(&mut self) -> Option<BufReader<TransportReader>> {
+        self.transport.take_stderr().map(BufReader::new)
+    }
+
+    /// Drain stderr on a background task, keeping recent lines
+    ///
+    /// Crash reports returned by [`receive`](Self::receive) and
+    /// [`close`](Self::close) then take their stderr tail from the buffer.
+    /// Returns `None` if the transport has no stderr or it was already taken.
+    /// See [`crate::stderr`].
+    pub fn capture_stderr(&mut self, pump: StderrPump) -> Option<StderrBuffer> {
+        let buffer = pump.spawn(self.transport.take_stderr()?);
+        self.stderr = Some(buffer.clone());
+        Some(buffer)
+    }
+
+    /// The captured stderr buffer, if [`capture_stderr`](Self::capture_stderr) was called
+    pub fn stderr(&self) -> Option<&StderrBuffer> {
+        self.stderr.as_ref()
+    }
+
+    /// Fail [`receive`](Self::receive) with [`Error::Timeout`] if no output arrives within `timeout`
+    ///
+    /// Also applies to the `query*` methods and response streams. The timer
+    /// restarts with every output, so long turns are fine as long as the CLI
+    /// keeps talking. A timeout leaves the turn running; use
+    /// [`interrupt`](Self::interrupt) or [`close`](Self::close) to stop a
+    /// stuck CLI. `None` (the default) waits forever.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// The idle timeout for [`receive`](Self::receive)
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Fail control requests with [`Error::Timeout`] if the CLI does not answer within `timeout`
+    ///
+    /// Covers [`send_control_request`](Self::send_control_request) and
+    /// everything built on it, including the initialization handshake of
+    /// [`enable_tool_approval`](Self::enable_tool_approval). `None` (the
+    /// default) waits forever.
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
+    /// The timeout for control requests
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Record every line sent and received from now on
+    ///
+    /// Replaces any recorder already attached. See [`crate::transcript`].
+    pub fn record_transcript(&mut self, recorder: TranscriptRecorder) {
+        if let Err(e) = recorder.start(TRANSCRIPT_PROVIDER, env!("CARGO_PKG_VERSION")) {
+            warn!("[TRANSCRIPT] Failed to write transcript header: {}", e);
+        }
+        self.recorder = Some(recorder);
+    }
+
+    /// Stop recording and return the recorder, if one was attached
+    pub fn stop_recording(&mut self) -> Option<TranscriptRecorder> {
+        let recorder = self.recorder.take();
+        if let Some(recorder) = &recorder {
+            if let Err(e) = recorder.flush() {
+                warn!("[TRANSCRIPT] Failed to flush transcript: {}", e);
+            }
+        }
+        recorder
     }
 
     /// Get the session UUID if available
-    /// Returns an error if no response has been received yet
+    /// Returns an error if the client was not spawned with a known session and
+    /// no response has been received yet
     pub fn session_uuid(&self) -> Result<Uuid> {
         self.session_uuid.ok_or(Error::SessionNotInitialized)
     }
@@ -380,64 +996,228 @@ impl AsyncClient {
             return Ok(());
         }
 
+        self.initialize(None).await
+    }
+
+    /// Register hook callbacks and perform the initialization handshake.
+    ///
+    /// The registry's configuration is sent in the `initialize` request. From
+    /// then on, `hook_callback` control requests are answered automatically
+    /// by [`receive`](Self::receive) using the registered closures. This also
+    /// enables the tool approval protocol, so `can_use_tool` requests are
+    /// still delivered to the caller.
+    ///
+    /// Hooks can only be registered once, before any other initialization;
+    /// otherwise [`Error::InvalidState`] is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use claude_codes::{AsyncClient, ClaudeCliBuilder, HookOutput, HookRegistry};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let child = ClaudeCliBuilder::new()
+    ///     .permission_prompt_tool("stdio")
+    ///     .spawn()
+    ///     .await?;
+    ///
+    /// let mut client = AsyncClient::new(child)?;
+    /// client
+    ///     .enable_hooks(HookRegistry::new().pre_tool_use(Some("Bash"), |input| {
+    ///         if input.tool_input["command"].as_str() == Some("rm -rf /") {
+    ///             HookOutput::deny_tool("Refusing to wipe the disk")
+    ///         } else {
+    ///             HookOutput::proceed()
+    ///         }
+    ///     }))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn enable_hooks(&mut self, hooks: HookRegistry) -> Result<()> {
+        if self.tool_approval_enabled {
+            return Err(Error::InvalidState(
+                "Hooks must be registered before the control protocol is initialized".to_string(),
+            ));
+        }
+
+        let config = hooks.config();
+        self.hooks = Some(hooks);
+        if let Err(e) = self.initialize(Some(config)).await {
+            self.hooks = None;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Register in-process MCP servers.
+    ///
+    /// `mcp_message` control requests addressed to these servers are answered
+    /// automatically by [`receive`](Self::receive). The CLI must know about the
+    /// servers, either through `--mcp-config` (see
+    /// [`McpServerRegistry::mcp_config_json`]) or by registering them before
+    /// [`enable_tool_approval`](Self::enable_tool_approval) /
+    /// [`enable_hooks`](Self::enable_hooks), which advertise them in the
+    /// `initialize` request.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use claude_codes::{AsyncClient, ClaudeCliBuilder, McpServerRegistry, McpToolResult, SdkMcpServer};
+    /// use serde_json::json;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let servers = McpServerRegistry::new().server(
+    ///     SdkMcpServer::new("clock", "1.0.0").tool_raw(
+    ///         "now",
+    ///         "Current UNIX time",
+    ///         json!({"type": "object"}),
+    ///         |_| Ok(McpToolResult::text("1700000000")),
+    ///     ),
+    /// );
+    ///
+    /// let child = ClaudeCliBuilder::new()
+    ///     .mcp_config([servers.mcp_config_json()])
+    ///     .allowed_tools(["mcp__clock__now"])
+    ///     .spawn()
+    ///     .await?;
+    ///
+    /// let mut client = AsyncClient::new(child)?;
+    /// client.register_mcp_servers(servers);
+    /// let responses = client.query("What time is it?").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_mcp_servers(&mut self, servers: McpServerRegistry) {
+        self.mcp_servers = Some(servers);
+    }
+
+    /// The in-process MCP servers registered with
+    /// [`register_mcp_servers`](Self::register_mcp_servers)
+    pub fn mcp_servers(&self) -> Option<&McpServerRegistry> {
+        self.mcp_servers.as_ref()
+    }
+
+    /// Send an `initialize` request and wait for the handshake to complete
+    async fn initialize(&mut self, hooks: Option<Value>) -> Result<()> {
         let request_id = format!("init-{}", Uuid::new_v4());
-        let init_request = ControlRequestMessage::initialize(&request_id);
+        let sdk_mcp_servers = self
+            .mcp_servers
+            .as_ref()
+            .filter(|servers| !servers.is_empty())
+            .map(McpServerRegistry::names);
+        let init_request = ControlRequestMessage::new(
+            &request_id,
+            ControlRequestPayload::Initialize(InitializeRequest {
+                hooks,
+                sdk_mcp_servers,
+            }),
+        );
 
         debug!("[TOOL_APPROVAL] Sending initialization handshake");
-        let json_line = Protocol::serialize(&init_request)?;
-        self.stdin
-            .write_all(json_line.as_bytes())
-            .await
-            .map_err(Error::Io)?;
-        self.stdin.flush().await.map_err(Error::Io)?;
+        self.write_control_request(&init_request).await?;
+
+        let limits = ReadLimits::within(self.request_timeout);
+        match self.await_control_response(&request_id, &limits).await? {
+            ControlResponsePayload::Success { .. } => {
+                debug!("[TOOL_APPROVAL] Initialization successful");
+                self.tool_approval_enabled = true;
+                Ok(())
+            }
+            ControlResponsePayload::Error { error, .. } => Err(Error::Protocol(format!(
+                "Tool approval initialization failed: {}",
+                error
+            ))),
+        }
+    }
 
-        // Wait for the initialization response
-        loop {
-            let mut line = String::new();
-            let bytes_read = self.stdout.read_line(&mut line).await.map_err(Error::Io)?;
+    /// Send a control request to the CLI and wait for its response.
+    ///
+    /// Outputs that arrive before the matching [`ControlResponse`] (for example
+    /// assistant messages from a turn in progress, or `can_use_tool` requests)
+    /// are buffered and returned by later calls to [`receive`](Self::receive).
+    ///
+    /// Returns the `response` body of a successful reply, or
+    /// [`Error::Protocol`] if the CLI answered with an error. Fails with
+    /// [`Error::Timeout`] if no reply arrives within the
+    /// [request timeout](Self::set_request_timeout).
+    pub async fn send_control_request(
+        &mut self,
+        request: ControlRequestPayload,
+    ) -> Result<Option<Value>> {
+        let limits = ReadLimits::within(self.request_timeout);
+        self.control_request(request, &limits).await
+    }
 
-            if bytes_read == 0 {
-                return Err(Error::ConnectionClosed);
-            }
+    async fn control_request(
+        &mut self,
+        request: ControlRequestPayload,
+        limits: &ReadLimits,
+    ) -> Result<Option<Value>> {
+        let subtype = request.subtype();
+        let request_id = format!("{}-{}", subtype, Uuid::new_v4());
+        let message = ControlRequestMessage::new(&request_id, request);
 
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
+        self.write_control_request(&message).await?;
 
-            debug!("[TOOL_APPROVAL] Received: {}", trimmed);
+        let response = self.await_control_response(&request_id, limits).await?;
+        control_result(subtype, response)
+    }
 
-            // Try to parse as ClaudeOutput
-            match ClaudeOutput::parse_json_tolerant(trimmed) {
-                Ok(ClaudeOutput::ControlResponse(resp)) => {
-                    use crate::io::ControlResponsePayload;
-                    match &resp.response {
-                        ControlResponsePayload::Success {
-                            request_id: rid, ..
-                        } if rid == &request_id => {
-                            debug!("[TOOL_APPROVAL] Initialization successful");
-                            self.tool_approval_enabled = true;
-                            return Ok(());
-                        }
-                        ControlResponsePayload::Error { error, .. } => {
-                            return Err(Error::Protocol(format!(
-                                "Tool approval initialization failed: {}",
-                                error
-                            )));
-                        }
-                        _ => {
-                            // Different request_id, keep waiting
-                            continue;
-                        }
-                    }
-                }
-                Ok(_) => {
-                    // Got a different message type (system, etc.), keep waiting
-                    continue;
-                }
-                Err(e) => {
-                    return Err(e.into());
+    /// Interrupt the turn in progress without killing the process.
+    ///
+    /// The CLI stops generating and finishes the turn with a `result` message,
+    /// which is still delivered through [`receive`](Self::receive).
+    pub async fn interrupt(&mut self) -> Result<()> {
+        info!("[CLIENT] Interrupting current turn");
+        self.send_control_request(ControlRequestPayload::Interrupt)
+            .await?;
+        Ok(())
+    }
+
+    /// Change the permission mode for the rest of the session.
+    pub async fn set_permission_mode(&mut self, mode: PermissionModeName) -> Result<()> {
+        info!("[CLIENT] Setting permission mode to {}", mode);
+        self.send_control_request(ControlRequestPayload::SetPermissionMode(
+            SetPermissionModeRequest { mode },
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Switch the model used for subsequent turns.
+    pub async fn set_model(&mut self, model: &str) -> Result<()> {
+        info!("[CLIENT] Setting model to {}", model);
+        self.send_control_request(ControlRequestPayload::SetModel(SetModelRequest {
+            model: Some(model.to_string()),
+        }))
+        .await?;
+        Ok(())
+    }
+
+    async fn write_control_request(&mut self, message: &ControlRequestMessage) -> Result<()> {
+        let json_line = Protocol::serialize(message)?;
+        debug!("[OUTGOING] Sending control request: {}", json_line.trim());
+        self.write_line(&json_line).await
+    }
+
+    /// Read outputs until the control response for `request_id` arrives,
+    /// answering hook callbacks and buffering everything else.
+    async fn await_control_response(
+        &mut self,
+        request_id: &str,
+        limits: &ReadLimits,
+    ) -> Result<ControlResponsePayload> {
+        loop {
+            match self.read_output(limits).await? {
+                ClaudeOutput::ControlResponse(resp) if resp.response.request_id() == request_id => {
+                    debug!("[CLIENT] Received control response for {}", request_id);
+                    return Ok(resp.response);
                 }
+                other => match self.auto_response(&other) {
+                    Some(response) => self.send_control_response(response).await?,
+                    None => self.buffered.push_back(other),
+                },
             }
         }
     }
@@ -476,19 +1256,108 @@ impl AsyncClient {
             "[TOOL_APPROVAL] Sending control response: {}",
             json_line.trim()
         );
-
-        self.stdin
-            .write_all(json_line.as_bytes())
-            .await
-            .map_err(Error::Io)?;
-        self.stdin.flush().await.map_err(Error::Io)?;
-        Ok(())
+        self.write_line(&json_line).await
     }
 
     /// Check if tool approval protocol is enabled
     pub fn is_tool_approval_enabled(&self) -> bool {
         self.tool_approval_enabled
     }
+
+    /// Answer `can_use_tool` requests with an [`ApprovalPolicy`].
+    ///
+    /// Requests the policy allows or denies are answered automatically inside
+    /// [`receive`](Self::receive); only requests it leaves to the caller
+    /// ([`PolicyDecision::Ask`](crate::PolicyDecision::Ask)) are returned.
+    /// Tool approval must still be enabled with
+    /// [`enable_tool_approval`](Self::enable_tool_approval).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use claude_codes::{ApprovalPolicy, AsyncClient, ClaudeCliBuilder, PolicyRule};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let child = ClaudeCliBuilder::new()
+    ///     .permission_prompt_tool("stdio")
+    ///     .spawn()
+    ///     .await?;
+    ///
+    /// let mut client = AsyncClient::new(child)?;
+    /// client.set_approval_policy(
+    ///     ApprovalPolicy::new()
+    ///         .rule(PolicyRule::bash_prefix("cargo ").allow())
+    ///         .rule(PolicyRule::tool("Bash").deny("Only cargo commands are allowed")),
+    /// );
+    /// client.enable_tool_approval().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_approval_policy(&mut self, policy: ApprovalPolicy) {
+        self.approval_policy = Some(policy);
+    }
+
+    /// Aggregate usage from every output returned by [`receive`](Self::receive).
+    ///
+    /// Budgets on the ledger marked with
+    /// [`Budget::interrupt`](crate::accounting::Budget::interrupt) interrupt the
+    /// turn in progress when exceeded. Replaces any ledger already set. See
+    /// [`crate::accounting`].
+    pub fn set_usage_ledger(&mut self, ledger: UsageLedger) {
+        self.usage_ledger = Some(ledger);
+    }
+
+    /// The usage ledger set with [`set_usage_ledger`](Self::set_usage_ledger), if any
+    pub fn usage_ledger(&self) -> Option<&UsageLedger> {
+        self.usage_ledger.as_ref()
+    }
+
+    /// Mutable access to the usage ledger, e.g. to add budgets
+    pub fn usage_ledger_mut(&mut self) -> Option<&mut UsageLedger> {
+        self.usage_ledger.as_mut()
+    }
+
+    /// Detach the usage ledger, returning it
+    pub fn take_usage_ledger(&mut self) -> Option<UsageLedger> {
+        self.usage_ledger.take()
+    }
+
+    /// Track rate limits from every output returned by [`receive`](Self::receive).
+    ///
+    /// The `query*` methods then wait out rejected windows and retry backoff
+    /// before sending, and [`query_with_retry`](Self::query_with_retry)
+    /// resends failed turns. Replaces any governor already set. See
+    /// [`crate::governor`].
+    pub fn set_rate_limit_governor(&mut self, governor: RateLimitGovernor) {
+        self.rate_limit_governor = Some(governor);
+    }
+
+    /// The rate-limit governor set with
+    /// [`set_rate_limit_governor`](Self::set_rate_limit_governor), if any
+    pub fn rate_limit_governor(&self) -> Option<&RateLimitGovernor> {
+        self.rate_limit_governor.as_ref()
+    }
+
+    /// Detach the rate-limit governor, returning it
+    pub fn take_rate_limit_governor(&mut self) -> Option<RateLimitGovernor> {
+        self.rate_limit_governor.take()
+    }
+
+    /// The approval policy set with [`set_approval_policy`](Self::set_approval_policy),
+    /// including its audit trail
+    pub fn approval_policy(&self) -> Option<&ApprovalPolicy> {
+        self.approval_policy.as_ref()
+    }
+
+    /// Mutable access to the approval policy, e.g. to drain its audit trail
+    pub fn approval_policy_mut(&mut self) -> Option<&mut ApprovalPolicy> {
+        self.approval_policy.as_mut()
+    }
+
+    /// The hook callbacks registered with [`enable_hooks`](Self::enable_hooks)
+    pub fn hooks(&self) -> Option<&HookRegistry> {
+        self.hooks.as_ref()
+    }
 }
 
 /// A response stream that yields ClaudeOutput messages
@@ -496,15 +1365,34 @@ impl AsyncClient {
 pub struct ResponseStream<'a> {
     client: &'a mut AsyncClient,
     finished: bool,
+    limits: ReadLimits,
 }
 
 impl ResponseStream<'_> {
+    /// Fail with [`Error::Timeout`] if no output arrives within `timeout`
+    ///
+    /// Overrides the client's [idle timeout](AsyncClient::set_idle_timeout)
+    /// for this stream.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.limits.idle = Some(timeout);
+        self
+    }
+
+    /// Stop when `cancel` fires
+    ///
+    /// The turn is interrupted and drained up to its result, and the stream
+    /// ends with [`Error::Cancelled`].
+    pub fn cancel_on(mut self, cancel: CancellationToken) -> Self {
+        self.limits.cancel = Some(cancel);
+        self
+    }
+
     /// Convert to a vector by collecting all responses
     pub async fn collect(mut self) -> Result<Vec<ClaudeOutput>> {
         let mut responses = Vec::new();
 
         while !self.finished {
-            let output = self.client.receive().await?;
+            let output = self.client.receive_within(self.limits.clone()).await?;
             let is_result = matches!(&output, ClaudeOutput::Result(_));
             responses.push(output);
 
@@ -523,7 +1411,7 @@ impl ResponseStream<'_> {
             return None;
         }
 
-        match self.client.receive().await {
+        match self.client.receive_within(self.limits.clone()).await {
             Ok(output) => {
                 if matches!(&output, ClaudeOutput::Result(_)) {
                     self.finished = true;
@@ -538,11 +1426,133 @@ impl ResponseStream<'_> {
     }
 }
 
+/// A cloneable handle for sending to a client from any task
+///
+/// Created by [`AsyncClient::writer`]. Sending does not borrow the client, so
+/// one task can consume outputs while others answer control requests, push
+/// follow-up messages or interrupt the turn.
+#[derive(Clone)]
+pub struct ClientWriter {
+    outbound: Arc<Outbound>,
+    recorder: Option<TranscriptRecorder>,
+    request_timeout: Option<Duration>,
+}
+
+impl ClientWriter {
+    /// Send a ClaudeInput directly
+    pub async fn send(&self, input: &ClaudeInput) -> Result<()> {
+        let json_line = Protocol::serialize(input)?;
+        debug!("[OUTGOING] Sending JSON to Claude: {}", json_line.trim());
+        self.write_line(&json_line).await?;
+        self.outbound.note_sent(input);
+        Ok(())
+    }
+
+    /// Send a control response back to the CLI
+    ///
+    /// See [`AsyncClient::send_control_response`].
+    pub async fn send_control_response(&self, response: ControlResponse) -> Result<()> {
+        let message: ControlResponseMessage = response.into();
+        let json_line = Protocol::serialize(&message)?;
+        debug!(
+            "[TOOL_APPROVAL] Sending control response: {}",
+            json_line.trim()
+        );
+        self.write_line(&json_line).await
+    }
+
+    /// Send a control request to the CLI and wait for its response.
+    ///
+    /// The response is picked up by whatever is receiving from the client, so
+    /// this only completes while something is. Fails with
+    /// [`Error::ConnectionClosed`] if the client is dropped or its stream ends
+    /// first, and with [`Error::Timeout`] after the
+    /// [request timeout](Self::set_request_timeout).
+    pub async fn send_control_request(
+        &self,
+        request: ControlRequestPayload,
+    ) -> Result<Option<Value>> {
+        let subtype = request.subtype();
+        let request_id = format!("{}-{}", subtype, Uuid::new_v4());
+        let message = ControlRequestMessage::new(&request_id, request);
+        let json_line = Protocol::serialize(&message)?;
+
+        let (tx, rx) = oneshot::channel();
+        self.outbound.lock_pending().insert(request_id.clone(), tx);
+        let response = self.await_response(&json_line, rx).await;
+        self.outbound.lock_pending().remove(&request_id);
+        control_result(subtype, response?)
+    }
+
+    async fn await_response(
+        &self,
+        json_line: &str,
+        rx: oneshot::Receiver<ControlResponsePayload>,
+    ) -> Result<ControlResponsePayload> {
+        if self.outbound.closed.load(Ordering::SeqCst) {
+            return Err(Error::ConnectionClosed);
+        }
+        debug!("[OUTGOING] Sending control request: {}", json_line.trim());
+        self.write_line(json_line).await?;
+        let response = match self.request_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, rx)
+                .await
+                .map_err(|_| Error::Timeout)?,
+            None => rx.await,
+        };
+        response.map_err(|_| Error::ConnectionClosed)
+    }
+
+    /// Interrupt the turn in progress without killing the process.
+    pub async fn interrupt(&self) -> Result<()> {
+        info!("[CLIENT] Interrupting current turn");
+        self.send_control_request(ControlRequestPayload::Interrupt)
+            .await?;
+        Ok(())
+    }
+
+    /// Change the permission mode for the rest of the session.
+    pub async fn set_permission_mode(&self, mode: PermissionModeName) -> Result<()> {
+        info!("[CLIENT] Setting permission mode to {}", mode);
+        self.send_control_request(ControlRequestPayload::SetPermissionMode(
+            SetPermissionModeRequest { mode },
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Switch the model used for subsequent turns.
+    pub async fn set_model(&self, model: &str) -> Result<()> {
+        info!("[CLIENT] Setting model to {}", model);
+        self.send_control_request(ControlRequestPayload::SetModel(SetModelRequest {
+            model: Some(model.to_string()),
+        }))
+        .await?;
+        Ok(())
+    }
+
+    /// Set how long control requests wait for their response
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
+    /// The timeout for control requests
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    async fn write_line(&self, json_line: &str) -> Result<()> {
+        record(&self.recorder, Direction::Sent, json_line);
+        self.outbound.write_line(json_line).await
+    }
+}
+
 impl Drop for AsyncClient {
     fn drop(&mut self) {
+        self.outbound.fail_pending();
         if self.is_alive() {
             // Try to kill the process
-            if let Err(e) = self.child.start_kill() {
+            if let Err(e) = self.transport.start_kill() {
                 error!("Failed to kill Claude process on drop: {}", e);
             }
         }
@@ -3,9 +3,10 @@
 use crate::cli::ClaudeCliBuilder;
 use crate::error::{Error, Result};
 use crate::io::{
-    ClaudeInput, ClaudeOutput, ContentBlock, ControlRequestMessage, ControlResponse,
-    ControlResponseMessage,
+    ClaudeInput, ClaudeOutput, ContentBlock, ControlRequestMessage, ControlRequestPayload,
+    ControlResponse, ControlResponseMessage,
 };
+use crate::policy::ApprovalPolicy;
 use crate::protocol::Protocol;
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
@@ -21,6 +22,8 @@ pub struct SyncClient {
     session_uuid: Option<Uuid>,
     /// Whether tool approval protocol has been initialized
     tool_approval_enabled: bool,
+    /// Tool approval policy applied to `can_use_tool` requests
+    approval_policy: Option<ApprovalPolicy>,
 }
 
 /// Buffer size for reading Claude's stdout (10MB).
@@ -44,6 +47,7 @@ impl SyncClient {
             stdout: BufReader::with_capacity(STDOUT_BUFFER_SIZE, stdout),
             session_uuid: None,
             tool_approval_enabled: false,
+            approval_policy: None,
         })
     }
 
@@ -112,8 +116,34 @@ impl SyncClient {
         })
     }
 
-    /// Read the next response from Claude
+    /// Read the next response from Claude, answering requests the policy decides
     fn read_next(&mut self) -> Result<Option<ClaudeOutput>> {
+        loop {
+            let Some(output) = self.read_output()? else {
+                return Ok(None);
+            };
+            if let Some(response) = self.auto_response(&output) {
+                self.send_control_response(response)?;
+                continue;
+            }
+            return Ok(Some(output));
+        }
+    }
+
+    /// Build the response for a control request the client handles itself
+    fn auto_response(&mut self, output: &ClaudeOutput) -> Option<ControlResponse> {
+        let ClaudeOutput::ControlRequest(request) = output else {
+            return None;
+        };
+        let ControlRequestPayload::CanUseTool(tool_request) = &request.request else {
+            return None;
+        };
+        let policy = self.approval_policy.as_mut()?;
+        policy.decide(&request.request_id, tool_request)
+    }
+
+    /// Read and parse the next output line from Claude
+    fn read_output(&mut self) -> Result<Option<ClaudeOutput>> {
         let mut line = String::new();
         match self.stdout.read_line(&mut line) {
             Ok(0) => {
@@ -124,7 +154,7 @@ impl SyncClient {
                 let trimmed = line.trim();
                 if trimmed.is_empty() {
                     debug!("[CLIENT] Skipping empty line");
-                    return self.read_next();
+                    return self.read_output();
                 }
 
                 debug!("[CLIENT] Received: {}", trimmed);
@@ -352,6 +382,28 @@ impl SyncClient {
     pub fn is_tool_approval_enabled(&self) -> bool {
         self.tool_approval_enabled
     }
+
+    /// Answer `can_use_tool` requests with an [`ApprovalPolicy`].
+    ///
+    /// Requests the policy allows or denies are answered while iterating
+    /// responses; only requests it leaves to the caller
+    /// ([`PolicyDecision::Ask`](crate::PolicyDecision::Ask)) are yielded.
+    /// Tool approval must still be enabled with
+    /// [`enable_tool_approval`](Self::enable_tool_approval).
+    pub fn set_approval_policy(&mut self, policy: ApprovalPolicy) {
+        self.approval_policy = Some(policy);
+    }
+
+    /// The approval policy set with [`set_approval_policy`](Self::set_approval_policy),
+    /// including its audit trail
+    pub fn approval_policy(&self) -> Option<&ApprovalPolicy> {
+        self.approval_policy.as_ref()
+    }
+
+    /// Mutable access to the approval policy, e.g. to drain its audit trail
+    pub fn approval_policy_mut(&mut self) -> Option<&mut ApprovalPolicy> {
+        self.approval_policy.as_mut()
+    }
 }
 
 // Protocol extension methods for synchronous I/O
@@ -0,0 +1,499 @@
+//! Session state folded from a stream of [`ClaudeOutput`] messages.
+//!
+//! [`Conversation`] ingests outputs one at a time and keeps the state most
+//! consumers otherwise rebuild by hand: the init message, turns and their
+//! results, tool calls and their results, subagent tasks, compaction
+//! boundaries, running totals and the latest rate-limit status.
+//!
+//! It implements `Serialize`/`Deserialize`, so a session can be persisted and
+//! reloaded, then continue ingesting where it left off.
+//!
+//! # Example
+//!
+//! ```
+//! use claude_codes::{ClaudeOutput, Conversation};
+//!
+//! let lines = [
+//!     r#"{"type":"system","subtype":"init","session_id":"s1","model":"claude-sonnet-4"}"#,
+//!     r#"{"type":"assistant","message":{"id":"msg_1","role":"assistant","model":"claude-sonnet-4","content":[{"type":"text","text":"Hello!"}]},"session_id":"s1"}"#,
+//!     r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":100,"duration_api_ms":80,"num_turns":1,"result":"Hello!","session_id":"s1","total_cost_usd":0.01}"#,
+//! ];
+//!
+//! let mut conversation = Conversation::new();
+//! for line in lines {
+//!     conversation.ingest(&ClaudeOutput::parse_json(line).unwrap());
+//! }
+//!
+//! assert_eq!(conversation.session_id(), Some("s1"));
+//! assert_eq!(conversation.turns().len(), 1);
+//! assert_eq!(conversation.turns()[0].text(), "Hello!");
+//! assert_eq!(conversation.totals().cost_usd, 0.01);
+//! ```
+
+use crate::io::{
+    AnthropicError, AssistantMessage, ClaudeOutput, CompactBoundaryMessage, ContentBlock,
+    InitMessage, RateLimitInfo, ResultMessage, StatusMessageStatus, SystemMessage, TaskStatus,
+    TaskType, TaskUsage,
+};
+use crate::tool_results::{ToolCall, ToolCallCorrelator};
+use serde::{Deserialize, Serialize};
+
+/// One turn: the assistant messages produced for a prompt, closed by its result.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Turn {
+    /// Assistant messages, including those from subagents
+    pub messages: Vec<AssistantMessage>,
+    /// The result that closed the turn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<ResultMessage>,
+}
+
+impl Turn {
+    /// Whether the turn has received its result.
+    pub fn is_complete(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Text of the top-level assistant messages, joined with newlines.
+    pub fn text(&self) -> String {
+        self.messages
+            .iter()
+            .filter(|msg| msg.parent_tool_use_id.is_none())
+            .flat_map(|msg| &msg.message.content)
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// IDs of the tool calls made during the turn, in order.
+    pub fn tool_use_ids(&self) -> impl Iterator<Item = &str> {
+        self.messages
+            .iter()
+            .flat_map(|msg| &msg.message.content)
+            .filter_map(|block| match block {
+                ContentBlock::ToolUse(tool_use) => Some(tool_use.id.as_str()),
+                _ => None,
+            })
+    }
+}
+
+/// A background task (subagent or background shell) tracked from the
+/// `task_started`, `task_progress` and `task_notification` system messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubagentTask {
+    /// Task identifier
+    pub task_id: String,
+    /// Kind of task
+    pub task_type: TaskType,
+    /// The tool call that started the task
+    pub tool_use_id: String,
+    /// Task description
+    pub description: String,
+    /// The most recent tool the task used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_tool_name: Option<String>,
+    /// Latest cumulative usage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TaskUsage>,
+    /// Final status, once the task has finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatus>,
+    /// Completion summary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// File holding the task's output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<String>,
+}
+
+impl SubagentTask {
+    /// Whether the task is still running.
+    pub fn is_active(&self) -> bool {
+        self.status.is_none()
+    }
+}
+
+/// Running totals across all results in the session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationTotals {
+    /// Session cost in USD, as last reported by the CLI (already cumulative)
+    pub cost_usd: f64,
+    /// Wall-clock time across turns
+    pub duration_ms: u64,
+    /// API time across turns
+    pub duration_api_ms: u64,
+    /// Agentic turns across results
+    pub num_turns: u64,
+    /// Input tokens across results
+    pub input_tokens: u64,
+    /// Output tokens across results
+    pub output_tokens: u64,
+    /// Cache creation tokens across results
+    pub cache_creation_input_tokens: u64,
+    /// Cache read tokens across results
+    pub cache_read_input_tokens: u64,
+}
+
+impl ConversationTotals {
+    fn add(&mut self, result: &ResultMessage) {
+        self.cost_usd = result.total_cost_usd;
+        self.duration_ms += result.duration_ms;
+        self.duration_api_ms += result.duration_api_ms;
+        self.num_turns += result.num_turns.max(0) as u64;
+        if let Some(usage) = &result.usage {
+            self.input_tokens += u64::from(usage.input_tokens);
+            self.output_tokens += u64::from(usage.output_tokens);
+            self.cache_creation_input_tokens += u64::from(usage.cache_creation_input_tokens);
+            self.cache_read_input_tokens += u64::from(usage.cache_read_input_tokens);
+        }
+    }
+}
+
+/// Structured session state built by folding [`ClaudeOutput`] messages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Conversation {
+    session_id: Option<String>,
+    init: Option<InitMessage>,
+    turns: Vec<Turn>,
+    tool_calls: ToolCallCorrelator,
+    tasks: Vec<SubagentTask>,
+    compactions: Vec<CompactBoundaryMessage>,
+    compacting: bool,
+    rate_limit: Option<RateLimitInfo>,
+    totals: ConversationTotals,
+    errors: Vec<AnthropicError>,
+}
+
+impl Conversation {
+    /// Create an empty conversation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold one output into the state.
+    pub fn ingest(&mut self, output: &ClaudeOutput) {
+        if let Some(session_id) = output.session_id() {
+            self.session_id = Some(session_id.to_string());
+        }
+        self.tool_calls.push(output);
+
+        match output {
+            ClaudeOutput::System(system) => self.ingest_system(system),
+            ClaudeOutput::Assistant(msg) => self.open_turn().messages.push(msg.clone()),
+            ClaudeOutput::Result(result) => {
+                self.totals.add(result);
+                self.open_turn().result = Some(result.clone());
+            }
+            ClaudeOutput::RateLimitEvent(evt) => {
+                self.rate_limit = Some(evt.rate_limit_info.clone());
+            }
+            ClaudeOutput::Error(err) => self.errors.push(err.clone()),
+            ClaudeOutput::User(_)
+            | ClaudeOutput::ControlRequest(_)
+            | ClaudeOutput::ControlResponse(_)
+            | ClaudeOutput::StreamEvent(_) => {}
+        }
+    }
+
+    fn ingest_system(&mut self, system: &SystemMessage) {
+        if let Some(init) = system.as_init() {
+            self.init = Some(init);
+        } else if let Some(status) = system.as_status() {
+            self.compacting = status.status == Some(StatusMessageStatus::Compacting);
+        } else if let Some(boundary) = system.as_compact_boundary() {
+            self.compacting = false;
+            self.compactions.push(boundary);
+        } else if let Some(started) = system.as_task_started() {
+            self.tasks.push(SubagentTask {
+                task_id: started.task_id,
+                task_type: started.task_type,
+                tool_use_id: started.tool_use_id,
+                description: started.description,
+                last_tool_name: None,
+                usage: None,
+                status: None,
+                summary: None,
+                output_file: None,
+            });
+        } else if let Some(progress) = system.as_task_progress() {
+            if let Some(task) = self.task_mut(&progress.task_id) {
+                task.last_tool_name = Some(progress.last_tool_name);
+                task.usage = Some(progress.usage);
+            }
+        } else if let Some(notification) = system.as_task_notification() {
+            if let Some(task) = self.task_mut(&notification.task_id) {
+                task.status = Some(notification.status);
+                task.summary = Some(notification.summary);
+                task.output_file = notification.output_file;
+                if notification.usage.is_some() {
+                    task.usage = notification.usage;
+                }
+            }
+        }
+    }
+
+    /// The last turn if it is still open, otherwise a new one.
+    fn open_turn(&mut self) -> &mut Turn {
+        if self.turns.last().is_none_or(Turn::is_complete) {
+            self.turns.push(Turn::default());
+        }
+        self.turns.last_mut().expect("turn was just pushed")
+    }
+
+    fn task_mut(&mut self, task_id: &str) -> Option<&mut SubagentTask> {
+        self.tasks.iter_mut().find(|task| task.task_id == task_id)
+    }
+
+    /// The session ID from the most recent output that carried one.
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// The init message, if received.
+    pub fn init(&self) -> Option<&InitMessage> {
+        self.init.as_ref()
+    }
+
+    /// All turns, oldest first. The last may still be in progress.
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// The turn in progress, if any.
+    pub fn current_turn(&self) -> Option<&Turn> {
+        self.turns.last().filter(|turn| !turn.is_complete())
+    }
+
+    /// The most recent result.
+    pub fn last_result(&self) -> Option<&ResultMessage> {
+        self.turns
+            .iter()
+            .rev()
+            .find_map(|turn| turn.result.as_ref())
+    }
+
+    /// All tool calls, in the order they were made.
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.tool_calls.calls()
+    }
+
+    /// Look up a tool call by its `tool_use` ID.
+    pub fn tool_call(&self, tool_use_id: &str) -> Option<&ToolCall> {
+        self.tool_calls.get(tool_use_id)
+    }
+
+    /// Tool calls still waiting for a result.
+    pub fn pending_tool_calls(&self) -> impl Iterator<Item = &ToolCall> {
+        self.tool_calls.pending()
+    }
+
+    /// All background tasks, in the order they started.
+    pub fn tasks(&self) -> &[SubagentTask] {
+        &self.tasks
+    }
+
+    /// Background tasks that have not finished.
+    pub fn active_tasks(&self) -> impl Iterator<Item = &SubagentTask> {
+        self.tasks.iter().filter(|task| task.is_active())
+    }
+
+    /// Compaction boundaries, oldest first.
+    pub fn compactions(&self) -> &[CompactBoundaryMessage] {
+        &self.compactions
+    }
+
+    /// Whether the CLI is compacting the context right now.
+    pub fn is_compacting(&self) -> bool {
+        self.compacting
+    }
+
+    /// The latest rate-limit status.
+    pub fn rate_limit(&self) -> Option<&RateLimitInfo> {
+        self.rate_limit.as_ref()
+    }
+
+    /// Running totals across results.
+    pub fn totals(&self) -> &ConversationTotals {
+        &self.totals
+    }
+
+    /// API errors received during the session.
+    pub fn errors(&self) -> &[AnthropicError] {
+        &self.errors
+    }
+}
+
+impl Extend<ClaudeOutput> for Conversation {
+    fn extend<I: IntoIterator<Item = ClaudeOutput>>(&mut self, outputs: I) {
+        for output in outputs {
+            self.ingest(&output);
+        }
+    }
+}
+
+impl FromIterator<ClaudeOutput> for Conversation {
+    fn from_iter<I: IntoIterator<Item = ClaudeOutput>>(outputs: I) -> Self {
+        let mut conversation = Self::new();
+        conversation.extend(outputs);
+        conversation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn output(value: serde_json::Value) -> ClaudeOutput {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn assistant(content: serde_json::Value) -> ClaudeOutput {
+        output(json!({
+            "type": "assistant",
+            "message": {"id": "msg", "role": "assistant", "model": "claude", "content": content},
+            "session_id": "s1"
+        }))
+    }
+
+    fn result(cost: f64, input_tokens: u32) -> ClaudeOutput {
+        output(json!({
+            "type": "result", "subtype": "success", "is_error": false,
+            "duration_ms": 100, "duration_api_ms": 50, "num_turns": 2,
+            "session_id": "s1", "total_cost_usd": cost,
+            "usage": {
+                "input_tokens": input_tokens, "output_tokens": 10,
+                "cache_creation_input_tokens": 0, "cache_read_input_tokens": 5,
+                "server_tool_use": {"web_search_requests": 0}, "service_tier": "standard"
+            }
+        }))
+    }
+
+    fn system(value: serde_json::Value) -> ClaudeOutput {
+        let mut value = value;
+        value["type"] = json!("system");
+        value["session_id"] = json!("s1");
+        output(value)
+    }
+
+    fn session() -> Vec<ClaudeOutput> {
+        vec![
+            system(json!({"subtype": "init", "model": "claude-sonnet-4", "tools": ["Bash"]})),
+            assistant(json!([
+                {"type": "text", "text": "Running it"},
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}},
+                {"type": "tool_use", "id": "t2", "name": "Task", "input": {"description": "d", "prompt": "p", "subagent_type": "Explore"}}
+            ])),
+            output(json!({
+                "type": "user",
+                "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t1", "content": "a.rs"}]},
+                "session_id": "08cd4ce5-1ce0-4dd4-8e7c-8b69712c514e"
+            })),
+            system(json!({
+                "subtype": "task_started", "task_id": "task_1", "task_type": "local_agent",
+                "tool_use_id": "t2", "description": "Explore", "uuid": "u1"
+            })),
+            system(json!({
+                "subtype": "task_progress", "task_id": "task_1", "tool_use_id": "t2",
+                "description": "Explore", "last_tool_name": "Grep",
+                "usage": {"duration_ms": 10, "tool_uses": 1, "total_tokens": 100}, "uuid": "u2"
+            })),
+            output(json!({
+                "type": "rate_limit_event",
+                "rate_limit_info": {"status": "allowed_warning", "utilization": 0.8, "overageDisabledReason": null, "isUsingOverage": false},
+                "session_id": "s1"
+            })),
+        ]
+    }
+
+    #[test]
+    fn test_ingest_open_turn() {
+        let conversation: Conversation = session().into_iter().collect();
+
+        assert_eq!(conversation.session_id(), Some("s1"));
+        assert_eq!(
+            conversation.init().unwrap().model.as_deref(),
+            Some("claude-sonnet-4")
+        );
+        let turn = conversation.current_turn().unwrap();
+        assert_eq!(turn.text(), "Running it");
+        assert_eq!(turn.tool_use_ids().collect::<Vec<_>>(), vec!["t1", "t2"]);
+
+        let pending: Vec<_> = conversation.pending_tool_calls().map(|c| &c.id).collect();
+        assert_eq!(pending, vec!["t2"]);
+
+        let task = conversation.active_tasks().next().unwrap();
+        assert_eq!(task.last_tool_name.as_deref(), Some("Grep"));
+        assert_eq!(task.usage.as_ref().unwrap().total_tokens, 100);
+
+        assert!(conversation.rate_limit().unwrap().utilization == Some(0.8));
+        assert!(conversation.last_result().is_none());
+    }
+
+    #[test]
+    fn test_results_close_turns_and_accumulate_totals() {
+        let mut conversation: Conversation = session().into_iter().collect();
+        conversation.ingest(&system(json!({
+            "subtype": "task_notification", "task_id": "task_1", "status": "completed",
+            "summary": "Found it", "output_file": null, "uuid": "u3"
+        })));
+        conversation.ingest(&result(0.02, 100));
+        conversation.ingest(&assistant(json!([{"type": "text", "text": "Again"}])));
+        conversation.ingest(&result(0.05, 40));
+
+        assert_eq!(conversation.turns().len(), 2);
+        assert!(conversation.current_turn().is_none());
+        assert_eq!(conversation.turns()[1].text(), "Again");
+        assert_eq!(conversation.active_tasks().count(), 0);
+        assert_eq!(conversation.tasks()[0].summary.as_deref(), Some("Found it"));
+
+        let totals = conversation.totals();
+        assert_eq!(totals.cost_usd, 0.05);
+        assert_eq!(totals.input_tokens, 140);
+        assert_eq!(totals.cache_read_input_tokens, 10);
+        assert_eq!(totals.num_turns, 4);
+        assert_eq!(totals.duration_ms, 200);
+    }
+
+    #[test]
+    fn test_compaction_tracking() {
+        let mut conversation = Conversation::new();
+        conversation.ingest(&system(
+            json!({"subtype": "status", "status": "compacting"}),
+        ));
+        assert!(conversation.is_compacting());
+
+        conversation.ingest(&system(json!({
+            "subtype": "compact_boundary",
+            "compact_metadata": {"pre_tokens": 150000, "trigger": "auto"}
+        })));
+        assert!(!conversation.is_compacting());
+        assert_eq!(
+            conversation.compactions()[0].compact_metadata.pre_tokens,
+            150000
+        );
+    }
+
+    #[test]
+    fn test_serde_roundtrip_resumes_ingesting() {
+        let conversation: Conversation = session().into_iter().collect();
+        let saved = serde_json::to_string(&conversation).unwrap();
+
+        let mut restored: Conversation = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored.turns().len(), 1);
+        assert_eq!(restored.tool_calls().len(), 2);
+
+        restored.ingest(&output(json!({
+            "type": "user",
+            "message": {"role": "user", "content": [{"type": "tool_result", "tool_use_id": "t2", "content": "done"}]},
+            "session_id": "08cd4ce5-1ce0-4dd4-8e7c-8b69712c514e"
+        })));
+        restored.ingest(&result(0.01, 1));
+        assert_eq!(restored.pending_tool_calls().count(), 0);
+        assert_eq!(restored.turns().len(), 1);
+        assert!(restored.turns()[0].is_complete());
+    }
+}
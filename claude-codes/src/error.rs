@@ -3,6 +3,8 @@
 use crate::io::ParseError;
 use thiserror::Error;
 
+pub use codes_core::exit::{ExitReport, STDERR_TAIL_LINES};
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("JSON serialization error: {0}")]
@@ -26,9 +28,15 @@ pub enum Error {
     #[error("Timeout occurred")]
     Timeout,
 
+    #[error("Operation cancelled")]
+    Cancelled,
+
     #[error("Connection closed")]
     ConnectionClosed,
 
+    #[error("Claude CLI crashed: {0}")]
+    ProcessCrashed(ExitReport),
+
     #[error("Deserialization error: {0}")]
     Deserialization(#[from] ParseError),
 
@@ -37,6 +45,72 @@ pub enum Error {
 
     #[error("Unknown error: {0}")]
     Unknown(String),
+
+    /// A read failed after the CLI had written to stderr
+    ///
+    /// Only produced when stderr is captured, with the last lines read
+    #[error("{source} (stderr: {})", stderr_tail.last().map_or("", String::as_str))]
+    WithStderr {
+        source: Box<Error>,
+        stderr_tail: Vec<String>,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The underlying error, looking through any attached stderr
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::WithStderr { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// The stderr lines attached to this error, oldest first
+    pub fn stderr_tail(&self) -> &[String] {
+        match self {
+            Error::WithStderr { stderr_tail, .. } => stderr_tail,
+            Error::ProcessCrashed(report) => &report.stderr_tail,
+            _ => &[],
+        }
+    }
+
+    /// Attach `stderr_tail` to a failed read
+    ///
+    /// Timeouts, cancellations, normal ends of stream and errors that already
+    /// carry stderr are returned unchanged
+    #[cfg(feature = "async-client")]
+    pub(crate) fn with_stderr(self, stderr_tail: Vec<String>) -> Error {
+        match self {
+            Error::Timeout
+            | Error::Cancelled
+            | Error::ConnectionClosed
+            | Error::ProcessCrashed(_)
+            | Error::WithStderr { .. } => self,
+            _ if stderr_tail.is_empty() => self,
+            source => Error::WithStderr {
+                source: Box::new(source),
+                stderr_tail,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "async-client")]
+impl From<codes_core::limits::ReadError> for Error {
+    fn from(error: codes_core::limits::ReadError) -> Self {
+        match error {
+            codes_core::limits::ReadError::Timeout => Error::Timeout,
+            codes_core::limits::ReadError::Cancelled => Error::Cancelled,
+            codes_core::limits::ReadError::Io(e) => Error::Io(e),
+        }
+    }
+}
+
+#[cfg(feature = "async-client")]
+impl From<codes_core::pool::PoolError> for Error {
+    fn from(error: codes_core::pool::PoolError) -> Self {
+        Error::InvalidState(error.to_string())
+    }
+}
@@ -0,0 +1,614 @@
+//! Rate-limit aware scheduling and backoff
+//!
+//! A [`RateLimitGovernor`] observes [`ClaudeOutput`] messages and keeps the
+//! latest [`RateLimitInfo`] for each rate limit window. From
+//! `rate_limit_event`s and API errors it works out when new queries may be
+//! sent:
+//!
+//! - A window whose status is `rejected` pauses new queries until its
+//!   `resets_at`.
+//! - An `overloaded_error` (HTTP 529) or `api_error` (HTTP 500) schedules a
+//!   retry after a jittered exponential backoff from the [`BackoffPolicy`].
+//! - A `rate_limit_error` (HTTP 429) waits for the rejected window to reset,
+//!   or backs off when no reset time is known.
+//!
+//! Transitions are reported as [`GovernorEvent`]s, including when overage
+//! billing starts or stops. Attach a governor with
+//! `AsyncClient::set_rate_limit_governor` to have queries wait automatically
+//! and `AsyncClient::query_with_retry` resend turns that failed with a
+//! retryable error.
+//!
+//! # Example
+//!
+//! ```
+//! use claude_codes::governor::{GovernorEvent, RateLimitGovernor};
+//! use claude_codes::{ClaudeOutput, RateLimitWindow};
+//!
+//! let mut governor = RateLimitGovernor::new();
+//!
+//! let output = ClaudeOutput::parse_json(r#"{"type":"rate_limit_event","rate_limit_info":{"status":"rejected","resetsAt":4102444800,"rateLimitType":"five_hour","isUsingOverage":false},"session_id":"s1"}"#).unwrap();
+//! let events = governor.observe(&output);
+//!
+//! assert!(matches!(events[0], GovernorEvent::Rejected { resets_at: Some(4102444800), .. }));
+//! assert!(governor.window(&RateLimitWindow::FiveHour).is_some());
+//! assert!(governor.wait_time().is_some());
+//! ```
+
+use crate::io::{
+    AnthropicError, AnthropicErrorDetails, ApiErrorType, ClaudeOutput, RateLimitInfo,
+    RateLimitStatus, RateLimitWindow, ResultMessage,
+};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// ============================================================================
+// Backoff
+// ============================================================================
+
+/// Exponential backoff with jitter for retrying failed turns
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackoffPolicy {
+    /// Delay before the first retry
+    pub initial: Duration,
+    /// Upper bound on any single delay
+    pub max: Duration,
+    /// Growth factor between attempts
+    pub multiplier: f64,
+    /// Fraction of each delay (0.0 to 1.0) that is randomized
+    pub jitter: f64,
+    /// Retries allowed before giving up
+    pub max_retries: u32,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_retries: 5,
+        }
+    }
+}
+
+impl BackoffPolicy {
+    /// The jittered delay before retry number `attempt` (starting at 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.delay_with(attempt, random_unit())
+    }
+
+    /// The delay for `attempt` with `unit` (0.0 to 1.0) as the random draw
+    fn delay_with(&self, attempt: u32, unit: f64) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = (self.initial.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        Duration::from_secs_f64(base * (1.0 - jitter * unit))
+    }
+}
+
+/// A uniformly distributed value in `[0, 1)` from the std hasher's random keys
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// ============================================================================
+// Events
+// ============================================================================
+
+/// A change in rate limit state reported by [`RateLimitGovernor::observe`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GovernorEvent {
+    /// A window started rejecting requests
+    Rejected {
+        window: Option<RateLimitWindow>,
+        /// Unix timestamp when the window resets
+        resets_at: Option<u64>,
+    },
+    /// A window is approaching its cap
+    Warning {
+        window: Option<RateLimitWindow>,
+        utilization: Option<f64>,
+    },
+    /// A window is allowing requests again
+    Recovered { window: Option<RateLimitWindow> },
+    /// Overage billing kicked in
+    OverageStarted { window: Option<RateLimitWindow> },
+    /// Overage billing stopped
+    OverageEnded { window: Option<RateLimitWindow> },
+    /// A turn failed with a retryable API error
+    RetryScheduled {
+        /// The retry this delay is for (starting at 1)
+        attempt: u32,
+        delay: Duration,
+        error: AnthropicError,
+    },
+    /// A turn failed with a retryable API error and no retries are left
+    RetriesExhausted {
+        attempts: u32,
+        error: AnthropicError,
+    },
+}
+
+impl fmt::Display for GovernorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn name(window: &Option<RateLimitWindow>) -> &str {
+            window.as_ref().map_or("unknown", |w| w.as_str())
+        }
+        match self {
+            GovernorEvent::Rejected { window, resets_at } => match resets_at {
+                Some(at) => write!(f, "{} rate limit reached, resets at {}", name(window), at),
+                None => write!(f, "{} rate limit reached", name(window)),
+            },
+            GovernorEvent::Warning {
+                window,
+                utilization,
+            } => match utilization {
+                Some(u) => write!(f, "{} rate limit at {:.0}%", name(window), u * 100.0),
+                None => write!(f, "{} rate limit approaching", name(window)),
+            },
+            GovernorEvent::Recovered { window } => {
+                write!(f, "{} rate limit allows requests again", name(window))
+            }
+            GovernorEvent::OverageStarted { window } => {
+                write!(f, "{} rate limit is now using overage", name(window))
+            }
+            GovernorEvent::OverageEnded { window } => {
+                write!(f, "{} rate limit stopped using overage", name(window))
+            }
+            GovernorEvent::RetryScheduled {
+                attempt,
+                delay,
+                error,
+            } => write!(
+                f,
+                "{} error, retry {} in {:?}",
+                error.error.error_type, attempt, delay
+            ),
+            GovernorEvent::RetriesExhausted { attempts, error } => write!(
+                f,
+                "{} error, giving up after {} retries",
+                error.error.error_type, attempts
+            ),
+        }
+    }
+}
+
+// ============================================================================
+// Governor
+// ============================================================================
+
+type EventCallback = Arc<dyn Fn(&GovernorEvent) + Send + Sync>;
+
+/// Tracks rate limit windows and decides when queries may be sent
+#[derive(Clone, Default)]
+pub struct RateLimitGovernor {
+    windows: HashMap<Option<RateLimitWindow>, RateLimitInfo>,
+    backoff: BackoffPolicy,
+    /// Retries scheduled since the last successful result
+    attempts: u32,
+    /// Earliest time the next query may be sent after a retryable error
+    retry_at: Option<SystemTime>,
+    /// Whether the last turn failed with a retryable error
+    retry_pending: bool,
+    /// Whether a retryable error was already handled in the current turn
+    turn_failed: bool,
+    callback: Option<EventCallback>,
+}
+
+impl RateLimitGovernor {
+    /// Create a governor with the default [`BackoffPolicy`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `backoff` for retryable errors
+    pub fn with_backoff(mut self, backoff: BackoffPolicy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Call `callback` for every event
+    pub fn on_event<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&GovernorEvent) + Send + Sync + 'static,
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// The backoff policy
+    pub fn backoff(&self) -> &BackoffPolicy {
+        &self.backoff
+    }
+
+    /// The latest info for a window
+    pub fn window(&self, window: &RateLimitWindow) -> Option<&RateLimitInfo> {
+        self.windows.get(&Some(window.clone()))
+    }
+
+    /// The latest info for every window seen, keyed by window type
+    pub fn windows(&self) -> impl Iterator<Item = (Option<&RateLimitWindow>, &RateLimitInfo)> {
+        self.windows.iter().map(|(k, v)| (k.as_ref(), v))
+    }
+
+    /// Whether any window is billing overage
+    pub fn is_using_overage(&self) -> bool {
+        self.windows.values().any(|info| info.is_using_overage)
+    }
+
+    /// Retries scheduled since the last successful result
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Whether the last turn failed with an error worth retrying
+    ///
+    /// False once [`BackoffPolicy::max_retries`] is used up.
+    pub fn should_retry(&self) -> bool {
+        self.retry_pending
+    }
+
+    /// When new queries may be sent again, if they are paused
+    pub fn paused_until(&self) -> Option<SystemTime> {
+        self.paused_until_at(SystemTime::now())
+    }
+
+    /// How long to wait before sending the next query, if at all
+    pub fn wait_time(&self) -> Option<Duration> {
+        let now = SystemTime::now();
+        self.paused_until_at(now)
+            .and_then(|until| until.duration_since(now).ok())
+    }
+
+    fn paused_until_at(&self, now: SystemTime) -> Option<SystemTime> {
+        let resets = self
+            .windows
+            .values()
+            .filter(|info| info.status == RateLimitStatus::Rejected)
+            .filter_map(|info| info.resets_at)
+            .map(|at| UNIX_EPOCH + Duration::from_secs(at));
+        resets
+            .chain(self.retry_at)
+            .filter(|until| *until > now)
+            .max()
+    }
+
+    /// Update state from an output, returning what changed
+    pub fn observe(&mut self, output: &ClaudeOutput) -> Vec<GovernorEvent> {
+        let events = match output {
+            ClaudeOutput::RateLimitEvent(event) => self.observe_info(&event.rate_limit_info),
+            ClaudeOutput::Error(error) => self.observe_error(error).into_iter().collect(),
+            ClaudeOutput::Result(result) => self.observe_result(result).into_iter().collect(),
+            _ => Vec::new(),
+        };
+        if let Some(callback) = &self.callback {
+            for event in &events {
+                callback(event);
+            }
+        }
+        events
+    }
+
+    /// Forget the retry state, e.g. before sending a different query
+    pub fn clear_retry(&mut self) {
+        self.attempts = 0;
+        self.retry_at = None;
+        self.retry_pending = false;
+        self.turn_failed = false;
+    }
+
+    fn observe_info(&mut self, info: &RateLimitInfo) -> Vec<GovernorEvent> {
+        let window = info.rate_limit_type.clone();
+        let previous = self.windows.insert(window.clone(), info.clone());
+        let was_status = previous.as_ref().map(|p| p.status.clone());
+        let was_overage = previous.as_ref().is_some_and(|p| p.is_using_overage);
+
+        let mut events = Vec::new();
+        if was_status.as_ref() != Some(&info.status) {
+            match info.status {
+                RateLimitStatus::Rejected => events.push(GovernorEvent::Rejected {
+                    window: window.clone(),
+                    resets_at: info.resets_at,
+                }),
+                RateLimitStatus::AllowedWarning => events.push(GovernorEvent::Warning {
+                    window: window.clone(),
+                    utilization: info.utilization,
+                }),
+                RateLimitStatus::Allowed if was_status == Some(RateLimitStatus::Rejected) => events
+                    .push(GovernorEvent::Recovered {
+                        window: window.clone(),
+                    }),
+                _ => {}
+            }
+        }
+        if info.is_using_overage && !was_overage {
+            events.push(GovernorEvent::OverageStarted {
+                window: window.clone(),
+            });
+        } else if !info.is_using_overage && was_overage {
+            events.push(GovernorEvent::OverageEnded { window });
+        }
+        events
+    }
+
+    fn observe_error(&mut self, error: &AnthropicError) -> Option<GovernorEvent> {
+        if !(error.is_overloaded() || error.is_server_error() || error.is_rate_limited()) {
+            return None;
+        }
+        self.turn_failed = true;
+        if self.attempts >= self.backoff.max_retries {
+            self.retry_pending = false;
+            return Some(GovernorEvent::RetriesExhausted {
+                attempts: self.attempts,
+                error: error.clone(),
+            });
+        }
+        self.attempts += 1;
+        let now = SystemTime::now();
+        let mut delay = self.backoff.delay(self.attempts);
+        if error.is_rate_limited() {
+            // Prefer the window's reset time over guessing
+            if let Some(wait) = self
+                .paused_until_at(now)
+                .and_then(|until| until.duration_since(now).ok())
+            {
+                delay = wait;
+            }
+        }
+        self.retry_at = Some(now + delay);
+        self.retry_pending = true;
+        Some(GovernorEvent::RetryScheduled {
+            attempt: self.attempts,
+            delay,
+            error: error.clone(),
+        })
+    }
+
+    fn observe_result(&mut self, result: &ResultMessage) -> Option<GovernorEvent> {
+        if !result.is_error {
+            self.clear_retry();
+            return None;
+        }
+        // The result ends the turn, so the next turn starts with a clean slate
+        if std::mem::take(&mut self.turn_failed) {
+            // Already handled from the error message itself
+            return None;
+        }
+        // The CLI may only report the API error in the result text
+        let Some((text, error_type)) = result
+            .result
+            .iter()
+            .chain(result.errors.iter())
+            .find_map(|text| retryable_error_type(text).map(|error_type| (text, error_type)))
+        else {
+            // Failed for a reason a retry will not fix, such as max turns
+            self.retry_pending = false;
+            return None;
+        };
+        let error = AnthropicError {
+            error: AnthropicErrorDetails {
+                error_type,
+                message: text.clone(),
+            },
+            request_id: None,
+        };
+        let event = self.observe_error(&error);
+        self.turn_failed = false;
+        event
+    }
+}
+
+/// The retryable API error a result or error text mentions, if any
+fn retryable_error_type(text: &str) -> Option<ApiErrorType> {
+    [
+        ApiErrorType::OverloadedError,
+        ApiErrorType::RateLimitError,
+        ApiErrorType::ApiError,
+    ]
+    .into_iter()
+    .find(|error_type| text.contains(error_type.as_str()))
+}
+
+impl fmt::Debug for RateLimitGovernor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimitGovernor")
+            .field("windows", &self.windows)
+            .field("backoff", &self.backoff)
+            .field("attempts", &self.attempts)
+            .field("retry_at", &self.retry_at)
+            .field("retry_pending", &self.retry_pending)
+            .field("turn_failed", &self.turn_failed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(json: &str) -> ClaudeOutput {
+        ClaudeOutput::parse_json(json).unwrap()
+    }
+
+    fn rate_limit(status: &str, resets_at: u64, overage: bool) -> ClaudeOutput {
+        output(&format!(
+            r#"{{"type":"rate_limit_event","rate_limit_info":{{"status":"{}","resetsAt":{},"rateLimitType":"five_hour","isUsingOverage":{}}},"session_id":"s1"}}"#,
+            status, resets_at, overage
+        ))
+    }
+
+    fn overloaded() -> ClaudeOutput {
+        output(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#)
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let policy = BackoffPolicy {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_retries: 10,
+        };
+        assert_eq!(policy.delay_with(1, 0.0), Duration::from_secs(1));
+        assert_eq!(policy.delay_with(3, 0.0), Duration::from_secs(4));
+        assert_eq!(policy.delay_with(8, 0.0), Duration::from_secs(5));
+        assert_eq!(policy.delay_with(2, 1.0), Duration::from_secs(1));
+        assert!(policy.delay(4) <= Duration::from_secs(5));
+        assert!(policy.delay(4) >= Duration::from_millis(2500));
+    }
+
+    #[test]
+    fn test_rejected_window_pauses_until_reset() {
+        let mut governor = RateLimitGovernor::new();
+        let reset = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 3600;
+
+        let events = governor.observe(&rate_limit("rejected", reset, false));
+        assert_eq!(
+            events,
+            vec![GovernorEvent::Rejected {
+                window: Some(RateLimitWindow::FiveHour),
+                resets_at: Some(reset),
+            }]
+        );
+        assert_eq!(
+            governor.paused_until(),
+            Some(UNIX_EPOCH + Duration::from_secs(reset))
+        );
+
+        let events = governor.observe(&rate_limit("allowed", reset, false));
+        assert_eq!(
+            events,
+            vec![GovernorEvent::Recovered {
+                window: Some(RateLimitWindow::FiveHour)
+            }]
+        );
+        assert_eq!(governor.wait_time(), None);
+    }
+
+    #[test]
+    fn test_overage_transitions() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut governor = RateLimitGovernor::new()
+            .on_event(move |event| sink.lock().unwrap().push(event.clone()));
+
+        governor.observe(&rate_limit("allowed", 0, false));
+        assert!(governor.observe(&rate_limit("allowed", 0, true)).len() == 1);
+        assert!(governor.is_using_overage());
+        governor.observe(&rate_limit("allowed", 0, false));
+
+        let seen = seen.lock().unwrap();
+        assert!(matches!(seen[0], GovernorEvent::OverageStarted { .. }));
+        assert!(matches!(seen[1], GovernorEvent::OverageEnded { .. }));
+    }
+
+    #[test]
+    fn test_overloaded_schedules_retries_until_exhausted() {
+        let mut governor = RateLimitGovernor::new().with_backoff(BackoffPolicy {
+            max_retries: 2,
+            ..Default::default()
+        });
+
+        for attempt in 1..=2 {
+            let events = governor.observe(&overloaded());
+            assert!(
+                matches!(events[0], GovernorEvent::RetryScheduled { attempt: a, .. } if a == attempt)
+            );
+            assert!(governor.should_retry());
+            assert!(governor.wait_time().is_some());
+        }
+
+        let events = governor.observe(&overloaded());
+        assert!(matches!(
+            events[0],
+            GovernorEvent::RetriesExhausted { attempts: 2, .. }
+        ));
+        assert!(!governor.should_retry());
+
+        governor.observe(&output(
+            r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"session_id":"s1","total_cost_usd":0.0}"#,
+        ));
+        assert_eq!(governor.attempts(), 0);
+        assert_eq!(governor.wait_time(), None);
+    }
+
+    fn failed_result(text: &str) -> ClaudeOutput {
+        output(&format!(
+            r#"{{"type":"result","subtype":"success","is_error":true,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"result":{},"session_id":"s1","total_cost_usd":0.0}}"#,
+            serde_json::to_string(text).unwrap()
+        ))
+    }
+
+    #[test]
+    fn test_error_and_result_of_one_turn_count_once() {
+        let mut governor = RateLimitGovernor::new().with_backoff(BackoffPolicy {
+            max_retries: 1,
+            ..Default::default()
+        });
+        let result = failed_result("API Error: 529 overloaded_error");
+
+        assert_eq!(governor.observe(&overloaded()).len(), 1);
+        assert!(governor.observe(&result).is_empty());
+        assert_eq!(governor.attempts(), 1);
+
+        let events = governor.observe(&overloaded());
+        assert!(matches!(events[0], GovernorEvent::RetriesExhausted { .. }));
+        assert!(governor.observe(&result).is_empty());
+        assert!(!governor.should_retry());
+    }
+
+    #[test]
+    fn test_non_retryable_failure_stops_retrying() {
+        let mut governor = RateLimitGovernor::new();
+        governor.observe(&failed_result("API Error: 529 overloaded_error"));
+        assert!(governor.should_retry());
+
+        assert!(governor
+            .observe(&failed_result("Reached maximum number of turns"))
+            .is_empty());
+        assert!(!governor.should_retry());
+    }
+
+    #[test]
+    fn test_overloaded_result_text_schedules_retry() {
+        let mut governor = RateLimitGovernor::new();
+        let events = governor.observe(&output(
+            r#"{"type":"result","subtype":"success","is_error":true,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"result":"API Error: 529 {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}","session_id":"s1","total_cost_usd":0.0}"#,
+        ));
+        assert!(matches!(
+            &events[0],
+            GovernorEvent::RetryScheduled { attempt: 1, error, .. } if error.is_overloaded()
+        ));
+    }
+
+    #[test]
+    fn test_api_error_result_text_schedules_retry() {
+        let mut governor = RateLimitGovernor::new();
+        let events = governor.observe(&output(
+            r#"{"type":"result","subtype":"success","is_error":true,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"result":"API Error: 500 {\"type\":\"error\",\"error\":{\"type\":\"api_error\",\"message\":\"Internal server error\"}}","session_id":"s1","total_cost_usd":0.0}"#,
+        ));
+        assert!(matches!(
+            &events[0],
+            GovernorEvent::RetryScheduled { attempt: 1, error, .. } if error.is_server_error()
+        ));
+        assert!(governor.should_retry());
+    }
+}
@@ -0,0 +1,458 @@
+//! Hook registration and callback dispatch
+//!
+//! A [`HookRegistry`] maps Rust closures to hook events. The client sends the
+//! registry's [`config`](HookRegistry::config) in the `initialize` handshake,
+//! and when the CLI later fires a `hook_callback` control request the registry
+//! looks up the closure by callback ID, runs it, and builds the
+//! [`ControlResponse`].
+//!
+//! Inputs that do not parse, or belong to a different event than the hook was
+//! registered for, never reach the closure. `PreToolUse` hooks then deny the
+//! tool so a policy hook cannot fail open; other hooks answer with a control
+//! error.
+//!
+//! # Example
+//!
+//! ```
+//! use claude_codes::hooks::HookRegistry;
+//! use claude_codes::HookOutput;
+//!
+//! let hooks = HookRegistry::new()
+//!     .pre_tool_use(Some("Bash"), |input| {
+//!         let command = input.tool_input["command"].as_str().unwrap_or_default();
+//!         if command.contains("rm -rf") {
+//!             HookOutput::deny_tool("Destructive commands are not allowed")
+//!         } else {
+//!             HookOutput::proceed()
+//!         }
+//!     })
+//!     .user_prompt_submit(|_| HookOutput::proceed().with_system_message("Prompt logged"));
+//!
+//! let config = hooks.config();
+//! assert_eq!(config["PreToolUse"][0]["matcher"], "Bash");
+//! ```
+
+use crate::io::{
+    ControlResponse, HookCallbackRequest, HookEvent, HookInput, HookMatcher, HookOutput,
+    NotificationHookInput, PostToolUseHookInput, PreCompactHookInput, PreToolUseHookInput,
+    SessionEndHookInput, SessionStartHookInput, StopHookInput, UserPromptSubmitHookInput,
+};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+/// A hook callback: receives the hook input and returns the hook output
+pub type HookFn = Arc<dyn Fn(&HookInput) -> HookOutput + Send + Sync>;
+
+/// A registered callback; `None` when the input is not for its event
+type Callback = Arc<dyn Fn(&HookInput) -> Option<HookOutput> + Send + Sync>;
+
+struct RegisteredHook {
+    event: HookEvent,
+    matcher: Option<String>,
+    timeout: Option<u64>,
+    callback: Callback,
+}
+
+/// A `hook_callback` request whose input the hook could not run on
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookInputError {
+    pub callback_id: String,
+    /// The event the hook was registered for
+    pub event: HookEvent,
+    pub message: String,
+}
+
+impl HookInputError {
+    /// Build the control response: a denial for `PreToolUse`, an error otherwise
+    pub fn to_response(&self, request_id: &str) -> ControlResponse {
+        match self.event {
+            HookEvent::PreToolUse => {
+                output_response(request_id, &HookOutput::deny_tool(self.to_string()))
+            }
+            _ => ControlResponse::error(request_id, self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for HookInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Hook {} ({}): {}",
+            self.callback_id, self.event, self.message
+        )
+    }
+}
+
+impl std::error::Error for HookInputError {}
+
+/// A set of hook callbacks keyed by generated callback IDs
+///
+/// Callback IDs are assigned in registration order (`hook_0`, `hook_1`, ...).
+/// Each registration becomes its own [`HookMatcher`] entry so the CLI reports
+/// exactly which closure to run.
+#[derive(Default)]
+pub struct HookRegistry {
+    hooks: Vec<RegisteredHook>,
+    ids: HashMap<String, usize>,
+    default_timeout: Option<u64>,
+}
+
+impl HookRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timeout (in seconds) sent for hooks registered after this call
+    pub fn default_timeout(mut self, seconds: u64) -> Self {
+        self.default_timeout = Some(seconds);
+        self
+    }
+
+    /// Register a callback for any event with an optional tool-name matcher
+    pub fn on<F>(self, event: HookEvent, matcher: Option<&str>, callback: F) -> Self
+    where
+        F: Fn(&HookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(event, matcher, move |input| Some(callback(input)))
+    }
+
+    fn register<F>(mut self, event: HookEvent, matcher: Option<&str>, callback: F) -> Self
+    where
+        F: Fn(&HookInput) -> Option<HookOutput> + Send + Sync + 'static,
+    {
+        let id = format!("hook_{}", self.hooks.len());
+        self.ids.insert(id, self.hooks.len());
+        self.hooks.push(RegisteredHook {
+            event,
+            matcher: matcher.map(str::to_string),
+            timeout: self.default_timeout,
+            callback: Arc::new(callback),
+        });
+        self
+    }
+
+    /// Register a `PreToolUse` callback, optionally restricted to matching tools
+    pub fn pre_tool_use<F>(self, matcher: Option<&str>, callback: F) -> Self
+    where
+        F: Fn(&PreToolUseHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::PreToolUse, matcher, move |input| {
+            typed(input.as_pre_tool_use(), &callback)
+        })
+    }
+
+    /// Register a `PostToolUse` callback, optionally restricted to matching tools
+    pub fn post_tool_use<F>(self, matcher: Option<&str>, callback: F) -> Self
+    where
+        F: Fn(&PostToolUseHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::PostToolUse, matcher, move |input| {
+            typed(input.as_post_tool_use(), &callback)
+        })
+    }
+
+    /// Register a `UserPromptSubmit` callback
+    pub fn user_prompt_submit<F>(self, callback: F) -> Self
+    where
+        F: Fn(&UserPromptSubmitHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::UserPromptSubmit, None, move |input| {
+            typed(input.as_user_prompt_submit(), &callback)
+        })
+    }
+
+    /// Register a `Stop` callback
+    pub fn stop<F>(self, callback: F) -> Self
+    where
+        F: Fn(&StopHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::Stop, None, move |input| {
+            typed(input.as_stop(), &callback)
+        })
+    }
+
+    /// Register a `SubagentStop` callback
+    pub fn subagent_stop<F>(self, callback: F) -> Self
+    where
+        F: Fn(&StopHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::SubagentStop, None, move |input| {
+            typed(input.as_subagent_stop(), &callback)
+        })
+    }
+
+    /// Register a `PreCompact` callback
+    pub fn pre_compact<F>(self, callback: F) -> Self
+    where
+        F: Fn(&PreCompactHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::PreCompact, None, move |input| {
+            typed(input.as_pre_compact(), &callback)
+        })
+    }
+
+    /// Register a `Notification` callback
+    pub fn notification<F>(self, callback: F) -> Self
+    where
+        F: Fn(&NotificationHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::Notification, None, move |input| {
+            typed(input.as_notification(), &callback)
+        })
+    }
+
+    /// Register a `SessionStart` callback
+    pub fn session_start<F>(self, callback: F) -> Self
+    where
+        F: Fn(&SessionStartHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::SessionStart, None, move |input| {
+            typed(input.as_session_start(), &callback)
+        })
+    }
+
+    /// Register a `SessionEnd` callback
+    pub fn session_end<F>(self, callback: F) -> Self
+    where
+        F: Fn(&SessionEndHookInput) -> HookOutput + Send + Sync + 'static,
+    {
+        self.register(HookEvent::SessionEnd, None, move |input| {
+            typed(input.as_session_end(), &callback)
+        })
+    }
+
+    /// Number of registered callbacks
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Whether no callbacks are registered
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Whether `callback_id` belongs to this registry
+    pub fn handles(&self, callback_id: &str) -> bool {
+        self.ids.contains_key(callback_id)
+    }
+
+    /// Build the `hooks` value for the `initialize` control request
+    pub fn config(&self) -> Value {
+        let mut events: BTreeMap<String, Vec<HookMatcher>> = BTreeMap::new();
+        for (index, hook) in self.hooks.iter().enumerate() {
+            events
+                .entry(hook.event.to_string())
+                .or_default()
+                .push(HookMatcher {
+                    matcher: hook.matcher.clone(),
+                    hook_callback_ids: vec![format!("hook_{}", index)],
+                    timeout: hook.timeout,
+                });
+        }
+        serde_json::to_value(events).unwrap_or_default()
+    }
+
+    /// Run the callback for a `hook_callback` request
+    ///
+    /// Returns `None` if the callback ID is not registered, and a
+    /// [`HookInputError`] without running the callback if the input does not
+    /// parse or is for a different event.
+    pub fn dispatch(
+        &self,
+        request: &HookCallbackRequest,
+    ) -> Option<Result<HookOutput, HookInputError>> {
+        let index = *self.ids.get(&request.callback_id)?;
+        let hook = &self.hooks[index];
+        let error = |message: String| HookInputError {
+            callback_id: request.callback_id.clone(),
+            event: hook.event.clone(),
+            message,
+        };
+        let input = match serde_json::from_value::<HookInput>(request.input.clone()) {
+            Ok(input) => input,
+            Err(e) => return Some(Err(error(format!("Invalid hook input: {}", e)))),
+        };
+        Some((hook.callback)(&input).ok_or_else(|| {
+            error(if input.hook_event_name == hook.event {
+                format!("Input does not parse as {} hook input", hook.event)
+            } else {
+                format!(
+                    "Expected {} input, got {}",
+                    hook.event, input.hook_event_name
+                )
+            })
+        }))
+    }
+
+    /// Run the callback for a `hook_callback` request and build the response
+    pub fn respond(
+        &self,
+        request_id: &str,
+        request: &HookCallbackRequest,
+    ) -> Option<ControlResponse> {
+        Some(match self.dispatch(request)? {
+            Ok(output) => output_response(request_id, &output),
+            Err(error) => error.to_response(request_id),
+        })
+    }
+}
+
+/// Build the success response carrying a hook's output
+pub(crate) fn output_response(request_id: &str, output: &HookOutput) -> ControlResponse {
+    match serde_json::to_value(output) {
+        Ok(body) => ControlResponse::success(request_id, body),
+        Err(e) => ControlResponse::error(request_id, e.to_string()),
+    }
+}
+
+impl fmt::Debug for HookRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookRegistry")
+            .field("config", &self.config())
+            .finish()
+    }
+}
+
+/// Run a typed callback, or `None` if the input did not parse as its event
+fn typed<T>(input: Option<T>, callback: &impl Fn(&T) -> HookOutput) -> Option<HookOutput> {
+    input.map(|input| callback(&input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{ControlResponsePayload, HookPermissionDecision};
+    use serde_json::json;
+
+    fn callback(callback_id: &str, input: Value) -> HookCallbackRequest {
+        HookCallbackRequest {
+            callback_id: callback_id.to_string(),
+            input,
+            tool_use_id: None,
+        }
+    }
+
+    #[test]
+    fn test_config_groups_by_event() {
+        let hooks = HookRegistry::new()
+            .default_timeout(30)
+            .pre_tool_use(Some("Bash"), |_| HookOutput::proceed())
+            .pre_tool_use(Some("Write|Edit"), |_| HookOutput::proceed())
+            .stop(|_| HookOutput::proceed());
+
+        assert_eq!(hooks.len(), 3);
+        assert_eq!(
+            hooks.config(),
+            json!({
+                "PreToolUse": [
+                    {"matcher": "Bash", "hookCallbackIds": ["hook_0"], "timeout": 30},
+                    {"matcher": "Write|Edit", "hookCallbackIds": ["hook_1"], "timeout": 30}
+                ],
+                "Stop": [
+                    {"hookCallbackIds": ["hook_2"], "timeout": 30}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_dispatch_runs_matching_callback() {
+        let hooks = HookRegistry::new()
+            .user_prompt_submit(|_| HookOutput::block("never called"))
+            .pre_tool_use(None, |input| {
+                if input.tool_input["command"] == "rm -rf /" {
+                    HookOutput::deny_tool("no")
+                } else {
+                    HookOutput::allow_tool()
+                }
+            });
+
+        let output = hooks
+            .dispatch(&callback(
+                "hook_1",
+                json!({
+                    "hook_event_name": "PreToolUse",
+                    "tool_name": "Bash",
+                    "tool_input": {"command": "rm -rf /"}
+                }),
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            output.hook_specific_output.unwrap().permission_decision,
+            Some(HookPermissionDecision::Deny)
+        );
+
+        assert!(!hooks.handles("hook_9"));
+        assert!(hooks.dispatch(&callback("hook_9", json!({}))).is_none());
+    }
+
+    #[test]
+    fn test_mismatched_input_never_reaches_callback() {
+        let hooks = HookRegistry::new()
+            .pre_tool_use(None, |_| HookOutput::allow_tool())
+            .stop(|_| HookOutput::proceed());
+        let stop_input = json!({"hook_event_name": "Stop", "stop_hook_active": false});
+
+        let error = hooks
+            .dispatch(&callback("hook_0", stop_input.clone()))
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.event, HookEvent::PreToolUse);
+        assert_eq!(error.message, "Expected PreToolUse input, got Stop");
+
+        // A PreToolUse hook fails closed by denying the tool
+        let response = hooks
+            .respond("req-1", &callback("hook_0", stop_input))
+            .unwrap();
+        match response.response {
+            ControlResponsePayload::Success { response, .. } => {
+                let output: HookOutput = serde_json::from_value(response.unwrap()).unwrap();
+                assert_eq!(
+                    output.hook_specific_output.unwrap().permission_decision,
+                    Some(HookPermissionDecision::Deny)
+                );
+            }
+            other => panic!("Expected a denial, got {:?}", other),
+        }
+
+        // Other hooks answer with a control error
+        let response = hooks
+            .respond("req-2", &callback("hook_1", json!({"no_event": true})))
+            .unwrap();
+        assert!(matches!(
+            response.response,
+            ControlResponsePayload::Error { .. }
+        ));
+    }
+
+    #[test]
+    fn test_respond_builds_control_response() {
+        let hooks = HookRegistry::new().on(HookEvent::Notification, None, |input| {
+            HookOutput::proceed().with_system_message(input.cwd().unwrap_or("?"))
+        });
+        let response = hooks
+            .respond(
+                "req-7",
+                &callback(
+                    "hook_0",
+                    json!({"hook_event_name": "Notification", "cwd": "/repo", "message": "hi"}),
+                ),
+            )
+            .unwrap();
+        match response.response {
+            ControlResponsePayload::Success {
+                request_id,
+                response,
+            } => {
+                assert_eq!(request_id, "req-7");
+                assert_eq!(response.unwrap(), json!({"systemMessage": "/repo"}));
+            }
+            other => panic!("Expected success, got {:?}", other),
+        }
+    }
+}
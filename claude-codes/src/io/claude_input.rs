@@ -33,6 +33,8 @@ impl ClaudeInput {
                 content: vec![ContentBlock::Text(TextBlock { text: text.into() })],
             },
             session_id: Some(session_id),
+            tool_use_result: None,
+            parent_tool_use_id: None,
         })
     }
 
@@ -44,6 +46,8 @@ impl ClaudeInput {
                 content: blocks,
             },
             session_id: Some(session_id),
+            tool_use_result: None,
+            parent_tool_use_id: None,
         })
     }
 
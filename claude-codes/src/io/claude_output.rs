@@ -7,6 +7,7 @@ use super::errors::{AnthropicError, ParseError};
 use super::message_types::{AssistantMessage, SystemMessage, UserMessage};
 use super::rate_limit::RateLimitEvent;
 use super::result::ResultMessage;
+use super::stream_event::StreamEventMessage;
 
 /// Top-level enum for all possible Claude output messages
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +36,9 @@ pub enum ClaudeOutput {
 
     /// Rate limit status event
     RateLimitEvent(RateLimitEvent),
+
+    /// Partial message event (with `--include-partial-messages`)
+    StreamEvent(StreamEventMessage),
 }
 
 impl ClaudeOutput {
@@ -49,6 +53,7 @@ impl ClaudeOutput {
             ClaudeOutput::ControlResponse(_) => "control_response".to_string(),
             ClaudeOutput::Error(_) => "error".to_string(),
             ClaudeOutput::RateLimitEvent(_) => "rate_limit_event".to_string(),
+            ClaudeOutput::StreamEvent(_) => "stream_event".to_string(),
         }
     }
 
@@ -110,6 +115,19 @@ impl ClaudeOutput {
         }
     }
 
+    /// Check if this is a partial message stream event
+    pub fn is_stream_event(&self) -> bool {
+        matches!(self, ClaudeOutput::StreamEvent(_))
+    }
+
+    /// Get the stream event if this is one
+    pub fn as_stream_event(&self) -> Option<&StreamEventMessage> {
+        match self {
+            ClaudeOutput::StreamEvent(evt) => Some(evt),
+            _ => None,
+        }
+    }
+
     /// Check if this is a result with error
     pub fn is_error(&self) -> bool {
         matches!(self, ClaudeOutput::Result(r) if r.is_error)
@@ -141,7 +159,8 @@ impl ClaudeOutput {
 
     /// Get the session ID from any message type that has one.
     ///
-    /// Returns the session ID from System, Assistant, or Result messages.
+    /// Returns the session ID from System, Assistant, Result, RateLimitEvent,
+    /// or StreamEvent messages.
     /// Returns `None` for User, ControlRequest, and ControlResponse messages.
     ///
     /// # Example
@@ -164,6 +183,7 @@ impl ClaudeOutput {
             ClaudeOutput::ControlResponse(_) => None,
             ClaudeOutput::Error(_) => None,
             ClaudeOutput::RateLimitEvent(evt) => Some(&evt.session_id),
+            ClaudeOutput::StreamEvent(evt) => Some(&evt.session_id),
         }
     }
 
@@ -159,6 +159,8 @@ pub struct ImageSource {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThinkingBlock {
     pub thinking: String,
+    /// Empty until the `signature_delta` arrives when streaming partial messages
+    #[serde(default)]
     pub signature: String,
 }
 
@@ -173,8 +175,9 @@ pub struct ToolUseBlock {
 impl ToolUseBlock {
     /// Try to parse the input as a typed ToolInput.
     ///
-    /// This attempts to deserialize the raw JSON input into a strongly-typed
-    /// `ToolInput` enum variant. Returns `None` if parsing fails.
+    /// The tool name selects the `ToolInput` variant (see
+    /// [`ToolInput::from_name_and_value`](crate::tool_inputs::ToolInput::from_name_and_value)).
+    /// Returns `None` if the input does not match the named tool's schema.
     ///
     /// # Example
     ///
@@ -193,14 +196,16 @@ impl ToolUseBlock {
     /// }
     /// ```
     pub fn typed_input(&self) -> Option<crate::tool_inputs::ToolInput> {
-        serde_json::from_value(self.input.clone()).ok()
+        self.try_typed_input().ok()
     }
 
     /// Parse the input as a typed ToolInput, returning an error on failure.
     ///
     /// Unlike `typed_input()`, this method returns the parsing error for debugging.
-    pub fn try_typed_input(&self) -> Result<crate::tool_inputs::ToolInput, serde_json::Error> {
-        serde_json::from_value(self.input.clone())
+    pub fn try_typed_input(
+        &self,
+    ) -> Result<crate::tool_inputs::ToolInput, crate::tool_inputs::ToolInputError> {
+        crate::tool_inputs::ToolInput::from_name_and_value(&self.name, self.input.clone())
     }
 }
 
@@ -159,13 +159,21 @@ impl<'de> Deserialize<'de> for PermissionBehavior {
     }
 }
 
-/// Named permission modes that can be set via `setMode`.
+/// Named permission modes that can be set via `setMode` or `set_permission_mode`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PermissionModeName {
     /// Accept all file edits without prompting.
     AcceptEdits,
     /// Bypass all permission checks.
     BypassPermissions,
+    /// Standard behavior, prompting for each sensitive action.
+    Default,
+    /// Delegate permission decisions.
+    Delegate,
+    /// Deny anything not pre-approved instead of prompting.
+    DontAsk,
+    /// Planning mode: analyze without modifying files.
+    Plan,
     /// A mode not yet known to this version of the crate.
     Unknown(String),
 }
@@ -175,6 +183,10 @@ impl PermissionModeName {
         match self {
             Self::AcceptEdits => "acceptEdits",
             Self::BypassPermissions => "bypassPermissions",
+            Self::Default => "default",
+            Self::Delegate => "delegate",
+            Self::DontAsk => "dontAsk",
+            Self::Plan => "plan",
             Self::Unknown(s) => s.as_str(),
         }
     }
@@ -191,6 +203,10 @@ impl From<&str> for PermissionModeName {
         match s {
             "acceptEdits" => Self::AcceptEdits,
             "bypassPermissions" => Self::BypassPermissions,
+            "default" => Self::Default,
+            "delegate" => Self::Delegate,
+            "dontAsk" => Self::DontAsk,
+            "plan" => Self::Plan,
             other => Self::Unknown(other.to_string()),
         }
     }
@@ -238,6 +254,27 @@ pub enum ControlRequestPayload {
     McpMessage(McpMessageRequest),
     /// Initialize request (sent by SDK to CLI)
     Initialize(InitializeRequest),
+    /// Interrupt the turn in progress (sent by SDK to CLI)
+    Interrupt,
+    /// Change the permission mode mid-session (sent by SDK to CLI)
+    SetPermissionMode(SetPermissionModeRequest),
+    /// Change the model mid-session (sent by SDK to CLI)
+    SetModel(SetModelRequest),
+}
+
+impl ControlRequestPayload {
+    /// The wire `subtype` of this payload
+    pub fn subtype(&self) -> &'static str {
+        match self {
+            ControlRequestPayload::CanUseTool(_) => "can_use_tool",
+            ControlRequestPayload::HookCallback(_) => "hook_callback",
+            ControlRequestPayload::McpMessage(_) => "mcp_message",
+            ControlRequestPayload::Initialize(_) => "initialize",
+            ControlRequestPayload::Interrupt => "interrupt",
+            ControlRequestPayload::SetPermissionMode(_) => "set_permission_mode",
+            ControlRequestPayload::SetModel(_) => "set_model",
+        }
+    }
 }
 
 /// A permission to grant for "remember this decision" functionality.
@@ -469,6 +506,30 @@ pub struct ToolPermissionRequest {
 }
 
 impl ToolPermissionRequest {
+    /// Parse the input into a typed [`ToolInput`](crate::tool_inputs::ToolInput), keyed by `tool_name`.
+    ///
+    /// # Example
+    /// ```
+    /// # use claude_codes::{ToolInput, ToolPermissionRequest};
+    /// # use serde_json::json;
+    /// let req = ToolPermissionRequest {
+    ///     tool_name: "Read".to_string(),
+    ///     input: json!({"file_path": "/tmp/test.txt"}),
+    ///     permission_suggestions: vec![],
+    ///     blocked_path: None,
+    ///     decision_reason: None,
+    ///     tool_use_id: None,
+    /// };
+    /// if let Ok(ToolInput::Read(read)) = req.typed_input() {
+    ///     assert_eq!(read.file_path, "/tmp/test.txt");
+    /// }
+    /// ```
+    pub fn typed_input(
+        &self,
+    ) -> Result<crate::tool_inputs::ToolInput, crate::tool_inputs::ToolInputError> {
+        crate::tool_inputs::ToolInput::from_name_and_value(&self.tool_name, self.input.clone())
+    }
+
     /// Allow the tool to execute with its original input.
     ///
     /// # Example
@@ -751,6 +812,28 @@ pub struct McpMessageRequest {
 pub struct InitializeRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hooks: Option<Value>,
+    /// Names of MCP servers hosted in-process by the SDK
+    #[serde(
+        rename = "sdkMcpServers",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sdk_mcp_servers: Option<Vec<String>>,
+}
+
+/// Set permission mode request (SDK -> CLI)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetPermissionModeRequest {
+    pub mode: PermissionModeName,
+}
+
+/// Set model request (SDK -> CLI)
+///
+/// A `model` of `None` asks the CLI to return to its default model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetModelRequest {
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 /// Control response to CLI
@@ -825,6 +908,16 @@ pub enum ControlResponsePayload {
     },
 }
 
+impl ControlResponsePayload {
+    /// The ID of the request this payload answers
+    pub fn request_id(&self) -> &str {
+        match self {
+            ControlResponsePayload::Success { request_id, .. }
+            | ControlResponsePayload::Error { request_id, .. } => request_id,
+        }
+    }
+}
+
 /// Wrapper for outgoing control responses (includes type tag)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ControlResponseMessage {
@@ -852,22 +945,56 @@ pub struct ControlRequestMessage {
 }
 
 impl ControlRequestMessage {
-    /// Create an initialization request to send to CLI
-    pub fn initialize(request_id: impl Into<String>) -> Self {
+    /// Create a control request to send to CLI with an arbitrary payload
+    pub fn new(request_id: impl Into<String>, request: ControlRequestPayload) -> Self {
         ControlRequestMessage {
             message_type: "control_request".to_string(),
             request_id: request_id.into(),
-            request: ControlRequestPayload::Initialize(InitializeRequest { hooks: None }),
+            request,
         }
     }
 
+    /// Create an initialization request to send to CLI
+    pub fn initialize(request_id: impl Into<String>) -> Self {
+        Self::new(
+            request_id,
+            ControlRequestPayload::Initialize(InitializeRequest {
+                hooks: None,
+                sdk_mcp_servers: None,
+            }),
+        )
+    }
+
     /// Create an initialization request with hooks configuration
     pub fn initialize_with_hooks(request_id: impl Into<String>, hooks: Value) -> Self {
-        ControlRequestMessage {
-            message_type: "control_request".to_string(),
-            request_id: request_id.into(),
-            request: ControlRequestPayload::Initialize(InitializeRequest { hooks: Some(hooks) }),
-        }
+        Self::new(
+            request_id,
+            ControlRequestPayload::Initialize(InitializeRequest {
+                hooks: Some(hooks),
+                sdk_mcp_servers: None,
+            }),
+        )
+    }
+
+    /// Create a request to interrupt the turn in progress
+    pub fn interrupt(request_id: impl Into<String>) -> Self {
+        Self::new(request_id, ControlRequestPayload::Interrupt)
+    }
+
+    /// Create a request to change the permission mode
+    pub fn set_permission_mode(request_id: impl Into<String>, mode: PermissionModeName) -> Self {
+        Self::new(
+            request_id,
+            ControlRequestPayload::SetPermissionMode(SetPermissionModeRequest { mode }),
+        )
+    }
+
+    /// Create a request to change the model (`None` restores the default)
+    pub fn set_model(request_id: impl Into<String>, model: Option<String>) -> Self {
+        Self::new(
+            request_id,
+            ControlRequestPayload::SetModel(SetModelRequest { model }),
+        )
     }
 }
 
@@ -1060,6 +1187,78 @@ mod tests {
         assert!(json.contains("\"subtype\":\"initialize\""));
     }
 
+    #[test]
+    fn test_control_request_message_interrupt() {
+        let msg = ControlRequestMessage::interrupt("int-1");
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "control_request",
+                "request_id": "int-1",
+                "request": {"subtype": "interrupt"}
+            })
+        );
+
+        let parsed: ControlRequestMessage = serde_json::from_value(json).unwrap();
+        assert!(matches!(parsed.request, ControlRequestPayload::Interrupt));
+        assert_eq!(parsed.request.subtype(), "interrupt");
+    }
+
+    #[test]
+    fn test_control_request_message_set_permission_mode() {
+        let msg = ControlRequestMessage::set_permission_mode("mode-1", PermissionModeName::Plan);
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["request"]["subtype"], "set_permission_mode");
+        assert_eq!(json["request"]["mode"], "plan");
+
+        let parsed: ControlRequestMessage = serde_json::from_value(json).unwrap();
+        match parsed.request {
+            ControlRequestPayload::SetPermissionMode(req) => {
+                assert_eq!(req.mode, PermissionModeName::Plan)
+            }
+            other => panic!("Expected SetPermissionMode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_control_request_message_set_model() {
+        let msg = ControlRequestMessage::set_model("model-1", Some("opus".to_string()));
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["request"]["subtype"], "set_model");
+        assert_eq!(json["request"]["model"], "opus");
+
+        // Resetting to the default model sends an explicit null
+        let reset = ControlRequestMessage::set_model("model-2", None);
+        let json = serde_json::to_value(&reset).unwrap();
+        assert!(json["request"]["model"].is_null());
+        assert_eq!(reset.request.subtype(), "set_model");
+    }
+
+    #[test]
+    fn test_permission_mode_name_roundtrip() {
+        for mode in [
+            "acceptEdits",
+            "bypassPermissions",
+            "default",
+            "delegate",
+            "dontAsk",
+            "plan",
+        ] {
+            let parsed = PermissionModeName::from(mode);
+            assert!(
+                !matches!(parsed, PermissionModeName::Unknown(_)),
+                "{}",
+                mode
+            );
+            assert_eq!(parsed.as_str(), mode);
+        }
+        assert_eq!(
+            PermissionModeName::from("futureMode"),
+            PermissionModeName::Unknown("futureMode".to_string())
+        );
+    }
+
     #[test]
     fn test_control_response_error() {
         let response = ControlResponse::error("req-err", "Something went wrong");
@@ -0,0 +1,676 @@
+//! Hook types for the control protocol.
+//!
+//! Hooks are registered in the `initialize` control request as a map from
+//! [`HookEvent`] name to a list of [`HookMatcher`]s. When a hook fires, the CLI
+//! sends a `hook_callback` control request whose `input` is a [`HookInput`], and
+//! expects a [`HookOutput`] as the success response body.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+use super::CompactionTrigger;
+use crate::tool_inputs::ToolInput;
+
+// ============================================================================
+// Hook Enums
+// ============================================================================
+
+/// The lifecycle event a hook is attached to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HookEvent {
+    /// Before a tool is executed; can allow, deny or rewrite the call.
+    PreToolUse,
+    /// After a tool has executed; can add context or block.
+    PostToolUse,
+    /// When the user submits a prompt; can add context or block.
+    UserPromptSubmit,
+    /// When the main agent finishes responding.
+    Stop,
+    /// When a subagent finishes responding.
+    SubagentStop,
+    /// Before the conversation is compacted.
+    PreCompact,
+    /// When the CLI emits a notification.
+    Notification,
+    /// When a session starts or resumes.
+    SessionStart,
+    /// When a session ends.
+    SessionEnd,
+    /// An event not yet known to this version of the crate.
+    Unknown(String),
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::PreToolUse => "PreToolUse",
+            Self::PostToolUse => "PostToolUse",
+            Self::UserPromptSubmit => "UserPromptSubmit",
+            Self::Stop => "Stop",
+            Self::SubagentStop => "SubagentStop",
+            Self::PreCompact => "PreCompact",
+            Self::Notification => "Notification",
+            Self::SessionStart => "SessionStart",
+            Self::SessionEnd => "SessionEnd",
+            Self::Unknown(s) => s.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for HookEvent {
+    fn from(s: &str) -> Self {
+        match s {
+            "PreToolUse" => Self::PreToolUse,
+            "PostToolUse" => Self::PostToolUse,
+            "UserPromptSubmit" => Self::UserPromptSubmit,
+            "Stop" => Self::Stop,
+            "SubagentStop" => Self::SubagentStop,
+            "PreCompact" => Self::PreCompact,
+            "Notification" => Self::Notification,
+            "SessionStart" => Self::SessionStart,
+            "SessionEnd" => Self::SessionEnd,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl Serialize for HookEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for HookEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from(s.as_str()))
+    }
+}
+
+/// Top-level decision returned by a hook.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HookDecision {
+    /// Approve the action.
+    Approve,
+    /// Block the action; `reason` is shown to Claude.
+    Block,
+    /// A decision not yet known to this version of the crate.
+    Unknown(String),
+}
+
+impl HookDecision {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Approve => "approve",
+            Self::Block => "block",
+            Self::Unknown(s) => s.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for HookDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for HookDecision {
+    fn from(s: &str) -> Self {
+        match s {
+            "approve" => Self::Approve,
+            "block" => Self::Block,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl Serialize for HookDecision {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for HookDecision {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from(s.as_str()))
+    }
+}
+
+/// Permission decision returned by a `PreToolUse` hook.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HookPermissionDecision {
+    /// Allow the tool call without prompting.
+    Allow,
+    /// Deny the tool call.
+    Deny,
+    /// Fall back to the normal permission prompt.
+    Ask,
+    /// A decision not yet known to this version of the crate.
+    Unknown(String),
+}
+
+impl HookPermissionDecision {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+            Self::Ask => "ask",
+            Self::Unknown(s) => s.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for HookPermissionDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for HookPermissionDecision {
+    fn from(s: &str) -> Self {
+        match s {
+            "allow" => Self::Allow,
+            "deny" => Self::Deny,
+            "ask" => Self::Ask,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl Serialize for HookPermissionDecision {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for HookPermissionDecision {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from(s.as_str()))
+    }
+}
+
+// ============================================================================
+// Hook Configuration
+// ============================================================================
+
+/// One entry in the `hooks` map of an `initialize` request.
+///
+/// `matcher` is a tool-name pattern for tool events (e.g. `"Bash"` or
+/// `"Write|Edit"`); `None` matches everything.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookMatcher {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    pub hook_callback_ids: Vec<String>,
+    /// Timeout in seconds for the callback
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+// ============================================================================
+// Hook Inputs
+// ============================================================================
+
+/// Input of a `hook_callback` control request.
+///
+/// Common fields (`session_id`, `transcript_path`, `cwd`, `permission_mode`)
+/// and event-specific fields are kept in `data`; use the `as_*` methods to
+/// parse the event-specific part.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookInput {
+    pub hook_event_name: HookEvent,
+    #[serde(flatten)]
+    pub data: Value, // Captures all other fields
+}
+
+impl HookInput {
+    /// The session the hook fired in
+    pub fn session_id(&self) -> Option<&str> {
+        self.data.get("session_id").and_then(Value::as_str)
+    }
+
+    /// Path to the session transcript file
+    pub fn transcript_path(&self) -> Option<&str> {
+        self.data.get("transcript_path").and_then(Value::as_str)
+    }
+
+    /// Working directory of the session
+    pub fn cwd(&self) -> Option<&str> {
+        self.data.get("cwd").and_then(Value::as_str)
+    }
+
+    /// Try to parse as a `PreToolUse` input
+    pub fn as_pre_tool_use(&self) -> Option<PreToolUseHookInput> {
+        self.parse_if(HookEvent::PreToolUse)
+    }
+
+    /// Try to parse as a `PostToolUse` input
+    pub fn as_post_tool_use(&self) -> Option<PostToolUseHookInput> {
+        self.parse_if(HookEvent::PostToolUse)
+    }
+
+    /// Try to parse as a `UserPromptSubmit` input
+    pub fn as_user_prompt_submit(&self) -> Option<UserPromptSubmitHookInput> {
+        self.parse_if(HookEvent::UserPromptSubmit)
+    }
+
+    /// Try to parse as a `Stop` input
+    pub fn as_stop(&self) -> Option<StopHookInput> {
+        self.parse_if(HookEvent::Stop)
+    }
+
+    /// Try to parse as a `SubagentStop` input
+    pub fn as_subagent_stop(&self) -> Option<StopHookInput> {
+        self.parse_if(HookEvent::SubagentStop)
+    }
+
+    /// Try to parse as a `PreCompact` input
+    pub fn as_pre_compact(&self) -> Option<PreCompactHookInput> {
+        self.parse_if(HookEvent::PreCompact)
+    }
+
+    /// Try to parse as a `Notification` input
+    pub fn as_notification(&self) -> Option<NotificationHookInput> {
+        self.parse_if(HookEvent::Notification)
+    }
+
+    /// Try to parse as a `SessionStart` input
+    pub fn as_session_start(&self) -> Option<SessionStartHookInput> {
+        self.parse_if(HookEvent::SessionStart)
+    }
+
+    /// Try to parse as a `SessionEnd` input
+    pub fn as_session_end(&self) -> Option<SessionEndHookInput> {
+        self.parse_if(HookEvent::SessionEnd)
+    }
+
+    fn parse_if<T: for<'de> Deserialize<'de>>(&self, event: HookEvent) -> Option<T> {
+        if self.hook_event_name != event {
+            return None;
+        }
+        serde_json::from_value(self.data.clone()).ok()
+    }
+}
+
+/// `PreToolUse` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreToolUseHookInput {
+    pub tool_name: String,
+    pub tool_input: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+}
+
+impl PreToolUseHookInput {
+    /// Try to parse the tool input into a typed [`ToolInput`], keyed by `tool_name`
+    pub fn typed_tool_input(&self) -> Option<ToolInput> {
+        ToolInput::from_name_and_value(&self.tool_name, self.tool_input.clone()).ok()
+    }
+}
+
+/// `PostToolUse` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostToolUseHookInput {
+    pub tool_name: String,
+    pub tool_input: Value,
+    #[serde(default)]
+    pub tool_response: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+}
+
+/// `UserPromptSubmit` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserPromptSubmitHookInput {
+    pub prompt: String,
+}
+
+/// `Stop` / `SubagentStop` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopHookInput {
+    /// True when the agent is already continuing because of a previous stop hook
+    #[serde(default)]
+    pub stop_hook_active: bool,
+}
+
+/// `PreCompact` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreCompactHookInput {
+    pub trigger: CompactionTrigger,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_instructions: Option<String>,
+}
+
+/// `Notification` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationHookInput {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// `SessionStart` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStartHookInput {
+    /// How the session started (e.g. `startup`, `resume`, `clear`, `compact`)
+    pub source: String,
+}
+
+/// `SessionEnd` hook input
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEndHookInput {
+    /// Why the session ended (e.g. `clear`, `logout`, `other`)
+    pub reason: String,
+}
+
+// ============================================================================
+// Hook Outputs
+// ============================================================================
+
+/// Response body for a `hook_callback` control request.
+///
+/// The default value lets the action continue unchanged.
+///
+/// # Example
+///
+/// ```
+/// use claude_codes::HookOutput;
+///
+/// let deny = HookOutput::deny_tool("rm -rf is not allowed");
+/// let json = serde_json::to_value(&deny).unwrap();
+/// assert_eq!(json["hookSpecificOutput"]["permissionDecision"], "deny");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookOutput {
+    /// Whether Claude should continue after the hook (`false` stops the turn)
+    #[serde(rename = "continue", skip_serializing_if = "Option::is_none")]
+    pub continue_: Option<bool>,
+    /// Hide the hook's output from the transcript
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress_output: Option<bool>,
+    /// Message shown to the user when `continue` is false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<HookDecision>,
+    /// Warning shown to the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
+    /// Explanation for `decision`, shown to Claude
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook_specific_output: Option<HookSpecificOutput>,
+}
+
+/// Event-specific part of a [`HookOutput`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookSpecificOutput {
+    pub hook_event_name: HookEvent,
+    /// `PreToolUse`: whether to allow, deny or ask
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision: Option<HookPermissionDecision>,
+    /// `PreToolUse`: explanation for the permission decision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision_reason: Option<String>,
+    /// `PreToolUse`: replacement tool input
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<Value>,
+    /// `PostToolUse` / `UserPromptSubmit` / `SessionStart`: context added for Claude
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_context: Option<String>,
+}
+
+impl HookSpecificOutput {
+    fn new(hook_event_name: HookEvent) -> Self {
+        Self {
+            hook_event_name,
+            permission_decision: None,
+            permission_decision_reason: None,
+            updated_input: None,
+            additional_context: None,
+        }
+    }
+}
+
+impl HookOutput {
+    /// Let the action continue unchanged
+    pub fn proceed() -> Self {
+        Self::default()
+    }
+
+    /// `PreToolUse`: allow the tool call without prompting
+    pub fn allow_tool() -> Self {
+        Self::permission(HookPermissionDecision::Allow, None, None)
+    }
+
+    /// `PreToolUse`: allow the tool call with a rewritten input
+    pub fn allow_tool_with(updated_input: Value) -> Self {
+        Self::permission(HookPermissionDecision::Allow, None, Some(updated_input))
+    }
+
+    /// `PreToolUse`: deny the tool call, telling Claude why
+    pub fn deny_tool(reason: impl Into<String>) -> Self {
+        Self::permission(HookPermissionDecision::Deny, Some(reason.into()), None)
+    }
+
+    /// `PreToolUse`: fall back to the normal permission prompt
+    pub fn ask(reason: impl Into<String>) -> Self {
+        Self::permission(HookPermissionDecision::Ask, Some(reason.into()), None)
+    }
+
+    /// Block the action (`PostToolUse`, `UserPromptSubmit`, `Stop`), telling Claude why
+    pub fn block(reason: impl Into<String>) -> Self {
+        Self {
+            decision: Some(HookDecision::Block),
+            reason: Some(reason.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Stop the turn entirely, showing `reason` to the user
+    pub fn stop(reason: impl Into<String>) -> Self {
+        Self {
+            continue_: Some(false),
+            stop_reason: Some(reason.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Add context for Claude (`PostToolUse`, `UserPromptSubmit`, `SessionStart`)
+    pub fn with_context(mut self, event: HookEvent, context: impl Into<String>) -> Self {
+        let specific = self
+            .hook_specific_output
+            .get_or_insert_with(|| HookSpecificOutput::new(event));
+        specific.additional_context = Some(context.into());
+        self
+    }
+
+    /// Attach a warning shown to the user
+    pub fn with_system_message(mut self, message: impl Into<String>) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+
+    fn permission(
+        decision: HookPermissionDecision,
+        reason: Option<String>,
+        updated_input: Option<Value>,
+    ) -> Self {
+        let mut specific = HookSpecificOutput::new(HookEvent::PreToolUse);
+        specific.permission_decision = Some(decision);
+        specific.permission_decision_reason = reason;
+        specific.updated_input = updated_input;
+        Self {
+            hook_specific_output: Some(specific),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_hook_event_roundtrip() {
+        for name in [
+            "PreToolUse",
+            "PostToolUse",
+            "UserPromptSubmit",
+            "Stop",
+            "SubagentStop",
+            "PreCompact",
+            "Notification",
+            "SessionStart",
+            "SessionEnd",
+        ] {
+            let event = HookEvent::from(name);
+            assert!(!matches!(event, HookEvent::Unknown(_)), "{}", name);
+            assert_eq!(event.as_str(), name);
+        }
+        assert_eq!(
+            HookEvent::from("FutureEvent"),
+            HookEvent::Unknown("FutureEvent".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hook_matcher_serialization() {
+        let matcher = HookMatcher {
+            matcher: Some("Bash".to_string()),
+            hook_callback_ids: vec!["hook_0".to_string()],
+            timeout: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&matcher).unwrap(),
+            json!({"matcher": "Bash", "hookCallbackIds": ["hook_0"]})
+        );
+    }
+
+    #[test]
+    fn test_parse_pre_tool_use_input() {
+        let input: HookInput = serde_json::from_value(json!({
+            "session_id": "abc",
+            "transcript_path": "/tmp/t.jsonl",
+            "cwd": "/repo",
+            "permission_mode": "default",
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Bash",
+            "tool_input": {"command": "ls -la"},
+            "tool_use_id": "toolu_1"
+        }))
+        .unwrap();
+
+        assert_eq!(input.hook_event_name, HookEvent::PreToolUse);
+        assert_eq!(input.session_id(), Some("abc"));
+        assert_eq!(input.cwd(), Some("/repo"));
+        assert!(input.as_post_tool_use().is_none());
+
+        let pre = input.as_pre_tool_use().unwrap();
+        assert_eq!(pre.tool_name, "Bash");
+        assert_eq!(pre.tool_use_id.as_deref(), Some("toolu_1"));
+        match pre.typed_tool_input() {
+            Some(ToolInput::Bash(bash)) => assert_eq!(bash.command, "ls -la"),
+            other => panic!("Expected Bash input, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_other_hook_inputs() {
+        let prompt: HookInput = serde_json::from_value(json!({
+            "session_id": "abc",
+            "hook_event_name": "UserPromptSubmit",
+            "prompt": "hello"
+        }))
+        .unwrap();
+        assert_eq!(prompt.as_user_prompt_submit().unwrap().prompt, "hello");
+
+        let stop: HookInput = serde_json::from_value(json!({
+            "hook_event_name": "SubagentStop",
+            "stop_hook_active": true
+        }))
+        .unwrap();
+        assert!(stop.as_stop().is_none());
+        assert!(stop.as_subagent_stop().unwrap().stop_hook_active);
+
+        let compact: HookInput = serde_json::from_value(json!({
+            "hook_event_name": "PreCompact",
+            "trigger": "manual",
+            "custom_instructions": "keep the plan"
+        }))
+        .unwrap();
+        let compact = compact.as_pre_compact().unwrap();
+        assert_eq!(compact.trigger, CompactionTrigger::Manual);
+        assert_eq!(
+            compact.custom_instructions.as_deref(),
+            Some("keep the plan")
+        );
+    }
+
+    #[test]
+    fn test_hook_output_default_is_empty_object() {
+        assert_eq!(
+            serde_json::to_value(HookOutput::proceed()).unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn test_hook_output_deny_tool() {
+        let json = serde_json::to_value(HookOutput::deny_tool("no")).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "hookSpecificOutput": {
+                    "hookEventName": "PreToolUse",
+                    "permissionDecision": "deny",
+                    "permissionDecisionReason": "no"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_hook_output_allow_with_and_context() {
+        let json =
+            serde_json::to_value(HookOutput::allow_tool_with(json!({"command": "ls"}))).unwrap();
+        assert_eq!(json["hookSpecificOutput"]["permissionDecision"], "allow");
+        assert_eq!(json["hookSpecificOutput"]["updatedInput"]["command"], "ls");
+
+        let json = serde_json::to_value(
+            HookOutput::proceed().with_context(HookEvent::PostToolUse, "tests passed"),
+        )
+        .unwrap();
+        assert_eq!(json["hookSpecificOutput"]["hookEventName"], "PostToolUse");
+        assert_eq!(
+            json["hookSpecificOutput"]["additionalContext"],
+            "tests passed"
+        );
+    }
+
+    #[test]
+    fn test_hook_output_block_and_stop() {
+        let json = serde_json::to_value(HookOutput::block("not yet")).unwrap();
+        assert_eq!(json, json!({"decision": "block", "reason": "not yet"}));
+
+        let json = serde_json::to_value(HookOutput::stop("budget exceeded")).unwrap();
+        assert_eq!(
+            json,
+            json!({"continue": false, "stopReason": "budget exceeded"})
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 /// MCP Server configuration types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,6 +8,8 @@ pub enum McpServerConfig {
     Stdio(McpStdioServerConfig),
     Sse(McpSseServerConfig),
     Http(McpHttpServerConfig),
+    /// A server hosted in-process by the SDK and reached over the control channel
+    Sdk(McpSdkServerConfig),
 }
 
 /// MCP stdio server configuration
@@ -34,3 +37,76 @@ pub struct McpHttpServerConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<std::collections::HashMap<String, String>>,
 }
+
+/// MCP SDK server configuration
+///
+/// Tells the CLI to route this server's traffic through `mcp_message` control
+/// requests instead of starting a process or opening a connection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpSdkServerConfig {
+    pub name: String,
+}
+
+// ============================================================================
+// MCP Tool Types
+// ============================================================================
+
+/// A tool advertised in an MCP `tools/list` response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolDefinition {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON Schema describing the tool's arguments
+    pub input_schema: Value,
+}
+
+/// A content item in an MCP `tools/call` result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum McpContent {
+    Text {
+        text: String,
+    },
+    Image {
+        /// Base64-encoded image data
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+/// Result of an MCP `tools/call` request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolResult {
+    pub content: Vec<McpContent>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl McpToolResult {
+    /// A successful result with a single text item
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![McpContent::Text { text: text.into() }],
+            is_error: false,
+        }
+    }
+
+    /// A successful result containing pretty-printed JSON
+    pub fn json(value: &Value) -> Self {
+        Self::text(serde_json::to_string_pretty(value).unwrap_or_default())
+    }
+
+    /// A failed tool call; the message is shown to Claude
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            content: vec![McpContent::Text {
+                text: message.into(),
+            }],
+            is_error: true,
+        }
+    }
+}
@@ -446,6 +446,13 @@ pub struct UserMessage {
         deserialize_with = "deserialize_optional_uuid"
     )]
     pub session_id: Option<Uuid>,
+    /// Structured result of the tool call this message answers, decoded with
+    /// [`ToolOutput::from_name_and_value`](crate::tool_results::ToolOutput::from_name_and_value)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_result: Option<Value>,
+    /// The `Task` tool call whose subagent this message belongs to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_tool_use_id: Option<String>,
 }
 
 /// Message content with role
@@ -34,17 +34,21 @@ mod claude_output;
 mod content_blocks;
 mod control;
 mod errors;
+mod hooks;
 mod mcp;
 mod message_types;
 mod rate_limit;
 mod result;
+mod stream_event;
 
 pub use claude_input::*;
 pub use claude_output::*;
 pub use content_blocks::*;
 pub use control::*;
 pub use errors::*;
+pub use hooks::*;
 pub use mcp::*;
 pub use message_types::*;
 pub use rate_limit::*;
 pub use result::*;
+pub use stream_event::*;
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 
 /// Result message for completed queries
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,10 +31,39 @@ pub struct ResultMessage {
     #[serde(default)]
     pub errors: Vec<String>,
 
+    /// Cumulative usage and cost for the session, broken down by model
+    #[serde(
+        default,
+        rename = "modelUsage",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub model_usage: HashMap<String, ModelUsage>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uuid: Option<String>,
 }
 
+/// Per-model usage from `ResultMessage.model_usage`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub web_search_requests: u64,
+    /// Cost in USD
+    #[serde(default, rename = "costUSD")]
+    pub cost_usd: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u64>,
+}
+
 /// A record of a tool permission that was denied during the session.
 ///
 /// This is included in `ResultMessage.permission_denials` to provide a summary
@@ -100,6 +130,38 @@ mod tests {
         assert!(!output.is_error());
     }
 
+    #[test]
+    fn test_deserialize_result_model_usage() {
+        let json = r#"{
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "duration_ms": 100,
+            "duration_api_ms": 200,
+            "num_turns": 1,
+            "session_id": "123",
+            "total_cost_usd": 0.05,
+            "modelUsage": {
+                "claude-sonnet-4-5": {
+                    "inputTokens": 10,
+                    "outputTokens": 20,
+                    "cacheReadInputTokens": 300,
+                    "cacheCreationInputTokens": 40,
+                    "webSearchRequests": 1,
+                    "costUSD": 0.05,
+                    "contextWindow": 200000
+                }
+            }
+        }"#;
+
+        let output: ClaudeOutput = serde_json::from_str(json).unwrap();
+        let result = output.as_result().unwrap();
+        let usage = &result.model_usage["claude-sonnet-4-5"];
+        assert_eq!(usage.cache_read_input_tokens, 300);
+        assert_eq!(usage.cost_usd, 0.05);
+        assert_eq!(usage.context_window, Some(200000));
+    }
+
     #[test]
     fn test_deserialize_result_with_permission_denials() {
         let json = r#"{
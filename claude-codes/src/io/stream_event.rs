@@ -0,0 +1,472 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::content_blocks::{ContentBlock, TextBlock, ThinkingBlock};
+use super::message_types::{AssistantMessage, AssistantMessageContent, AssistantUsage, StopReason};
+
+/// Partial message event emitted with `--include-partial-messages`
+///
+/// Each line wraps one raw Anthropic streaming event. The complete
+/// `assistant` message still follows once the API message finishes; use a
+/// [`PartialMessageAccumulator`] to rebuild it incrementally in the meantime.
+///
+/// # Example
+/// ```
+/// use claude_codes::{ClaudeOutput, StreamEvent};
+///
+/// let json = r#"{"type":"stream_event","session_id":"abc","uuid":"u1",
+///     "event":{"type":"content_block_delta","index":0,
+///     "delta":{"type":"text_delta","text":"Hel"}}}"#;
+/// let output: ClaudeOutput = serde_json::from_str(json).unwrap();
+///
+/// let event = output.as_stream_event().unwrap();
+/// assert_eq!(event.event.text_delta(), Some("Hel"));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamEventMessage {
+    /// The raw streaming event
+    pub event: StreamEvent,
+    /// Session identifier
+    pub session_id: String,
+    /// Unique identifier for this message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// Set when the event belongs to a subagent's tool use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_tool_use_id: Option<String>,
+}
+
+/// A raw Anthropic streaming event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Start of an API message; `content` is empty
+    MessageStart { message: AssistantMessageContent },
+
+    /// Start of a content block at `index`
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+
+    /// Incremental update to the content block at `index`
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentBlockDelta,
+    },
+
+    /// End of the content block at `index`
+    ContentBlockStop { index: usize },
+
+    /// Top-level message changes (stop reason, cumulative usage)
+    MessageDelta {
+        delta: MessageDeltaBody,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<AssistantUsage>,
+    },
+
+    /// End of the API message
+    MessageStop,
+
+    /// Keep-alive
+    Ping,
+
+    /// Error reported mid-stream
+    Error { error: Value },
+
+    /// An event type not yet known to this version of the crate
+    #[serde(other)]
+    Unknown,
+}
+
+impl StreamEvent {
+    /// The text appended by a `text_delta`, if this is one
+    pub fn text_delta(&self) -> Option<&str> {
+        match self {
+            StreamEvent::ContentBlockDelta {
+                delta: ContentBlockDelta::TextDelta { text },
+                ..
+            } => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The thinking text appended by a `thinking_delta`, if this is one
+    pub fn thinking_delta(&self) -> Option<&str> {
+        match self {
+            StreamEvent::ContentBlockDelta {
+                delta: ContentBlockDelta::ThinkingDelta { thinking },
+                ..
+            } => Some(thinking),
+            _ => None,
+        }
+    }
+
+    /// The content block index this event refers to, if any
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            StreamEvent::ContentBlockStart { index, .. }
+            | StreamEvent::ContentBlockDelta { index, .. }
+            | StreamEvent::ContentBlockStop { index } => Some(*index),
+            _ => None,
+        }
+    }
+}
+
+/// Incremental content for a single content block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlockDelta {
+    /// Text appended to a text block
+    TextDelta { text: String },
+
+    /// A fragment of a tool use's input JSON
+    ///
+    /// Fragments are only valid JSON once concatenated up to `content_block_stop`.
+    InputJsonDelta { partial_json: String },
+
+    /// Text appended to a thinking block
+    ThinkingDelta { thinking: String },
+
+    /// Signature for a thinking block
+    SignatureDelta { signature: String },
+
+    /// A delta type not yet known to this version of the crate
+    #[serde(other)]
+    Unknown,
+}
+
+/// Body of a `message_delta` event
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageDeltaBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<String>,
+}
+
+/// Rebuilds assistant messages from `stream_event` deltas
+///
+/// Feed every [`StreamEventMessage`] to [`push`](Self::push). While a message
+/// is in flight, [`current`](Self::current) exposes the content received so
+/// far (tool inputs stay `{}` until their block ends; see
+/// [`partial_tool_input`](Self::partial_tool_input)). On `message_stop` the
+/// finished [`AssistantMessage`] is returned.
+///
+/// # Example
+/// ```
+/// use claude_codes::{ClaudeOutput, PartialMessageAccumulator};
+///
+/// let lines = [
+///     r#"{"type":"message_start","message":{"id":"msg_1","role":"assistant","model":"claude-sonnet-4","content":[]}}"#,
+///     r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+///     r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello, "}}"#,
+///     r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"world!"}}"#,
+///     r#"{"type":"content_block_stop","index":0}"#,
+///     r#"{"type":"message_stop"}"#,
+/// ];
+///
+/// let mut accumulator = PartialMessageAccumulator::new();
+/// let mut finished = None;
+/// for event in lines {
+///     let json = format!(r#"{{"type":"stream_event","session_id":"abc","event":{}}}"#, event);
+///     if let ClaudeOutput::StreamEvent(msg) = serde_json::from_str(&json).unwrap() {
+///         finished = accumulator.push(&msg).or(finished);
+///     }
+/// }
+///
+/// let message = finished.unwrap();
+/// assert_eq!(message.session_id, "abc");
+/// assert_eq!(ClaudeOutput::Assistant(message).text_content().unwrap(), "Hello, world!");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PartialMessageAccumulator {
+    message: Option<AssistantMessageContent>,
+    session_id: String,
+    uuid: Option<String>,
+    parent_tool_use_id: Option<String>,
+    partial_json: BTreeMap<usize, String>,
+}
+
+impl PartialMessageAccumulator {
+    /// Create an empty accumulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a `stream_event` message
+    ///
+    /// Returns the completed assistant message on `message_stop`.
+    pub fn push(&mut self, msg: &StreamEventMessage) -> Option<AssistantMessage> {
+        if matches!(msg.event, StreamEvent::MessageStart { .. }) {
+            self.session_id = msg.session_id.clone();
+            self.uuid = msg.uuid.clone();
+            self.parent_tool_use_id = msg.parent_tool_use_id.clone();
+        }
+        let content = self.apply(&msg.event)?;
+        Some(AssistantMessage {
+            message: content,
+            session_id: std::mem::take(&mut self.session_id),
+            uuid: self.uuid.take(),
+            parent_tool_use_id: self.parent_tool_use_id.take(),
+        })
+    }
+
+    /// Apply a raw streaming event
+    ///
+    /// Returns the completed message content on `message_stop`. Events that
+    /// arrive without a preceding `message_start`, and blocks that start past
+    /// the next free index, are ignored.
+    pub fn apply(&mut self, event: &StreamEvent) -> Option<AssistantMessageContent> {
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.message = Some(message.clone());
+                self.partial_json.clear();
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                let message = self.message.as_mut()?;
+                match message.content.len().cmp(index) {
+                    std::cmp::Ordering::Greater => message.content[*index] = content_block.clone(),
+                    std::cmp::Ordering::Equal => message.content.push(content_block.clone()),
+                    // A block would land past the end; skip it rather than misplace it
+                    std::cmp::Ordering::Less => return None,
+                }
+                if let ContentBlock::ToolUse(_) = content_block {
+                    self.partial_json.insert(*index, String::new());
+                }
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                let block = self.message.as_mut()?.content.get_mut(*index)?;
+                match (block, delta) {
+                    (
+                        ContentBlock::Text(TextBlock { text }),
+                        ContentBlockDelta::TextDelta { text: more },
+                    ) => {
+                        text.push_str(more);
+                    }
+                    (
+                        ContentBlock::Thinking(ThinkingBlock { thinking, .. }),
+                        ContentBlockDelta::ThinkingDelta { thinking: more },
+                    ) => thinking.push_str(more),
+                    (
+                        ContentBlock::Thinking(ThinkingBlock { signature, .. }),
+                        ContentBlockDelta::SignatureDelta { signature: more },
+                    ) => signature.push_str(more),
+                    (
+                        ContentBlock::ToolUse(_),
+                        ContentBlockDelta::InputJsonDelta { partial_json },
+                    ) => {
+                        self.partial_json
+                            .entry(*index)
+                            .or_default()
+                            .push_str(partial_json);
+                    }
+                    _ => {}
+                }
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                let json = self.partial_json.remove(index)?;
+                if let Some(ContentBlock::ToolUse(tool_use)) =
+                    self.message.as_mut()?.content.get_mut(*index)
+                {
+                    if let Ok(input) = serde_json::from_str(&json) {
+                        tool_use.input = input;
+                    }
+                }
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                let message = self.message.as_mut()?;
+                if delta.stop_reason.is_some() {
+                    message.stop_reason = delta.stop_reason.clone();
+                }
+                if delta.stop_sequence.is_some() {
+                    message.stop_sequence = delta.stop_sequence.clone();
+                }
+                if let Some(usage) = usage {
+                    match &mut message.usage {
+                        Some(current) => current.output_tokens = usage.output_tokens,
+                        None => message.usage = Some(usage.clone()),
+                    }
+                }
+            }
+            StreamEvent::MessageStop => {
+                self.partial_json.clear();
+                return self.message.take();
+            }
+            StreamEvent::Ping | StreamEvent::Error { .. } | StreamEvent::Unknown => {}
+        }
+        None
+    }
+
+    /// The message received so far, if one is in flight
+    pub fn current(&self) -> Option<&AssistantMessageContent> {
+        self.message.as_ref()
+    }
+
+    /// Whether a message has started but not yet stopped
+    pub fn is_in_progress(&self) -> bool {
+        self.message.is_some()
+    }
+
+    /// Concatenated text of the in-flight message's text blocks
+    pub fn text(&self) -> String {
+        self.message
+            .iter()
+            .flat_map(|m| m.content.iter())
+            .filter_map(|block| match block {
+                ContentBlock::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Input JSON received so far for the tool use block at `index`
+    ///
+    /// Returns `None` once the block has ended and its input was parsed.
+    pub fn partial_tool_input(&self, index: usize) -> Option<&str> {
+        self.partial_json.get(&index).map(String::as_str)
+    }
+
+    /// Discard any in-flight message
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::ClaudeOutput;
+
+    fn event(json: &str) -> StreamEventMessage {
+        let line = format!(
+            r#"{{"type":"stream_event","session_id":"s1","uuid":"u1","event":{}}}"#,
+            json
+        );
+        match ClaudeOutput::parse_json(&line).unwrap() {
+            ClaudeOutput::StreamEvent(msg) => msg,
+            other => panic!("Expected stream_event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_stream_events() {
+        let msg = event(
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"comm"}}"#,
+        );
+        assert_eq!(msg.session_id, "s1");
+        assert_eq!(msg.event.index(), Some(1));
+        assert!(matches!(
+            msg.event,
+            StreamEvent::ContentBlockDelta {
+                delta: ContentBlockDelta::InputJsonDelta { .. },
+                ..
+            }
+        ));
+
+        let msg = event(
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+        );
+        assert!(matches!(
+            msg.event,
+            StreamEvent::ContentBlockStart {
+                content_block: ContentBlock::Thinking(_),
+                ..
+            }
+        ));
+
+        let msg = event(
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use","stop_sequence":null},"usage":{"output_tokens":42}}"#,
+        );
+        match msg.event {
+            StreamEvent::MessageDelta { delta, usage } => {
+                assert_eq!(delta.stop_reason, Some(StopReason::ToolUse));
+                assert_eq!(usage.unwrap().output_tokens, 42);
+            }
+            other => panic!("Expected message_delta, got {:?}", other),
+        }
+
+        assert!(matches!(
+            event(r#"{"type":"brand_new_event","foo":1}"#).event,
+            StreamEvent::Unknown
+        ));
+    }
+
+    #[test]
+    fn test_accumulate_thinking_and_tool_use() {
+        let mut acc = PartialMessageAccumulator::new();
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_1","role":"assistant","model":"claude-sonnet-4","content":[],"usage":{"input_tokens":10,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Let me "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"look."}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"tu_1","name":"Bash","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"command\":"}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"ls -la\"}"}}"#,
+        ];
+        for json in events {
+            assert!(acc.push(&event(json)).is_none());
+        }
+        assert!(acc.is_in_progress());
+        assert_eq!(acc.partial_tool_input(1), Some(r#"{"command":"ls -la"}"#));
+
+        acc.push(&event(r#"{"type":"content_block_stop","index":1}"#));
+        assert_eq!(acc.partial_tool_input(1), None);
+        acc.push(&event(r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":30}}"#));
+
+        let done = acc.push(&event(r#"{"type":"message_stop"}"#)).unwrap();
+        assert!(!acc.is_in_progress());
+        assert_eq!(done.session_id, "s1");
+        assert_eq!(done.uuid.as_deref(), Some("u1"));
+        assert_eq!(done.message.stop_reason, Some(StopReason::ToolUse));
+        let usage = done.message.usage.as_ref().unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (10, 30));
+
+        match &done.message.content[0] {
+            ContentBlock::Thinking(t) => {
+                assert_eq!(t.thinking, "Let me look.");
+                assert_eq!(t.signature, "sig");
+            }
+            other => panic!("Expected thinking, got {:?}", other),
+        }
+        let output = ClaudeOutput::Assistant(done);
+        let bash = output.as_tool_use("Bash").unwrap();
+        assert_eq!(bash.input["command"], "ls -la");
+    }
+
+    #[test]
+    fn test_events_without_message_start_are_ignored() {
+        let mut acc = PartialMessageAccumulator::new();
+        acc.push(&event(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"x"}}"#,
+        ));
+        assert!(acc.push(&event(r#"{"type":"message_stop"}"#)).is_none());
+        assert_eq!(acc.text(), "");
+    }
+
+    #[test]
+    fn test_block_started_past_the_end_is_ignored() {
+        let mut acc = PartialMessageAccumulator::new();
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_1","role":"assistant","model":"claude-sonnet-4","content":[]}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"tu_1","name":"Bash","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"{}"}}"#,
+        ];
+        for json in events {
+            assert!(acc.push(&event(json)).is_none());
+        }
+        assert_eq!(acc.partial_tool_input(2), None);
+
+        let done = acc.push(&event(r#"{"type":"message_stop"}"#)).unwrap();
+        assert_eq!(done.message.content.len(), 1);
+        assert!(matches!(&done.message.content[0], ContentBlock::Text(t) if t.text == "Hi"));
+    }
+}
@@ -69,7 +69,18 @@
 //! - [`protocol`] - Core JSON Lines protocol implementation
 //! - [`io`] - Top-level message types (`ClaudeInput`, `ClaudeOutput`)
 //! - [`messages`] - Detailed message structures for requests and responses
+//! - [`conversation`] - Session state folded from a stream of outputs
+//! - [`accounting`] - Token and cost totals per session, model and subagent, with budgets
+//! - [`governor`] - Rate-limit tracking, query pausing and retry backoff
+//! - [`tool_inputs`] / [`tool_results`] - Typed tool inputs and results, and tool-call correlation
 //! - [`cli`] - Builder for configuring Claude CLI invocation
+//! - [`pool`] - Supervised pool of concurrent sessions with crash recovery
+//! - [`transport`] - Byte-stream transports the async client runs over
+//! - [`stderr`] - Background stderr capture and CLI diagnostics
+//! - [`transcript`] - Recording and replaying wire-level session transcripts
+//! - [`hooks`] - Hook callbacks answered automatically by the async client
+//! - [`mcp_server`] - In-process MCP servers answered over the control channel
+//! - [`policy`] - Declarative tool approval rules applied by both clients
 //! - [`error`] - Error types and result aliases
 //! - [`version`] - Version compatibility checking
 //!
@@ -90,6 +101,7 @@
 //! - **User** - Input messages from the user
 //! - **Assistant** - Claude's responses
 //! - **Result** - Session completion with timing and cost info
+//! - **StreamEvent** - Token-by-token deltas (with `--include-partial-messages`)
 //!
 //! # Examples
 //!
@@ -99,11 +111,19 @@
 //! - `basic_repl.rs` - Interactive REPL implementation
 
 // Core modules always available
+pub mod accounting;
+pub mod conversation;
 pub mod error;
+pub mod governor;
+pub mod hooks;
 pub mod io;
+pub mod mcp_server;
 pub mod messages;
 pub mod protocol;
+pub mod stderr;
 pub mod tool_inputs;
+pub mod tool_results;
+pub mod transcript;
 pub mod types;
 
 // Client modules
@@ -111,15 +131,25 @@ pub mod types;
 pub mod client_async;
 #[cfg(feature = "sync-client")]
 pub mod client_sync;
+#[cfg(feature = "async-client")]
+pub mod pool;
+#[cfg(feature = "async-client")]
+pub mod transport;
 
 // Client-related modules
 #[cfg(any(feature = "sync-client", feature = "async-client"))]
 pub mod cli;
 #[cfg(any(feature = "sync-client", feature = "async-client"))]
+pub mod policy;
+#[cfg(any(feature = "sync-client", feature = "async-client"))]
 pub mod version;
 
+// Offline test support (scripted fake CLI)
+#[cfg(feature = "testing")]
+pub mod testing;
+
 // Core exports always available
-pub use error::{Error, Result};
+pub use error::{Error, ExitReport, Result};
 pub use io::{
     AnthropicError, AnthropicErrorDetails, ApiErrorType, AssistantMessageContent, ClaudeInput,
     ClaudeOutput, ParseError,
@@ -140,9 +170,22 @@ pub use io::{
     ControlResponseMessage, ControlResponsePayload, HookCallbackRequest, InitializeRequest,
     McpMessageRequest, Permission, PermissionBehavior, PermissionDenial, PermissionDestination,
     PermissionModeName, PermissionResult, PermissionRule, PermissionSuggestion, PermissionType,
-    ToolPermissionRequest, ToolUseBlock,
+    SetModelRequest, SetPermissionModeRequest, ToolPermissionRequest, ToolUseBlock,
 };
 
+// Hook types and registry
+pub use hooks::{HookFn, HookInputError, HookRegistry};
+pub use io::{
+    HookDecision, HookEvent, HookInput, HookMatcher, HookOutput, HookPermissionDecision,
+    HookSpecificOutput, NotificationHookInput, PostToolUseHookInput, PreCompactHookInput,
+    PreToolUseHookInput, SessionEndHookInput, SessionStartHookInput, StopHookInput,
+    UserPromptSubmitHookInput,
+};
+
+// In-process MCP server types
+pub use io::{McpContent, McpSdkServerConfig, McpServerConfig, McpToolDefinition, McpToolResult};
+pub use mcp_server::{McpServerRegistry, McpToolFn, SdkMcpServer};
+
 // System message and assistant message types
 pub use io::{
     ApiKeySource, CompactBoundaryMessage, CompactMetadata, CompactionTrigger, InitMessage,
@@ -158,26 +201,48 @@ pub use io::{
 };
 
 // Usage types
-pub use io::{AssistantUsage, CacheCreationDetails};
+pub use io::{AssistantUsage, CacheCreationDetails, ModelUsage};
+
+// Partial message streaming types
+pub use io::{
+    ContentBlockDelta, MessageDeltaBody, PartialMessageAccumulator, StreamEvent, StreamEventMessage,
+};
 
 // Typed tool input types
 pub use tool_inputs::{
     AllowedPrompt, AskUserQuestionInput, BashInput, EditInput, EnterPlanModeInput,
-    ExitPlanModeInput, GlobInput, GrepInput, GrepOutputMode, KillShellInput, NotebookCellType,
-    NotebookEditInput, NotebookEditMode, Question, QuestionMetadata, QuestionOption, ReadInput,
-    SkillInput, SubagentType, TaskInput, TaskOutputInput, TodoItem, TodoStatus, TodoWriteInput,
-    ToolInput, WebFetchInput, WebSearchInput, WriteInput,
+    ExitPlanModeInput, GlobInput, GrepInput, GrepOutputMode, KillShellInput, McpToolInput,
+    NotebookCellType, NotebookEditInput, NotebookEditMode, Question, QuestionMetadata,
+    QuestionOption, ReadInput, SkillInput, SubagentType, TaskInput, TaskOutputInput, TodoItem,
+    TodoStatus, TodoWriteInput, ToolInput, ToolInputError, WebFetchInput, WebSearchInput,
+    WriteInput,
+};
+
+// Session state
+pub use conversation::{Conversation, ConversationTotals, SubagentTask, Turn};
+
+// Typed tool result types
+pub use tool_results::{
+    BashOutput, EditOutput, GlobOutput, GrepOutput, PatchHunk, ReadFile, ReadOutput,
+    SubagentOutput, ToolCall, ToolCallCorrelator, ToolOutput, ToolOutputError, WebFetchOutput,
+    WriteOutput,
 };
 
 // Client exports
 #[cfg(feature = "async-client")]
-pub use client_async::{AsyncClient, AsyncStreamProcessor};
+pub use client_async::{AsyncClient, AsyncStreamProcessor, ClientWriter};
 #[cfg(feature = "sync-client")]
 pub use client_sync::{StreamProcessor, SyncClient};
+#[cfg(feature = "async-client")]
+pub use tokio_util::sync::CancellationToken;
+#[cfg(feature = "async-client")]
+pub use transport::{ProcessTransport, StreamTransport, Transport};
 
 // Client-related exports
 #[cfg(any(feature = "sync-client", feature = "async-client"))]
 pub use cli::{ClaudeCliBuilder, CliFlag, InputFormat, OutputFormat, PermissionMode};
+#[cfg(any(feature = "sync-client", feature = "async-client"))]
+pub use policy::{ApprovalPolicy, AuditEntry, PolicyDecision, PolicyRule};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,400 @@
+//! In-process MCP servers answered over the control channel
+//!
+//! The CLI can route an MCP server's JSON-RPC traffic through `mcp_message`
+//! control requests instead of a separate process. An [`SdkMcpServer`] defines
+//! tools as Rust closures; an [`McpServerRegistry`] holds one or more servers,
+//! produces the `--mcp-config` value that points the CLI at them, and answers
+//! `initialize`, `tools/list` and `tools/call` messages.
+//!
+//! Tools are exposed to Claude as `mcp__<server>__<tool>`, so remember to allow
+//! them (for example with `ClaudeCliBuilder::allowed_tools`) or approve them
+//! through the tool approval protocol.
+//!
+//! # Example
+//!
+//! ```
+//! use claude_codes::mcp_server::{McpServerRegistry, SdkMcpServer};
+//! use claude_codes::McpToolResult;
+//! use serde::Deserialize;
+//! use serde_json::json;
+//!
+//! #[derive(Deserialize)]
+//! struct AddArgs {
+//!     a: i64,
+//!     b: i64,
+//! }
+//!
+//! let calculator = SdkMcpServer::new("calc", "1.0.0").tool(
+//!     "add",
+//!     "Add two integers",
+//!     json!({
+//!         "type": "object",
+//!         "properties": {"a": {"type": "integer"}, "b": {"type": "integer"}},
+//!         "required": ["a", "b"]
+//!     }),
+//!     |args: AddArgs| Ok(McpToolResult::text((args.a + args.b).to_string())),
+//! );
+//!
+//! let servers = McpServerRegistry::new().server(calculator);
+//! let config: serde_json::Value = serde_json::from_str(&servers.mcp_config_json()).unwrap();
+//! assert_eq!(config["mcpServers"]["calc"], json!({"type": "sdk", "name": "calc"}));
+//! ```
+
+use crate::io::{
+    ControlResponse, McpMessageRequest, McpSdkServerConfig, McpServerConfig, McpToolDefinition,
+    McpToolResult,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// MCP protocol version reported when the client does not request one
+pub const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error code for an unknown method
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for invalid parameters (including unknown tools)
+const INVALID_PARAMS: i64 = -32602;
+
+/// A tool handler: receives the call arguments and returns the tool result
+///
+/// Returning `Err` produces a result with `isError: true` carrying the message.
+pub type McpToolFn = Arc<dyn Fn(Value) -> Result<McpToolResult, String> + Send + Sync>;
+
+struct SdkMcpTool {
+    definition: McpToolDefinition,
+    handler: McpToolFn,
+}
+
+/// An MCP server whose tools are Rust closures
+pub struct SdkMcpServer {
+    name: String,
+    version: String,
+    tools: Vec<SdkMcpTool>,
+}
+
+impl SdkMcpServer {
+    /// Create a server with the given name and version
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Register a tool whose arguments are deserialized into `I`
+    ///
+    /// `input_schema` is the JSON Schema advertised to Claude. Arguments that
+    /// fail to deserialize are reported back as a tool error.
+    pub fn tool<I, F>(
+        self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: F,
+    ) -> Self
+    where
+        I: DeserializeOwned,
+        F: Fn(I) -> Result<McpToolResult, String> + Send + Sync + 'static,
+    {
+        self.tool_raw(name, description, input_schema, move |args| {
+            let args =
+                serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))?;
+            handler(args)
+        })
+    }
+
+    /// Register a tool that receives its arguments as raw JSON
+    pub fn tool_raw<F>(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> Result<McpToolResult, String> + Send + Sync + 'static,
+    {
+        self.tools.push(SdkMcpTool {
+            definition: McpToolDefinition {
+                name: name.into(),
+                description: Some(description.into()),
+                input_schema,
+            },
+            handler: Arc::new(handler),
+        });
+        self
+    }
+
+    /// The server name used in `--mcp-config` and `mcp_message` requests
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Definitions of the registered tools, as returned by `tools/list`
+    pub fn tool_definitions(&self) -> impl Iterator<Item = &McpToolDefinition> {
+        self.tools.iter().map(|tool| &tool.definition)
+    }
+
+    /// Call a tool directly
+    ///
+    /// Returns `None` if no tool with that name is registered.
+    pub fn call_tool(&self, name: &str, arguments: Value) -> Option<McpToolResult> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.definition.name == name)?;
+        Some((tool.handler)(arguments).unwrap_or_else(McpToolResult::error))
+    }
+
+    /// Answer a JSON-RPC message addressed to this server
+    ///
+    /// Notifications (messages without an `id`) are acknowledged with an
+    /// empty result, which the CLI ignores.
+    pub fn handle_message(&self, message: &Value) -> Value {
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params
+                    .get("protocolVersion")
+                    .and_then(Value::as_str)
+                    .unwrap_or(MCP_PROTOCOL_VERSION),
+                "capabilities": {"tools": {}},
+                "serverInfo": {"name": self.name, "version": self.version}
+            })),
+            "tools/list" => Ok(json!({
+                "tools": self.tool_definitions().collect::<Vec<_>>()
+            })),
+            "tools/call" => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+                match self.call_tool(name, arguments) {
+                    Some(result) => Ok(serde_json::to_value(result).unwrap_or_default()),
+                    None => Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+                }
+            }
+            _ if method.starts_with("notifications/") => Ok(json!({})),
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+
+        match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": code, "message": message}
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for SdkMcpServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SdkMcpServer")
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .field(
+                "tools",
+                &self
+                    .tool_definitions()
+                    .map(|tool| tool.name.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// A set of in-process MCP servers keyed by name
+#[derive(Debug, Default)]
+pub struct McpServerRegistry {
+    servers: BTreeMap<String, SdkMcpServer>,
+}
+
+impl McpServerRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a server, replacing any existing server with the same name
+    pub fn server(mut self, server: SdkMcpServer) -> Self {
+        self.insert(server);
+        self
+    }
+
+    /// Add a server in place, replacing any existing server with the same name
+    pub fn insert(&mut self, server: SdkMcpServer) {
+        self.servers.insert(server.name.clone(), server);
+    }
+
+    /// Look up a server by name
+    pub fn get(&self, name: &str) -> Option<&SdkMcpServer> {
+        self.servers.get(name)
+    }
+
+    /// Names of the registered servers, in sorted order
+    pub fn names(&self) -> Vec<String> {
+        self.servers.keys().cloned().collect()
+    }
+
+    /// Whether no servers are registered
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// The `mcpServers` map pointing the CLI at these servers
+    pub fn mcp_servers_config(&self) -> BTreeMap<String, McpServerConfig> {
+        self.servers
+            .keys()
+            .map(|name| {
+                (
+                    name.clone(),
+                    McpServerConfig::Sdk(McpSdkServerConfig { name: name.clone() }),
+                )
+            })
+            .collect()
+    }
+
+    /// JSON for `--mcp-config` (pass it to `ClaudeCliBuilder::mcp_config`)
+    pub fn mcp_config_json(&self) -> String {
+        json!({"mcpServers": self.mcp_servers_config()}).to_string()
+    }
+
+    /// Answer an `mcp_message` control request
+    ///
+    /// Returns `None` if the request names a server not in this registry.
+    pub fn respond(
+        &self,
+        request_id: &str,
+        request: &McpMessageRequest,
+    ) -> Option<ControlResponse> {
+        let server = self.servers.get(&request.server_name)?;
+        let reply = server.handle_message(&request.message);
+        Some(ControlResponse::success(
+            request_id,
+            json!({"mcp_response": reply}),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{ControlResponsePayload, McpContent};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct EchoArgs {
+        text: String,
+    }
+
+    fn echo_server() -> SdkMcpServer {
+        SdkMcpServer::new("echo", "0.1.0")
+            .tool(
+                "echo",
+                "Echo the input",
+                json!({"type": "object", "properties": {"text": {"type": "string"}}}),
+                |args: EchoArgs| Ok(McpToolResult::text(args.text)),
+            )
+            .tool_raw("fail", "Always fails", json!({"type": "object"}), |_| {
+                Err("boom".to_string())
+            })
+    }
+
+    #[test]
+    fn test_initialize_and_list() {
+        let server = echo_server();
+        let reply = server.handle_message(&json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {"protocolVersion": "2025-06-18"}
+        }));
+        assert_eq!(reply["id"], 0);
+        assert_eq!(reply["result"]["protocolVersion"], "2025-06-18");
+        assert_eq!(reply["result"]["serverInfo"]["name"], "echo");
+
+        let reply =
+            server.handle_message(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}));
+        let tools = reply["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["name"], "echo");
+        assert_eq!(tools[0]["inputSchema"]["type"], "object");
+    }
+
+    #[test]
+    fn test_tools_call() {
+        let server = echo_server();
+        let reply = server.handle_message(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": {"name": "echo", "arguments": {"text": "hi"}}
+        }));
+        assert_eq!(
+            reply["result"],
+            json!({"content": [{"type": "text", "text": "hi"}]})
+        );
+
+        let result = server.call_tool("fail", json!({})).unwrap();
+        assert!(result.is_error);
+        assert_eq!(
+            result.content,
+            vec![McpContent::Text {
+                text: "boom".to_string()
+            }]
+        );
+
+        let result = server.call_tool("echo", json!({"wrong": 1})).unwrap();
+        assert!(result.is_error);
+    }
+
+    #[test]
+    fn test_unknown_tool_and_method() {
+        let server = echo_server();
+        let reply = server.handle_message(&json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": {"name": "missing"}
+        }));
+        assert_eq!(reply["error"]["code"], INVALID_PARAMS);
+
+        let reply =
+            server.handle_message(&json!({"jsonrpc": "2.0", "id": 4, "method": "resources/list"}));
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+
+        let reply = server
+            .handle_message(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}));
+        assert_eq!(reply["result"], json!({}));
+    }
+
+    #[test]
+    fn test_registry_respond() {
+        let servers = McpServerRegistry::new().server(echo_server());
+        assert_eq!(servers.names(), vec!["echo".to_string()]);
+
+        let request = McpMessageRequest {
+            server_name: "echo".to_string(),
+            message: json!({"jsonrpc": "2.0", "id": 5, "method": "tools/list"}),
+        };
+        match servers.respond("req-1", &request).unwrap().response {
+            ControlResponsePayload::Success { response, .. } => {
+                assert_eq!(response.unwrap()["mcp_response"]["id"], 5);
+            }
+            other => panic!("Expected success, got {:?}", other),
+        }
+
+        let other = McpMessageRequest {
+            server_name: "other".to_string(),
+            message: json!({}),
+        };
+        assert!(servers.respond("req-2", &other).is_none());
+    }
+}
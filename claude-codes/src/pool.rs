@@ -0,0 +1,192 @@
+//! Pool of Claude sessions for running many CLI processes concurrently
+//!
+//! A [`SessionPool`] spawns [`AsyncClient`]s from a [`ClaudeCliBuilder`]
+//! template, one CLI process and session per worker, and hands them out to
+//! callers with [`SessionPool::checkout`] or runs a whole turn on an idle
+//! worker with [`SessionPool::query`]. At most
+//! [`max_workers`](SessionPool::max_workers) clients are checked out at once;
+//! further callers wait for one to be returned.
+//!
+//! Workers are health-checked without sending a prompt: a worker whose
+//! process has exited is restarted with `--resume` on its session UUID, so
+//! the conversation carries on in the new process. A worker that has never
+//! reported a session is restarted fresh. Per-client state such as approval
+//! policies is not carried over, so set it in [`SessionPool::on_spawn`],
+//! which runs for every spawned and restarted client.
+//!
+//! The supervisor itself is [`codes_core::pool::Pool`], shared with
+//! `codex_codes::pool`; this module plugs [`AsyncClient`] into it.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use claude_codes::pool::SessionPool;
+//! use claude_codes::ClaudeCliBuilder;
+//! use std::sync::Arc;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let pool = Arc::new(
+//!     SessionPool::new(ClaudeCliBuilder::new().model("sonnet"))
+//!         .max_workers(8)
+//!         .max_restarts(3),
+//! );
+//! let _monitor = pool.clone().spawn_health_checks(std::time::Duration::from_secs(30));
+//!
+//! let mut tasks = Vec::new();
+//! for i in 0..32 {
+//!     let pool = pool.clone();
+//!     tasks.push(tokio::spawn(async move {
+//!         pool.query(&format!("Summarize issue #{}", i)).await
+//!     }));
+//! }
+//! for task in tasks {
+//!     let outputs = task.await??;
+//!     println!("{} outputs", outputs.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cli::ClaudeCliBuilder;
+use crate::client_async::AsyncClient;
+use crate::error::{Error, Result};
+use crate::io::ClaudeOutput;
+use crate::transport::BoxFuture;
+use codes_core::pool::{Pool, PoolClient, PoolQuery};
+use uuid::Uuid;
+
+pub use codes_core::pool::{PoolError, PoolStats, WorkerStatus};
+
+/// A supervisor for many concurrent Claude sessions
+pub type SessionPool = Pool<AsyncClient>;
+
+/// A client checked out of a [`SessionPool`]
+///
+/// Dereferences to [`AsyncClient`]. Returned to the pool on drop.
+pub type PooledClient<'a> = codes_core::pool::PooledClient<'a, AsyncClient>;
+
+/// Health of one idle worker, from [`SessionPool::health_check`]
+pub type HealthReport = codes_core::pool::HealthReport<Uuid>;
+
+/// Each worker gets its own session ID; restarts add `--resume` with the
+/// worker's session
+impl PoolClient for AsyncClient {
+    type Builder = ClaudeCliBuilder;
+    type Session = Uuid;
+    type Error = Error;
+
+    fn spawn(builder: &ClaudeCliBuilder, resume: Option<Uuid>) -> BoxFuture<'static, Result<Self>> {
+        let builder = match resume {
+            Some(session) => builder.clone().resume(Some(session.to_string())),
+            None => builder.clone().session_id(Uuid::new_v4()),
+        };
+        Box::pin(AsyncClient::from_builder(builder))
+    }
+
+    fn is_alive(&mut self) -> bool {
+        AsyncClient::is_alive(self)
+    }
+
+    fn session(&self) -> Option<Uuid> {
+        self.session_uuid().ok()
+    }
+
+    fn shutdown(self) -> BoxFuture<'static, Result<()>> {
+        Box::pin(AsyncClient::shutdown(self))
+    }
+}
+
+impl PoolQuery for AsyncClient {
+    type Output = Vec<ClaudeOutput>;
+
+    fn query<'a>(&'a mut self, text: &'a str) -> BoxFuture<'a, Result<Vec<ClaudeOutput>>> {
+        Box::pin(AsyncClient::query(self, text))
+    }
+
+    fn is_disconnect(error: &Error) -> bool {
+        matches!(
+            error.root(),
+            Error::ConnectionClosed | Error::ProcessCrashed(_) | Error::Io(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{StreamTransport, Transport, TransportReader, TransportWriter};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    /// A stream transport whose liveness the fake CLI controls
+    struct Switchable {
+        inner: StreamTransport,
+        alive: Arc<AtomicBool>,
+    }
+
+    impl Transport for Switchable {
+        fn connect(&mut self) -> std::io::Result<(TransportReader, TransportWriter)> {
+            self.inner.connect()
+        }
+
+        fn is_alive(&mut self) -> bool {
+            self.alive.load(Ordering::SeqCst)
+        }
+    }
+
+    /// Spawn a fake CLI that answers `answers` queries on `session`, then exits
+    fn fake_cli(session: Uuid, answers: usize) -> Result<AsyncClient> {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let alive = Arc::new(AtomicBool::new(true));
+        let flag = alive.clone();
+        tokio::spawn(async move {
+            let (read, mut write) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(read).lines();
+            for _ in 0..answers {
+                if lines.next_line().await.ok().flatten().is_none() {
+                    return;
+                }
+                let result = format!(
+                    r#"{{"type":"result","subtype":"success","is_error":false,"duration_ms":1,"duration_api_ms":1,"num_turns":1,"result":"ok","session_id":"{0}","uuid":"{0}","total_cost_usd":0.0}}"#,
+                    session
+                );
+                write
+                    .write_all(format!("{}\n", result).as_bytes())
+                    .await
+                    .unwrap();
+            }
+            let _ = lines.next_line().await;
+            flag.store(false, Ordering::SeqCst);
+        });
+        AsyncClient::from_transport(Switchable {
+            inner: StreamTransport::from_stream(client_io),
+            alive,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_query_resumes_a_worker_that_died_mid_turn() {
+        let session = Uuid::new_v4();
+        let resumes = Arc::new(Mutex::new(Vec::new()));
+        let spawned = resumes.clone();
+        let pool = SessionPool::with_spawner(move |resume| {
+            let mut spawned = spawned.lock().unwrap();
+            spawned.push(resume);
+            // The first process dies on its second query; its replacement keeps going
+            let answers = if spawned.len() == 1 { 1 } else { usize::MAX };
+            Box::pin(async move { fake_cli(session, answers) })
+        })
+        .max_workers(1);
+
+        let outputs = pool.query("one").await.unwrap();
+        assert!(matches!(outputs.last(), Some(ClaudeOutput::Result(_))));
+        assert_eq!(pool.checkout().await.unwrap().session(), Some(session));
+
+        let outputs = pool.query("two").await.unwrap();
+        assert!(matches!(outputs.last(), Some(ClaudeOutput::Result(_))));
+
+        assert_eq!(*resumes.lock().unwrap(), vec![None, Some(session)]);
+        assert_eq!(pool.stats().restarts, 1);
+    }
+}
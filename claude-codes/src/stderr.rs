@@ -0,0 +1,36 @@
+//! Background stderr capture and diagnostics
+//!
+//! The Claude CLI writes warnings, update notices and failures to stderr. If
+//! nobody reads the pipe it eventually fills up and the CLI blocks, so
+//! long-running clients should drain it. A [`StderrPump`] does this on a
+//! background task: it keeps a bounded ring buffer of recent lines in a
+//! [`StderrBuffer`], forwards every line to `log` and an optional callback,
+//! and recognizes a few well-known shapes as [`Diagnostic`]s. These types live
+//! in the shared `codes-core` crate and are re-exported here.
+//!
+//! Attach a pump with `AsyncClient::capture_stderr`; the client then fills
+//! the stderr tail of [`Error::ProcessCrashed`](crate::Error::ProcessCrashed)
+//! reports from the buffer.
+//!
+//! # Example
+//!
+//! ```
+//! use claude_codes::stderr::Diagnostic;
+//!
+//! let diagnostic = Diagnostic::parse("MCP server \"github\" failed to start: spawn ENOENT");
+//! assert_eq!(
+//!     diagnostic,
+//!     Some(Diagnostic::McpServerFailed {
+//!         server: Some("github".to_string()),
+//!         message: "MCP server \"github\" failed to start: spawn ENOENT".to_string(),
+//!     })
+//! );
+//! assert!(Diagnostic::parse("Loading settings...").is_none());
+//! ```
+
+pub use codes_core::stderr::Diagnostic;
+
+#[cfg(feature = "async-client")]
+pub use codes_core::stderr::{
+    StderrBuffer, StderrLine, StderrPump, DEFAULT_STDERR_CAPACITY, MAX_STDERR_LINE_BYTES,
+};
@@ -0,0 +1,703 @@
+//! Offline test support for code built on the Claude clients
+//!
+//! This module provides [`MockScript`], a scripted stand-in for the Claude CLI.
+//! A script is a list of [`ScriptStep`]s that either read a line from the client
+//! and check it, or write a canned line back. Scripts serialize to JSONL so they
+//! can live next to the captures in `test_cases/`.
+//!
+//! The crate ships a `fake-claude` binary (built with the `testing` feature) that
+//! loads a script from the file named by [`SCRIPT_ENV_VAR`] and replays it over
+//! stdin/stdout, so [`AsyncClient`](crate::AsyncClient) and
+//! [`SyncClient`](crate::SyncClient) can be driven end-to-end without a network.
+//! With the `async-client` feature, [`MockScript::into_transport`] replays a
+//! script in-process instead, with no binary involved.
+//!
+//! # Placeholders
+//!
+//! String values inside emitted messages are rewritten before they are written:
+//!
+//! - `"$request_id"` becomes the `request_id` of the most recently expected
+//!   control request or control response, which is how scripted control
+//!   round-trips answer randomly generated request IDs.
+//! - `"$session_id"` becomes the value passed to `--session-id`, or a nil UUID
+//!   if none was given.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use claude_codes::testing::{MockScript, SCRIPT_ENV_VAR};
+//! use claude_codes::{AsyncClient, ClaudeCliBuilder};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let script = MockScript::new()
+//!     .expect("user")
+//!     .emit_assistant_text("pong")
+//!     .emit_result("pong");
+//! let path = std::env::temp_dir().join("ping.jsonl");
+//! script.write_to(&path)?;
+//!
+//! let mut cmd = ClaudeCliBuilder::new()
+//!     .command(env!("CARGO_BIN_EXE_fake-claude"))
+//!     .build_command();
+//! cmd.env(SCRIPT_ENV_VAR, &path);
+//! let mut client = AsyncClient::new(cmd.spawn()?)?;
+//! assert!(client.ping().await);
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// Environment variable the `fake-claude` binary reads its script path from
+pub const SCRIPT_ENV_VAR: &str = "FAKE_CLAUDE_SCRIPT";
+
+/// Placeholder replaced with the most recently captured control `request_id`
+pub const REQUEST_ID_PLACEHOLDER: &str = "$request_id";
+
+/// Placeholder replaced with the session ID the fake CLI was started with
+pub const SESSION_ID_PLACEHOLDER: &str = "$session_id";
+
+/// Checks applied to a line read from the client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Expectation {
+    /// Required value of the top-level `type` field (e.g. `user`, `control_request`)
+    #[serde(rename = "type")]
+    pub message_type: String,
+
+    /// Required control subtype (`request.subtype` or `response.subtype`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtype: Option<String>,
+
+    /// Required `request_id` (top-level for requests, nested for responses)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
+    /// Substring that must appear somewhere in the raw line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+}
+
+/// A single step of a [`MockScript`]
+///
+/// Serialized externally tagged, so a JSONL script looks like:
+///
+/// ```text
+/// {"expect":{"type":"control_request","subtype":"initialize"}}
+/// {"emit":{"type":"control_response","response":{"subtype":"success","request_id":"$request_id"}}}
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptStep {
+    /// Read one non-empty line from the client and check it
+    Expect(Expectation),
+    /// Write a JSON message to stdout, after placeholder substitution
+    Emit(Value),
+    /// Write a raw line to stdout verbatim (useful for malformed output)
+    EmitRaw(String),
+    /// Write a line to stderr
+    Stderr(String),
+    /// Stop replaying and exit with the given status code
+    Exit(i32),
+}
+
+/// A scripted conversation replayed by the fake Claude CLI
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MockScript {
+    steps: Vec<ScriptStep>,
+}
+
+impl MockScript {
+    /// Create an empty script
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a script from JSONL, one [`ScriptStep`] per line
+    ///
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn from_jsonl(text: &str) -> serde_json::Result<Self> {
+        let steps = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(serde_json::from_str)
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        Ok(Self { steps })
+    }
+
+    /// Load a script from a JSONL file
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_jsonl(&text).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Serialize the script to JSONL
+    pub fn to_jsonl(&self) -> String {
+        let mut out = String::new();
+        for step in &self.steps {
+            // ScriptStep only contains JSON-compatible data, so this cannot fail
+            out.push_str(&serde_json::to_string(step).expect("script step serializes"));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Write the script to a JSONL file
+    pub fn write_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_jsonl())
+    }
+
+    /// The steps of this script, in order
+    pub fn steps(&self) -> &[ScriptStep] {
+        &self.steps
+    }
+
+    /// Append an arbitrary step
+    pub fn step(mut self, step: ScriptStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Expect a message with the given top-level `type`
+    pub fn expect(self, message_type: &str) -> Self {
+        self.step(ScriptStep::Expect(Expectation {
+            message_type: message_type.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    /// Expect a user message whose raw line contains `text`
+    pub fn expect_user_containing(self, text: &str) -> Self {
+        self.step(ScriptStep::Expect(Expectation {
+            message_type: "user".to_string(),
+            contains: Some(text.to_string()),
+            ..Default::default()
+        }))
+    }
+
+    /// Expect an SDK-to-CLI control request with the given subtype
+    pub fn expect_control_request(self, subtype: &str) -> Self {
+        self.step(ScriptStep::Expect(Expectation {
+            message_type: "control_request".to_string(),
+            subtype: Some(subtype.to_string()),
+            ..Default::default()
+        }))
+    }
+
+    /// Expect a control response answering the given `request_id`
+    pub fn expect_control_response(self, request_id: &str) -> Self {
+        self.step(ScriptStep::Expect(Expectation {
+            message_type: "control_response".to_string(),
+            request_id: Some(request_id.to_string()),
+            ..Default::default()
+        }))
+    }
+
+    /// Emit a JSON message
+    pub fn emit(self, message: Value) -> Self {
+        self.step(ScriptStep::Emit(message))
+    }
+
+    /// Emit a raw line verbatim
+    pub fn emit_raw(self, line: &str) -> Self {
+        self.step(ScriptStep::EmitRaw(line.to_string()))
+    }
+
+    /// Write a line to stderr
+    pub fn stderr(self, line: &str) -> Self {
+        self.step(ScriptStep::Stderr(line.to_string()))
+    }
+
+    /// Exit with the given status code
+    pub fn exit(self, code: i32) -> Self {
+        self.step(ScriptStep::Exit(code))
+    }
+
+    /// Emit a successful control response for the last expected request
+    pub fn emit_control_success(self) -> Self {
+        self.emit(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": REQUEST_ID_PLACEHOLDER
+            }
+        }))
+    }
+
+    /// Emit a control error response for the last expected request
+    pub fn emit_control_error(self, error: &str) -> Self {
+        self.emit(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "error",
+                "request_id": REQUEST_ID_PLACEHOLDER,
+                "error": error
+            }
+        }))
+    }
+
+    /// Emit a minimal `system`/`init` message
+    pub fn emit_init(self) -> Self {
+        self.emit(json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": SESSION_ID_PLACEHOLDER,
+            "cwd": "/tmp",
+            "model": "claude-sonnet-4-5",
+            "tools": [],
+            "mcp_servers": []
+        }))
+    }
+
+    /// Emit an assistant message with a single text block
+    pub fn emit_assistant_text(self, text: &str) -> Self {
+        self.emit(json!({
+            "type": "assistant",
+            "message": {
+                "id": "msg_mock",
+                "role": "assistant",
+                "model": "claude-sonnet-4-5",
+                "content": [{"type": "text", "text": text}]
+            },
+            "session_id": SESSION_ID_PLACEHOLDER
+        }))
+    }
+
+    /// Emit an assistant message containing one `tool_use` block
+    pub fn emit_tool_use(self, id: &str, name: &str, input: Value) -> Self {
+        self.emit(json!({
+            "type": "assistant",
+            "message": {
+                "id": "msg_mock",
+                "role": "assistant",
+                "model": "claude-sonnet-4-5",
+                "content": [{"type": "tool_use", "id": id, "name": name, "input": input}]
+            },
+            "session_id": SESSION_ID_PLACEHOLDER
+        }))
+    }
+
+    /// Emit a CLI-to-SDK `can_use_tool` control request
+    pub fn emit_can_use_tool(self, request_id: &str, tool_name: &str, input: Value) -> Self {
+        self.emit(json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": {
+                "subtype": "can_use_tool",
+                "tool_name": tool_name,
+                "input": input
+            }
+        }))
+    }
+
+    /// Emit a CLI-to-SDK `hook_callback` control request
+    pub fn emit_hook_callback(self, request_id: &str, callback_id: &str, input: Value) -> Self {
+        self.emit(json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": {
+                "subtype": "hook_callback",
+                "callback_id": callback_id,
+                "input": input
+            }
+        }))
+    }
+
+    /// Emit a CLI-to-SDK `mcp_message` control request carrying a JSON-RPC message
+    pub fn emit_mcp_message(self, request_id: &str, server_name: &str, message: Value) -> Self {
+        self.emit(json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": {
+                "subtype": "mcp_message",
+                "server_name": server_name,
+                "message": message
+            }
+        }))
+    }
+
+    /// Emit a successful `result` message ending the turn
+    pub fn emit_result(self, result: &str) -> Self {
+        self.emit(json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "duration_ms": 10,
+            "duration_api_ms": 8,
+            "num_turns": 1,
+            "result": result,
+            "session_id": SESSION_ID_PLACEHOLDER,
+            "total_cost_usd": 0.0,
+            "uuid": SESSION_ID_PLACEHOLDER
+        }))
+    }
+
+    /// Replay the script over the given streams
+    ///
+    /// Returns the exit code requested by an [`ScriptStep::Exit`] step, or `0`
+    /// once every step has run. A failed expectation or a client that hangs up
+    /// early is reported as an `InvalidData` / `UnexpectedEof` I/O error.
+    pub fn run<R, W, E>(
+        &self,
+        session_id: &str,
+        mut reader: R,
+        mut writer: W,
+        mut stderr: E,
+    ) -> std::io::Result<i32>
+    where
+        R: BufRead,
+        W: Write,
+        E: Write,
+    {
+        let mut last_request_id = String::new();
+
+        for (index, step) in self.steps.iter().enumerate() {
+            match step {
+                ScriptStep::Expect(expectation) => {
+                    let line = read_non_empty_line(&mut reader)?
+                        .ok_or_else(|| closed_early(index, expectation))?;
+                    let value = check_expectation(index, expectation, &line)?;
+                    if let Some(id) = captured_request_id(&value) {
+                        last_request_id = id.to_string();
+                    }
+                }
+                ScriptStep::Emit(message) => {
+                    let message = substitute(message, &last_request_id, session_id);
+                    writeln!(writer, "{}", message)?;
+                    writer.flush()?;
+                }
+                ScriptStep::EmitRaw(line) => {
+                    writeln!(writer, "{}", line)?;
+                    writer.flush()?;
+                }
+                ScriptStep::Stderr(line) => {
+                    writeln!(stderr, "{}", line)?;
+                    stderr.flush()?;
+                }
+                ScriptStep::Exit(code) => return Ok(*code),
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(feature = "async-client")]
+impl MockScript {
+    /// Replay the script over async streams
+    ///
+    /// Behaves exactly like [`MockScript::run`].
+    pub async fn run_async<R, W, E>(
+        &self,
+        session_id: &str,
+        reader: R,
+        mut writer: W,
+        mut stderr: E,
+    ) -> std::io::Result<i32>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+        E: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let mut reader = tokio::io::BufReader::new(reader);
+        let mut last_request_id = String::new();
+
+        for (index, step) in self.steps.iter().enumerate() {
+            match step {
+                ScriptStep::Expect(expectation) => {
+                    let line = loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).await? == 0 {
+                            return Err(closed_early(index, expectation));
+                        }
+                        if !line.trim().is_empty() {
+                            break line.trim().to_string();
+                        }
+                    };
+                    let value = check_expectation(index, expectation, &line)?;
+                    if let Some(id) = captured_request_id(&value) {
+                        last_request_id = id.to_string();
+                    }
+                }
+                ScriptStep::Emit(message) => {
+                    let message = substitute(message, &last_request_id, session_id);
+                    writer
+                        .write_all(format!("{}\n", message).as_bytes())
+                        .await?;
+                    writer.flush().await?;
+                }
+                ScriptStep::EmitRaw(line) => {
+                    writer.write_all(format!("{}\n", line).as_bytes()).await?;
+                    writer.flush().await?;
+                }
+                ScriptStep::Stderr(line) => {
+                    stderr.write_all(format!("{}\n", line).as_bytes()).await?;
+                    stderr.flush().await?;
+                }
+                ScriptStep::Exit(code) => return Ok(*code),
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// Replay the script in a background task and return a connected transport
+    ///
+    /// This is the in-process alternative to the `fake-claude` binary: pass the
+    /// result to [`AsyncClient::from_transport`](crate::AsyncClient::from_transport).
+    /// When the script finishes the connection closes, and a failed expectation
+    /// is written to the transport's stderr stream. Must be called from within a
+    /// Tokio runtime.
+    pub fn into_transport(self, session_id: &str) -> crate::transport::StreamTransport {
+        const PIPE_CAPACITY: usize = 64 * 1024;
+
+        let (client_io, script_io) = tokio::io::duplex(PIPE_CAPACITY);
+        let (stderr_reader, mut stderr_writer) = tokio::io::duplex(PIPE_CAPACITY);
+        let session_id = session_id.to_string();
+
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+
+            let (reader, writer) = tokio::io::split(script_io);
+            let result = self
+                .run_async(&session_id, reader, writer, &mut stderr_writer)
+                .await;
+            if let Err(e) = result {
+                let _ = stderr_writer
+                    .write_all(format!("fake-claude: {}\n", e).as_bytes())
+                    .await;
+            }
+        });
+
+        crate::transport::StreamTransport::from_stream(client_io).with_stderr(stderr_reader)
+    }
+}
+
+fn closed_early(index: usize, expectation: &Expectation) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::UnexpectedEof,
+        format!(
+            "step {}: client closed stdin while expecting {:?}",
+            index, expectation.message_type
+        ),
+    )
+}
+
+fn read_non_empty_line<R: BufRead>(reader: &mut R) -> std::io::Result<Option<String>> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let trimmed = line.trim();
+        if !trimmed.is_empty() {
+            return Ok(Some(trimmed.to_string()));
+        }
+    }
+}
+
+fn check_expectation(
+    index: usize,
+    expectation: &Expectation,
+    line: &str,
+) -> std::io::Result<Value> {
+    let mismatch = |detail: String| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("step {}: {} (line: {})", index, detail, line),
+        )
+    };
+
+    let value: Value =
+        serde_json::from_str(line).map_err(|e| mismatch(format!("invalid JSON: {}", e)))?;
+
+    let actual_type = value.get("type").and_then(Value::as_str).unwrap_or("");
+    if actual_type != expectation.message_type {
+        return Err(mismatch(format!(
+            "expected type {:?}, got {:?}",
+            expectation.message_type, actual_type
+        )));
+    }
+
+    if let Some(ref subtype) = expectation.subtype {
+        let actual = value
+            .get("request")
+            .or_else(|| value.get("response"))
+            .and_then(|v| v.get("subtype"))
+            .and_then(Value::as_str);
+        if actual != Some(subtype.as_str()) {
+            return Err(mismatch(format!(
+                "expected subtype {:?}, got {:?}",
+                subtype, actual
+            )));
+        }
+    }
+
+    if let Some(ref request_id) = expectation.request_id {
+        let actual = captured_request_id(&value);
+        if actual != Some(request_id.as_str()) {
+            return Err(mismatch(format!(
+                "expected request_id {:?}, got {:?}",
+                request_id, actual
+            )));
+        }
+    }
+
+    if let Some(ref needle) = expectation.contains {
+        if !line.contains(needle.as_str()) {
+            return Err(mismatch(format!("expected line to contain {:?}", needle)));
+        }
+    }
+
+    Ok(value)
+}
+
+/// The request ID carried by a control request or control response, if any
+fn captured_request_id(value: &Value) -> Option<&str> {
+    value
+        .get("request_id")
+        .or_else(|| value.get("response").and_then(|r| r.get("request_id")))
+        .and_then(Value::as_str)
+}
+
+fn substitute(value: &Value, request_id: &str, session_id: &str) -> Value {
+    match value {
+        Value::String(s) if s == REQUEST_ID_PLACEHOLDER => Value::String(request_id.to_string()),
+        Value::String(s) if s == SESSION_ID_PLACEHOLDER => Value::String(session_id.to_string()),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| substitute(v, request_id, session_id))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), substitute(v, request_id, session_id)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClaudeOutput;
+    use std::io::Cursor;
+
+    fn run_script(script: &MockScript, input: &str) -> (std::io::Result<i32>, String, String) {
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let code = script.run("session-1", Cursor::new(input), &mut stdout, &mut stderr);
+        (
+            code,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_script_jsonl_roundtrip() {
+        let script = MockScript::new()
+            .expect_control_request("initialize")
+            .emit_control_success()
+            .stderr("warming up")
+            .exit(3);
+
+        let parsed = MockScript::from_jsonl(&script.to_jsonl()).unwrap();
+        assert_eq!(parsed, script);
+    }
+
+    #[test]
+    fn test_from_jsonl_skips_comments_and_blanks() {
+        let text = "# a comment\n\n{\"expect\":{\"type\":\"user\"}}\n";
+        let script = MockScript::from_jsonl(text).unwrap();
+        assert_eq!(script.steps().len(), 1);
+    }
+
+    #[test]
+    fn test_control_round_trip_substitutes_request_id() {
+        let script = MockScript::new()
+            .expect_control_request("initialize")
+            .emit_control_success();
+        let input = r#"{"type":"control_request","request_id":"init-42","request":{"subtype":"initialize"}}"#;
+
+        let (code, stdout, _) = run_script(&script, input);
+        assert_eq!(code.unwrap(), 0);
+
+        let output = ClaudeOutput::parse_json(stdout.trim()).unwrap();
+        match output {
+            ClaudeOutput::ControlResponse(resp) => {
+                let json = serde_json::to_value(&resp).unwrap();
+                assert_eq!(json["response"]["request_id"], "init-42");
+            }
+            other => panic!("expected control response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_session_id_substitution_and_builtin_messages_parse() {
+        let script = MockScript::new()
+            .emit_init()
+            .emit_assistant_text("hello")
+            .emit_tool_use("toolu_1", "Bash", json!({"command": "ls"}))
+            .emit_can_use_tool("req-1", "Bash", json!({"command": "ls"}))
+            .emit_result("done");
+
+        let (code, stdout, _) = run_script(&script, "");
+        assert_eq!(code.unwrap(), 0);
+
+        let outputs: Vec<ClaudeOutput> = stdout
+            .lines()
+            .map(|line| ClaudeOutput::parse_json(line).unwrap())
+            .collect();
+        assert_eq!(outputs.len(), 5);
+        assert!(outputs[0].is_system_init());
+        assert_eq!(outputs[1].text_content().as_deref(), Some("hello"));
+        assert_eq!(outputs[2].tool_uses().count(), 1);
+        assert!(outputs[3].is_control_request());
+        assert_eq!(outputs[4].session_id(), Some("session-1"));
+    }
+
+    #[test]
+    fn test_expectation_mismatch_is_invalid_data() {
+        let script = MockScript::new().expect("control_request");
+        let (code, _, _) = run_script(&script, "{\"type\":\"user\"}\n");
+        let err = code.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("expected type"));
+    }
+
+    #[test]
+    fn test_expect_control_response_checks_request_id() {
+        let script = MockScript::new().expect_control_response("req-1");
+        let input =
+            r#"{"type":"control_response","response":{"subtype":"success","request_id":"req-2"}}"#;
+        let (code, _, _) = run_script(&script, input);
+        assert!(code.unwrap_err().to_string().contains("request_id"));
+    }
+
+    #[test]
+    fn test_eof_while_expecting_is_unexpected_eof() {
+        let script = MockScript::new().expect("user");
+        let (code, _, _) = run_script(&script, "\n\n");
+        assert_eq!(code.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_exit_stops_replay() {
+        let script = MockScript::new()
+            .stderr("fatal: auth failed")
+            .exit(2)
+            .emit_result("never");
+        let (code, stdout, stderr) = run_script(&script, "");
+        assert_eq!(code.unwrap(), 2);
+        assert!(stdout.is_empty());
+        assert_eq!(stderr.trim(), "fatal: auth failed");
+    }
+}
@@ -676,6 +676,57 @@ pub struct AllowedPrompt {
 // ToolInput Enum - Unified type for all tool inputs
 // ============================================================================
 
+/// Input for an MCP tool, named `mcp__<server>__<tool>`.
+///
+/// MCP tools have arbitrary schemas, so the input is kept as raw JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpToolInput {
+    /// MCP server name
+    pub server: String,
+    /// Tool name within the server
+    pub tool: String,
+    /// The tool's input
+    pub input: Value,
+}
+
+impl McpToolInput {
+    /// Split an `mcp__<server>__<tool>` name into server and tool.
+    ///
+    /// The server name ends at the first `__` after the prefix, so tool names
+    /// may themselves contain `__`.
+    pub fn split_name(name: &str) -> Option<(&str, &str)> {
+        let rest = name.strip_prefix("mcp__")?;
+        let (server, tool) = rest.split_once("__")?;
+        if server.is_empty() || tool.is_empty() {
+            return None;
+        }
+        Some((server, tool))
+    }
+
+    /// The full `mcp__<server>__<tool>` name.
+    pub fn full_name(&self) -> String {
+        format!("mcp__{}__{}", self.server, self.tool)
+    }
+}
+
+fn serialize_mcp_input<S: Serializer>(
+    input: &McpToolInput,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    input.input.serialize(serializer)
+}
+
+/// A named tool's input did not match that tool's schema.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid input for tool {tool_name}: {source}")]
+pub struct ToolInputError {
+    /// The tool whose schema was expected
+    pub tool_name: String,
+    /// The underlying deserialization error
+    #[source]
+    pub source: serde_json::Error,
+}
+
 /// Unified enum representing input for any Claude Code tool.
 ///
 /// This enum uses `#[serde(untagged)]` to automatically deserialize based on
@@ -704,6 +755,10 @@ pub struct AllowedPrompt {
 ///
 /// The variants are ordered from most specific (most required fields) to least
 /// specific to ensure correct deserialization with `#[serde(untagged)]`.
+///
+/// Shape-based deserialization can still pick the wrong variant (an MCP tool
+/// taking a `command` field decodes as `Bash`). When the tool name is known,
+/// prefer [`ToolInput::from_name_and_value`], which is deterministic.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum ToolInput {
@@ -758,6 +813,10 @@ pub enum ToolInput {
     /// EnterPlanMode tool (empty input)
     EnterPlanMode(EnterPlanModeInput),
 
+    /// MCP tool (`mcp__<server>__<tool>`) - only produced by name-directed decoding
+    #[serde(skip_deserializing, serialize_with = "serialize_mcp_input")]
+    Mcp(McpToolInput),
+
     /// Unknown tool input - fallback for custom/new tools
     ///
     /// This variant captures any tool input that doesn't match the known schemas.
@@ -769,10 +828,77 @@ pub enum ToolInput {
 }
 
 impl ToolInput {
+    /// Decode a tool input using the tool's name to pick the variant.
+    ///
+    /// Built-in tool names decode into their struct, and a schema mismatch is
+    /// reported as an error naming the tool rather than falling through to
+    /// another variant. `mcp__<server>__<tool>` names produce [`ToolInput::Mcp`];
+    /// any other name produces [`ToolInput::Unknown`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use claude_codes::ToolInput;
+    /// use serde_json::json;
+    ///
+    /// // Shaped like Bash input, but named as an MCP tool
+    /// let input = ToolInput::from_name_and_value("mcp__shell__run", json!({"command": "ls"})).unwrap();
+    /// let mcp = input.as_mcp().unwrap();
+    /// assert_eq!((mcp.server.as_str(), mcp.tool.as_str()), ("shell", "run"));
+    ///
+    /// // A known tool whose input does not match its schema
+    /// let err = ToolInput::from_name_and_value("Read", json!({"path": "/tmp/x"})).unwrap_err();
+    /// assert_eq!(err.tool_name, "Read");
+    /// ```
+    pub fn from_name_and_value(name: &str, value: Value) -> Result<ToolInput, ToolInputError> {
+        fn decode<T: serde::de::DeserializeOwned>(
+            name: &str,
+            value: Value,
+            variant: fn(T) -> ToolInput,
+        ) -> Result<ToolInput, ToolInputError> {
+            serde_json::from_value(value)
+                .map(variant)
+                .map_err(|source| ToolInputError {
+                    tool_name: name.to_string(),
+                    source,
+                })
+        }
+
+        if let Some((server, tool)) = McpToolInput::split_name(name) {
+            return Ok(ToolInput::Mcp(McpToolInput {
+                server: server.to_string(),
+                tool: tool.to_string(),
+                input: value,
+            }));
+        }
+
+        match name {
+            "Bash" => decode(name, value, ToolInput::Bash),
+            "Read" => decode(name, value, ToolInput::Read),
+            "Write" => decode(name, value, ToolInput::Write),
+            "Edit" => decode(name, value, ToolInput::Edit),
+            "Glob" => decode(name, value, ToolInput::Glob),
+            "Grep" => decode(name, value, ToolInput::Grep),
+            "Task" => decode(name, value, ToolInput::Task),
+            "WebFetch" => decode(name, value, ToolInput::WebFetch),
+            "WebSearch" => decode(name, value, ToolInput::WebSearch),
+            "TodoWrite" => decode(name, value, ToolInput::TodoWrite),
+            "AskUserQuestion" => decode(name, value, ToolInput::AskUserQuestion),
+            "NotebookEdit" => decode(name, value, ToolInput::NotebookEdit),
+            "TaskOutput" => decode(name, value, ToolInput::TaskOutput),
+            "KillShell" => decode(name, value, ToolInput::KillShell),
+            "Skill" => decode(name, value, ToolInput::Skill),
+            "EnterPlanMode" => decode(name, value, ToolInput::EnterPlanMode),
+            "ExitPlanMode" => decode(name, value, ToolInput::ExitPlanMode),
+            _ => Ok(ToolInput::Unknown(value)),
+        }
+    }
+
     /// Returns the tool name if it can be determined from the input type.
     ///
     /// For `Unknown` variants, returns `None` since the tool name cannot be
-    /// determined from the input structure alone.
+    /// determined from the input structure alone. For `Mcp` variants, use
+    /// [`McpToolInput::full_name`].
     pub fn tool_name(&self) -> Option<&'static str> {
         match self {
             ToolInput::Bash(_) => Some("Bash"),
@@ -792,7 +918,7 @@ impl ToolInput {
             ToolInput::Skill(_) => Some("Skill"),
             ToolInput::EnterPlanMode(_) => Some("EnterPlanMode"),
             ToolInput::ExitPlanMode(_) => Some("ExitPlanMode"),
-            ToolInput::Unknown(_) => None,
+            ToolInput::Mcp(_) | ToolInput::Unknown(_) => None,
         }
     }
 
@@ -916,6 +1042,14 @@ impl ToolInput {
         }
     }
 
+    /// Try to get the input as an MCP tool input.
+    pub fn as_mcp(&self) -> Option<&McpToolInput> {
+        match self {
+            ToolInput::Mcp(input) => Some(input),
+            _ => None,
+        }
+    }
+
     /// Try to get the input as an unknown Value.
     pub fn as_unknown(&self) -> Option<&Value> {
         match self {
@@ -1297,4 +1431,56 @@ mod tests {
         assert_eq!(input.skill, "commit");
         assert_eq!(input.args, Some("-m 'Fix bug'".to_string()));
     }
+
+    #[test]
+    fn test_from_name_and_value_mcp() {
+        let input = ToolInput::from_name_and_value(
+            "mcp__shell__run__now",
+            serde_json::json!({"command": "ls"}),
+        )
+        .unwrap();
+        let mcp = input.as_mcp().unwrap();
+        assert_eq!(mcp.server, "shell");
+        assert_eq!(mcp.tool, "run__now");
+        assert_eq!(mcp.full_name(), "mcp__shell__run__now");
+        assert_eq!(input.tool_name(), None);
+        // Serializes back to the raw input
+        assert_eq!(
+            serde_json::to_value(&input).unwrap(),
+            serde_json::json!({"command": "ls"})
+        );
+    }
+
+    #[test]
+    fn test_from_name_and_value_is_name_directed() {
+        // Shape-guessing would pick Bash; the name says Read
+        let value = serde_json::json!({"file_path": "/tmp/a", "command": "ls"});
+        let input = ToolInput::from_name_and_value("Read", value).unwrap();
+        assert!(matches!(input, ToolInput::Read(_)));
+
+        let input =
+            ToolInput::from_name_and_value("CustomTool", serde_json::json!({"command": "ls"}))
+                .unwrap();
+        assert!(input.is_unknown());
+    }
+
+    #[test]
+    fn test_from_name_and_value_schema_mismatch() {
+        let err =
+            ToolInput::from_name_and_value("Bash", serde_json::json!({"cmd": "ls"})).unwrap_err();
+        assert_eq!(err.tool_name, "Bash");
+        assert!(err.to_string().starts_with("invalid input for tool Bash:"));
+        assert!(err.to_string().contains("command"));
+    }
+
+    #[test]
+    fn test_mcp_split_name() {
+        assert_eq!(
+            McpToolInput::split_name("mcp__github__create_issue"),
+            Some(("github", "create_issue"))
+        );
+        assert_eq!(McpToolInput::split_name("mcp__github"), None);
+        assert_eq!(McpToolInput::split_name("mcp____tool"), None);
+        assert_eq!(McpToolInput::split_name("Bash"), None);
+    }
 }
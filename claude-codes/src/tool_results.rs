@@ -0,0 +1,858 @@
+//! Typed tool results and tool-call correlation.
+//!
+//! When a tool finishes, the CLI sends a `user` message holding a
+//! [`ToolResultBlock`] with the text shown to the model, and a
+//! `tool_use_result` field with the structured result. This module provides:
+//!
+//! - Strongly-typed structs for the structured results of the built-in tools
+//!   ([`BashOutput`], [`ReadOutput`], [`EditOutput`], ...), decoded by tool name
+//!   with [`ToolOutput::from_name_and_value`]
+//! - [`ToolCallCorrelator`], which pairs each `tool_use` block with its result
+//!   across `assistant` and `user` outputs
+//!
+//! # Example
+//!
+//! ```
+//! use claude_codes::{ClaudeOutput, ToolCallCorrelator, ToolOutput};
+//!
+//! let assistant = ClaudeOutput::parse_json(r#"{"type":"assistant","message":{"id":"msg_1","role":"assistant","model":"claude","content":[{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"echo hi"}}]},"session_id":"s"}"#).unwrap();
+//! let user = ClaudeOutput::parse_json(r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"hi"}]},"session_id":"08cd4ce5-1ce0-4dd4-8e7c-8b69712c514e","tool_use_result":{"stdout":"hi","stderr":"","interrupted":false,"isImage":false}}"#).unwrap();
+//!
+//! let mut correlator = ToolCallCorrelator::new();
+//! correlator.push(&assistant);
+//! let completed = correlator.push(&user);
+//!
+//! assert_eq!(completed.len(), 1);
+//! if let Some(ToolOutput::Bash(bash)) = completed[0].typed_output() {
+//!     assert_eq!(bash.stdout, "hi");
+//! }
+//! ```
+
+use crate::io::{ClaudeOutput, ContentBlock, ToolResultBlock, ToolResultContent};
+use crate::tool_inputs::{GrepOutputMode, ToolInput, ToolInputError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// ============================================================================
+// Individual Tool Result Structs
+// ============================================================================
+
+/// Result of the Bash tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BashOutput {
+    /// Captured standard output
+    #[serde(default)]
+    pub stdout: String,
+
+    /// Captured standard error
+    #[serde(default)]
+    pub stderr: String,
+
+    /// Whether the command was interrupted
+    #[serde(default)]
+    pub interrupted: bool,
+
+    /// Whether stdout holds image data
+    #[serde(default)]
+    pub is_image: bool,
+
+    /// Explanation of a non-zero exit that is not a failure (e.g. `grep` finding nothing)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_code_interpretation: Option<String>,
+
+    /// ID of the shell when the command was run in the background
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_task_id: Option<String>,
+}
+
+/// Result of the Read tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReadOutput {
+    /// Kind of content read ("text", "image", "notebook", ...)
+    #[serde(rename = "type", default)]
+    pub kind: String,
+
+    /// The file that was read
+    pub file: ReadFile,
+}
+
+/// File contents returned by the Read tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadFile {
+    /// Absolute path of the file
+    pub file_path: String,
+
+    /// The lines that were read
+    #[serde(default)]
+    pub content: String,
+
+    /// Number of lines returned
+    #[serde(default)]
+    pub num_lines: u64,
+
+    /// 1-based line the read started at
+    #[serde(default)]
+    pub start_line: u64,
+
+    /// Number of lines in the whole file
+    #[serde(default)]
+    pub total_lines: u64,
+}
+
+impl ReadFile {
+    /// The inclusive 1-based line range that was read, or `None` if no lines were returned.
+    pub fn line_range(&self) -> Option<(u64, u64)> {
+        if self.num_lines == 0 {
+            return None;
+        }
+        let start = self.start_line.max(1);
+        Some((start, start + self.num_lines - 1))
+    }
+
+    /// Whether the read covered only part of the file.
+    pub fn is_partial(&self) -> bool {
+        self.num_lines < self.total_lines
+    }
+}
+
+/// One hunk of a unified diff.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchHunk {
+    /// First line of the hunk in the old file
+    pub old_start: u64,
+
+    /// Number of lines from the old file
+    pub old_lines: u64,
+
+    /// First line of the hunk in the new file
+    pub new_start: u64,
+
+    /// Number of lines in the new file
+    pub new_lines: u64,
+
+    /// Diff lines, each prefixed with ' ', '-' or '+'
+    #[serde(default)]
+    pub lines: Vec<String>,
+}
+
+/// Result of the Edit tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EditOutput {
+    /// Absolute path of the edited file
+    pub file_path: String,
+
+    /// The text that was replaced
+    #[serde(default)]
+    pub old_string: String,
+
+    /// The replacement text
+    #[serde(default)]
+    pub new_string: String,
+
+    /// File contents before the edit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_file: Option<String>,
+
+    /// The change as diff hunks
+    #[serde(default)]
+    pub structured_patch: Vec<PatchHunk>,
+
+    /// Whether the user modified the proposed edit
+    #[serde(default)]
+    pub user_modified: bool,
+
+    /// Whether every occurrence was replaced
+    #[serde(default)]
+    pub replace_all: bool,
+}
+
+/// Result of the Write tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteOutput {
+    /// "create" for a new file, "update" when overwriting
+    #[serde(rename = "type", default)]
+    pub kind: String,
+
+    /// Absolute path of the written file
+    pub file_path: String,
+
+    /// The contents written
+    #[serde(default)]
+    pub content: String,
+
+    /// The change as diff hunks (empty for new files)
+    #[serde(default)]
+    pub structured_patch: Vec<PatchHunk>,
+
+    /// File contents before the write, when it existed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_file: Option<String>,
+}
+
+impl WriteOutput {
+    /// Whether the write created a new file.
+    pub fn is_create(&self) -> bool {
+        self.kind == "create"
+    }
+}
+
+/// Result of the Glob tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobOutput {
+    /// Matching paths
+    #[serde(default)]
+    pub filenames: Vec<String>,
+
+    /// Number of matches
+    #[serde(default)]
+    pub num_files: u64,
+
+    /// Whether the match list was truncated
+    #[serde(default)]
+    pub truncated: bool,
+
+    /// Search time in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Result of the Grep tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GrepOutput {
+    /// The output mode the search ran in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<GrepOutputMode>,
+
+    /// Files with matches
+    #[serde(default)]
+    pub filenames: Vec<String>,
+
+    /// Number of files with matches
+    #[serde(default)]
+    pub num_files: u64,
+
+    /// Matching lines (`content` mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+
+    /// Number of lines in `content`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_lines: Option<u64>,
+
+    /// Total number of matches (`count` mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_matches: Option<u64>,
+
+    /// The head limit that truncated the output, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applied_limit: Option<u64>,
+}
+
+/// Result of the Task tool - the subagent's final report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubagentOutput {
+    /// Completion status (e.g. "completed")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+
+    /// The prompt the subagent was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+
+    /// ID of the subagent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+
+    /// The subagent's final message
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+
+    /// Wall-clock time the subagent ran for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_duration_ms: Option<u64>,
+
+    /// Tokens used by the subagent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_tokens: Option<u64>,
+
+    /// Number of tool calls the subagent made
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_tool_use_count: Option<u64>,
+
+    /// Raw usage breakdown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Value>,
+}
+
+impl SubagentOutput {
+    /// The text of the subagent's final message.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Result of the WebFetch tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebFetchOutput {
+    /// The URL that was fetched
+    #[serde(default)]
+    pub url: String,
+
+    /// HTTP status code
+    #[serde(default)]
+    pub code: u16,
+
+    /// HTTP status text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_text: Option<String>,
+
+    /// Size of the response body in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+
+    /// The processed page content
+    #[serde(default)]
+    pub result: String,
+
+    /// Fetch time in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+// ============================================================================
+// ToolOutput Enum - Unified type for all tool results
+// ============================================================================
+
+/// A named tool's structured result did not match that tool's schema.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid output for tool {tool_name}: {source}")]
+pub struct ToolOutputError {
+    /// The tool whose result schema was expected
+    pub tool_name: String,
+    /// The underlying deserialization error
+    #[source]
+    pub source: serde_json::Error,
+}
+
+/// Structured result of a tool call, from the `tool_use_result` field of a
+/// `user` message.
+///
+/// Results have no type tag, so they are always decoded by tool name with
+/// [`ToolOutput::from_name_and_value`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ToolOutput {
+    /// Bash result
+    Bash(BashOutput),
+
+    /// Read result
+    Read(ReadOutput),
+
+    /// Edit result
+    Edit(EditOutput),
+
+    /// Write result
+    Write(WriteOutput),
+
+    /// Glob result
+    Glob(GlobOutput),
+
+    /// Grep result
+    Grep(GrepOutput),
+
+    /// Task (subagent) result
+    Task(SubagentOutput),
+
+    /// WebFetch result
+    WebFetch(WebFetchOutput),
+
+    /// The tool failed; the CLI reports the error text instead of a structured result
+    Error(String),
+
+    /// Result of a tool without a typed model
+    Unknown(Value),
+}
+
+impl ToolOutput {
+    /// Decode a structured tool result using the tool's name to pick the variant.
+    ///
+    /// A string value is the CLI's error text and produces [`ToolOutput::Error`].
+    /// Tools without a typed model produce [`ToolOutput::Unknown`].
+    pub fn from_name_and_value(name: &str, value: Value) -> Result<ToolOutput, ToolOutputError> {
+        fn decode<T: serde::de::DeserializeOwned>(
+            name: &str,
+            value: Value,
+            variant: fn(T) -> ToolOutput,
+        ) -> Result<ToolOutput, ToolOutputError> {
+            serde_json::from_value(value)
+                .map(variant)
+                .map_err(|source| ToolOutputError {
+                    tool_name: name.to_string(),
+                    source,
+                })
+        }
+
+        if let Value::String(text) = value {
+            return Ok(ToolOutput::Error(text));
+        }
+
+        match name {
+            "Bash" => decode(name, value, ToolOutput::Bash),
+            "Read" => decode(name, value, ToolOutput::Read),
+            "Edit" => decode(name, value, ToolOutput::Edit),
+            "Write" => decode(name, value, ToolOutput::Write),
+            "Glob" => decode(name, value, ToolOutput::Glob),
+            "Grep" => decode(name, value, ToolOutput::Grep),
+            "Task" => decode(name, value, ToolOutput::Task),
+            "WebFetch" => decode(name, value, ToolOutput::WebFetch),
+            _ => Ok(ToolOutput::Unknown(value)),
+        }
+    }
+
+    /// Try to get the result as a Bash result.
+    pub fn as_bash(&self) -> Option<&BashOutput> {
+        match self {
+            ToolOutput::Bash(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Try to get the result as a Read result.
+    pub fn as_read(&self) -> Option<&ReadOutput> {
+        match self {
+            ToolOutput::Read(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Try to get the result as an Edit result.
+    pub fn as_edit(&self) -> Option<&EditOutput> {
+        match self {
+            ToolOutput::Edit(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Try to get the result as a Write result.
+    pub fn as_write(&self) -> Option<&WriteOutput> {
+        match self {
+            ToolOutput::Write(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Try to get the result as a Glob result.
+    pub fn as_glob(&self) -> Option<&GlobOutput> {
+        match self {
+            ToolOutput::Glob(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Try to get the result as a Grep result.
+    pub fn as_grep(&self) -> Option<&GrepOutput> {
+        match self {
+            ToolOutput::Grep(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Try to get the result as a Task (subagent) result.
+    pub fn as_task(&self) -> Option<&SubagentOutput> {
+        match self {
+            ToolOutput::Task(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Try to get the result as a WebFetch result.
+    pub fn as_web_fetch(&self) -> Option<&WebFetchOutput> {
+        match self {
+            ToolOutput::WebFetch(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// The error text, if the tool failed.
+    pub fn as_error(&self) -> Option<&str> {
+        match self {
+            ToolOutput::Error(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// Tool Call Correlation
+// ============================================================================
+
+/// A tool call and, once it arrives, its result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// The `tool_use` ID
+    pub id: String,
+    /// Name of the tool
+    pub name: String,
+    /// Raw tool input
+    pub input: Value,
+    /// The `tool_use_id` of the Task call this call was made under, for subagent calls
+    pub parent_tool_use_id: Option<String>,
+    /// The result block, once received
+    pub result: Option<ToolResultBlock>,
+    /// The structured `tool_use_result`, when the CLI sent one
+    pub raw_output: Option<Value>,
+}
+
+impl ToolCall {
+    /// Whether the result has arrived.
+    pub fn is_complete(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Whether the tool reported an error.
+    pub fn is_error(&self) -> bool {
+        self.result
+            .as_ref()
+            .and_then(|r| r.is_error)
+            .unwrap_or(false)
+    }
+
+    /// The input decoded by tool name.
+    pub fn typed_input(&self) -> Result<ToolInput, ToolInputError> {
+        ToolInput::from_name_and_value(&self.name, self.input.clone())
+    }
+
+    /// The structured result decoded by tool name.
+    ///
+    /// Returns `None` while the call is pending, when the CLI sent no
+    /// structured result, or when it does not match the tool's result schema.
+    pub fn typed_output(&self) -> Option<ToolOutput> {
+        let raw = self.raw_output.clone()?;
+        ToolOutput::from_name_and_value(&self.name, raw).ok()
+    }
+
+    /// The text of the result as shown to the model.
+    pub fn result_text(&self) -> Option<String> {
+        let content = self.result.as_ref()?.content.as_ref()?;
+        Some(match content {
+            ToolResultContent::Text(text) => text.clone(),
+            ToolResultContent::Structured(blocks) => blocks
+                .iter()
+                .filter_map(|block| block.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+    }
+
+    /// The exit code of a completed Bash call, as reported by the CLI.
+    ///
+    /// The CLI only reports a code for failed commands, in their "Exit code N"
+    /// error text; successful commands yield `None` rather than an assumed 0.
+    pub fn exit_code(&self) -> Option<i32> {
+        if self.name != "Bash" || !self.is_error() {
+            return None;
+        }
+        let text = match &self.raw_output {
+            Some(Value::String(text)) => text.clone(),
+            _ => self.result_text()?,
+        };
+        let text = text.strip_prefix("Error: ").unwrap_or(&text);
+        text.strip_prefix("Exit code ")?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    }
+}
+
+/// Pairs `tool_use` blocks from assistant messages with the `tool_result`
+/// blocks that answer them.
+///
+/// Feed every [`ClaudeOutput`] to [`push`](Self::push); calls are kept in the
+/// order they were made, giving a tool-call timeline for the session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolCallCorrelator {
+    calls: Vec<ToolCall>,
+    index: HashMap<String, usize>,
+}
+
+impl ToolCallCorrelator {
+    /// Create an empty correlator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an output. Returns the calls completed by it.
+    ///
+    /// Results whose `tool_use_id` has not been seen are ignored.
+    pub fn push(&mut self, output: &ClaudeOutput) -> Vec<&ToolCall> {
+        match output {
+            ClaudeOutput::Assistant(msg) => {
+                for block in &msg.message.content {
+                    if let ContentBlock::ToolUse(tool_use) = block {
+                        if self.index.contains_key(&tool_use.id) {
+                            continue;
+                        }
+                        self.index.insert(tool_use.id.clone(), self.calls.len());
+                        self.calls.push(ToolCall {
+                            id: tool_use.id.clone(),
+                            name: tool_use.name.clone(),
+                            input: tool_use.input.clone(),
+                            parent_tool_use_id: msg.parent_tool_use_id.clone(),
+                            result: None,
+                            raw_output: None,
+                        });
+                    }
+                }
+                Vec::new()
+            }
+            ClaudeOutput::User(msg) => {
+                let results: Vec<&ToolResultBlock> = msg
+                    .message
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::ToolResult(result) => Some(result),
+                        _ => None,
+                    })
+                    .collect();
+                // `tool_use_result` describes the message's result only when there is exactly one
+                let raw_output = match results.as_slice() {
+                    [_] => msg.tool_use_result.clone(),
+                    _ => None,
+                };
+                let mut completed = Vec::new();
+                for result in results {
+                    if let Some(&i) = self.index.get(&result.tool_use_id) {
+                        let call = &mut self.calls[i];
+                        call.result = Some(result.clone());
+                        call.raw_output = raw_output.clone();
+                        completed.push(i);
+                    }
+                }
+                completed.into_iter().map(|i| &self.calls[i]).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Look up a call by its `tool_use` ID.
+    pub fn get(&self, tool_use_id: &str) -> Option<&ToolCall> {
+        self.index.get(tool_use_id).map(|&i| &self.calls[i])
+    }
+
+    /// All calls, in the order they were made.
+    pub fn calls(&self) -> &[ToolCall] {
+        &self.calls
+    }
+
+    /// Calls still waiting for a result.
+    pub fn pending(&self) -> impl Iterator<Item = &ToolCall> {
+        self.calls.iter().filter(|call| !call.is_complete())
+    }
+
+    /// Consume the correlator, returning all calls in order.
+    pub fn into_calls(self) -> Vec<ToolCall> {
+        self.calls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assistant(blocks: Value) -> ClaudeOutput {
+        serde_json::from_value(json!({
+            "type": "assistant",
+            "message": {"id": "msg_1", "role": "assistant", "model": "claude", "content": blocks},
+            "session_id": "s"
+        }))
+        .unwrap()
+    }
+
+    fn user(blocks: Value, tool_use_result: Option<Value>) -> ClaudeOutput {
+        let mut msg = json!({
+            "type": "user",
+            "message": {"role": "user", "content": blocks},
+            "session_id": "08cd4ce5-1ce0-4dd4-8e7c-8b69712c514e"
+        });
+        if let Some(result) = tool_use_result {
+            msg["tool_use_result"] = result;
+        }
+        serde_json::from_value(msg).unwrap()
+    }
+
+    #[test]
+    fn test_read_output() {
+        let output = ToolOutput::from_name_and_value(
+            "Read",
+            json!({
+                "type": "text",
+                "file": {
+                    "filePath": "/src/main.rs",
+                    "content": "fn main() {}",
+                    "numLines": 10,
+                    "startLine": 5,
+                    "totalLines": 40
+                }
+            }),
+        )
+        .unwrap();
+        let read = output.as_read().unwrap();
+        assert_eq!(read.kind, "text");
+        assert_eq!(read.file.line_range(), Some((5, 14)));
+        assert!(read.file.is_partial());
+    }
+
+    #[test]
+    fn test_edit_output_structured_patch() {
+        let output = ToolOutput::from_name_and_value(
+            "Edit",
+            json!({
+                "filePath": "/src/lib.rs",
+                "oldString": "a",
+                "newString": "b",
+                "originalFile": "a\n",
+                "structuredPatch": [{
+                    "oldStart": 1, "oldLines": 1, "newStart": 1, "newLines": 1,
+                    "lines": ["-a", "+b"]
+                }],
+                "userModified": false,
+                "replaceAll": false
+            }),
+        )
+        .unwrap();
+        let edit = output.as_edit().unwrap();
+        assert_eq!(edit.structured_patch[0].lines, vec!["-a", "+b"]);
+    }
+
+    #[test]
+    fn test_grep_and_glob_outputs() {
+        let grep = ToolOutput::from_name_and_value(
+            "Grep",
+            json!({"mode": "files_with_matches", "filenames": ["a.rs", "b.rs"], "numFiles": 2}),
+        )
+        .unwrap();
+        let grep = grep.as_grep().unwrap();
+        assert_eq!(grep.mode, Some(GrepOutputMode::FilesWithMatches));
+        assert_eq!(grep.filenames.len(), 2);
+
+        let glob = ToolOutput::from_name_and_value(
+            "Glob",
+            json!({"filenames": ["a.rs"], "durationMs": 3, "numFiles": 1, "truncated": false}),
+        )
+        .unwrap();
+        assert_eq!(glob.as_glob().unwrap().num_files, 1);
+    }
+
+    #[test]
+    fn test_error_and_unknown_outputs() {
+        let output =
+            ToolOutput::from_name_and_value("Bash", json!("Error: Exit code 2\nboom")).unwrap();
+        assert_eq!(output.as_error(), Some("Error: Exit code 2\nboom"));
+
+        let output = ToolOutput::from_name_and_value("mcp__x__y", json!({"anything": 1})).unwrap();
+        assert!(matches!(output, ToolOutput::Unknown(_)));
+
+        let err = ToolOutput::from_name_and_value("Read", json!({"file": 3})).unwrap_err();
+        assert_eq!(err.tool_name, "Read");
+        assert!(err.to_string().starts_with("invalid output for tool Read"));
+    }
+
+    #[test]
+    fn test_correlator_pairs_calls_with_results() {
+        let mut correlator = ToolCallCorrelator::new();
+        let completed = correlator.push(&assistant(json!([
+            {"type": "text", "text": "Let me look"},
+            {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "false"}},
+            {"type": "tool_use", "id": "t2", "name": "Glob", "input": {"pattern": "*.rs"}}
+        ])));
+        assert!(completed.is_empty());
+        assert_eq!(correlator.pending().count(), 2);
+
+        let completed = correlator.push(&user(
+            json!([{"type": "tool_result", "tool_use_id": "t1", "content": "Exit code 1", "is_error": true}]),
+            Some(json!("Error: Exit code 1")),
+        ));
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].id, "t1");
+        assert!(completed[0].is_error());
+        assert_eq!(completed[0].exit_code(), Some(1));
+
+        correlator.push(&user(
+            json!([{"type": "tool_result", "tool_use_id": "t2", "content": "a.rs"}]),
+            Some(json!({"filenames": ["a.rs"], "numFiles": 1, "truncated": false})),
+        ));
+        assert_eq!(correlator.pending().count(), 0);
+
+        let glob = correlator.get("t2").unwrap();
+        assert_eq!(glob.result_text().as_deref(), Some("a.rs"));
+        assert!(matches!(glob.typed_input(), Ok(ToolInput::Glob(_))));
+        assert_eq!(
+            glob.typed_output().unwrap().as_glob().unwrap().filenames,
+            vec!["a.rs"]
+        );
+
+        let names: Vec<_> = correlator.calls().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Bash", "Glob"]);
+    }
+
+    #[test]
+    fn test_correlator_ignores_unknown_and_ambiguous_results() {
+        let mut correlator = ToolCallCorrelator::new();
+        correlator.push(&assistant(json!([
+            {"type": "tool_use", "id": "t1", "name": "Read", "input": {"file_path": "/a"}},
+            {"type": "tool_use", "id": "t2", "name": "Read", "input": {"file_path": "/b"}}
+        ])));
+
+        let completed = correlator.push(&user(
+            json!([{"type": "tool_result", "tool_use_id": "nope", "content": "?"}]),
+            None,
+        ));
+        assert!(completed.is_empty());
+
+        // Two results in one message: the single tool_use_result cannot be attributed
+        let completed = correlator.push(&user(
+            json!([
+                {"type": "tool_result", "tool_use_id": "t1", "content": "a"},
+                {"type": "tool_result", "tool_use_id": "t2", "content": "b"}
+            ]),
+            Some(json!({"type": "text", "file": {"filePath": "/a"}})),
+        ));
+        assert_eq!(completed.len(), 2);
+        assert!(completed.iter().all(|c| c.raw_output.is_none()));
+        assert_eq!(correlator.get("t2").unwrap().exit_code(), None);
+    }
+
+    #[test]
+    fn test_successful_bash_has_no_reported_exit_code() {
+        let mut correlator = ToolCallCorrelator::new();
+        correlator.push(&assistant(json!([
+            {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "true"}}
+        ])));
+        let completed = correlator.push(&user(
+            json!([{"type": "tool_result", "tool_use_id": "t1", "content": ""}]),
+            Some(json!({"stdout": "", "stderr": "", "interrupted": false, "isImage": false})),
+        ));
+        assert!(!completed[0].is_error());
+        assert_eq!(completed[0].exit_code(), None);
+    }
+}
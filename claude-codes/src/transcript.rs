@@ -0,0 +1,46 @@
+//! Wire-level session transcripts: recording and deterministic replay
+//!
+//! A transcript is a versioned JSONL file. The first line is a
+//! [`TranscriptHeader`]; every following line is a [`TranscriptRecord`] holding
+//! one raw protocol line, the direction it travelled and when.
+//!
+//! ```text
+//! {"transcript_version":1,"provider":"claude","crate_version":"2.1.51","started_at_ms":1767225600000}
+//! {"timestamp_ms":1767225600010,"direction":"sent","line":"{\"type\":\"user\",...}"}
+//! {"timestamp_ms":1767225601200,"direction":"received","line":"{\"type\":\"assistant\",...}"}
+//! ```
+//!
+//! Attach a [`TranscriptRecorder`] to a client with
+//! `AsyncClient::record_transcript` to capture a session, then feed the file
+//! back through a [`ReplayTransport`] to reproduce it without the CLI.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use claude_codes::transcript::{ReplayTransport, Transcript, TranscriptRecorder};
+//! use claude_codes::AsyncClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Record a live session
+//! let mut client = AsyncClient::with_defaults().await?;
+//! client.record_transcript(TranscriptRecorder::create("session.jsonl")?);
+//! client.query("What is 2 + 2?").await?;
+//!
+//! // Replay it later, e.g. in a test
+//! let transcript = Transcript::load("session.jsonl")?;
+//! let mut replayed = AsyncClient::from_transport(ReplayTransport::new(transcript))?;
+//! let responses = replayed.query("What is 2 + 2?").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use codes_core::transcript::{
+    Direction, Transcript, TranscriptHeader, TranscriptRecord, TranscriptRecorder,
+    TRANSCRIPT_VERSION,
+};
+
+#[cfg(feature = "async-client")]
+pub use codes_core::transcript::ReplayTransport;
+
+/// Provider name written to transcripts recorded by this crate
+pub const TRANSCRIPT_PROVIDER: &str = "claude";
@@ -0,0 +1,37 @@
+//! Byte-stream transports for the asynchronous client
+//!
+//! [`AsyncClient`](crate::AsyncClient) handles JSON Lines framing and the
+//! control protocol, and delegates everything else to a [`Transport`]: where
+//! the bytes come from, where they go, and how the other end is started and
+//! stopped.
+//!
+//! Two implementations are provided:
+//!
+//! - [`ProcessTransport`] - a spawned `claude` process, talking over its stdio
+//!   pipes. This is what [`AsyncClient::new`](crate::AsyncClient::new) uses.
+//! - [`StreamTransport`] - any [`AsyncRead`](tokio::io::AsyncRead) /
+//!   [`AsyncWrite`](tokio::io::AsyncWrite) pair, such as a Unix socket, an SSH
+//!   channel, a container exec stream or a [`tokio::io::duplex`] pipe.
+//!
+//! The transports live in the shared `codes-core` crate and are re-exported
+//! here.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use claude_codes::transport::StreamTransport;
+//! use claude_codes::AsyncClient;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let socket = tokio::net::UnixStream::connect("/tmp/claude.sock").await?;
+//! let mut client = AsyncClient::from_transport(StreamTransport::from_stream(socket))?;
+//! let responses = client.query("Hello!").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use codes_core::transport::{
+    BoxFuture, ProcessTransport, StreamTransport, Transport, TransportReader, TransportWriter,
+};
+
+pub(crate) use codes_core::transport::STDERR_DRAIN_TIMEOUT;
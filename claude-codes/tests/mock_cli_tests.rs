@@ -0,0 +1,968 @@
+//! Offline client tests driven by the scripted `fake-claude` binary
+//!
+//! Run with: `cargo test --features testing`
+
+#![cfg(all(feature = "testing", feature = "async-client", feature = "sync-client"))]
+
+use claude_codes::testing::{MockScript, SCRIPT_ENV_VAR};
+use claude_codes::{AsyncClient, ClaudeCliBuilder, ClaudeInput, ClaudeOutput, SyncClient};
+use serde_json::json;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const FAKE_CLAUDE: &str = env!("CARGO_BIN_EXE_fake-claude");
+
+/// Write a script to a unique temp file and return its path
+fn script_file(script: &MockScript) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("fake-claude-{}.jsonl", Uuid::new_v4()));
+    script.write_to(&path).expect("Failed to write script");
+    path
+}
+
+fn async_client(script: &MockScript) -> AsyncClient {
+    let mut cmd = ClaudeCliBuilder::new()
+        .command(FAKE_CLAUDE)
+        .session_id(Uuid::nil())
+        .build_command();
+    cmd.env(SCRIPT_ENV_VAR, script_file(script));
+    let child = cmd.spawn().expect("Failed to spawn fake-claude");
+    AsyncClient::new(child).expect("Failed to create async client")
+}
+
+fn sync_client(script: &MockScript) -> SyncClient {
+    let path = script_file(script);
+    // spawn_sync() offers no way to add environment variables
+    let child = std::process::Command::new(FAKE_CLAUDE)
+        .env(SCRIPT_ENV_VAR, path)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn fake-claude");
+    SyncClient::new(child).expect("Failed to create sync client")
+}
+
+#[tokio::test]
+async fn test_async_query_stream_replays_fixture() {
+    let init: serde_json::Value = serde_json::from_str(include_str!(
+        "../test_cases/tool_use_captures/tool_msg_0.json"
+    ))
+    .unwrap();
+    let script = MockScript::new()
+        .expect_user_containing("What is 2 + 2?")
+        .emit(init)
+        .emit_assistant_text("4")
+        .emit_result("4");
+
+    let mut client = async_client(&script);
+    let outputs = client
+        .query_stream("What is 2 + 2?")
+        .await
+        .expect("Failed to send query")
+        .collect()
+        .await
+        .expect("Failed to collect responses");
+
+    assert_eq!(outputs.len(), 3);
+    assert!(outputs[0].is_system_init());
+    assert_eq!(outputs[1].text_content().as_deref(), Some("4"));
+    assert!(matches!(outputs[2], ClaudeOutput::Result(_)));
+    assert_eq!(
+        client.session_uuid().unwrap(),
+        Uuid::nil(),
+        "session UUID should be captured from the result message uuid"
+    );
+}
+
+#[tokio::test]
+async fn test_async_ping() {
+    let script = MockScript::new()
+        .expect_user_containing("ping")
+        .emit_assistant_text("pong")
+        .emit_result("pong");
+
+    let mut client = async_client(&script);
+    assert!(client.ping().await);
+}
+
+#[tokio::test]
+async fn test_async_ping_fails_without_pong() {
+    let script = MockScript::new()
+        .expect("user")
+        .emit_assistant_text("ping yourself")
+        .emit_result("no");
+
+    let mut client = async_client(&script);
+    assert!(!client.ping().await);
+}
+
+#[tokio::test]
+async fn test_async_enable_tool_approval_round_trip() {
+    let script = MockScript::new()
+        .expect_control_request("initialize")
+        .emit_init()
+        .emit_control_success()
+        .expect_user_containing("list files")
+        .emit_can_use_tool("req-1", "Bash", json!({"command": "ls"}))
+        .expect_control_response("req-1")
+        .emit_tool_use("toolu_1", "Bash", json!({"command": "ls"}))
+        .emit_result("done");
+
+    let mut client = async_client(&script);
+    client
+        .enable_tool_approval()
+        .await
+        .expect("Initialization handshake failed");
+    assert!(client.is_tool_approval_enabled());
+
+    client
+        .send(&ClaudeInput::user_message("list files", Uuid::nil()))
+        .await
+        .unwrap();
+
+    // The init message emitted during the handshake is buffered, not dropped
+    assert!(client.receive().await.unwrap().is_system_init());
+
+    let request = match client.receive().await.unwrap() {
+        ClaudeOutput::ControlRequest(req) => req,
+        other => panic!("Expected control request, got {:?}", other),
+    };
+    let tool_request = match &request.request {
+        claude_codes::ControlRequestPayload::CanUseTool(req) => req,
+        other => panic!("Expected can_use_tool, got {:?}", other),
+    };
+    assert_eq!(tool_request.tool_name, "Bash");
+    client
+        .send_control_response(tool_request.allow(&request.request_id))
+        .await
+        .unwrap();
+
+    let tool_use = client.receive().await.unwrap();
+    assert_eq!(tool_use.tool_uses().count(), 1);
+    assert!(matches!(
+        client.receive().await.unwrap(),
+        ClaudeOutput::Result(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_async_enable_tool_approval_error() {
+    let script = MockScript::new()
+        .expect_control_request("initialize")
+        .emit_control_error("hooks not supported");
+
+    let mut client = async_client(&script);
+    let err = client.enable_tool_approval().await.unwrap_err();
+    assert!(err.to_string().contains("hooks not supported"));
+    assert!(!client.is_tool_approval_enabled());
+}
+
+#[tokio::test]
+async fn test_async_receive_after_failed_exit_is_process_crashed() {
+    let script = MockScript::new().stderr("fatal: not logged in").exit(1);
+
+    let mut client = async_client(&script);
+    match client.receive().await {
+        Err(claude_codes::Error::ProcessCrashed(report)) => {
+            assert_eq!(report.code, Some(1));
+            assert_eq!(report.stderr_tail, vec!["fatal: not logged in"]);
+        }
+        other => panic!("expected ProcessCrashed, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_sync_query_and_ping() {
+    let script = MockScript::new()
+        .expect_user_containing("hello")
+        .emit_assistant_text("hi there")
+        .emit_result("hi there")
+        .expect_user_containing("ping")
+        .emit_assistant_text("PONG")
+        .emit_result("pong");
+
+    let mut client = sync_client(&script);
+    let outputs = client
+        .query(ClaudeInput::user_message("hello", Uuid::nil()))
+        .expect("Query failed");
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].text_content().as_deref(), Some("hi there"));
+
+    assert!(client.ping());
+}
+
+#[test]
+fn test_sync_enable_tool_approval() {
+    let script = MockScript::new()
+        .expect_control_request("initialize")
+        .emit_control_success();
+
+    let mut client = sync_client(&script);
+    client
+        .enable_tool_approval()
+        .expect("Initialization handshake failed");
+    assert!(client.is_tool_approval_enabled());
+}
+
+#[tokio::test]
+async fn test_in_process_transport_query_and_tool_approval() {
+    let script = MockScript::new()
+        .expect_control_request("initialize")
+        .emit_control_success()
+        .expect_user_containing("hello")
+        .emit_assistant_text("hi")
+        .emit_result("hi");
+
+    let mut client = AsyncClient::from_transport(script.into_transport(&Uuid::nil().to_string()))
+        .expect("Failed to create client over stream transport");
+    assert_eq!(client.pid(), None);
+
+    client.enable_tool_approval().await.unwrap();
+    let outputs = client.query("hello").await.unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].text_content().as_deref(), Some("hi"));
+
+    // The script is exhausted, so the connection closes
+    assert!(matches!(
+        client.receive().await,
+        Err(claude_codes::Error::ConnectionClosed)
+    ));
+}
+
+#[tokio::test]
+async fn test_in_process_transport_reports_script_mismatch_on_stderr() {
+    use tokio::io::AsyncBufReadExt;
+
+    let script = MockScript::new().expect_control_request("initialize");
+    let mut client = AsyncClient::from_transport(script.into_transport("session")).unwrap();
+    let mut stderr = client.take_stderr().expect("stream transport has stderr");
+
+    client
+        .send(&ClaudeInput::user_message("unexpected", Uuid::nil()))
+        .await
+        .unwrap();
+    assert!(client.receive().await.is_err());
+
+    let mut line = String::new();
+    stderr.read_line(&mut line).await.unwrap();
+    assert!(
+        line.contains("expected type \"control_request\""),
+        "{}",
+        line
+    );
+}
+
+#[tokio::test]
+async fn test_async_interrupt_buffers_in_flight_output() {
+    let script = MockScript::new()
+        .expect_user_containing("write a novel")
+        .emit_assistant_text("Chapter 1")
+        .expect_control_request("interrupt")
+        .emit_assistant_text("Chapter 2")
+        .emit_control_success()
+        .emit_result("interrupted");
+
+    let mut client = async_client(&script);
+    let first = {
+        let mut stream = client.query_stream("write a novel").await.unwrap();
+        stream.next().await.unwrap().unwrap()
+    };
+    assert_eq!(first.text_content().as_deref(), Some("Chapter 1"));
+
+    client.interrupt().await.expect("Interrupt failed");
+
+    // Output that arrived before the control response is not lost
+    let buffered = client.receive().await.unwrap();
+    assert_eq!(buffered.text_content().as_deref(), Some("Chapter 2"));
+    assert!(matches!(
+        client.receive().await.unwrap(),
+        ClaudeOutput::Result(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_async_cancelled_stream_interrupts_and_drains_turn() {
+    let script = MockScript::new()
+        .expect_user_containing("write a novel")
+        .emit_assistant_text("Chapter 1")
+        .expect_control_request("interrupt")
+        .emit_assistant_text("Chapter 2")
+        .emit_control_success()
+        .emit_result("interrupted")
+        .expect_user_containing("summarize")
+        .emit_assistant_text("It was short")
+        .emit_result("It was short");
+
+    let mut client = async_client(&script);
+    let cancel = claude_codes::CancellationToken::new();
+    {
+        let mut stream = client
+            .query_stream("write a novel")
+            .await
+            .unwrap()
+            .cancel_on(cancel.clone());
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.text_content().as_deref(), Some("Chapter 1"));
+
+        cancel.cancel();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(claude_codes::Error::Cancelled))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    // The interrupted turn was drained, so the next query starts clean
+    let outputs = client.query("summarize").await.unwrap();
+    assert_eq!(outputs[0].text_content().as_deref(), Some("It was short"));
+}
+
+#[tokio::test]
+async fn test_async_idle_and_request_timeouts() {
+    use std::time::Duration;
+
+    let script = MockScript::new()
+        .expect_user_containing("hello")
+        .emit_assistant_text("thinking")
+        .expect_control_request("set_model")
+        .expect_user_containing("never sent");
+
+    let mut client = async_client(&script);
+    client.set_idle_timeout(Some(Duration::from_millis(200)));
+    client.set_request_timeout(Some(Duration::from_millis(200)));
+
+    let err = client.query("hello").await.unwrap_err();
+    assert!(matches!(err, claude_codes::Error::Timeout), "{:?}", err);
+
+    // The CLI never answers, so the control request times out too
+    let err = client.set_model("opus").await.unwrap_err();
+    assert!(matches!(err, claude_codes::Error::Timeout), "{:?}", err);
+    assert!(client.is_alive());
+}
+
+#[tokio::test]
+async fn test_async_writer_sends_while_reader_streams() {
+    let script = MockScript::new()
+        .expect_user_containing("list files")
+        .emit_can_use_tool("perm-1", "Bash", json!({"command": "ls"}))
+        .expect_control_response("perm-1")
+        .emit_assistant_text("Listing")
+        .expect_user_containing("count them too")
+        .expect_control_request("set_model")
+        .emit_control_success()
+        .emit_result("3 files");
+
+    let client = async_client(&script);
+    let writer = client.writer();
+    let (mut outputs, reader) = client.spawn_reader(16);
+
+    writer
+        .send(&ClaudeInput::user_message("list files", Uuid::nil()))
+        .await
+        .unwrap();
+    let mut texts = Vec::new();
+    while let Some(output) = outputs.recv().await {
+        match output.unwrap() {
+            ClaudeOutput::ControlRequest(req) => {
+                let claude_codes::ControlRequestPayload::CanUseTool(perm) = &req.request else {
+                    panic!("unexpected control request: {:?}", req);
+                };
+                // Answer from another task while the reader keeps streaming
+                let writer = writer.clone();
+                let response = perm.allow(&req.request_id);
+                tokio::spawn(async move { writer.send_control_response(response).await })
+                    .await
+                    .unwrap()
+                    .unwrap();
+            }
+            ClaudeOutput::Result(_) => break,
+            output => {
+                texts.push(output.text_content().unwrap());
+                writer
+                    .send(&ClaudeInput::user_message("count them too", Uuid::nil()))
+                    .await
+                    .unwrap();
+                // The reader task delivers the response to the writer
+                writer.set_model("opus").await.unwrap();
+            }
+        }
+    }
+    assert_eq!(texts, vec!["Listing"]);
+
+    // The script is done, so the reader stops and hands the client back
+    let mut client = reader.await.unwrap();
+    assert!(matches!(
+        writer.interrupt().await,
+        Err(claude_codes::Error::ConnectionClosed)
+    ));
+    assert!(matches!(
+        client.receive().await,
+        Err(claude_codes::Error::ConnectionClosed)
+    ));
+}
+
+#[tokio::test]
+async fn test_async_budget_interrupts_turn() {
+    use claude_codes::accounting::{Budget, UsageLedger};
+
+    let script = MockScript::new()
+        .expect_user_containing("write a novel")
+        .emit(json!({
+            "type": "assistant",
+            "message": {
+                "id": "msg_1", "role": "assistant", "model": "claude-sonnet-4",
+                "content": [{"type": "text", "text": "Chapter 1"}],
+                "usage": {"input_tokens": 10, "output_tokens": 5000}
+            },
+            "session_id": "00000000-0000-0000-0000-000000000000"
+        }))
+        .expect_control_request("interrupt")
+        .emit_control_success()
+        .emit_result("interrupted");
+
+    let mut client = async_client(&script);
+    client.set_usage_ledger(UsageLedger::new().budget(Budget::output_tokens(1000).interrupt()));
+
+    let outputs = client.query("write a novel").await.unwrap();
+    assert_eq!(outputs[0].text_content().as_deref(), Some("Chapter 1"));
+    assert!(matches!(outputs.last(), Some(ClaudeOutput::Result(_))));
+
+    let ledger = client.usage_ledger().unwrap();
+    assert!(ledger.budgets()[0].is_exceeded());
+    assert_eq!(ledger.report().total.tokens.output_tokens, 5000);
+}
+
+#[tokio::test]
+async fn test_async_query_with_retry_resends_overloaded_turn() {
+    use claude_codes::governor::{BackoffPolicy, RateLimitGovernor};
+    use std::time::Duration;
+
+    let script = MockScript::new()
+        .expect_user_containing("summarize")
+        .emit(json!({
+            "type": "error",
+            "error": {"type": "overloaded_error", "message": "Overloaded"}
+        }))
+        .emit(json!({
+            "type": "result", "subtype": "success", "is_error": true,
+            "duration_ms": 1, "duration_api_ms": 1, "num_turns": 1,
+            "result": "API Error: 529 Overloaded",
+            "session_id": "00000000-0000-0000-0000-000000000000", "total_cost_usd": 0.0
+        }))
+        .expect_user_containing("summarize")
+        .emit_assistant_text("Done")
+        .emit_result("Done");
+
+    let mut client = async_client(&script);
+    client.set_rate_limit_governor(RateLimitGovernor::new().with_backoff(BackoffPolicy {
+        initial: Duration::from_millis(10),
+        ..Default::default()
+    }));
+
+    let outputs = client.query_with_retry("summarize").await.unwrap();
+    assert_eq!(outputs[0].text_content().as_deref(), Some("Done"));
+
+    let governor = client.rate_limit_governor().unwrap();
+    assert!(!governor.should_retry());
+    assert_eq!(governor.attempts(), 0);
+}
+
+#[tokio::test]
+async fn test_async_query_with_retry_gives_up_on_result_text_failures() {
+    use claude_codes::governor::{BackoffPolicy, RateLimitGovernor};
+    use std::time::Duration;
+
+    let overloaded = json!({
+        "type": "result", "subtype": "success", "is_error": true,
+        "duration_ms": 1, "duration_api_ms": 1, "num_turns": 1,
+        "result": "API Error: 529 {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}",
+        "session_id": "00000000-0000-0000-0000-000000000000", "total_cost_usd": 0.0
+    });
+    // Both turns fail only through the result text; a third send would
+    // find the script exhausted
+    let script = MockScript::new()
+        .expect_user_containing("summarize")
+        .emit(overloaded.clone())
+        .expect_user_containing("summarize")
+        .emit(overloaded);
+
+    let mut client = async_client(&script);
+    client.set_rate_limit_governor(RateLimitGovernor::new().with_backoff(BackoffPolicy {
+        initial: Duration::from_millis(10),
+        max_retries: 1,
+        ..Default::default()
+    }));
+
+    let outputs = client.query_with_retry("summarize").await.unwrap();
+    assert!(outputs.last().unwrap().is_error());
+
+    let governor = client.rate_limit_governor().unwrap();
+    assert!(!governor.should_retry());
+    assert_eq!(governor.attempts(), 1);
+}
+
+#[tokio::test]
+async fn test_session_pool_restarts_exited_worker_on_its_session() {
+    use claude_codes::pool::{SessionPool, WorkerStatus};
+
+    // fake-claude exits once the script is done, like a crashed CLI
+    let script = MockScript::new()
+        .expect_user_containing("hello")
+        .emit_assistant_text("hi")
+        .emit_result("hi");
+    let builder = ClaudeCliBuilder::new()
+        .command(FAKE_CLAUDE)
+        .env(SCRIPT_ENV_VAR, script_file(&script).to_string_lossy());
+    let pool = SessionPool::new(builder).max_workers(1);
+
+    let outputs = pool.query("hello").await.unwrap();
+    assert_eq!(outputs[0].text_content().as_deref(), Some("hi"));
+    let session = outputs.last().unwrap().session_id().unwrap().to_string();
+    assert_eq!(pool.stats().idle, 1);
+
+    let mut restarted = None;
+    for _ in 0..100 {
+        let reports = pool.health_check().await;
+        if reports[0].status == WorkerStatus::Restarted {
+            restarted = Some(reports[0].clone());
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let report = restarted.expect("worker was never restarted");
+    assert_eq!(report.session.unwrap().to_string(), session);
+    assert_eq!(pool.stats().restarts, 1);
+
+    let outputs = pool.query("hello").await.unwrap();
+    assert_eq!(outputs[0].text_content().as_deref(), Some("hi"));
+    assert_eq!(pool.stats().busy, 0);
+}
+
+#[tokio::test]
+async fn test_async_crash_is_reported_with_stderr_tail() {
+    let script = MockScript::new()
+        .expect_user_containing("hello")
+        .stderr("panic: out of cheese")
+        .exit(3);
+
+    let mut client = async_client(&script);
+    let err = client.query("hello").await.unwrap_err();
+    let claude_codes::Error::ProcessCrashed(report) = err else {
+        panic!("expected ProcessCrashed, got {:?}", err);
+    };
+    assert_eq!(report.code, Some(3));
+    assert!(!report.killed);
+    assert_eq!(report.stderr_tail, vec!["panic: out of cheese"]);
+}
+
+#[tokio::test]
+async fn test_async_captured_stderr_is_attached_to_crash() {
+    use claude_codes::stderr::{Diagnostic, StderrPump};
+
+    let script = MockScript::new()
+        .stderr("Update available! Run: claude update")
+        .expect_user_containing("hello")
+        .stderr("Invalid API key · Please run /login")
+        .exit(1);
+
+    let mut client = async_client(&script);
+    let buffer = client.capture_stderr(StderrPump::new()).unwrap();
+    assert!(client.take_stderr().is_none());
+
+    let err = client.query("hello").await.unwrap_err();
+    let claude_codes::Error::ProcessCrashed(report) = err else {
+        panic!("expected ProcessCrashed, got {:?}", err);
+    };
+    assert_eq!(
+        report.stderr_tail,
+        vec![
+            "Update available! Run: claude update",
+            "Invalid API key · Please run /login"
+        ]
+    );
+    assert!(matches!(
+        report.diagnostics()[..],
+        [
+            Diagnostic::VersionNotice { .. },
+            Diagnostic::AuthFailure { .. }
+        ]
+    ));
+    assert_eq!(buffer.diagnostics(), report.diagnostics());
+}
+
+#[tokio::test]
+async fn test_async_captured_stderr_is_attached_to_parse_errors() {
+    use claude_codes::stderr::StderrPump;
+    use claude_codes::transport::StreamTransport;
+    use tokio::io::AsyncWriteExt;
+
+    let (client_io, mut cli_io) = tokio::io::duplex(4096);
+    let transport = StreamTransport::from_stream(client_io)
+        .with_stderr(std::io::Cursor::new(b"error: bad config\n".to_vec()));
+    let mut client = AsyncClient::from_transport(transport).unwrap();
+    client
+        .capture_stderr(StderrPump::new())
+        .unwrap()
+        .closed()
+        .await;
+
+    cli_io.write_all(b"not json\n").await.unwrap();
+    let err = client.receive().await.unwrap_err();
+    assert!(
+        matches!(err.root(), claude_codes::Error::Deserialization(_)),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.stderr_tail(), ["error: bad config"]);
+}
+
+#[tokio::test]
+async fn test_async_normal_exit_is_connection_closed() {
+    let script = MockScript::new()
+        .expect_user_containing("hello")
+        .emit_assistant_text("bye")
+        .exit(0);
+
+    let mut client = async_client(&script);
+    let err = client.query("hello").await.unwrap_err();
+    assert!(matches!(err, claude_codes::Error::ConnectionClosed));
+}
+
+#[tokio::test]
+async fn test_async_close_lets_cli_exit_on_stdin_eof() {
+    let script = MockScript::new().expect_user_containing("never sent");
+
+    let client = async_client(&script);
+    let report = client
+        .close(std::time::Duration::from_secs(5))
+        .await
+        .unwrap()
+        .expect("process transport reports exit");
+    // fake-claude fails the pending expectation when stdin closes
+    assert!(!report.killed);
+    assert_eq!(report.code, Some(1));
+    assert!(report.stderr_tail[0].contains("fake-claude"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_async_close_kills_after_grace_period() {
+    let child = tokio::process::Command::new("sleep")
+        .arg("30")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let client = AsyncClient::new(child).unwrap();
+
+    let report = client
+        .close(std::time::Duration::from_millis(100))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(report.killed);
+    assert_eq!(report.code, None);
+    assert_eq!(report.signal, Some(9));
+}
+
+#[tokio::test]
+async fn test_async_set_permission_mode_and_model() {
+    let script = MockScript::new()
+        .step(claude_codes::testing::ScriptStep::Expect(
+            claude_codes::testing::Expectation {
+                message_type: "control_request".to_string(),
+                subtype: Some("set_permission_mode".to_string()),
+                contains: Some(r#""mode":"acceptEdits""#.to_string()),
+                ..Default::default()
+            },
+        ))
+        .emit_control_success()
+        .expect_control_request("set_model")
+        .emit_control_error("unknown model: gpt-5");
+
+    let mut client = async_client(&script);
+    client
+        .set_permission_mode(claude_codes::PermissionModeName::AcceptEdits)
+        .await
+        .expect("set_permission_mode failed");
+
+    let err = client.set_model("gpt-5").await.unwrap_err();
+    assert!(matches!(err, claude_codes::Error::Protocol(_)));
+    assert!(err.to_string().contains("set_model"));
+    assert!(err.to_string().contains("unknown model: gpt-5"));
+}
+
+#[tokio::test]
+async fn test_async_hooks_are_answered_automatically() {
+    use claude_codes::testing::{Expectation, ScriptStep};
+    use claude_codes::{HookOutput, HookRegistry};
+
+    let script = MockScript::new()
+        .step(ScriptStep::Expect(Expectation {
+            message_type: "control_request".to_string(),
+            subtype: Some("initialize".to_string()),
+            contains: Some(r#""hookCallbackIds":["hook_0"]"#.to_string()),
+            ..Default::default()
+        }))
+        .emit_control_success()
+        .expect_user_containing("clean up")
+        .emit_hook_callback(
+            "hook-req-1",
+            "hook_0",
+            json!({
+                "session_id": "abc",
+                "hook_event_name": "PreToolUse",
+                "tool_name": "Bash",
+                "tool_input": {"command": "rm -rf /"}
+            }),
+        )
+        .step(ScriptStep::Expect(Expectation {
+            message_type: "control_response".to_string(),
+            request_id: Some("hook-req-1".to_string()),
+            contains: Some(r#""permissionDecision":"deny""#.to_string()),
+            ..Default::default()
+        }))
+        .emit_assistant_text("I won't do that")
+        .emit_result("refused");
+
+    let hooks = HookRegistry::new().pre_tool_use(Some("Bash"), |input| {
+        if input.tool_input["command"]
+            .as_str()
+            .is_some_and(|c| c.starts_with("rm -rf"))
+        {
+            HookOutput::deny_tool("Destructive command")
+        } else {
+            HookOutput::proceed()
+        }
+    });
+
+    let mut client = async_client(&script);
+    client
+        .enable_hooks(hooks)
+        .await
+        .expect("Hook handshake failed");
+    assert!(client.is_tool_approval_enabled());
+    assert!(client.enable_hooks(HookRegistry::new()).await.is_err());
+
+    // The hook callback is answered inside receive() and never surfaces
+    let outputs = client.query("clean up").await.unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(
+        outputs[0].text_content().as_deref(),
+        Some("I won't do that")
+    );
+}
+
+#[tokio::test]
+async fn test_async_sdk_mcp_server_answers_tools() {
+    use claude_codes::testing::{Expectation, ScriptStep};
+    use claude_codes::{McpServerRegistry, McpToolResult, SdkMcpServer};
+
+    #[derive(serde::Deserialize)]
+    struct AddArgs {
+        a: i64,
+        b: i64,
+    }
+
+    let expect_response = |request_id: &str, contains: &str| {
+        ScriptStep::Expect(Expectation {
+            message_type: "control_response".to_string(),
+            request_id: Some(request_id.to_string()),
+            contains: Some(contains.to_string()),
+            ..Default::default()
+        })
+    };
+
+    let script = MockScript::new()
+        .step(ScriptStep::Expect(Expectation {
+            message_type: "control_request".to_string(),
+            subtype: Some("initialize".to_string()),
+            contains: Some(r#""sdkMcpServers":["calc"]"#.to_string()),
+            ..Default::default()
+        }))
+        .emit_control_success()
+        .expect_user_containing("add")
+        .emit_mcp_message(
+            "mcp-1",
+            "calc",
+            json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}),
+        )
+        .step(expect_response("mcp-1", r#""name":"add""#))
+        .emit_mcp_message(
+            "mcp-2",
+            "calc",
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "params": {"name": "add", "arguments": {"a": 2, "b": 3}}
+            }),
+        )
+        .step(expect_response("mcp-2", r#""text":"5""#))
+        .emit_assistant_text("2 + 3 = 5")
+        .emit_result("5");
+
+    let servers = McpServerRegistry::new().server(SdkMcpServer::new("calc", "1.0.0").tool(
+        "add",
+        "Add two integers",
+        json!({"type": "object"}),
+        |args: AddArgs| Ok(McpToolResult::text((args.a + args.b).to_string())),
+    ));
+
+    let mut client = async_client(&script);
+    client.register_mcp_servers(servers);
+    client.enable_tool_approval().await.unwrap();
+
+    let outputs = client.query("add 2 and 3").await.unwrap();
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0].text_content().as_deref(), Some("2 + 3 = 5"));
+}
+
+/// Script a turn with three tool requests: one allowed, one denied, one asked
+fn policy_script() -> MockScript {
+    MockScript::new()
+        .expect_control_request("initialize")
+        .emit_control_success()
+        .expect_user_containing("build it")
+        .emit_can_use_tool("perm-1", "Bash", json!({"command": "cargo build"}))
+        .expect_control_response("perm-1")
+        .emit_can_use_tool("perm-2", "Bash", json!({"command": "rm -rf target"}))
+        .step(claude_codes::testing::ScriptStep::Expect(
+            claude_codes::testing::Expectation {
+                message_type: "control_response".to_string(),
+                request_id: Some("perm-2".to_string()),
+                contains: Some("No deletes".to_string()),
+                ..Default::default()
+            },
+        ))
+        .emit_can_use_tool(
+            "perm-3",
+            "WebFetch",
+            json!({"url": "https://example.org", "prompt": "read"}),
+        )
+        .expect_control_response("perm-3")
+        .emit_result("built")
+}
+
+fn build_policy() -> claude_codes::ApprovalPolicy {
+    use claude_codes::{ApprovalPolicy, PolicyRule};
+
+    ApprovalPolicy::new()
+        .rule(PolicyRule::bash_prefix("cargo ").allow())
+        .rule(
+            PolicyRule::bash_regex(r"\brm\b")
+                .unwrap()
+                .deny("No deletes"),
+        )
+}
+
+#[tokio::test]
+async fn test_async_approval_policy_answers_requests() {
+    let mut client = async_client(&policy_script());
+    client.set_approval_policy(build_policy());
+    client.enable_tool_approval().await.unwrap();
+    client
+        .send(&ClaudeInput::user_message("build it", Uuid::nil()))
+        .await
+        .unwrap();
+
+    // Only the request the policy leaves to the caller surfaces
+    let request = match client.receive().await.unwrap() {
+        ClaudeOutput::ControlRequest(req) => req,
+        other => panic!("Expected control request, got {:?}", other),
+    };
+    assert_eq!(request.request_id, "perm-3");
+    if let claude_codes::ControlRequestPayload::CanUseTool(tool) = &request.request {
+        client
+            .send_control_response(tool.allow(&request.request_id))
+            .await
+            .unwrap();
+    }
+    assert!(matches!(
+        client.receive().await.unwrap(),
+        ClaudeOutput::Result(_)
+    ));
+
+    let audit = client.approval_policy().unwrap().audit_log();
+    assert_eq!(audit.len(), 3);
+    assert_eq!(audit[0].decision, claude_codes::PolicyDecision::Allow);
+    assert!(matches!(
+        audit[1].decision,
+        claude_codes::PolicyDecision::Deny { .. }
+    ));
+    assert_eq!(audit[2].decision, claude_codes::PolicyDecision::Ask);
+}
+
+#[test]
+fn test_sync_approval_policy_answers_requests() {
+    let script = MockScript::new()
+        .expect_control_request("initialize")
+        .emit_control_success()
+        .expect_user_containing("build it")
+        .emit_can_use_tool("perm-1", "Bash", json!({"command": "cargo build"}))
+        .expect_control_response("perm-1")
+        .emit_can_use_tool("perm-2", "Bash", json!({"command": "rm -rf target"}))
+        .expect_control_response("perm-2")
+        .emit_result("built");
+
+    let mut client = sync_client(&script);
+    client.set_approval_policy(build_policy());
+    client.enable_tool_approval().unwrap();
+
+    // Both requests are decided by the policy, so only the result is yielded
+    let outputs = client
+        .query(ClaudeInput::user_message("build it", Uuid::nil()))
+        .unwrap();
+    assert_eq!(outputs.len(), 1);
+    assert!(matches!(outputs[0], ClaudeOutput::Result(_)));
+
+    let audit = client.approval_policy_mut().unwrap().take_audit_log();
+    assert_eq!(audit.len(), 2);
+    assert_eq!(audit[0].rule.as_deref(), Some("bash prefix \"cargo \""));
+}
+
+#[tokio::test]
+async fn test_recorded_transcript_replays_deterministically() {
+    use claude_codes::transcript::{Direction, ReplayTransport, Transcript, TranscriptRecorder};
+
+    let script = MockScript::new()
+        .expect_control_request("initialize")
+        .emit_control_success()
+        .expect_user_containing("hello")
+        .emit_assistant_text("hi")
+        .emit_result("hi");
+
+    let path = std::env::temp_dir().join(format!("transcript-{}.jsonl", Uuid::new_v4()));
+    let mut client = AsyncClient::from_transport(script.into_transport("session")).unwrap();
+    client.record_transcript(TranscriptRecorder::create(&path).unwrap());
+    client.enable_tool_approval().await.unwrap();
+    let live = client.query("hello").await.unwrap();
+    client.stop_recording();
+
+    let transcript = Transcript::load(&path).unwrap();
+    let directions: Vec<_> = transcript.records.iter().map(|r| r.direction).collect();
+    assert_eq!(
+        directions,
+        vec![
+            Direction::Sent,
+            Direction::Received,
+            Direction::Sent,
+            Direction::Received,
+            Direction::Received,
+        ]
+    );
+    assert_eq!(transcript.received_messages().count(), 3);
+
+    // The replayed client generates a fresh initialize request_id; the
+    // transport maps it onto the recorded one
+    let mut replayed = AsyncClient::from_transport(ReplayTransport::new(transcript)).unwrap();
+    replayed.enable_tool_approval().await.unwrap();
+    let replayed_outputs = replayed.query("hello").await.unwrap();
+    assert_eq!(replayed_outputs.len(), live.len());
+    assert_eq!(replayed_outputs[0].text_content().as_deref(), Some("hi"));
+    assert!(matches!(
+        replayed.receive().await,
+        Err(claude_codes::Error::ConnectionClosed)
+    ));
+}
@@ -0,0 +1,95 @@
+//! Exit reports for CLI processes.
+
+use crate::stderr::Diagnostic;
+use std::fmt;
+use std::process::ExitStatus;
+
+/// Number of stderr lines kept in an [`ExitReport`].
+pub const STDERR_TAIL_LINES: usize = 20;
+
+/// How a CLI process ended.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExitReport {
+    /// Exit code, if the process exited on its own.
+    pub code: Option<i32>,
+    /// Signal that terminated the process (Unix only).
+    pub signal: Option<i32>,
+    /// Whether the process was killed after its grace period ran out.
+    pub killed: bool,
+    /// The last lines the process wrote to stderr, oldest first.
+    pub stderr_tail: Vec<String>,
+}
+
+impl ExitReport {
+    /// Build a report from an exit status.
+    pub fn from_status(status: &ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(status);
+        #[cfg(not(unix))]
+        let signal = None;
+        Self {
+            code: status.code(),
+            signal,
+            ..Self::default()
+        }
+    }
+
+    /// Keep the last [`STDERR_TAIL_LINES`] lines of `stderr`.
+    pub fn with_stderr(mut self, stderr: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(stderr);
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+        let start = lines.len().saturating_sub(STDERR_TAIL_LINES);
+        self.stderr_tail = lines[start..].iter().map(|l| l.to_string()).collect();
+        self
+    }
+
+    /// Whether the process exited with code 0.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Recognized warnings and errors in the stderr tail, oldest first.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.stderr_tail
+            .iter()
+            .filter_map(|line| Diagnostic::parse(line))
+            .collect()
+    }
+}
+
+impl fmt::Display for ExitReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {}", code)?,
+            (None, Some(signal)) => write!(f, "killed by signal {}", signal)?,
+            (None, None) => f.write_str("unknown exit status")?,
+        }
+        if self.killed {
+            f.write_str(" after grace period")?;
+        }
+        if let Some(last) = self.stderr_tail.last() {
+            write!(f, ": {}", last)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_report_keeps_stderr_tail() {
+        let stderr: String = (0..30).map(|i| format!("line {}\n\n", i)).collect();
+        let report = ExitReport {
+            code: Some(1),
+            ..ExitReport::default()
+        }
+        .with_stderr(stderr.as_bytes());
+
+        assert_eq!(report.stderr_tail.len(), STDERR_TAIL_LINES);
+        assert_eq!(report.stderr_tail[0], "line 10");
+        assert!(!report.success());
+        assert_eq!(report.to_string(), "exit code 1: line 29");
+    }
+}
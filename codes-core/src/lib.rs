@@ -0,0 +1,30 @@
+//! Shared plumbing for the `claude-codes` and `codex-codes` clients.
+//!
+//! Both clients drive a local CLI process over newline-delimited JSON and
+//! need the same machinery around it: a [`Transport`](transport::Transport)
+//! abstraction over the byte streams, a pump that drains and classifies the
+//! process's stderr, wire transcripts for record and replay, exit reports
+//! for telling a crash from a normal end, and a supervised pool of clients. This crate holds that machinery
+//! once; each client crate re-exports it from its own modules, so most users
+//! never depend on this crate directly.
+//!
+//! # Feature Flags
+//!
+//! | Feature | Description | WASM-compatible |
+//! |---------|-------------|-----------------|
+//! | (none) | Exit reports, stderr diagnostics and transcript types | Yes |
+//! | `async` | Transports, the stderr pump, replay, read limits and client pools, using tokio | No |
+
+pub mod exit;
+pub mod stderr;
+pub mod transcript;
+
+#[cfg(feature = "async")]
+pub mod limits;
+#[cfg(feature = "async")]
+pub mod pool;
+#[cfg(feature = "async")]
+pub mod transport;
+
+pub use exit::{ExitReport, STDERR_TAIL_LINES};
+pub use stderr::Diagnostic;
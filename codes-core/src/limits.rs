@@ -0,0 +1,117 @@
+//! Time and cancellation bounds for reads from a transport.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Why a guarded read did not complete.
+#[derive(Debug)]
+pub enum ReadError {
+    /// The idle timeout or the deadline passed first.
+    Timeout,
+    /// The cancellation token fired first.
+    Cancelled,
+    /// The read itself failed.
+    Io(std::io::Error),
+}
+
+/// Bounds on how long a read from the transport may wait.
+#[derive(Debug, Clone, Default)]
+pub struct ReadLimits {
+    /// Longest wait for the next line.
+    pub idle: Option<Duration>,
+    /// When the whole call must be finished by.
+    pub deadline: Option<Instant>,
+    /// Stops the wait when cancelled.
+    pub cancel: Option<CancellationToken>,
+}
+
+impl ReadLimits {
+    /// Limit each read to `idle`.
+    pub fn idle(idle: Option<Duration>) -> Self {
+        Self {
+            idle,
+            ..Self::default()
+        }
+    }
+
+    /// Limit the whole call to `timeout` from now.
+    pub fn within(timeout: Option<Duration>) -> Self {
+        Self {
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the cancellation token has already fired.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Run a read, failing with `Timeout` or `Cancelled` if a limit is hit first.
+    pub async fn guard<T>(
+        &self,
+        read: impl Future<Output = std::io::Result<T>>,
+    ) -> Result<T, ReadError> {
+        let remaining = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let wait = match (self.idle, remaining) {
+            (Some(idle), Some(remaining)) => Some(idle.min(remaining)),
+            (idle, remaining) => idle.or(remaining),
+        };
+        let timed = async {
+            match wait {
+                Some(wait) => tokio::time::timeout(wait, read)
+                    .await
+                    .map_err(|_| ReadError::Timeout)?,
+                None => read.await,
+            }
+            .map_err(ReadError::Io)
+        };
+        match &self.cancel {
+            Some(cancel) => tokio::select! {
+                biased;
+                _ = cancel.cancelled() => Err(ReadError::Cancelled),
+                result = timed => result,
+            },
+            None => timed.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_guard_reports_the_limit_that_was_hit() {
+        let pending = std::future::pending::<std::io::Result<()>>;
+
+        let idle = ReadLimits::idle(Some(Duration::from_millis(10)));
+        assert!(matches!(
+            idle.guard(pending()).await,
+            Err(ReadError::Timeout)
+        ));
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let cancelled = ReadLimits {
+            cancel: Some(cancel),
+            ..ReadLimits::within(Some(Duration::from_secs(60)))
+        };
+        assert!(cancelled.is_cancelled());
+        assert!(matches!(
+            cancelled.guard(pending()).await,
+            Err(ReadError::Cancelled)
+        ));
+
+        assert_eq!(
+            ReadLimits::default().guard(async { Ok(7) }).await.unwrap(),
+            7
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! Supervised pools of clients for running many CLI processes concurrently.
+//!
+//! A [`Pool`] spawns clients from a builder template, one CLI process per
+//! worker, and hands them out to callers with [`Pool::checkout`] or runs a
+//! whole turn on an idle worker with [`Pool::query`]. At most
+//! [`max_workers`](Pool::max_workers) clients are checked out at once;
+//! further callers wait for one to be returned.
+//!
+//! Workers are health-checked without sending a prompt: a worker whose
+//! process has exited is restarted, resuming the [session](PoolClient::session)
+//! it last reported so the conversation carries on in the new process.
+//! Per-client state is not carried over, so set it in [`Pool::on_spawn`],
+//! which runs for every spawned and restarted client.
+//!
+//! The client crates implement [`PoolClient`] for their `AsyncClient`s and
+//! export the pool as `pool::SessionPool`.
+
+use crate::transport::BoxFuture;
+use log::{debug, info, warn};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
+
+/// Default cap on concurrently checked-out clients.
+const DEFAULT_MAX_WORKERS: usize = 4;
+
+/// Default number of restarts allowed per worker.
+const DEFAULT_MAX_RESTARTS: u32 = 3;
+
+type SetupFn<C> = Arc<dyn Fn(&mut C) + Send + Sync>;
+type SpawnFn<C> = Arc<
+    dyn Fn(
+            Option<<C as PoolClient>::Session>,
+        ) -> BoxFuture<'static, Result<C, <C as PoolClient>::Error>>
+        + Send
+        + Sync,
+>;
+
+/// A client a [`Pool`] can spawn, supervise and restart.
+pub trait PoolClient: Send + Sized + 'static {
+    /// Template every worker is spawned from.
+    type Builder: Clone + Send + Sync + 'static;
+    /// What a restarted worker resumes.
+    type Session: Clone + fmt::Debug + PartialEq + Send + Sync + 'static;
+    /// The client's error type, which pool failures convert into.
+    type Error: From<PoolError> + fmt::Display + Send + 'static;
+
+    /// Spawn a client from `builder`, resuming `session` if given.
+    fn spawn(
+        builder: &Self::Builder,
+        session: Option<Self::Session>,
+    ) -> BoxFuture<'static, Result<Self, Self::Error>>;
+
+    /// Whether the client's process is still running.
+    fn is_alive(&mut self) -> bool;
+
+    /// The session the client has reported so far, if any.
+    fn session(&self) -> Option<Self::Session> {
+        None
+    }
+
+    /// Shut the client down.
+    fn shutdown(self) -> BoxFuture<'static, Result<(), Self::Error>>;
+}
+
+/// A [`PoolClient`] that runs a whole turn from a text prompt.
+pub trait PoolQuery: PoolClient {
+    /// What a turn returns.
+    type Output: Send;
+
+    /// Send `text` and collect the turn's output.
+    fn query<'a>(&'a mut self, text: &'a str) -> BoxFuture<'a, Result<Self::Output, Self::Error>>;
+
+    /// Whether `error` means the connection to the process was lost, so the
+    /// turn is worth retrying on a restarted worker.
+    fn is_disconnect(error: &Self::Error) -> bool;
+}
+
+/// A failure of the pool itself, converted into the client's error type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolError {
+    /// The pool was shut down.
+    Closed,
+    /// A worker's process exited more often than the pool allows.
+    RestartLimit { worker: usize, max_restarts: u32 },
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::Closed => f.write_str("session pool is closed"),
+            PoolError::RestartLimit {
+                worker,
+                max_restarts,
+            } => write!(f, "worker {} exceeded {} restarts", worker, max_restarts),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+// ---------------------------------------------------------------------------
+// Health reporting
+// ---------------------------------------------------------------------------
+
+/// Outcome of a health check for one worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerStatus {
+    /// The process is running.
+    Healthy,
+    /// The process had exited and was restarted.
+    Restarted,
+    /// The process had exited and could not be restarted; the worker was removed.
+    Failed(String),
+}
+
+/// Health of one idle worker, from [`Pool::health_check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthReport<S> {
+    pub worker: usize,
+    /// The session the worker resumes on restart, once known.
+    pub session: Option<S>,
+    pub status: WorkerStatus,
+}
+
+/// Snapshot of pool occupancy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    pub max_workers: usize,
+    /// Clients currently checked out.
+    pub busy: usize,
+    /// Workers waiting to be checked out.
+    pub idle: usize,
+    /// Restarts across all workers since the pool was created.
+    pub restarts: usize,
+}
+
+// ---------------------------------------------------------------------------
+// Pool
+// ---------------------------------------------------------------------------
+
+struct Worker<C: PoolClient> {
+    id: usize,
+    client: C,
+    session: Option<C::Session>,
+    restarts: u32,
+}
+
+impl<C: PoolClient> Worker<C> {
+    /// Remember the session the client reported, for resuming after a crash.
+    fn note_session(&mut self) {
+        if let Some(session) = self.client.session() {
+            self.session = Some(session);
+        }
+    }
+}
+
+/// A supervisor for many concurrent clients.
+pub struct Pool<C: PoolClient> {
+    spawn: SpawnFn<C>,
+    setup: Option<SetupFn<C>>,
+    max_workers: usize,
+    max_restarts: u32,
+    permits: Semaphore,
+    idle: Mutex<Vec<Worker<C>>>,
+    next_id: AtomicUsize,
+    restarts: AtomicUsize,
+}
+
+impl<C: PoolClient> Pool<C> {
+    /// Create a pool that spawns workers from `builder` with [`PoolClient::spawn`].
+    pub fn new(builder: C::Builder) -> Self {
+        Self::with_spawner(move |session| C::spawn(&builder, session))
+    }
+
+    /// Create a pool that spawns workers with a custom function.
+    ///
+    /// `spawn` receives the session to resume when restarting a worker that
+    /// has one, and `None` for a fresh worker.
+    pub fn with_spawner<F>(spawn: F) -> Self
+    where
+        F: Fn(Option<C::Session>) -> BoxFuture<'static, Result<C, C::Error>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            spawn: Arc::new(spawn),
+            setup: None,
+            max_workers: DEFAULT_MAX_WORKERS,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            permits: Semaphore::new(DEFAULT_MAX_WORKERS),
+            idle: Mutex::new(Vec::new()),
+            next_id: AtomicUsize::new(0),
+            restarts: AtomicUsize::new(0),
+        }
+    }
+
+    /// Cap the number of clients checked out at once (default 4).
+    pub fn max_workers(mut self, max_workers: usize) -> Self {
+        self.max_workers = max_workers;
+        self.permits = Semaphore::new(max_workers);
+        self
+    }
+
+    /// Restarts allowed per worker before it is dropped (default 3).
+    pub fn max_restarts(mut self, max_restarts: u32) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Configure every spawned or restarted client, e.g. to set an approval policy.
+    pub fn on_spawn<F>(mut self, setup: F) -> Self
+    where
+        F: Fn(&mut C) + Send + Sync + 'static,
+    {
+        self.setup = Some(Arc::new(setup));
+        self
+    }
+
+    /// Check out an idle client, spawning one if none is idle.
+    ///
+    /// Waits while `max_workers` clients are already checked out. A worker
+    /// whose process has exited is restarted before it is handed out. The
+    /// client returns to the pool when the [`PooledClient`] is dropped.
+    pub async fn checkout(&self) -> Result<PooledClient<'_, C>, C::Error> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|_| PoolError::Closed)?;
+
+        let idle = self.lock_idle().pop();
+        let mut worker = match idle {
+            Some(worker) => worker,
+            None => self.spawn_worker().await?,
+        };
+        if !worker.client.is_alive() {
+            warn!("[POOL] Worker {} exited, restarting", worker.id);
+            self.restart(&mut worker).await?;
+        }
+
+        debug!("[POOL] Checked out worker {}", worker.id);
+        Ok(PooledClient {
+            pool: self,
+            worker: Some(worker),
+            _permit: permit,
+        })
+    }
+
+    /// Check every idle worker, restarting those whose process has exited.
+    ///
+    /// Checked-out workers are checked when they are next checked out.
+    pub async fn health_check(&self) -> Vec<HealthReport<C::Session>> {
+        let mut dead = Vec::new();
+        {
+            let mut idle = self.lock_idle();
+            for mut worker in std::mem::take(&mut *idle) {
+                if worker.client.is_alive() {
+                    idle.push(worker);
+                } else {
+                    dead.push(worker);
+                }
+            }
+        }
+
+        let mut reports: Vec<HealthReport<C::Session>> = self
+            .lock_idle()
+            .iter()
+            .map(|worker| HealthReport {
+                worker: worker.id,
+                session: worker.session.clone(),
+                status: WorkerStatus::Healthy,
+            })
+            .collect();
+
+        for mut worker in dead {
+            let status = match self.restart(&mut worker).await {
+                Ok(()) => WorkerStatus::Restarted,
+                Err(e) => WorkerStatus::Failed(e.to_string()),
+            };
+            reports.push(HealthReport {
+                worker: worker.id,
+                session: worker.session.clone(),
+                status: status.clone(),
+            });
+            if status == WorkerStatus::Restarted {
+                self.return_worker(worker);
+            }
+        }
+        reports
+    }
+
+    /// Run [`health_check`](Self::health_check) every `interval` in a background task.
+    pub fn spawn_health_checks(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                for report in self.health_check().await {
+                    match report.status {
+                        WorkerStatus::Healthy => {}
+                        WorkerStatus::Restarted => {
+                            info!("[POOL] Restarted worker {}", report.worker)
+                        }
+                        WorkerStatus::Failed(e) => {
+                            warn!("[POOL] Dropped worker {}: {}", report.worker, e)
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Current occupancy.
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            max_workers: self.max_workers,
+            busy: self.max_workers - self.permits.available_permits(),
+            idle: self.lock_idle().len(),
+            restarts: self.restarts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop accepting checkouts and shut down every idle worker.
+    ///
+    /// Checked-out clients are dropped (killing their process) when returned.
+    pub async fn shutdown(&self) -> Result<(), C::Error> {
+        self.permits.close();
+        let workers = std::mem::take(&mut *self.lock_idle());
+        for worker in workers {
+            debug!("[POOL] Shutting down worker {}", worker.id);
+            worker.client.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, Vec<Worker<C>>> {
+        // A panic while holding the lock cannot leave the Vec inconsistent.
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn spawn_client(&self, session: Option<C::Session>) -> Result<C, C::Error> {
+        let mut client = (self.spawn)(session).await?;
+        if let Some(setup) = &self.setup {
+            setup(&mut client);
+        }
+        Ok(client)
+    }
+
+    async fn spawn_worker(&self) -> Result<Worker<C>, C::Error> {
+        let client = self.spawn_client(None).await?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        info!("[POOL] Spawned worker {}", id);
+        Ok(Worker {
+            id,
+            client,
+            session: None,
+            restarts: 0,
+        })
+    }
+
+    async fn restart(&self, worker: &mut Worker<C>) -> Result<(), C::Error> {
+        if worker.restarts >= self.max_restarts {
+            return Err(PoolError::RestartLimit {
+                worker: worker.id,
+                max_restarts: self.max_restarts,
+            }
+            .into());
+        }
+        worker.note_session();
+        worker.client = self.spawn_client(worker.session.clone()).await?;
+        worker.restarts += 1;
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+        info!(
+            "[POOL] Restarted worker {} (session {:?})",
+            worker.id, worker.session
+        );
+        Ok(())
+    }
+
+    fn return_worker(&self, mut worker: Worker<C>) {
+        worker.note_session();
+        let mut idle = self.lock_idle();
+        if self.permits.is_closed() || idle.len() >= self.max_workers {
+            debug!("[POOL] Dropping surplus worker {}", worker.id);
+            return;
+        }
+        idle.push(worker);
+    }
+}
+
+impl<C: PoolQuery> Pool<C> {
+    /// Run a query on an idle worker and collect its output.
+    ///
+    /// If the worker's process dies mid-turn, it is restarted on its session
+    /// and the query is sent once more.
+    pub async fn query(&self, text: &str) -> Result<C::Output, C::Error> {
+        let mut client = self.checkout().await?;
+        match client.query(text).await {
+            Err(e) if C::is_disconnect(&e) && !client.is_alive() => {
+                let worker = client.worker.as_mut().expect("worker present until drop");
+                warn!(
+                    "[POOL] Worker {} died mid-turn ({}), retrying",
+                    worker.id, e
+                );
+                self.restart(worker).await?;
+                client.query(text).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// A client checked out of a [`Pool`].
+///
+/// Dereferences to the client. Returned to the pool on drop.
+pub struct PooledClient<'a, C: PoolClient> {
+    pool: &'a Pool<C>,
+    worker: Option<Worker<C>>,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl<C: PoolClient> PooledClient<'_, C> {
+    /// The worker's ID within the pool.
+    pub fn worker_id(&self) -> usize {
+        self.worker().id
+    }
+
+    /// The session the worker resumes on restart, once known.
+    pub fn session(&self) -> Option<C::Session> {
+        let worker = self.worker();
+        worker.client.session().or_else(|| worker.session.clone())
+    }
+
+    /// Remove the worker from the pool instead of returning it.
+    pub fn discard(mut self) {
+        if let Some(worker) = self.worker.take() {
+            debug!("[POOL] Discarding worker {}", worker.id);
+        }
+    }
+
+    fn worker(&self) -> &Worker<C> {
+        self.worker.as_ref().expect("worker present until drop")
+    }
+}
+
+impl<C: PoolClient> Deref for PooledClient<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.worker().client
+    }
+}
+
+impl<C: PoolClient> DerefMut for PooledClient<'_, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self
+            .worker
+            .as_mut()
+            .expect("worker present until drop")
+            .client
+    }
+}
+
+impl<C: PoolClient> Drop for PooledClient<'_, C> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            debug!("[POOL] Returned worker {}", worker.id);
+            self.pool.return_worker(worker);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    /// A client whose liveness and session the test controls.
+    struct Fake {
+        alive: Arc<AtomicBool>,
+        session: Option<u32>,
+    }
+
+    impl PoolClient for Fake {
+        type Builder = ();
+        type Session = u32;
+        type Error = PoolError;
+
+        fn spawn(_: &(), session: Option<u32>) -> BoxFuture<'static, Result<Self, PoolError>> {
+            Box::pin(async move {
+                Ok(Fake {
+                    alive: Arc::new(AtomicBool::new(true)),
+                    session,
+                })
+            })
+        }
+
+        fn is_alive(&mut self) -> bool {
+            self.alive.load(Ordering::SeqCst)
+        }
+
+        fn session(&self) -> Option<u32> {
+            self.session
+        }
+
+        fn shutdown(self) -> BoxFuture<'static, Result<(), PoolError>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    type Spawned = Arc<Mutex<Vec<(Option<u32>, Arc<AtomicBool>)>>>;
+
+    fn pool(spawned: Spawned) -> Pool<Fake> {
+        Pool::with_spawner(move |session| {
+            let alive = Arc::new(AtomicBool::new(true));
+            spawned.lock().unwrap().push((session, alive.clone()));
+            Box::pin(async move { Ok(Fake { alive, session }) })
+        })
+    }
+
+    #[tokio::test]
+    async fn test_checkout_caps_concurrency_and_reuses_workers() {
+        let pool = pool(Arc::default()).max_workers(2);
+
+        let first = pool.checkout().await.unwrap();
+        let second = pool.checkout().await.unwrap();
+        assert_ne!(first.worker_id(), second.worker_id());
+        assert_eq!(pool.stats().busy, 2);
+
+        let blocked = tokio::time::timeout(Duration::from_millis(50), pool.checkout()).await;
+        assert!(blocked.is_err());
+
+        let id = first.worker_id();
+        drop(first);
+        let third = pool.checkout().await.unwrap();
+        assert_eq!(third.worker_id(), id);
+
+        drop(second);
+        drop(third);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                max_workers: 2,
+                busy: 0,
+                idle: 2,
+                restarts: 0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_check_restarts_exited_workers_on_their_session() {
+        let spawned = Spawned::default();
+        let pool = pool(spawned.clone()).max_restarts(1);
+
+        let mut client = pool.checkout().await.unwrap();
+        client.session = Some(7);
+        drop(client);
+        spawned.lock().unwrap()[0].1.store(false, Ordering::SeqCst);
+
+        let reports = pool.health_check().await;
+        assert_eq!(
+            reports,
+            vec![HealthReport {
+                worker: 0,
+                session: Some(7),
+                status: WorkerStatus::Restarted
+            }]
+        );
+        assert_eq!(spawned.lock().unwrap()[1].0, Some(7));
+        assert_eq!(pool.stats().restarts, 1);
+
+        spawned.lock().unwrap()[1].1.store(false, Ordering::SeqCst);
+        let reports = pool.health_check().await;
+        assert_eq!(
+            reports[0].status,
+            WorkerStatus::Failed("worker 0 exceeded 1 restarts".to_string())
+        );
+        assert_eq!(pool.stats().idle, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_the_pool() {
+        let pool = pool(Arc::default());
+        drop(pool.checkout().await.unwrap());
+        pool.shutdown().await.unwrap();
+
+        assert_eq!(pool.stats().idle, 0);
+        assert!(matches!(pool.checkout().await, Err(PoolError::Closed)));
+    }
+}
@@ -0,0 +1,565 @@
+//! Background stderr capture and diagnostics.
+//!
+//! Both CLIs write warnings, update notices and failures to stderr. If nobody
+//! reads the pipe it eventually fills up and the process blocks, so
+//! long-running clients should drain it. A [`StderrPump`] does this on a
+//! background task: it keeps a bounded ring buffer of recent lines in a
+//! [`StderrBuffer`], forwards every line to `log` and an optional callback,
+//! and recognizes a few well-known shapes as [`Diagnostic`]s.
+//!
+//! The classifier knows the phrasing of both the Claude CLI and the Codex
+//! app-server, so one [`Diagnostic`] type serves both clients.
+//!
+//! # Example
+//!
+//! ```
+//! use codes_core::stderr::Diagnostic;
+//!
+//! let diagnostic = Diagnostic::parse("MCP server \"github\" failed to start: spawn ENOENT");
+//! assert_eq!(
+//!     diagnostic,
+//!     Some(Diagnostic::McpServerFailed {
+//!         server: Some("github".to_string()),
+//!         message: "MCP server \"github\" failed to start: spawn ENOENT".to_string(),
+//!     })
+//! );
+//! assert!(Diagnostic::parse("Loading settings...").is_none());
+//! ```
+
+use std::fmt;
+
+#[cfg(feature = "async")]
+pub use pump::{
+    StderrBuffer, StderrLine, StderrPump, DEFAULT_STDERR_CAPACITY, MAX_STDERR_LINE_BYTES,
+};
+
+// ---------------------------------------------------------------------------
+// Diagnostics
+// ---------------------------------------------------------------------------
+
+/// A recognized warning or error line from a CLI's stderr.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// Missing, invalid or expired credentials.
+    AuthFailure { message: String },
+    /// A newer CLI version is available.
+    VersionNotice { message: String },
+    /// An MCP server failed to start or connect.
+    McpServerFailed {
+        server: Option<String>,
+        message: String,
+    },
+    /// Any other line logged as a warning.
+    Warning { message: String },
+    /// Any other line logged as an error.
+    Error { message: String },
+}
+
+/// Phrases that identify an authentication failure.
+const AUTH_PATTERNS: &[&str] = &[
+    // Claude CLI
+    "invalid api key",
+    "authentication_error",
+    "authentication failed",
+    "please run /login",
+    // Codex app-server
+    "codex login",
+    "invalid_api_key",
+    "incorrect api key",
+    "refresh token",
+    // Both
+    "not logged in",
+    "token has expired",
+    "401 unauthorized",
+];
+
+/// Phrases that identify an update notice.
+const VERSION_PATTERNS: &[&str] = &[
+    "update available",
+    "new version",
+    "please update",
+    "please upgrade",
+];
+
+impl Diagnostic {
+    /// Classify a stderr line, or `None` for ordinary output.
+    ///
+    /// ANSI escape sequences are stripped before matching and from the
+    /// returned message.
+    pub fn parse(line: &str) -> Option<Self> {
+        let message = strip_ansi(line).trim().to_string();
+        let lower = message.to_lowercase();
+
+        if lower.contains("mcp") && (lower.contains("fail") || lower.contains("error")) {
+            return Some(Self::McpServerFailed {
+                server: quoted_name(&message),
+                message,
+            });
+        }
+        if AUTH_PATTERNS.iter().any(|p| lower.contains(p)) {
+            return Some(Self::AuthFailure { message });
+        }
+        if VERSION_PATTERNS.iter().any(|p| lower.contains(p)) {
+            return Some(Self::VersionNotice { message });
+        }
+        if lower.starts_with("error") || lower.starts_with("fatal") || message.contains(" ERROR ") {
+            return Some(Self::Error { message });
+        }
+        if lower.starts_with("warn") || message.contains(" WARN ") {
+            return Some(Self::Warning { message });
+        }
+        None
+    }
+
+    /// The full stderr line, without ANSI escapes.
+    pub fn message(&self) -> &str {
+        match self {
+            Self::AuthFailure { message }
+            | Self::VersionNotice { message }
+            | Self::McpServerFailed { message, .. }
+            | Self::Warning { message }
+            | Self::Error { message } => message,
+        }
+    }
+
+    /// Whether this is a failure rather than a warning or notice.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            Self::AuthFailure { .. } | Self::McpServerFailed { .. } | Self::Error { .. }
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AuthFailure { message } => write!(f, "authentication failure: {}", message),
+            Self::VersionNotice { message } => write!(f, "version notice: {}", message),
+            Self::McpServerFailed {
+                server: Some(server),
+                message,
+            } => write!(f, "MCP server {} failed: {}", server, message),
+            Self::McpServerFailed {
+                server: None,
+                message,
+            } => write!(f, "MCP server failed: {}", message),
+            Self::Warning { message } => write!(f, "warning: {}", message),
+            Self::Error { message } => write!(f, "error: {}", message),
+        }
+    }
+}
+
+/// Remove ANSI escape sequences (colors, cursor movement) from a line.
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            // CSI sequences end with a byte in '@'..='~'.
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The first name wrapped in double quotes, single quotes or backticks.
+fn quoted_name(message: &str) -> Option<String> {
+    let (start, quote) = message
+        .char_indices()
+        .find(|(_, c)| matches!(c, '"' | '\'' | '`'))?;
+    let rest = &message[start + 1..];
+    let end = rest.find(quote)?;
+    Some(rest[..end].to_string()).filter(|name| !name.is_empty())
+}
+
+// ---------------------------------------------------------------------------
+// Pump
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "async")]
+mod pump {
+    use super::Diagnostic;
+    use log::{debug, error, info, warn};
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader};
+    use tokio::sync::watch;
+
+    /// Number of lines a [`StderrPump`] keeps by default.
+    pub const DEFAULT_STDERR_CAPACITY: usize = 200;
+
+    /// Longest stderr line kept, in bytes; the rest of a longer line is dropped.
+    pub const MAX_STDERR_LINE_BYTES: usize = 64 * 1024;
+
+    /// Consecutive read errors after which the pump stops draining.
+    const MAX_CONSECUTIVE_ERRORS: usize = 8;
+
+    type LineCallback = Arc<dyn Fn(&StderrLine) + Send + Sync>;
+
+    /// One line read from stderr.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct StderrLine {
+        /// The line as written, without its trailing newline.
+        pub text: String,
+        /// What the line was recognized as, if anything.
+        pub diagnostic: Option<Diagnostic>,
+    }
+
+    /// Configuration for a background task that drains a stderr stream.
+    #[derive(Clone)]
+    pub struct StderrPump {
+        capacity: usize,
+        callback: Option<LineCallback>,
+    }
+
+    impl Default for StderrPump {
+        fn default() -> Self {
+            Self {
+                capacity: DEFAULT_STDERR_CAPACITY,
+                callback: None,
+            }
+        }
+    }
+
+    impl std::fmt::Debug for StderrPump {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("StderrPump")
+                .field("capacity", &self.capacity)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl StderrPump {
+        /// Create a pump that keeps [`DEFAULT_STDERR_CAPACITY`] lines.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Keep at most `capacity` recent lines (and diagnostics).
+        pub fn capacity(mut self, capacity: usize) -> Self {
+            self.capacity = capacity.max(1);
+            self
+        }
+
+        /// Call `callback` for every line read.
+        pub fn on_line<F>(mut self, callback: F) -> Self
+        where
+            F: Fn(&StderrLine) + Send + Sync + 'static,
+        {
+            self.callback = Some(Arc::new(callback));
+            self
+        }
+
+        /// Drain `reader` on a background task until it closes.
+        ///
+        /// Must be called from within a Tokio runtime.
+        pub fn spawn<R>(self, reader: R) -> StderrBuffer
+        where
+            R: AsyncRead + Send + Unpin + 'static,
+        {
+            let (closed_tx, closed_rx) = watch::channel(false);
+            let buffer = StderrBuffer {
+                inner: Arc::new(Mutex::new(Ring {
+                    capacity: self.capacity,
+                    ..Ring::default()
+                })),
+                closed: closed_rx,
+            };
+            let ring = buffer.inner.clone();
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(reader);
+                let mut buf = Vec::new();
+                let mut errors = 0;
+                loop {
+                    match read_line(&mut reader, &mut buf).await {
+                        Ok(consumed) => {
+                            errors = 0;
+                            let text = String::from_utf8_lossy(&buf)
+                                .trim_end_matches(['\r', '\n'])
+                                .to_string();
+                            buf.clear();
+                            if !text.trim().is_empty() {
+                                let line = StderrLine {
+                                    diagnostic: Diagnostic::parse(&text),
+                                    text,
+                                };
+                                forward(&line);
+                                if let Some(callback) = &self.callback {
+                                    callback(&line);
+                                }
+                                ring.lock().unwrap().push(line);
+                            }
+                            if consumed == 0 {
+                                break;
+                            }
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                        Err(e) => {
+                            errors += 1;
+                            if errors >= MAX_CONSECUTIVE_ERRORS {
+                                debug!("[STDERR] Stopped reading stderr: {}", e);
+                                break;
+                            }
+                            debug!("[STDERR] Error reading stderr: {}", e);
+                        }
+                    }
+                }
+                let _ = closed_tx.send(true);
+            });
+            buffer
+        }
+    }
+
+    /// Read up to and including the next newline into `buf`.
+    ///
+    /// Bytes past [`MAX_STDERR_LINE_BYTES`] are consumed but not kept, so a
+    /// runaway line cannot grow the buffer without bound. Returns the number
+    /// of bytes consumed; 0 means end of stream.
+    async fn read_line<R>(reader: &mut R, buf: &mut Vec<u8>) -> std::io::Result<usize>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut consumed = 0;
+        loop {
+            let available = reader.fill_buf().await?;
+            if available.is_empty() {
+                return Ok(consumed);
+            }
+            let (used, done) = match available.iter().position(|&b| b == b'\n') {
+                Some(newline) => (newline + 1, true),
+                None => (available.len(), false),
+            };
+            let room = MAX_STDERR_LINE_BYTES.saturating_sub(buf.len());
+            buf.extend_from_slice(&available[..used.min(room)]);
+            reader.consume(used);
+            consumed += used;
+            if done {
+                return Ok(consumed);
+            }
+        }
+    }
+
+    /// Log a line at a level matching its diagnostic.
+    fn forward(line: &StderrLine) {
+        match &line.diagnostic {
+            Some(Diagnostic::VersionNotice { .. }) => info!("[STDERR] {}", line.text),
+            Some(Diagnostic::Warning { .. }) => warn!("[STDERR] {}", line.text),
+            Some(_) => error!("[STDERR] {}", line.text),
+            None => debug!("[STDERR] {}", line.text),
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Ring {
+        capacity: usize,
+        lines: VecDeque<String>,
+        diagnostics: VecDeque<Diagnostic>,
+        total: usize,
+    }
+
+    impl Ring {
+        fn push(&mut self, line: StderrLine) {
+            self.total += 1;
+            if self.lines.len() == self.capacity {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(line.text);
+            if let Some(diagnostic) = line.diagnostic {
+                if self.diagnostics.len() == self.capacity {
+                    self.diagnostics.pop_front();
+                }
+                self.diagnostics.push_back(diagnostic);
+            }
+        }
+    }
+
+    /// Recent stderr lines collected by a [`StderrPump`].
+    ///
+    /// Cloning is cheap; all clones see the same buffer.
+    #[derive(Debug, Clone)]
+    pub struct StderrBuffer {
+        inner: Arc<Mutex<Ring>>,
+        closed: watch::Receiver<bool>,
+    }
+
+    impl StderrBuffer {
+        /// The buffered lines, oldest first.
+        pub fn lines(&self) -> Vec<String> {
+            self.inner.lock().unwrap().lines.iter().cloned().collect()
+        }
+
+        /// The last `n` buffered lines, oldest first.
+        pub fn tail(&self, n: usize) -> Vec<String> {
+            let ring = self.inner.lock().unwrap();
+            let start = ring.lines.len().saturating_sub(n);
+            ring.lines.range(start..).cloned().collect()
+        }
+
+        /// The buffered diagnostics, oldest first.
+        pub fn diagnostics(&self) -> Vec<Diagnostic> {
+            self.inner
+                .lock()
+                .unwrap()
+                .diagnostics
+                .iter()
+                .cloned()
+                .collect()
+        }
+
+        /// Lines read so far, including those dropped from the buffer.
+        pub fn total_lines(&self) -> usize {
+            self.inner.lock().unwrap().total
+        }
+
+        /// Whether the stream has closed.
+        pub fn is_closed(&self) -> bool {
+            *self.closed.borrow()
+        }
+
+        /// Wait for the stream to close.
+        pub async fn closed(&self) {
+            let mut closed = self.closed.clone();
+            let _ = closed.wait_for(|closed| *closed).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_claude_shapes() {
+        assert!(matches!(
+            Diagnostic::parse("Invalid API key · Please run /login"),
+            Some(Diagnostic::AuthFailure { .. })
+        ));
+        assert!(matches!(
+            Diagnostic::parse("\x1b[33mUpdate available! Run: npm i -g @anthropic-ai/claude-code\x1b[0m"),
+            Some(Diagnostic::VersionNotice { message }) if message.starts_with("Update available!")
+        ));
+        assert_eq!(
+            Diagnostic::parse("[ERROR] MCP server 'linear' Connection failed: timeout"),
+            Some(Diagnostic::McpServerFailed {
+                server: Some("linear".to_string()),
+                message: "[ERROR] MCP server 'linear' Connection failed: timeout".to_string(),
+            })
+        );
+        assert!(matches!(
+            Diagnostic::parse("Error: ENOENT: no such file or directory"),
+            Some(ref d @ Diagnostic::Error { .. }) if d.is_error()
+        ));
+        assert!(matches!(
+            Diagnostic::parse("Warning: settings.json has an unknown key"),
+            Some(ref d @ Diagnostic::Warning { .. }) if !d.is_error()
+        ));
+        assert_eq!(Diagnostic::parse("Loading configuration"), None);
+    }
+
+    #[test]
+    fn test_parse_codex_shapes() {
+        assert!(matches!(
+            Diagnostic::parse("Not logged in. Run `codex login` first."),
+            Some(Diagnostic::AuthFailure { .. })
+        ));
+        assert!(matches!(
+            Diagnostic::parse("\x1b[1m✨ Update available! 0.101.0 -> 0.102.0\x1b[0m"),
+            Some(Diagnostic::VersionNotice { message }) if message.starts_with("✨ Update")
+        ));
+        assert!(matches!(
+            Diagnostic::parse("2026-01-01T00:00:00Z ERROR codex_core::mcp: MCP client for `github` failed to start"),
+            Some(Diagnostic::McpServerFailed { server: Some(ref name), .. }) if name == "github"
+        ));
+        assert!(matches!(
+            Diagnostic::parse("2026-01-01T00:00:00Z ERROR codex_core::codex: turn failed"),
+            Some(ref d @ Diagnostic::Error { .. }) if d.is_error()
+        ));
+        assert!(matches!(
+            Diagnostic::parse("2026-01-01T00:00:00Z  WARN codex_core::config: unknown key"),
+            Some(ref d @ Diagnostic::Warning { .. }) if !d.is_error()
+        ));
+        assert_eq!(Diagnostic::parse("listening on stdio://"), None);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_pump_keeps_bounded_tail() {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(0));
+        let counter = seen.clone();
+        let stderr: String =
+            (0..10).map(|i| format!("line {}\n", i)).collect::<String>() + "error: out of cheese\n";
+
+        let buffer = StderrPump::new()
+            .capacity(4)
+            .on_line(move |_| *counter.lock().unwrap() += 1)
+            .spawn(std::io::Cursor::new(stderr.into_bytes()));
+        buffer.closed().await;
+
+        assert!(buffer.is_closed());
+        assert_eq!(buffer.total_lines(), 11);
+        assert_eq!(*seen.lock().unwrap(), 11);
+        assert_eq!(
+            buffer.lines(),
+            vec!["line 7", "line 8", "line 9", "error: out of cheese"]
+        );
+        assert_eq!(buffer.tail(1), vec!["error: out of cheese"]);
+        assert_eq!(
+            buffer.diagnostics(),
+            vec![Diagnostic::Error {
+                message: "error: out of cheese".to_string()
+            }]
+        );
+    }
+
+    /// A reader that fails once before yielding its bytes.
+    #[cfg(feature = "async")]
+    struct Flaky {
+        failed: bool,
+        inner: std::io::Cursor<Vec<u8>>,
+    }
+
+    #[cfg(feature = "async")]
+    impl tokio::io::AsyncRead for Flaky {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            if !self.failed {
+                self.failed = true;
+                return std::task::Poll::Ready(Err(std::io::Error::other("transient")));
+            }
+            std::pin::Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_pump_survives_bad_bytes_long_lines_and_errors() {
+        let mut stderr = b"bad \xff byte\r\n".to_vec();
+        stderr.extend(std::iter::repeat_n(b'x', MAX_STDERR_LINE_BYTES * 2));
+        stderr.extend_from_slice(b"\nerror: still here\nno newline");
+
+        let buffer = StderrPump::new().spawn(Flaky {
+            failed: false,
+            inner: std::io::Cursor::new(stderr),
+        });
+        buffer.closed().await;
+
+        let lines = buffer.lines();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "bad \u{fffd} byte");
+        assert_eq!(lines[1].len(), MAX_STDERR_LINE_BYTES);
+        assert_eq!(lines[2], "error: still here");
+        assert_eq!(lines[3], "no newline");
+    }
+}
@@ -0,0 +1,418 @@
+//! Wire-level session transcripts: recording and deterministic replay.
+//!
+//! A transcript is a versioned JSONL file. The first line is a
+//! [`TranscriptHeader`] naming the provider and crate that recorded it; every
+//! following line is a [`TranscriptRecord`] holding one raw protocol line, the
+//! direction it travelled and when. The format is the same for both clients.
+//!
+//! ```text
+//! {"transcript_version":1,"provider":"claude","crate_version":"2.1.51","started_at_ms":1767225600000}
+//! {"timestamp_ms":1767225600010,"direction":"sent","line":"{\"type\":\"user\",...}"}
+//! {"timestamp_ms":1767225601200,"direction":"received","line":"{\"type\":\"assistant\",...}"}
+//! ```
+//!
+//! A [`TranscriptRecorder`] captures a session once a client has started it
+//! with [`TranscriptRecorder::start`], which the clients' `record_transcript`
+//! methods do. A [`ReplayTransport`] feeds a recorded file back to a client to
+//! reproduce the session without the CLI.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current transcript format version.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// Direction a transcript line travelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Written by the client to the CLI.
+    Sent,
+    /// Read by the client from the CLI.
+    Received,
+}
+
+/// First line of a transcript file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptHeader {
+    pub transcript_version: u32,
+    /// `"claude"` or `"codex"`.
+    pub provider: String,
+    /// Version of the client crate that recorded the transcript.
+    pub crate_version: String,
+    /// Milliseconds since the UNIX epoch when recording started.
+    pub started_at_ms: u64,
+}
+
+impl TranscriptHeader {
+    /// A header for a recording starting now.
+    pub fn new(provider: &str, crate_version: &str) -> Self {
+        Self {
+            transcript_version: TRANSCRIPT_VERSION,
+            provider: provider.to_string(),
+            crate_version: crate_version.to_string(),
+            started_at_ms: now_ms(),
+        }
+    }
+}
+
+/// One protocol line in a transcript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptRecord {
+    /// Milliseconds since the UNIX epoch.
+    pub timestamp_ms: u64,
+    pub direction: Direction,
+    /// The raw line, without its trailing newline.
+    pub line: String,
+}
+
+impl TranscriptRecord {
+    /// Parse the line as JSON.
+    pub fn json(&self) -> Option<Value> {
+        serde_json::from_str(&self.line).ok()
+    }
+}
+
+/// A parsed transcript.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    pub header: TranscriptHeader,
+    pub records: Vec<TranscriptRecord>,
+}
+
+impl Transcript {
+    /// Parse a transcript from JSONL text.
+    ///
+    /// Fails if the header is missing or has an unsupported version.
+    pub fn from_jsonl(text: &str) -> std::io::Result<Self> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: TranscriptHeader = lines
+            .next()
+            .ok_or_else(|| invalid_data("transcript is empty".to_string()))
+            .and_then(|line| serde_json::from_str(line).map_err(|e| invalid_data(e.to_string())))?;
+        if header.transcript_version > TRANSCRIPT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported transcript version {} (newest supported is {})",
+                header.transcript_version, TRANSCRIPT_VERSION
+            )));
+        }
+        let records = lines
+            .map(|line| serde_json::from_str(line).map_err(|e| invalid_data(e.to_string())))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Self { header, records })
+    }
+
+    /// Load a transcript from a file.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::from_jsonl(&std::fs::read_to_string(path)?)
+    }
+
+    /// Serialize the transcript to JSONL.
+    pub fn to_jsonl(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&serde_json::to_string(&self.header).unwrap_or_default());
+        out.push('\n');
+        for record in &self.records {
+            out.push_str(&serde_json::to_string(record).unwrap_or_default());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Lines received from the CLI, parsed as JSON.
+    ///
+    /// This is the shape of the captures in each crate's `test_cases/`, one
+    /// message per file.
+    pub fn received_messages(&self) -> impl Iterator<Item = Value> + '_ {
+        self.records
+            .iter()
+            .filter(|record| record.direction == Direction::Received)
+            .filter_map(TranscriptRecord::json)
+    }
+
+    /// Lines sent by the client, parsed as JSON.
+    pub fn sent_messages(&self) -> impl Iterator<Item = Value> + '_ {
+        self.records
+            .iter()
+            .filter(|record| record.direction == Direction::Sent)
+            .filter_map(TranscriptRecord::json)
+    }
+}
+
+/// Writes transcript records as they happen.
+///
+/// Cloning a recorder yields another handle to the same output, so a caller
+/// can keep one to [`flush`](Self::flush) or inspect the destination while the
+/// client holds the other. The clients log write errors and carry on, so
+/// recording never fails a session.
+#[derive(Clone)]
+pub struct TranscriptRecorder {
+    out: Arc<Mutex<Output>>,
+}
+
+struct Output {
+    writer: Box<dyn Write + Send>,
+    /// Whether the header has been written.
+    started: bool,
+}
+
+impl TranscriptRecorder {
+    /// Record a transcript to any writer.
+    ///
+    /// Nothing is written until [`start`](Self::start) is called.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            out: Arc::new(Mutex::new(Output {
+                writer: Box::new(writer),
+                started: false,
+            })),
+        }
+    }
+
+    /// Record a transcript to a new file, truncating any existing one.
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Write the header, unless an earlier call already did.
+    pub fn start(&self, provider: &str, crate_version: &str) -> std::io::Result<()> {
+        let mut out = self.lock()?;
+        if out.started {
+            return Ok(());
+        }
+        write_json(
+            &mut out.writer,
+            &TranscriptHeader::new(provider, crate_version),
+        )?;
+        out.started = true;
+        Ok(())
+    }
+
+    /// Record one protocol line.
+    ///
+    /// Fails if the recorder has not been started.
+    pub fn record(&self, direction: Direction, line: &str) -> std::io::Result<()> {
+        let mut out = self.lock()?;
+        if !out.started {
+            return Err(std::io::Error::other("transcript recorder not started"));
+        }
+        write_json(
+            &mut out.writer,
+            &TranscriptRecord {
+                timestamp_ms: now_ms(),
+                direction,
+                line: line.trim_end_matches(['\r', '\n']).to_string(),
+            },
+        )
+    }
+
+    /// Flush buffered records to the underlying writer.
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.lock()?.writer.flush()
+    }
+
+    fn lock(&self) -> std::io::Result<std::sync::MutexGuard<'_, Output>> {
+        self.out
+            .lock()
+            .map_err(|_| std::io::Error::other("transcript writer poisoned"))
+    }
+}
+
+fn write_json<T: Serialize>(out: &mut impl Write, value: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(value).map_err(std::io::Error::other)?;
+    line.push('\n');
+    out.write_all(line.as_bytes())?;
+    out.flush()
+}
+
+impl std::fmt::Debug for TranscriptRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranscriptRecorder").finish_non_exhaustive()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+// ---------------------------------------------------------------------------
+// Replay
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "async")]
+pub use replay::ReplayTransport;
+
+#[cfg(feature = "async")]
+mod replay {
+    use super::{Direction, Transcript};
+    use crate::transport::{StreamTransport, Transport, TransportReader, TransportWriter};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    /// Transport that plays a recorded transcript back to a client.
+    ///
+    /// Received lines are written to the client in order. Each sent record
+    /// waits for the client to write one line; its content is not compared.
+    /// Claude control `request_id`s, which the client generates at random, are
+    /// mapped onto the recorded ones so control responses in the transcript
+    /// still match. Codex JSON-RPC IDs are sequential, so a client that repeats
+    /// the recorded calls reproduces them without remapping. Divergences are
+    /// reported on the transport's stderr stream.
+    #[derive(Debug)]
+    pub struct ReplayTransport {
+        inner: StreamTransport,
+    }
+
+    impl ReplayTransport {
+        /// Start replaying `transcript` on a background task.
+        ///
+        /// Must be called from within a Tokio runtime.
+        pub fn new(transcript: Transcript) -> Self {
+            let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+            let (stderr_reader, stderr_writer) = tokio::io::duplex(8 * 1024);
+            tokio::spawn(async move {
+                let (reader, writer) = tokio::io::split(server_side);
+                let mut stderr = stderr_writer;
+                if let Err(e) = replay(transcript, reader, writer).await {
+                    let _ = stderr
+                        .write_all(format!("replay: {}\n", e).as_bytes())
+                        .await;
+                }
+            });
+            Self {
+                inner: StreamTransport::from_stream(client_side).with_stderr(stderr_reader),
+            }
+        }
+    }
+
+    impl Transport for ReplayTransport {
+        fn connect(&mut self) -> std::io::Result<(TransportReader, TransportWriter)> {
+            self.inner.connect()
+        }
+
+        fn take_stderr(&mut self) -> Option<TransportReader> {
+            self.inner.take_stderr()
+        }
+    }
+
+    async fn replay<R, W>(transcript: Transcript, reader: R, mut writer: W) -> std::io::Result<()>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        let mut reader = BufReader::new(reader);
+        // Recorded request_id -> request_id the client actually used.
+        let mut request_ids: HashMap<String, String> = HashMap::new();
+
+        for record in &transcript.records {
+            match record.direction {
+                Direction::Received => {
+                    let mut line = record.line.clone();
+                    for (recorded, actual) in &request_ids {
+                        line = line.replace(recorded.as_str(), actual.as_str());
+                    }
+                    writer.write_all(line.as_bytes()).await?;
+                    writer.write_all(b"\n").await?;
+                    writer.flush().await?;
+                }
+                Direction::Sent => {
+                    let actual = loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).await? == 0 {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::UnexpectedEof,
+                                "client closed the connection before the transcript ended",
+                            ));
+                        }
+                        if !line.trim().is_empty() {
+                            break line;
+                        }
+                    };
+                    if let (Some(recorded), Some(actual)) =
+                        (request_id(&record.line), request_id(&actual))
+                    {
+                        if recorded != actual {
+                            request_ids.insert(recorded, actual);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The top-level `request_id` of a Claude SDK-to-CLI control request.
+    fn request_id(line: &str) -> Option<String> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        value.get("request_id")?.as_str().map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_record_and_parse_roundtrip() {
+        let buf = SharedBuf::default();
+        let recorder = TranscriptRecorder::new(buf.clone());
+        assert!(recorder.record(Direction::Sent, "{}").is_err());
+        recorder.start("claude", "2.1.51").unwrap();
+        recorder.start("claude", "2.1.51").unwrap();
+        recorder
+            .record(Direction::Sent, "{\"type\":\"user\"}\n")
+            .unwrap();
+        recorder
+            .record(Direction::Received, "{\"type\":\"result\"}")
+            .unwrap();
+
+        let text = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let transcript = Transcript::from_jsonl(&text).unwrap();
+        assert_eq!(transcript.header.transcript_version, TRANSCRIPT_VERSION);
+        assert_eq!(transcript.header.provider, "claude");
+        assert_eq!(transcript.header.crate_version, "2.1.51");
+        assert_eq!(transcript.records.len(), 2);
+        assert_eq!(transcript.records[0].line, "{\"type\":\"user\"}");
+        assert_eq!(transcript.records[1].direction, Direction::Received);
+        assert_eq!(
+            transcript.received_messages().next().unwrap()["type"],
+            "result"
+        );
+        assert_eq!(
+            Transcript::from_jsonl(&transcript.to_jsonl()).unwrap(),
+            transcript
+        );
+    }
+
+    #[test]
+    fn test_rejects_missing_header_and_future_version() {
+        assert!(Transcript::from_jsonl("").is_err());
+        let future = r#"{"transcript_version":99,"provider":"claude","crate_version":"9","started_at_ms":0}"#;
+        assert!(Transcript::from_jsonl(future).is_err());
+    }
+}
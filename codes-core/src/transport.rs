@@ -0,0 +1,334 @@
+//! Byte-stream transports for the asynchronous clients.
+//!
+//! Each client handles newline-delimited JSON framing and request
+//! correlation, and delegates everything else to a [`Transport`]: where the
+//! bytes come from, where they go, and how the other end is started and
+//! stopped.
+//!
+//! Two implementations are provided:
+//!
+//! - [`ProcessTransport`] — a spawned CLI process, talking over its stdio
+//!   pipes. This is what the clients use by default.
+//! - [`StreamTransport`] — any [`AsyncRead`]/[`AsyncWrite`] pair, such as a Unix
+//!   socket, an SSH channel, a container exec stream or a [`tokio::io::duplex`]
+//!   pipe.
+
+use crate::exit::ExitReport;
+use log::{debug, warn};
+use std::future::Future;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::process::Child;
+
+/// How long to wait for stderr to drain once the process has exited.
+pub const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Boxed read half of a transport (the process's stdout).
+pub type TransportReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Boxed write half of a transport (the process's stdin).
+pub type TransportWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// A boxed, sendable future, used for async methods on [`Transport`].
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A connection to something that speaks a client's line-delimited JSON protocol.
+///
+/// The client calls [`connect`](Transport::connect) exactly once when it is
+/// constructed and keeps the returned streams for its lifetime. The remaining
+/// methods cover lifecycle management and have no-op defaults, so a plain
+/// stream transport only needs to implement `connect`.
+pub trait Transport: Send {
+    /// Hand over the read and write halves of the connection.
+    ///
+    /// Returns an error if the streams are unavailable or were already taken.
+    fn connect(&mut self) -> std::io::Result<(TransportReader, TransportWriter)>;
+
+    /// Take the diagnostic (stderr) stream, if the transport has one.
+    fn take_stderr(&mut self) -> Option<TransportReader> {
+        None
+    }
+
+    /// Whether the other end is still running.
+    ///
+    /// Transports that cannot tell report `true`; a closed connection then
+    /// surfaces as an error on the client's next read.
+    fn is_alive(&mut self) -> bool {
+        true
+    }
+
+    /// OS process ID of the other end, if it is a local process.
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+
+    /// Begin terminating the other end without waiting (used on drop).
+    fn start_kill(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Terminate the other end and wait for it to exit.
+    fn shutdown(&mut self) -> BoxFuture<'_, std::io::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Wait up to `grace` for the other end to exit on its own, then kill it.
+    ///
+    /// Called after the client has closed its write half. Returns how the
+    /// other end exited, or `None` if the transport cannot tell.
+    fn close(&mut self, grace: Duration) -> BoxFuture<'_, std::io::Result<Option<ExitReport>>> {
+        let _ = grace;
+        Box::pin(async { Ok(None) })
+    }
+
+    /// How the other end exited, waiting at most `wait` for it to do so.
+    ///
+    /// Used when the read half hits end of stream, to tell a crash from a
+    /// normal end. Returns `None` if it is still running or the transport
+    /// cannot tell.
+    fn exit_report(&mut self, wait: Duration) -> BoxFuture<'_, Option<ExitReport>> {
+        let _ = wait;
+        Box::pin(async { None })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Process transport
+// ---------------------------------------------------------------------------
+
+/// Transport over the stdio pipes of a spawned CLI process.
+///
+/// The child must have been spawned with piped stdin and stdout, as the
+/// clients' CLI builders do. Piped stderr is optional and exposed through
+/// [`Transport::take_stderr`].
+#[derive(Debug)]
+pub struct ProcessTransport {
+    child: Child,
+}
+
+impl ProcessTransport {
+    /// Wrap a spawned child process.
+    pub fn new(child: Child) -> Self {
+        Self { child }
+    }
+
+    /// Access the underlying child process.
+    pub fn child(&self) -> &Child {
+        &self.child
+    }
+
+    /// Mutably access the underlying child process.
+    pub fn child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Build an exit report, draining whatever is left on stderr.
+    async fn report(&mut self, status: ExitStatus, killed: bool) -> ExitReport {
+        let mut stderr = Vec::new();
+        if let Some(mut pipe) = self.child.stderr.take() {
+            // A grandchild may hold the pipe open, so don't wait for EOF forever.
+            let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, pipe.read_to_end(&mut stderr)).await;
+        }
+        ExitReport {
+            killed,
+            ..ExitReport::from_status(&status)
+        }
+        .with_stderr(&stderr)
+    }
+}
+
+impl From<Child> for ProcessTransport {
+    fn from(child: Child) -> Self {
+        Self::new(child)
+    }
+}
+
+impl Transport for ProcessTransport {
+    fn connect(&mut self) -> std::io::Result<(TransportReader, TransportWriter)> {
+        let stdin = self
+            .child
+            .stdin
+            .take()
+            .ok_or_else(|| std::io::Error::other("Failed to get stdin"))?;
+        let stdout = self
+            .child
+            .stdout
+            .take()
+            .ok_or_else(|| std::io::Error::other("Failed to get stdout"))?;
+        Ok((Box::new(stdout), Box::new(stdin)))
+    }
+
+    fn take_stderr(&mut self) -> Option<TransportReader> {
+        self.child
+            .stderr
+            .take()
+            .map(|stderr| Box::new(stderr) as TransportReader)
+    }
+
+    fn is_alive(&mut self) -> bool {
+        self.child.try_wait().ok().flatten().is_none()
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    fn start_kill(&mut self) -> std::io::Result<()> {
+        self.child.start_kill()
+    }
+
+    fn shutdown(&mut self) -> BoxFuture<'_, std::io::Result<()>> {
+        Box::pin(async move {
+            debug!("[CLIENT] Killing process {:?}", self.child.id());
+            self.child.kill().await
+        })
+    }
+
+    fn close(&mut self, grace: Duration) -> BoxFuture<'_, std::io::Result<Option<ExitReport>>> {
+        Box::pin(async move {
+            let (status, killed) = match tokio::time::timeout(grace, self.child.wait()).await {
+                Ok(status) => (status?, false),
+                Err(_) => {
+                    warn!(
+                        "[CLIENT] Process {:?} did not exit within {:?}, killing it",
+                        self.child.id(),
+                        grace
+                    );
+                    self.child.kill().await?;
+                    (self.child.wait().await?, true)
+                }
+            };
+            Ok(Some(self.report(status, killed).await))
+        })
+    }
+
+    fn exit_report(&mut self, wait: Duration) -> BoxFuture<'_, Option<ExitReport>> {
+        Box::pin(async move {
+            let status = tokio::time::timeout(wait, self.child.wait())
+                .await
+                .ok()?
+                .ok()?;
+            Some(self.report(status, false).await)
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Stream transport
+// ---------------------------------------------------------------------------
+
+/// Transport over an arbitrary pair of async byte streams.
+///
+/// The transport does not own a process, so [`Transport::is_alive`] always
+/// reports `true` and shutdown is a no-op; the connection closes when the
+/// client (and with it both streams) is dropped.
+pub struct StreamTransport {
+    io: Option<(TransportReader, TransportWriter)>,
+    stderr: Option<TransportReader>,
+}
+
+impl StreamTransport {
+    /// Create a transport from separate read and write halves.
+    pub fn new<R, W>(reader: R, writer: W) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        Self {
+            io: Some((Box::new(reader), Box::new(writer))),
+            stderr: None,
+        }
+    }
+
+    /// Create a transport from a single bidirectional stream.
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        Self::new(reader, writer)
+    }
+
+    /// Attach a diagnostic stream returned by [`Transport::take_stderr`].
+    pub fn with_stderr<E>(mut self, stderr: E) -> Self
+    where
+        E: AsyncRead + Send + Unpin + 'static,
+    {
+        self.stderr = Some(Box::new(stderr));
+        self
+    }
+}
+
+impl std::fmt::Debug for StreamTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamTransport")
+            .field("connected", &self.io.is_none())
+            .field("has_stderr", &self.stderr.is_some())
+            .finish()
+    }
+}
+
+impl Transport for StreamTransport {
+    fn connect(&mut self) -> std::io::Result<(TransportReader, TransportWriter)> {
+        self.io
+            .take()
+            .ok_or_else(|| std::io::Error::other("Stream transport already connected"))
+    }
+
+    fn take_stderr(&mut self) -> Option<TransportReader> {
+        self.stderr.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_stream_transport_connects_once() {
+        let (client_side, mut server_side) = tokio::io::duplex(64);
+        let mut transport = StreamTransport::from_stream(client_side);
+
+        let (mut reader, mut writer) = transport.connect().unwrap();
+        assert!(transport.connect().is_err());
+        assert!(transport.is_alive());
+        assert_eq!(transport.pid(), None);
+
+        writer.write_all(b"ping\n").await.unwrap();
+        let mut buf = [0u8; 5];
+        server_side.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping\n");
+
+        server_side.write_all(b"pong\n").await.unwrap();
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong\n");
+    }
+
+    #[tokio::test]
+    async fn test_stream_transport_stderr() {
+        let (reader, _) = tokio::io::duplex(8);
+        let (writer, _) = tokio::io::duplex(8);
+        let mut transport = StreamTransport::new(reader, writer).with_stderr(&b"oops\n"[..]);
+
+        let mut stderr = transport.take_stderr().unwrap();
+        let mut text = String::new();
+        stderr.read_to_string(&mut text).await.unwrap();
+        assert_eq!(text, "oops\n");
+        assert!(transport.take_stderr().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_process_transport_requires_pipes() {
+        let child = tokio::process::Command::new("true")
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut transport = ProcessTransport::new(child);
+        assert!(transport.connect().is_err());
+        transport.shutdown().await.ok();
+    }
+}
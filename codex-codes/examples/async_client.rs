@@ -4,7 +4,8 @@
 //! until the turn completes.
 
 use codex_codes::{
-    protocol::methods, AsyncClient, ServerMessage, ThreadStartParams, TurnStartParams, UserInput,
+    AsyncClient, ServerNotification, ThreadStartParams, TurnStartParams, TypedServerMessage,
+    UserInput,
 };
 use std::error::Error;
 
@@ -27,18 +28,16 @@ async fn main() -> Result<(), Box<dyn Error>> {
             input: vec![UserInput::Text {
                 text: "What is the capital of France?".to_string(),
             }],
-            model: None,
-            reasoning_effort: None,
-            sandbox_policy: None,
+            ..Default::default()
         })
         .await?;
 
     // Stream notifications until the turn completes
     let mut stream = client.events();
-    while let Some(result) = stream.next().await {
+    while let Some(result) = stream.next_typed().await {
         match result {
             Ok(msg) => {
-                if handle_message(&msg) {
+                if handle_message(msg) {
                     break;
                 }
             }
@@ -55,45 +54,34 @@ async fn main() -> Result<(), Box<dyn Error>> {
 }
 
 /// Handle a server message. Returns true if the turn is complete.
-fn handle_message(msg: &ServerMessage) -> bool {
+fn handle_message(msg: TypedServerMessage) -> bool {
     match msg {
-        ServerMessage::Notification { method, params } => {
-            match method.as_str() {
-                methods::AGENT_MESSAGE_DELTA => {
-                    if let Some(params) = params {
-                        if let Some(delta) = params.get("delta").and_then(|d| d.as_str()) {
-                            print!("{}", delta);
-                        }
-                    }
+        TypedServerMessage::Notification(notification) => {
+            match notification {
+                ServerNotification::AgentMessageDelta(delta) => {
+                    print!("{}", delta.delta);
                 }
-                methods::TURN_STARTED => {
+                ServerNotification::TurnStarted(_) => {
                     println!("[turn started]");
                 }
-                methods::TURN_COMPLETED => {
+                ServerNotification::TurnCompleted(_) => {
                     println!("\n[turn completed]");
                     return true;
                 }
-                methods::ITEM_STARTED => {
-                    // Items starting — could inspect the item type
+                ServerNotification::ItemStarted(_) | ServerNotification::ItemCompleted(_) => {
+                    // Item lifecycle — could inspect the item type
                 }
-                methods::ITEM_COMPLETED => {
-                    // Items completing
+                ServerNotification::Error(err) => {
+                    eprintln!("[error] {}", err.error);
                 }
-                methods::ERROR => {
-                    if let Some(params) = params {
-                        if let Some(error) = params.get("error").and_then(|e| e.as_str()) {
-                            eprintln!("[error] {}", error);
-                        }
-                    }
-                }
-                _ => {
-                    log::debug!("Notification: {}", method);
+                other => {
+                    log::debug!("Notification: {}", other.method());
                 }
             }
             false
         }
-        ServerMessage::Request { method, .. } => {
-            eprintln!("[server request: {}] (unhandled)", method);
+        TypedServerMessage::Request(request) => {
+            eprintln!("[server request: {}] (unhandled)", request.method());
             false
         }
     }
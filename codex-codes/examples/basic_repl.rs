@@ -46,9 +46,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 input: vec![UserInput::Text {
                     text: input.to_string(),
                 }],
-                model: None,
-                reasoning_effort: None,
-                sandbox_policy: None,
+                ..Default::default()
             })
             .await?;
 
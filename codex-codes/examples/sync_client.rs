@@ -25,9 +25,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         input: vec![UserInput::Text {
             text: "What is the capital of France?".to_string(),
         }],
-        model: None,
-        reasoning_effort: None,
-        sandbox_policy: None,
+        ..Default::default()
     })?;
 
     // Iterate notifications until the turn completes
@@ -0,0 +1,474 @@
+//! Token accounting with budgets.
+//!
+//! A [`UsageLedger`] aggregates [`TokenUsage`] per thread and per model into a
+//! [`UsageReport`]. It consumes app-server `thread/tokenUsage/updated`
+//! notifications (cumulative per thread) and exec-format `turn.completed`
+//! events (per turn). Reports are serializable, so totals can be carried
+//! across runs with [`UsageLedger::from_report`] or combined with
+//! [`UsageReport::merge`]. Codex does not report prices, so there is no USD
+//! figure — convert token totals with your own rates.
+//!
+//! [`Budget`]s fire once when a metric crosses its limit, calling an optional
+//! callback. Budgets marked with [`Budget::interrupt`] also make `AsyncClient`
+//! send `turn/interrupt` for the thread that crossed the limit, when the ledger
+//! is attached with `AsyncClient::set_usage_ledger`.
+//!
+//! # Example
+//!
+//! ```
+//! use codex_codes::accounting::{Budget, UsageLedger};
+//! use codex_codes::ServerNotification;
+//! use serde_json::json;
+//!
+//! let mut ledger = UsageLedger::new().budget(Budget::total_tokens(10_000).interrupt());
+//! ledger.set_thread_model("th_1", "gpt-5-codex");
+//!
+//! let notification = ServerNotification::from_parts(
+//!     "thread/tokenUsage/updated".to_string(),
+//!     Some(json!({"threadId": "th_1", "usage": {"inputTokens": 9000, "outputTokens": 2000}})),
+//! );
+//! let exceeded = ledger.record_notification(&notification);
+//!
+//! assert_eq!(exceeded[0].thread_id.as_deref(), Some("th_1"));
+//! assert_eq!(ledger.report().models["gpt-5-codex"].output_tokens, 2000);
+//! ```
+
+use crate::io::events::{ThreadEvent, Usage};
+use crate::protocol::{ServerNotification, ThreadStartResponse, TokenUsage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+// ---------------------------------------------------------------------------
+// Report
+// ---------------------------------------------------------------------------
+
+fn add(total: &mut TokenUsage, other: &TokenUsage) {
+    total.input_tokens += other.input_tokens;
+    total.output_tokens += other.output_tokens;
+    total.cached_input_tokens += other.cached_input_tokens;
+}
+
+/// Aggregated token usage across threads.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Totals across everything recorded.
+    pub total: TokenUsage,
+    /// Per thread ID.
+    pub threads: BTreeMap<String, TokenUsage>,
+    /// Per model, for threads whose model is known.
+    pub models: BTreeMap<String, TokenUsage>,
+}
+
+impl UsageReport {
+    /// Add another report's totals into this one.
+    pub fn merge(&mut self, other: &UsageReport) {
+        add(&mut self.total, &other.total);
+        for (key, usage) in &other.threads {
+            add(self.threads.entry(key.clone()).or_default(), usage);
+        }
+        for (key, usage) in &other.models {
+            add(self.models.entry(key.clone()).or_default(), usage);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Budgets
+// ---------------------------------------------------------------------------
+
+/// What a [`Budget`] limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetMetric {
+    /// Input plus output tokens.
+    TotalTokens,
+    /// Input tokens, including cached ones.
+    InputTokens,
+    /// Output tokens.
+    OutputTokens,
+}
+
+impl BudgetMetric {
+    fn observe(&self, usage: &TokenUsage) -> u64 {
+        match self {
+            BudgetMetric::TotalTokens => usage.input_tokens + usage.output_tokens,
+            BudgetMetric::InputTokens => usage.input_tokens,
+            BudgetMetric::OutputTokens => usage.output_tokens,
+        }
+    }
+}
+
+impl fmt::Display for BudgetMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BudgetMetric::TotalTokens => "total_tokens",
+            BudgetMetric::InputTokens => "input_tokens",
+            BudgetMetric::OutputTokens => "output_tokens",
+        })
+    }
+}
+
+/// A budget that was crossed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetExceeded {
+    pub metric: BudgetMetric,
+    pub limit: u64,
+    /// The value that crossed the limit.
+    pub observed: u64,
+    /// Whether the budget asks for the thread's turn to be interrupted.
+    pub interrupt: bool,
+    /// The thread whose usage crossed the limit, when known.
+    pub thread_id: Option<String>,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} budget exceeded: {} > {}",
+            self.metric, self.observed, self.limit
+        )
+    }
+}
+
+type BudgetCallback = Arc<dyn Fn(&BudgetExceeded) + Send + Sync>;
+
+/// A limit on the ledger's total usage.
+#[derive(Clone)]
+pub struct Budget {
+    metric: BudgetMetric,
+    limit: u64,
+    interrupt: bool,
+    callback: Option<BudgetCallback>,
+    fired: bool,
+}
+
+impl Budget {
+    fn new(metric: BudgetMetric, limit: u64) -> Self {
+        Self {
+            metric,
+            limit,
+            interrupt: false,
+            callback: None,
+            fired: false,
+        }
+    }
+
+    /// Limit input plus output tokens.
+    pub fn total_tokens(limit: u64) -> Self {
+        Self::new(BudgetMetric::TotalTokens, limit)
+    }
+
+    /// Limit input tokens.
+    pub fn input_tokens(limit: u64) -> Self {
+        Self::new(BudgetMetric::InputTokens, limit)
+    }
+
+    /// Limit output tokens.
+    pub fn output_tokens(limit: u64) -> Self {
+        Self::new(BudgetMetric::OutputTokens, limit)
+    }
+
+    /// Interrupt the thread's turn when the budget is exceeded.
+    pub fn interrupt(mut self) -> Self {
+        self.interrupt = true;
+        self
+    }
+
+    /// Call `callback` when the budget is exceeded.
+    pub fn on_exceeded<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&BudgetExceeded) + Send + Sync + 'static,
+    {
+        self.callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// The metric this budget limits.
+    pub fn metric(&self) -> BudgetMetric {
+        self.metric
+    }
+
+    /// The limit.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Whether the budget has already fired.
+    pub fn is_exceeded(&self) -> bool {
+        self.fired
+    }
+
+    fn check(&mut self, total: &TokenUsage, thread_id: Option<&str>) -> Option<BudgetExceeded> {
+        if self.fired {
+            return None;
+        }
+        let observed = self.metric.observe(total);
+        if observed <= self.limit {
+            return None;
+        }
+        self.fired = true;
+        let exceeded = BudgetExceeded {
+            metric: self.metric,
+            limit: self.limit,
+            observed,
+            interrupt: self.interrupt,
+            thread_id: thread_id.map(str::to_string),
+        };
+        if let Some(callback) = &self.callback {
+            callback(&exceeded);
+        }
+        Some(exceeded)
+    }
+}
+
+impl fmt::Debug for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Budget")
+            .field("metric", &self.metric)
+            .field("limit", &self.limit)
+            .field("interrupt", &self.interrupt)
+            .field("fired", &self.fired)
+            .finish()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Ledger
+// ---------------------------------------------------------------------------
+
+/// Aggregates token usage and enforces [`Budget`]s.
+#[derive(Debug, Clone, Default)]
+pub struct UsageLedger {
+    report: UsageReport,
+    budgets: Vec<Budget>,
+    /// Model per thread, from `thread/start` responses.
+    thread_models: HashMap<String, String>,
+    /// Latest cumulative usage seen per thread.
+    cumulative: HashMap<String, TokenUsage>,
+    /// Thread of the exec stream being recorded.
+    exec_thread: Option<String>,
+}
+
+impl UsageLedger {
+    /// Create an empty ledger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continue aggregating on top of a report from earlier runs.
+    pub fn from_report(report: UsageReport) -> Self {
+        Self {
+            report,
+            ..Self::default()
+        }
+    }
+
+    /// Add a budget.
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budgets.push(budget);
+        self
+    }
+
+    /// Add a budget to an existing ledger.
+    pub fn add_budget(&mut self, budget: Budget) {
+        self.budgets.push(budget);
+    }
+
+    /// The budgets, in the order they were added.
+    pub fn budgets(&self) -> &[Budget] {
+        &self.budgets
+    }
+
+    /// Re-arm budgets that have fired.
+    pub fn reset_budgets(&mut self) {
+        for budget in &mut self.budgets {
+            budget.fired = false;
+        }
+    }
+
+    /// The aggregated report.
+    pub fn report(&self) -> &UsageReport {
+        &self.report
+    }
+
+    /// Consume the ledger, returning its report.
+    pub fn into_report(self) -> UsageReport {
+        self.report
+    }
+
+    /// Attribute a thread's usage to `model`.
+    pub fn set_thread_model(&mut self, thread_id: &str, model: &str) {
+        self.thread_models
+            .insert(thread_id.to_string(), model.to_string());
+    }
+
+    /// Learn a thread's model from its `thread/start` response.
+    pub fn record_thread_start(&mut self, response: &ThreadStartResponse) {
+        if let Some(model) = &response.model {
+            self.set_thread_model(response.thread_id(), model);
+        }
+    }
+
+    /// Record an app-server notification. Returns the budgets it pushed over their limits.
+    ///
+    /// Only `thread/tokenUsage/updated` carries usage; its totals are
+    /// cumulative, so the increase since the previous update is recorded.
+    pub fn record_notification(
+        &mut self,
+        notification: &ServerNotification,
+    ) -> Vec<BudgetExceeded> {
+        let ServerNotification::ThreadTokenUsageUpdated(update) = notification else {
+            return Vec::new();
+        };
+        let previous = self
+            .cumulative
+            .insert(update.thread_id.clone(), update.usage)
+            .unwrap_or_default();
+        let delta = TokenUsage {
+            input_tokens: update
+                .usage
+                .input_tokens
+                .saturating_sub(previous.input_tokens),
+            output_tokens: update
+                .usage
+                .output_tokens
+                .saturating_sub(previous.output_tokens),
+            cached_input_tokens: update
+                .usage
+                .cached_input_tokens
+                .saturating_sub(previous.cached_input_tokens),
+        };
+        self.add_usage(&update.thread_id, &delta);
+        self.check_budgets(Some(&update.thread_id))
+    }
+
+    /// Record an exec-format event. Returns the budgets it pushed over their limits.
+    ///
+    /// `turn.completed` usage is per turn and is attributed to the thread
+    /// announced by the stream's `thread.started` event.
+    pub fn record_event(&mut self, event: &ThreadEvent) -> Vec<BudgetExceeded> {
+        match event {
+            ThreadEvent::ThreadStarted(started) => {
+                self.exec_thread = Some(started.thread_id.clone());
+                Vec::new()
+            }
+            ThreadEvent::TurnCompleted(completed) => {
+                let Usage {
+                    input_tokens,
+                    cached_input_tokens,
+                    output_tokens,
+                } = completed.usage;
+                let usage = TokenUsage {
+                    input_tokens,
+                    output_tokens,
+                    cached_input_tokens,
+                };
+                let thread_id = self.exec_thread.clone().unwrap_or_default();
+                self.add_usage(&thread_id, &usage);
+                self.check_budgets(self.exec_thread.clone().as_deref())
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn add_usage(&mut self, thread_id: &str, usage: &TokenUsage) {
+        add(&mut self.report.total, usage);
+        add(
+            self.report
+                .threads
+                .entry(thread_id.to_string())
+                .or_default(),
+            usage,
+        );
+        if let Some(model) = self.thread_models.get(thread_id) {
+            add(self.report.models.entry(model.clone()).or_default(), usage);
+        }
+    }
+
+    fn check_budgets(&mut self, thread_id: Option<&str>) -> Vec<BudgetExceeded> {
+        let total = &self.report.total;
+        self.budgets
+            .iter_mut()
+            .filter_map(|budget| budget.check(total, thread_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn usage_update(thread_id: &str, input: u64, output: u64) -> ServerNotification {
+        ServerNotification::from_parts(
+            "thread/tokenUsage/updated".to_string(),
+            Some(json!({
+                "threadId": thread_id,
+                "usage": {"inputTokens": input, "outputTokens": output, "cachedInputTokens": 0}
+            })),
+        )
+    }
+
+    #[test]
+    fn test_cumulative_updates_are_not_double_counted() {
+        let mut ledger = UsageLedger::new();
+        ledger.set_thread_model("th_1", "gpt-5-codex");
+        ledger.record_notification(&usage_update("th_1", 100, 10));
+        ledger.record_notification(&usage_update("th_1", 250, 30));
+        ledger.record_notification(&usage_update("th_2", 5, 5));
+
+        let report = ledger.report();
+        assert_eq!(report.total.input_tokens, 255);
+        assert_eq!(report.threads["th_1"].output_tokens, 30);
+        assert_eq!(report.models["gpt-5-codex"].input_tokens, 250);
+        assert!(!report.models.contains_key("th_2"));
+    }
+
+    #[test]
+    fn test_exec_events_add_per_turn_usage() {
+        let mut ledger = UsageLedger::new();
+        let events = [
+            r#"{"type":"thread.started","thread_id":"th_exec"}"#,
+            r#"{"type":"turn.completed","usage":{"input_tokens":10,"cached_input_tokens":2,"output_tokens":3}}"#,
+            r#"{"type":"turn.completed","usage":{"input_tokens":20,"cached_input_tokens":0,"output_tokens":4}}"#,
+        ];
+        for event in events {
+            ledger.record_event(&serde_json::from_str(event).unwrap());
+        }
+        let usage = &ledger.report().threads["th_exec"];
+        assert_eq!(usage.input_tokens, 30);
+        assert_eq!(usage.cached_input_tokens, 2);
+        assert_eq!(usage.output_tokens, 7);
+    }
+
+    #[test]
+    fn test_budget_fires_once() {
+        let mut ledger = UsageLedger::new().budget(Budget::output_tokens(20));
+        assert!(ledger
+            .record_notification(&usage_update("th_1", 0, 10))
+            .is_empty());
+        let exceeded = ledger.record_notification(&usage_update("th_1", 0, 25));
+        assert_eq!(exceeded.len(), 1);
+        assert_eq!(exceeded[0].observed, 25);
+        assert!(!exceeded[0].interrupt);
+        assert!(ledger
+            .record_notification(&usage_update("th_1", 0, 50))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_report_merge_and_roundtrip() {
+        let mut ledger = UsageLedger::new();
+        ledger.record_notification(&usage_update("th_1", 10, 1));
+        let json = serde_json::to_string(ledger.report()).unwrap();
+        let report: UsageReport = serde_json::from_str(&json).unwrap();
+
+        let mut merged = report.clone();
+        merged.merge(&report);
+        assert_eq!(merged.total.input_tokens, 20);
+        assert_eq!(merged.threads["th_1"].output_tokens, 2);
+    }
+}
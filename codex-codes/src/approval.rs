@@ -0,0 +1,199 @@
+//! Approval callbacks answered automatically by the clients.
+//!
+//! The app-server asks the client before running commands or applying file
+//! changes (`item/commandExecution/requestApproval` and
+//! `item/fileChange/requestApproval`). Register an [`ApprovalHandler`] with
+//! `AsyncClient::set_approval_handler` or `SyncClient::set_approval_handler`
+//! and the client decodes those requests, calls the handler, and sends the
+//! response itself — approval requests then never reach `next_message`.
+//!
+//! # Example
+//!
+//! ```
+//! use codex_codes::approval::ApprovalHandler;
+//! use codex_codes::{
+//!     CommandApprovalDecision, CommandExecutionApprovalParams, FileChangeApprovalDecision,
+//!     FileChangeApprovalParams,
+//! };
+//!
+//! /// Allow read-only commands, refuse everything else.
+//! struct ReadOnly;
+//!
+//! impl ApprovalHandler for ReadOnly {
+//!     fn command_execution(
+//!         &mut self,
+//!         params: &CommandExecutionApprovalParams,
+//!     ) -> CommandApprovalDecision {
+//!         if params.command.starts_with("ls") || params.command.starts_with("cat ") {
+//!             CommandApprovalDecision::Accept
+//!         } else {
+//!             CommandApprovalDecision::Decline
+//!         }
+//!     }
+//!
+//!     fn file_change(&mut self, _params: &FileChangeApprovalParams) -> FileChangeApprovalDecision {
+//!         FileChangeApprovalDecision::Decline
+//!     }
+//! }
+//! ```
+
+use crate::jsonrpc::RequestId;
+use crate::protocol::{
+    CommandApprovalDecision, CommandExecutionApprovalParams, CommandExecutionApprovalResponse,
+    FileChangeApprovalDecision, FileChangeApprovalParams, FileChangeApprovalResponse,
+    ServerMessage, ServerRequest,
+};
+use serde_json::Value;
+
+/// Decides approval requests from the app-server.
+///
+/// Handlers run synchronously on the task reading the connection, so they
+/// should return promptly.
+pub trait ApprovalHandler: Send {
+    /// Decide whether the agent may run a command.
+    fn command_execution(
+        &mut self,
+        params: &CommandExecutionApprovalParams,
+    ) -> CommandApprovalDecision;
+
+    /// Decide whether the agent may apply file changes.
+    fn file_change(&mut self, params: &FileChangeApprovalParams) -> FileChangeApprovalDecision;
+}
+
+/// Accepts every approval request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApproveAll;
+
+impl ApprovalHandler for ApproveAll {
+    fn command_execution(
+        &mut self,
+        _params: &CommandExecutionApprovalParams,
+    ) -> CommandApprovalDecision {
+        CommandApprovalDecision::Accept
+    }
+
+    fn file_change(&mut self, _params: &FileChangeApprovalParams) -> FileChangeApprovalDecision {
+        FileChangeApprovalDecision::Accept
+    }
+}
+
+/// Declines every approval request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeclineAll;
+
+impl ApprovalHandler for DeclineAll {
+    fn command_execution(
+        &mut self,
+        _params: &CommandExecutionApprovalParams,
+    ) -> CommandApprovalDecision {
+        CommandApprovalDecision::Decline
+    }
+
+    fn file_change(&mut self, _params: &FileChangeApprovalParams) -> FileChangeApprovalDecision {
+        FileChangeApprovalDecision::Decline
+    }
+}
+
+/// Run `handler` on an approval request.
+///
+/// Returns the request ID and response payload to send, or hands the message
+/// back unchanged if it is not an approval request.
+pub(crate) fn dispatch(
+    handler: &mut dyn ApprovalHandler,
+    msg: ServerMessage,
+) -> std::result::Result<(RequestId, Value), ServerMessage> {
+    let (id, method, params) = match msg {
+        ServerMessage::Request { id, method, params } => (id, method, params),
+        notification => return Err(notification),
+    };
+    let (id, response) = match ServerRequest::from_parts(id, method, params) {
+        ServerRequest::CommandExecutionApproval { id, params } => {
+            let decision = handler.command_execution(&params);
+            (
+                id,
+                serde_json::to_value(CommandExecutionApprovalResponse { decision }),
+            )
+        }
+        ServerRequest::FileChangeApproval { id, params } => {
+            let decision = handler.file_change(&params);
+            (
+                id,
+                serde_json::to_value(FileChangeApprovalResponse { decision }),
+            )
+        }
+        ServerRequest::Unknown { id, method, params } => {
+            return Err(ServerMessage::Request { id, method, params })
+        }
+    };
+    Ok((id, response.unwrap_or(Value::Null)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::methods;
+    use serde_json::json;
+
+    fn command_request() -> ServerMessage {
+        ServerMessage::Request {
+            id: RequestId::Integer(3),
+            method: methods::CMD_EXEC_APPROVAL.to_string(),
+            params: Some(json!({
+                "threadId": "th_1",
+                "turnId": "t_1",
+                "callId": "call_1",
+                "command": "rm -rf build",
+                "cwd": "/work"
+            })),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_command_approval() {
+        let (id, response) = dispatch(&mut ApproveAll, command_request()).unwrap();
+        assert_eq!(id, RequestId::Integer(3));
+        assert_eq!(response, json!({"decision": "accept"}));
+
+        let (_, response) = dispatch(&mut DeclineAll, command_request()).unwrap();
+        assert_eq!(response, json!({"decision": "decline"}));
+    }
+
+    #[test]
+    fn test_dispatch_file_change_approval() {
+        let msg = ServerMessage::Request {
+            id: RequestId::String("fc".to_string()),
+            method: methods::FILE_CHANGE_APPROVAL.to_string(),
+            params: Some(json!({
+                "threadId": "th_1",
+                "turnId": "t_1",
+                "callId": "call_2",
+                "changes": {}
+            })),
+        };
+        let (id, response) = dispatch(&mut ApproveAll, msg).unwrap();
+        assert_eq!(id, RequestId::String("fc".to_string()));
+        assert_eq!(response, json!({"decision": "accept"}));
+    }
+
+    #[test]
+    fn test_dispatch_passes_other_messages_through() {
+        let notification = ServerMessage::Notification {
+            method: methods::TURN_STARTED.to_string(),
+            params: None,
+        };
+        assert!(dispatch(&mut ApproveAll, notification).is_err());
+
+        let other = ServerMessage::Request {
+            id: RequestId::Integer(9),
+            method: "item/tool/requestUserInput".to_string(),
+            params: Some(json!({"question": "?"})),
+        };
+        match dispatch(&mut ApproveAll, other) {
+            Err(ServerMessage::Request { method, params, .. }) => {
+                assert_eq!(method, "item/tool/requestUserInput");
+                assert_eq!(params, Some(json!({"question": "?"})));
+            }
+            other => panic!("expected the request back, got {:?}", other),
+        }
+    }
+}
@@ -9,15 +9,23 @@
 //! 1. Create a client with [`AsyncClient::start`] (spawns and initializes the app-server)
 //! 2. Call [`AsyncClient::thread_start`] to create a conversation session
 //! 3. Call [`AsyncClient::turn_start`] to send user input
-//! 4. Consume [`AsyncClient::next_message`] to stream notifications
+//! 4. Consume [`AsyncClient::next_message`] (or [`AsyncClient::next_typed_message`])
+//!    to stream notifications
 //! 5. Handle approval requests via [`AsyncClient::respond`]
 //! 6. Repeat steps 3-5 for follow-up turns
 //! 7. The client kills the app-server on [`Drop`]
 //!
+//! [`AsyncClient::run_turn`] covers steps 3-5 in one call, returning the
+//! turn's items as a [`TurnOutcome`]; [`AsyncClient::stream_turn`] does the
+//! same while yielding progress along the way.
+//!
 //! # Example
 //!
 //! ```ignore
-//! use codex_codes::{AsyncClient, ThreadStartParams, TurnStartParams, UserInput, ServerMessage};
+//! use codex_codes::{
+//!     AsyncClient, ServerNotification, ThreadStartParams, TurnStartParams, TypedServerMessage,
+//!     UserInput,
+//! };
 //!
 //! let mut client = AsyncClient::start().await?;
 //! let thread = client.thread_start(&ThreadStartParams::default()).await?;
@@ -25,44 +33,65 @@
 //! client.turn_start(&TurnStartParams {
 //!     thread_id: thread.thread_id().to_string(),
 //!     input: vec![UserInput::Text { text: "Hello!".into() }],
-//!     model: None,
-//!     reasoning_effort: None,
-//!     sandbox_policy: None,
+//!     ..Default::default()
 //! }).await?;
 //!
-//! while let Some(msg) = client.next_message().await? {
+//! while let Some(msg) = client.next_typed_message().await? {
 //!     match msg {
-//!         ServerMessage::Notification { method, params } => {
-//!             if method == "turn/completed" { break; }
-//!         }
-//!         ServerMessage::Request { id, method, .. } => {
-//!             client.respond(id, &serde_json::json!({"decision": "accept"})).await?;
+//!         TypedServerMessage::Notification(ServerNotification::TurnCompleted(_)) => break,
+//!         TypedServerMessage::Notification(_) => {}
+//!         TypedServerMessage::Request(req) => {
+//!             client.respond(req.id().clone(), &serde_json::json!({"decision": "accept"})).await?;
 //!         }
 //!     }
 //! }
 //! ```
 
+use crate::accounting::UsageLedger;
+use crate::approval::ApprovalHandler;
 use crate::cli::AppServerBuilder;
-use crate::error::{Error, Result};
+use crate::error::{Error, ExitReport, Result, STDERR_TAIL_LINES};
 use crate::jsonrpc::{
     JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, RequestId,
 };
 use crate::protocol::{
-    ClientInfo, InitializeParams, InitializeResponse, ServerMessage, ThreadArchiveParams,
-    ThreadArchiveResponse, ThreadStartParams, ThreadStartResponse, TurnInterruptParams,
-    TurnInterruptResponse, TurnStartParams, TurnStartResponse,
+    ClientInfo, CommandApprovalDecision, CommandExecutionApprovalResponse,
+    FileChangeApprovalDecision, FileChangeApprovalResponse, InitializeParams, InitializeResponse,
+    ServerMessage, ServerNotification, ServerRequest, ThreadArchiveParams, ThreadArchiveResponse,
+    ThreadForkParams, ThreadForkResponse, ThreadListParams, ThreadListResponse, ThreadReadParams,
+    ThreadReadResponse, ThreadResumeParams, ThreadResumeResponse, ThreadRollbackParams,
+    ThreadRollbackResponse, ThreadStartParams, ThreadStartResponse, TurnInterruptParams,
+    TurnInterruptResponse, TurnStartParams, TurnStartResponse, TurnSteerParams, TurnSteerResponse,
+    TypedServerMessage, UserInput,
+};
+use crate::stderr::{StderrBuffer, StderrPump};
+use crate::transcript::{Direction, TranscriptRecorder, TRANSCRIPT_PROVIDER};
+use crate::transport::{
+    ProcessTransport, Transport, TransportReader, TransportWriter, STDERR_DRAIN_TIMEOUT,
 };
+use crate::turn::{TurnAggregator, TurnOutcome, TurnUpdate};
+use codes_core::limits::ReadLimits;
 use log::{debug, error, warn};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
-use tokio::process::{Child, ChildStderr};
+use tokio_util::sync::CancellationToken;
 
 /// Buffer size for reading stdout (10MB).
 const STDOUT_BUFFER_SIZE: usize = 10 * 1024 * 1024;
 
+/// How long to wait for the process to exit once its stdout has closed.
+const EXIT_WAIT: Duration = Duration::from_secs(2);
+
+/// How long cancelled turns may take to wind down after their interrupt.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON-RPC error code for requests the client does not handle.
+const METHOD_NOT_FOUND: i64 = -32601;
+
 /// Asynchronous multi-turn client for the Codex app-server.
 ///
 /// Communicates with a long-lived `codex app-server` process via
@@ -70,16 +99,34 @@ const STDOUT_BUFFER_SIZE: usize = 10 * 1024 * 1024;
 /// correlation and buffers incoming notifications that arrive while
 /// waiting for RPC responses.
 ///
+/// The byte streams come from a [`Transport`]: a spawned process by default,
+/// or any async reader/writer pair via [`AsyncClient::from_transport`].
+///
 /// The client automatically kills the app-server process when dropped.
 pub struct AsyncClient {
-    child: Child,
-    writer: BufWriter<tokio::process::ChildStdin>,
-    reader: BufReader<tokio::process::ChildStdout>,
-    stderr: Option<BufReader<ChildStderr>>,
+    transport: Box<dyn Transport>,
+    writer: BufWriter<TransportWriter>,
+    reader: BufReader<TransportReader>,
     next_id: AtomicI64,
     /// Buffered incoming messages (notifications/server requests) that arrived
     /// while waiting for a response to a client request.
     buffered: VecDeque<ServerMessage>,
+    /// Answers approval requests automatically when set.
+    approval_handler: Option<Box<dyn ApprovalHandler>>,
+    /// Wire-level transcript of every line sent and received.
+    recorder: Option<TranscriptRecorder>,
+    /// Usage ledger fed every notification returned by `next_message`.
+    usage_ledger: Option<UsageLedger>,
+    /// Recent stderr lines, once a pump has been attached.
+    stderr: Option<StderrBuffer>,
+    /// Longest wait for the next message in `next_message`.
+    idle_timeout: Option<Duration>,
+    /// Longest wait for the response to a request.
+    request_timeout: Option<Duration>,
+    /// Threads with a turn started and not yet completed.
+    active_turns: HashSet<String>,
+    /// Bytes of a partially read line, kept across reads that time out.
+    line_buf: Vec<u8>,
 }
 
 impl AsyncClient {
@@ -109,16 +156,22 @@ impl AsyncClient {
     /// cannot be established, or the initialization handshake fails.
     pub async fn start_with(builder: AppServerBuilder) -> Result<Self> {
         let mut client = Self::spawn(builder).await?;
-        client
-            .initialize(&InitializeParams {
-                client_info: ClientInfo {
-                    name: "codex-codes".to_string(),
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                    title: None,
-                },
-                capabilities: None,
-            })
-            .await?;
+        client.initialize(&default_initialize_params()).await?;
+        Ok(client)
+    }
+
+    /// Connect over a custom [`Transport`] and perform the `initialize` handshake.
+    ///
+    /// Use this to talk to an app-server reachable over a socket, pipe or
+    /// other byte stream instead of a locally spawned process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transport cannot provide its streams or the
+    /// initialization handshake fails.
+    pub async fn start_with_transport<T: Transport + 'static>(transport: T) -> Result<Self> {
+        let mut client = Self::from_transport(transport)?;
+        client.initialize(&default_initialize_params()).await?;
         Ok(client)
     }
 
@@ -130,25 +183,32 @@ impl AsyncClient {
     pub async fn spawn(builder: AppServerBuilder) -> Result<Self> {
         crate::version::check_codex_version_async().await?;
 
-        let mut child = builder.spawn().await?;
+        let child = builder.spawn().await?;
+        Self::from_transport(ProcessTransport::new(child))
+    }
 
-        let stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| Error::Protocol("Failed to get stdin".to_string()))?;
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| Error::Protocol("Failed to get stdout".to_string()))?;
-        let stderr = child.stderr.take().map(BufReader::new);
+    /// Create a client over a custom [`Transport`] without performing the
+    /// `initialize` handshake.
+    ///
+    /// As with [`AsyncClient::spawn`], you **must** call
+    /// [`AsyncClient::initialize`] before any other requests.
+    pub fn from_transport<T: Transport + 'static>(mut transport: T) -> Result<Self> {
+        let (reader, writer) = transport.connect().map_err(Error::Io)?;
 
         Ok(Self {
-            child,
-            writer: BufWriter::new(stdin),
-            reader: BufReader::with_capacity(STDOUT_BUFFER_SIZE, stdout),
-            stderr,
+            transport: Box::new(transport),
+            writer: BufWriter::new(writer),
+            reader: BufReader::with_capacity(STDOUT_BUFFER_SIZE, reader),
             next_id: AtomicI64::new(1),
             buffered: VecDeque::new(),
+            approval_handler: None,
+            recorder: None,
+            usage_ledger: None,
+            stderr: None,
+            idle_timeout: None,
+            request_timeout: None,
+            active_turns: HashSet::new(),
+            line_buf: Vec::new(),
         })
     }
 
@@ -162,10 +222,22 @@ impl AsyncClient {
     /// - [`Error::JsonRpc`] if the server returns a JSON-RPC error
     /// - [`Error::ServerClosed`] if the connection drops before a response arrives
     /// - [`Error::Json`] if response deserialization fails
+    /// - [`Error::Timeout`] if no response arrives within the
+    ///   [request timeout](AsyncClient::set_request_timeout)
     pub async fn request<P: Serialize, R: DeserializeOwned>(
         &mut self,
         method: &str,
         params: &P,
+    ) -> Result<R> {
+        let limits = ReadLimits::within(self.request_timeout);
+        self.request_within(method, params, &limits).await
+    }
+
+    async fn request_within<P: Serialize, R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: &P,
+        limits: &ReadLimits,
     ) -> Result<R> {
         let id = RequestId::Integer(self.next_id.fetch_add(1, Ordering::Relaxed));
 
@@ -179,7 +251,7 @@ impl AsyncClient {
 
         // Read lines until we get a response matching our id
         loop {
-            let msg = self.read_message().await?;
+            let msg = self.read_message(limits).await?;
             match msg {
                 JsonRpcMessage::Response(resp) if resp.id == id => {
                     let result: R = serde_json::from_value(resp.result).map_err(Error::Json)?;
@@ -199,11 +271,14 @@ impl AsyncClient {
                     });
                 }
                 JsonRpcMessage::Request(req) => {
-                    self.buffered.push_back(ServerMessage::Request {
+                    let msg = ServerMessage::Request {
                         id: req.id,
                         method: req.method,
                         params: req.params,
-                    });
+                    };
+                    if let Some(msg) = self.auto_approve(msg).await? {
+                        self.buffered.push_back(msg);
+                    }
                 }
                 // Response/error for a different id — unexpected
                 JsonRpcMessage::Response(resp) => {
@@ -230,8 +305,13 @@ impl AsyncClient {
         &mut self,
         params: &ThreadStartParams,
     ) -> Result<ThreadStartResponse> {
-        self.request(crate::protocol::methods::THREAD_START, params)
-            .await
+        let response: ThreadStartResponse = self
+            .request(crate::protocol::methods::THREAD_START, params)
+            .await?;
+        if let Some(ledger) = self.usage_ledger.as_mut() {
+            ledger.record_thread_start(&response);
+        }
+        Ok(response)
     }
 
     /// Start a new turn within a thread.
@@ -239,8 +319,11 @@ impl AsyncClient {
     /// Sends user input to the agent. After calling this, use [`AsyncClient::next_message`]
     /// to stream notifications until `turn/completed` arrives.
     pub async fn turn_start(&mut self, params: &TurnStartParams) -> Result<TurnStartResponse> {
-        self.request(crate::protocol::methods::TURN_START, params)
-            .await
+        let response = self
+            .request(crate::protocol::methods::TURN_START, params)
+            .await?;
+        self.active_turns.insert(params.thread_id.clone());
+        Ok(response)
     }
 
     /// Interrupt an active turn.
@@ -252,6 +335,47 @@ impl AsyncClient {
             .await
     }
 
+    /// Add input to the turn in progress without interrupting it.
+    pub async fn turn_steer(&mut self, params: &TurnSteerParams) -> Result<TurnSteerResponse> {
+        self.request(crate::protocol::methods::TURN_STEER, params)
+            .await
+    }
+
+    /// Send `input` to a thread and wait for the turn to complete.
+    ///
+    /// Deltas and items are folded into a [`TurnOutcome`] by a
+    /// [`TurnAggregator`]. Approval requests go to the
+    /// [approval handler](AsyncClient::set_approval_handler) if one is set;
+    /// any it does not answer are declined. Use [`AsyncClient::stream_turn`]
+    /// to see progress or decide requests yourself.
+    pub async fn run_turn(
+        &mut self,
+        thread_id: &str,
+        input: Vec<UserInput>,
+    ) -> Result<TurnOutcome> {
+        self.stream_turn(&TurnStartParams::new(thread_id, input))
+            .await?
+            .finish()
+            .await
+    }
+
+    /// Start a turn and stream its progress as [`TurnUpdate`]s.
+    ///
+    /// The stream ends after the turn's `turn/completed`; call
+    /// [`TurnStream::finish`] for the [`TurnOutcome`]. Notifications for other
+    /// threads, and for earlier turns on this one, are read and dropped while
+    /// the stream is in use. Server requests for other threads are held back
+    /// unanswered and returned by [`AsyncClient::next_message`] once the
+    /// stream is dropped.
+    pub async fn stream_turn(&mut self, params: &TurnStartParams) -> Result<TurnStream<'_>> {
+        self.turn_start(params).await?;
+        Ok(TurnStream {
+            client: self,
+            aggregator: TurnAggregator::new(params.thread_id.clone()),
+            deferred: Vec::new(),
+        })
+    }
+
     /// Archive a thread.
     pub async fn thread_archive(
         &mut self,
@@ -261,6 +385,54 @@ impl AsyncClient {
             .await
     }
 
+    /// Resume a persisted thread so new turns can be started on it.
+    pub async fn thread_resume(
+        &mut self,
+        params: &ThreadResumeParams,
+    ) -> Result<ThreadResumeResponse> {
+        let response: ThreadResumeResponse = self
+            .request(crate::protocol::methods::THREAD_RESUME, params)
+            .await?;
+        if let Some(ledger) = self.usage_ledger.as_mut() {
+            ledger.record_thread_start(&response);
+        }
+        Ok(response)
+    }
+
+    /// Copy a thread's history into a new thread.
+    ///
+    /// The response describes the new thread; the original is left untouched.
+    pub async fn thread_fork(&mut self, params: &ThreadForkParams) -> Result<ThreadForkResponse> {
+        let response: ThreadForkResponse = self
+            .request(crate::protocol::methods::THREAD_FORK, params)
+            .await?;
+        if let Some(ledger) = self.usage_ledger.as_mut() {
+            ledger.record_thread_start(&response);
+        }
+        Ok(response)
+    }
+
+    /// Drop the most recent turns from a thread.
+    pub async fn thread_rollback(
+        &mut self,
+        params: &ThreadRollbackParams,
+    ) -> Result<ThreadRollbackResponse> {
+        self.request(crate::protocol::methods::THREAD_ROLLBACK, params)
+            .await
+    }
+
+    /// List stored threads, a page at a time.
+    pub async fn thread_list(&mut self, params: &ThreadListParams) -> Result<ThreadListResponse> {
+        self.request(crate::protocol::methods::THREAD_LIST, params)
+            .await
+    }
+
+    /// Read a stored thread, optionally with its turns and items.
+    pub async fn thread_read(&mut self, params: &ThreadReadParams) -> Result<ThreadReadResponse> {
+        self.request(crate::protocol::methods::THREAD_READ, params)
+            .await
+    }
+
     /// Perform the `initialize` handshake with the app-server.
     ///
     /// Sends `initialize` with the given params and then sends the
@@ -307,7 +479,8 @@ impl AsyncClient {
     /// Returns buffered messages first (from notifications that arrived during
     /// an [`AsyncClient::request`] call), then reads from the wire.
     ///
-    /// Returns `Ok(None)` when the app-server closes the connection (EOF).
+    /// Returns `Ok(None)` when the app-server closes the connection (EOF),
+    /// or [`Error::ServerCrashed`] if it exited with an error.
     ///
     /// # Typical notification methods
     ///
@@ -319,15 +492,115 @@ impl AsyncClient {
     /// | `item/started` / `item/completed` | Item lifecycle |
     /// | `turn/completed` | Agent finished the turn |
     /// | `error` | Server-side error |
+    ///
+    /// Fails with [`Error::Timeout`] if nothing arrives within the
+    /// [idle timeout](AsyncClient::set_idle_timeout).
     pub async fn next_message(&mut self) -> Result<Option<ServerMessage>> {
+        self.next_message_within(ReadLimits::idle(self.idle_timeout))
+            .await
+    }
+
+    /// Read the next message, failing with [`Error::Timeout`] if nothing arrives within `timeout`.
+    ///
+    /// Overrides the client's [idle timeout](AsyncClient::set_idle_timeout)
+    /// for this call. A timeout leaves the turn running; call again to keep
+    /// waiting.
+    pub async fn next_message_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<ServerMessage>> {
+        self.next_message_within(ReadLimits::idle(Some(timeout)))
+            .await
+    }
+
+    /// Read the next message, stopping early if `cancel` fires.
+    ///
+    /// On cancellation every turn in progress is interrupted with
+    /// `turn/interrupt` and drained up to its `turn/completed`, so the client
+    /// is ready for the next turn, and [`Error::Cancelled`] is returned.
+    pub async fn next_message_cancellable(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<Option<ServerMessage>> {
+        self.next_message_within(ReadLimits {
+            idle: self.idle_timeout,
+            cancel: Some(cancel.clone()),
+            ..ReadLimits::default()
+        })
+        .await
+    }
+
+    /// Read within `limits`, winding down active turns if cancelled.
+    async fn next_message_within(&mut self, limits: ReadLimits) -> Result<Option<ServerMessage>> {
+        match self.next_message_limited(&limits).await {
+            Err(Error::Cancelled) => {
+                self.cancel_turns().await;
+                Err(Error::Cancelled)
+            }
+            other => other,
+        }
+    }
+
+    async fn next_message_limited(&mut self, limits: &ReadLimits) -> Result<Option<ServerMessage>> {
+        if limits.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        let msg = self.read_next_message(limits).await?;
+        if let Some(ServerMessage::Notification { method, params }) = &msg {
+            if method == crate::protocol::methods::TURN_COMPLETED {
+                let thread_id = params
+                    .as_ref()
+                    .and_then(|params| params.get("threadId"))
+                    .and_then(|thread_id| thread_id.as_str());
+                if let Some(thread_id) = thread_id {
+                    self.active_turns.remove(thread_id);
+                }
+            }
+        }
+        if let Some(msg) = &msg {
+            self.account(msg).await?;
+        }
+        Ok(msg)
+    }
+
+    /// Interrupt every turn in progress and drain them up to `turn/completed`.
+    async fn cancel_turns(&mut self) {
+        let limits = ReadLimits::within(Some(CANCEL_TIMEOUT));
+        let threads: Vec<String> = self.active_turns.iter().cloned().collect();
+        for thread_id in threads {
+            debug!("[CLIENT] Cancelled, interrupting turn on {}", thread_id);
+            let params = TurnInterruptParams { thread_id };
+            let interrupted: Result<TurnInterruptResponse> = self
+                .request_within(crate::protocol::methods::TURN_INTERRUPT, &params, &limits)
+                .await;
+            if let Err(e) = interrupted {
+                warn!("[CLIENT] Interrupt after cancellation failed: {}", e);
+                return;
+            }
+        }
+        while !self.active_turns.is_empty() {
+            match self.next_message_limited(&limits).await {
+                Ok(Some(_)) => {}
+                Ok(None) => return,
+                Err(e) => {
+                    warn!("[CLIENT] Failed to drain cancelled turn: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn read_next_message(&mut self, limits: &ReadLimits) -> Result<Option<ServerMessage>> {
         // Drain buffered messages first
-        if let Some(msg) = self.buffered.pop_front() {
-            return Ok(Some(msg));
+        while let Some(msg) = self.buffered.pop_front() {
+            if let Some(msg) = self.auto_approve(msg).await? {
+                return Ok(Some(msg));
+            }
         }
 
         // Read from the wire
         loop {
-            let msg = match self.read_message_opt().await? {
+            let msg = match self.read_message_opt(limits).await? {
                 Some(m) => m,
                 None => return Ok(None),
             };
@@ -340,11 +613,14 @@ impl AsyncClient {
                     }));
                 }
                 JsonRpcMessage::Request(req) => {
-                    return Ok(Some(ServerMessage::Request {
+                    let msg = ServerMessage::Request {
                         id: req.id,
                         method: req.method,
                         params: req.params,
-                    }));
+                    };
+                    if let Some(msg) = self.auto_approve(msg).await? {
+                        return Ok(Some(msg));
+                    }
                 }
                 // Unexpected responses without a pending request
                 JsonRpcMessage::Response(resp) => {
@@ -363,42 +639,199 @@ impl AsyncClient {
         }
     }
 
+    /// Answer approval requests automatically with `handler`.
+    ///
+    /// `item/commandExecution/requestApproval` and `item/fileChange/requestApproval`
+    /// requests are then decided by the handler and responded to by the client,
+    /// including those that arrive while waiting for an RPC response; they are
+    /// no longer returned by [`AsyncClient::next_message`]. Replaces any handler
+    /// already set. See [`crate::approval`].
+    pub fn set_approval_handler<H: ApprovalHandler + 'static>(&mut self, handler: H) {
+        self.approval_handler = Some(Box::new(handler));
+    }
+
+    /// Remove the approval handler, returning approval requests to the caller.
+    pub fn clear_approval_handler(&mut self) -> Option<Box<dyn ApprovalHandler>> {
+        self.approval_handler.take()
+    }
+
+    /// Aggregate token usage from the notifications returned by [`AsyncClient::next_message`].
+    ///
+    /// Thread models are learned from [`AsyncClient::thread_start`] responses.
+    /// When a budget marked with [`Budget::interrupt`](crate::accounting::Budget::interrupt)
+    /// is exceeded, the client sends `turn/interrupt` for the thread that
+    /// crossed it. Replaces any ledger already set. See [`crate::accounting`].
+    pub fn set_usage_ledger(&mut self, ledger: UsageLedger) {
+        self.usage_ledger = Some(ledger);
+    }
+
+    /// The usage ledger, if one is set.
+    pub fn usage_ledger(&self) -> Option<&UsageLedger> {
+        self.usage_ledger.as_ref()
+    }
+
+    /// Mutable access to the usage ledger, e.g. to add budgets.
+    pub fn usage_ledger_mut(&mut self) -> Option<&mut UsageLedger> {
+        self.usage_ledger.as_mut()
+    }
+
+    /// Detach the usage ledger, returning it.
+    pub fn take_usage_ledger(&mut self) -> Option<UsageLedger> {
+        self.usage_ledger.take()
+    }
+
+    /// Read the next incoming server message, decoded by method.
+    ///
+    /// Like [`AsyncClient::next_message`], but yields a [`TypedServerMessage`] so
+    /// callers can match on [`ServerNotification`] and
+    /// [`ServerRequest`] variants instead of method strings.
+    pub async fn next_typed_message(&mut self) -> Result<Option<TypedServerMessage>> {
+        Ok(self.next_message().await?.map(ServerMessage::into_typed))
+    }
+
     /// Return an async event stream over [`ServerMessage`]s.
     ///
     /// Wraps [`AsyncClient::next_message`] in a stream-like API. Call
     /// [`EventStream::next`] in a loop, or [`EventStream::collect`] to
     /// gather all messages until EOF.
     pub fn events(&mut self) -> EventStream<'_> {
-        EventStream { client: self }
+        let limits = ReadLimits::idle(self.idle_timeout);
+        EventStream {
+            client: self,
+            limits,
+        }
+    }
+
+    /// Fail [`AsyncClient::next_message`] with [`Error::Timeout`] if nothing arrives within `timeout`.
+    ///
+    /// Also applies to [`EventStream`]s. The timer restarts with every
+    /// message, so long turns are fine as long as the server keeps talking.
+    /// A timeout leaves the turn running — use [`AsyncClient::turn_interrupt`]
+    /// or [`AsyncClient::close`] to stop a stuck server. `None` (the default)
+    /// waits forever.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    /// The idle timeout for [`AsyncClient::next_message`].
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Fail [`AsyncClient::request`] with [`Error::Timeout`] if no response arrives within `timeout`.
+    ///
+    /// Covers every RPC, including `initialize`, `thread/start` and
+    /// `turn/start`. `None` (the default) waits forever.
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
+    /// The timeout for RPCs.
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
     }
 
     /// Take the stderr reader (can only be called once).
     ///
     /// Useful for logging or diagnostics. Returns `None` on subsequent calls.
-    pub fn take_stderr(&mut self) -> Option<BufReader<ChildStderr>> {
-        self.stderr.take()
+    /// The app-server blocks once the pipe fills up, so the caller must keep
+    /// reading it — or use [`capture_stderr`](Self::capture_stderr) instead.
+    pub fn take_stderr(&mut self) -> Option<BufReader<TransportReader>> {
+        self.transport.take_stderr().map(BufReader::new)
+    }
+
+    /// Drain stderr on a background task, keeping recent lines.
+    ///
+    /// Crash reports returned by [`next_message`](Self::next_message) and
+    /// [`close`](Self::close) then take their stderr tail from the buffer.
+    /// Returns `None` if the transport has no stderr or it was already taken.
+    /// See [`crate::stderr`].
+    pub fn capture_stderr(&mut self, pump: StderrPump) -> Option<StderrBuffer> {
+        let buffer = pump.spawn(self.transport.take_stderr()?);
+        self.stderr = Some(buffer.clone());
+        Some(buffer)
     }
 
-    /// Get the process ID.
+    /// The captured stderr buffer, if [`capture_stderr`](Self::capture_stderr) was called.
+    pub fn stderr(&self) -> Option<&StderrBuffer> {
+        self.stderr.as_ref()
+    }
+
+    /// Fill an exit report's stderr tail from the pump, if one is attached.
+    async fn attach_stderr(&mut self, report: &mut ExitReport) {
+        let Some(buffer) = &self.stderr else {
+            return;
+        };
+        if report.stderr_tail.is_empty() {
+            // The pipe closes with the process; give the pump a moment to catch up.
+            let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, buffer.closed()).await;
+            report.stderr_tail = buffer.tail(STDERR_TAIL_LINES);
+        }
+    }
+
+    /// Get the process ID, if the transport is a local process.
     pub fn pid(&self) -> Option<u32> {
-        self.child.id()
+        self.transport.pid()
     }
 
-    /// Check if the child process is still running.
+    /// Check if the app-server is still running.
     pub fn is_alive(&mut self) -> bool {
-        self.child.try_wait().ok().flatten().is_none()
+        self.transport.is_alive()
+    }
+
+    /// Close stdin and wait for the app-server to exit, killing it after `grace`.
+    ///
+    /// Closing stdin lets the app-server finish up and exit on its own.
+    /// Consumes the client. Returns how the process exited, or `None` for
+    /// transports that cannot tell (such as
+    /// [`StreamTransport`](crate::transport::StreamTransport)).
+    pub async fn close(mut self, grace: Duration) -> Result<Option<ExitReport>> {
+        debug!("[CLIENT] Closing");
+        if let Err(e) = self.writer.shutdown().await {
+            debug!("[CLIENT] Failed to shut down stdin: {}", e);
+        }
+        // Dropping the writer closes the app-server's stdin.
+        self.writer = BufWriter::new(Box::new(tokio::io::sink()));
+        let mut report = self.transport.close(grace).await.map_err(Error::Io)?;
+        if let Some(report) = &mut report {
+            self.attach_stderr(report).await;
+            debug!("[CLIENT] App-server exited: {}", report);
+        }
+        Ok(report)
     }
 
-    /// Shut down the app-server process.
+    /// Kill the app-server process.
     ///
     /// Consumes the client. If you don't call this explicitly, the
     /// [`Drop`] implementation will kill the process automatically.
+    /// Use [`close`](Self::close) to let it exit on its own first.
     pub async fn shutdown(mut self) -> Result<()> {
         debug!("[CLIENT] Shutting down");
-        self.child.kill().await.map_err(Error::Io)?;
+        self.transport.shutdown().await.map_err(Error::Io)?;
         Ok(())
     }
 
+    /// Record every line sent and received from now on.
+    ///
+    /// Replaces any recorder already attached. See [`crate::transcript`].
+    pub fn record_transcript(&mut self, recorder: TranscriptRecorder) {
+        if let Err(e) = recorder.start(TRANSCRIPT_PROVIDER, env!("CARGO_PKG_VERSION")) {
+            warn!("[CLIENT] Failed to write transcript header: {}", e);
+        }
+        self.recorder = Some(recorder);
+    }
+
+    /// Stop recording and return the recorder, if one was attached.
+    pub fn stop_recording(&mut self) -> Option<TranscriptRecorder> {
+        let recorder = self.recorder.take();
+        if let Some(recorder) = &recorder {
+            if let Err(e) = recorder.flush() {
+                warn!("[CLIENT] Failed to flush transcript: {}", e);
+            }
+        }
+        recorder
+    }
+
     // -- internal --
 
     async fn send_notification(&mut self, method: &str) -> Result<()> {
@@ -409,9 +842,67 @@ impl AsyncClient {
         self.send_raw(&notif).await
     }
 
+    /// Answer `msg` with the approval handler if it is an approval request.
+    ///
+    /// Returns the message back when it was not handled.
+    async fn auto_approve(&mut self, msg: ServerMessage) -> Result<Option<ServerMessage>> {
+        let handler = match self.approval_handler.as_mut() {
+            Some(handler) => handler,
+            None => return Ok(Some(msg)),
+        };
+        match crate::approval::dispatch(handler.as_mut(), msg) {
+            Ok((id, response)) => {
+                debug!(
+                    "[CLIENT] Approval handler answered request {}: {}",
+                    id, response
+                );
+                self.respond(id, &response).await?;
+                Ok(None)
+            }
+            Err(msg) => Ok(Some(msg)),
+        }
+    }
+
+    /// Feed a token usage notification to the ledger, interrupting the turn if a budget asks to.
+    async fn account(&mut self, msg: &ServerMessage) -> Result<()> {
+        let Some(ledger) = self.usage_ledger.as_mut() else {
+            return Ok(());
+        };
+        let ServerMessage::Notification { method, params } = msg else {
+            return Ok(());
+        };
+        if method != crate::protocol::methods::THREAD_TOKEN_USAGE_UPDATED {
+            return Ok(());
+        }
+        let notification = ServerNotification::from_parts(method.clone(), params.clone());
+        let exceeded = ledger.record_notification(&notification);
+        for budget in &exceeded {
+            warn!("[CLIENT] {}", budget);
+        }
+        let interrupt_thread = exceeded
+            .iter()
+            .filter(|budget| budget.interrupt)
+            .find_map(|budget| budget.thread_id.clone());
+        if let Some(thread_id) = interrupt_thread {
+            match self
+                .turn_interrupt(&TurnInterruptParams { thread_id })
+                .await
+            {
+                Err(Error::JsonRpc { message, .. }) => {
+                    warn!("[CLIENT] Interrupt was rejected: {}", message)
+                }
+                other => {
+                    other?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     async fn send_raw<T: Serialize>(&mut self, msg: &T) -> Result<()> {
         let json = serde_json::to_string(msg).map_err(Error::Json)?;
         debug!("[CLIENT] Sending: {}", json);
+        self.record(Direction::Sent, &json);
         self.writer
             .write_all(json.as_bytes())
             .await
@@ -421,28 +912,58 @@ impl AsyncClient {
         Ok(())
     }
 
-    async fn read_message(&mut self) -> Result<JsonRpcMessage> {
-        self.read_message_opt().await?.ok_or(Error::ServerClosed)
+    fn record(&self, direction: Direction, line: &str) {
+        if let Some(recorder) = &self.recorder {
+            if let Err(e) = recorder.record(direction, line) {
+                warn!("[CLIENT] Failed to record transcript line: {}", e);
+            }
+        }
+    }
+
+    async fn read_message(&mut self, limits: &ReadLimits) -> Result<JsonRpcMessage> {
+        self.read_message_opt(limits)
+            .await?
+            .ok_or(Error::ServerClosed)
     }
 
-    async fn read_message_opt(&mut self) -> Result<Option<JsonRpcMessage>> {
-        let mut line = String::new();
+    /// Read the next message, attaching captured stderr to failures.
+    async fn read_message_opt(&mut self, limits: &ReadLimits) -> Result<Option<JsonRpcMessage>> {
+        let result = self.read_message_line(limits).await;
+        result.map_err(|error| match &self.stderr {
+            Some(buffer) => error.with_stderr(buffer.tail(STDERR_TAIL_LINES)),
+            None => error,
+        })
+    }
 
+    /// Read and parse the next message line from the transport.
+    async fn read_message_line(&mut self, limits: &ReadLimits) -> Result<Option<JsonRpcMessage>> {
         loop {
-            line.clear();
-            let bytes_read = self.reader.read_line(&mut line).await.map_err(Error::Io)?;
+            // `read_until` keeps partial lines in `line_buf` if the wait is cut short.
+            let bytes_read = limits
+                .guard(self.reader.read_until(b'\n', &mut self.line_buf))
+                .await?;
 
-            if bytes_read == 0 {
+            if bytes_read == 0 && self.line_buf.is_empty() {
                 debug!("[CLIENT] Stream closed (EOF)");
-                return Ok(None);
+                return match self.transport.exit_report(EXIT_WAIT).await {
+                    Some(mut report) if !report.success() => {
+                        self.attach_stderr(&mut report).await;
+                        error!("[CLIENT] App-server crashed: {}", report);
+                        Err(Error::ServerCrashed(report))
+                    }
+                    _ => Ok(None),
+                };
             }
 
+            let line = String::from_utf8(std::mem::take(&mut self.line_buf))
+                .map_err(|e| Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?;
             let trimmed = line.trim();
             if trimmed.is_empty() {
                 continue;
             }
 
             debug!("[CLIENT] Received: {}", trimmed);
+            self.record(Direction::Received, trimmed);
 
             match serde_json::from_str::<JsonRpcMessage>(trimmed) {
                 Ok(msg) => return Ok(Some(msg)),
@@ -460,10 +981,22 @@ impl AsyncClient {
     }
 }
 
+/// The [`InitializeParams`] sent by [`AsyncClient::start`].
+fn default_initialize_params() -> InitializeParams {
+    InitializeParams {
+        client_info: ClientInfo {
+            name: "codex-codes".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            title: None,
+        },
+        capabilities: None,
+    }
+}
+
 impl Drop for AsyncClient {
     fn drop(&mut self) {
         if self.is_alive() {
-            if let Err(e) = self.child.start_kill() {
+            if let Err(e) = self.transport.start_kill() {
                 error!("Failed to kill app-server process on drop: {}", e);
             }
         }
@@ -473,18 +1006,44 @@ impl Drop for AsyncClient {
 /// Async stream of [`ServerMessage`]s from an [`AsyncClient`].
 pub struct EventStream<'a> {
     client: &'a mut AsyncClient,
+    limits: ReadLimits,
 }
 
 impl EventStream<'_> {
+    /// Fail with [`Error::Timeout`] if no message arrives within `timeout`.
+    ///
+    /// Overrides the client's [idle timeout](AsyncClient::set_idle_timeout)
+    /// for this stream.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.limits.idle = Some(timeout);
+        self
+    }
+
+    /// Stop when `cancel` fires.
+    ///
+    /// Turns in progress are interrupted and drained, and the stream yields
+    /// [`Error::Cancelled`].
+    pub fn cancel_on(mut self, cancel: CancellationToken) -> Self {
+        self.limits.cancel = Some(cancel);
+        self
+    }
+
     /// Get the next server message.
     pub async fn next(&mut self) -> Option<Result<ServerMessage>> {
-        match self.client.next_message().await {
+        match self.client.next_message_within(self.limits.clone()).await {
             Ok(Some(msg)) => Some(Ok(msg)),
             Ok(None) => None,
             Err(e) => Some(Err(e)),
         }
     }
 
+    /// Get the next server message, decoded by method.
+    pub async fn next_typed(&mut self) -> Option<Result<TypedServerMessage>> {
+        self.next()
+            .await
+            .map(|result| result.map(ServerMessage::into_typed))
+    }
+
     /// Collect all remaining messages.
     pub async fn collect(mut self) -> Result<Vec<ServerMessage>> {
         let mut msgs = Vec::new();
@@ -495,6 +1054,124 @@ impl EventStream<'_> {
     }
 }
 
+/// Progress of one turn, from [`AsyncClient::stream_turn`].
+pub struct TurnStream<'a> {
+    client: &'a mut AsyncClient,
+    aggregator: TurnAggregator,
+    /// Requests for other threads, handed back to the client on drop.
+    deferred: Vec<ServerMessage>,
+}
+
+impl TurnStream<'_> {
+    /// Get the next update, or `None` once the turn has completed.
+    ///
+    /// Yields [`Error::ServerClosed`] if the connection closes mid-turn.
+    /// Requests for other threads are held back rather than yielded.
+    pub async fn next(&mut self) -> Option<Result<TurnUpdate>> {
+        while !self.aggregator.is_complete() {
+            let msg = match self.client.next_message().await {
+                Ok(Some(msg)) => msg,
+                Ok(None) => return Some(Err(Error::ServerClosed)),
+                Err(e) => return Some(Err(e)),
+            };
+            if request_thread(&msg).is_some_and(|thread| thread != self.aggregator.thread_id()) {
+                debug!("[CLIENT] Holding back request for another thread");
+                self.deferred.push(msg);
+                continue;
+            }
+            match msg.into_typed() {
+                TypedServerMessage::Notification(notification) => {
+                    if let Some(update) = self.aggregator.observe(&notification) {
+                        return Some(Ok(update));
+                    }
+                }
+                TypedServerMessage::Request(request) => {
+                    return Some(Ok(TurnUpdate::Request(request)));
+                }
+            }
+        }
+        None
+    }
+
+    /// The items and usage seen so far.
+    pub fn aggregator(&self) -> &TurnAggregator {
+        &self.aggregator
+    }
+
+    /// Answer a [`TurnUpdate::Request`]. See [`AsyncClient::respond`].
+    pub async fn respond<R: Serialize>(&mut self, id: RequestId, result: &R) -> Result<()> {
+        self.client.respond(id, result).await
+    }
+
+    /// Ask the server to stop the turn; keep reading to see it complete.
+    pub async fn interrupt(&mut self) -> Result<()> {
+        let params = TurnInterruptParams {
+            thread_id: self.aggregator.thread_id().to_string(),
+        };
+        self.client.turn_interrupt(&params).await?;
+        Ok(())
+    }
+
+    /// Read the rest of the turn and return its outcome.
+    ///
+    /// Requests for this turn's thread still unanswered are declined.
+    pub async fn finish(mut self) -> Result<TurnOutcome> {
+        while let Some(update) = self.next().await {
+            if let TurnUpdate::Request(request) = update? {
+                self.decline(request).await?;
+            }
+        }
+        let thread_id = self.aggregator.thread_id().to_string();
+        Ok(
+            std::mem::replace(&mut self.aggregator, TurnAggregator::new(thread_id))
+                .into_outcome()
+                .expect("stream ends only once the turn completes"),
+        )
+    }
+
+    async fn decline(&mut self, request: ServerRequest) -> Result<()> {
+        match request {
+            ServerRequest::CommandExecutionApproval { id, .. } => {
+                debug!("[CLIENT] Declining unanswered command approval {}", id);
+                let response = CommandExecutionApprovalResponse {
+                    decision: CommandApprovalDecision::Decline,
+                };
+                self.client.respond(id, &response).await
+            }
+            ServerRequest::FileChangeApproval { id, .. } => {
+                debug!("[CLIENT] Declining unanswered file change approval {}", id);
+                let response = FileChangeApprovalResponse {
+                    decision: FileChangeApprovalDecision::Decline,
+                };
+                self.client.respond(id, &response).await
+            }
+            ServerRequest::Unknown { id, method, .. } => {
+                warn!("[CLIENT] Rejecting unsupported request {} ({})", id, method);
+                self.client
+                    .respond_error(id, METHOD_NOT_FOUND, &format!("unsupported: {}", method))
+                    .await
+            }
+        }
+    }
+}
+
+impl Drop for TurnStream<'_> {
+    fn drop(&mut self) {
+        // Ahead of anything buffered since, in the order they arrived
+        for msg in self.deferred.drain(..).rev() {
+            self.client.buffered.push_front(msg);
+        }
+    }
+}
+
+/// The thread a server request names in its params, if any.
+fn request_thread(msg: &ServerMessage) -> Option<&str> {
+    match msg {
+        ServerMessage::Request { params, .. } => params.as_ref()?.get("threadId")?.as_str(),
+        ServerMessage::Notification { .. } => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -503,4 +1180,684 @@ mod tests {
     fn test_buffer_size() {
         assert_eq!(STDOUT_BUFFER_SIZE, 10 * 1024 * 1024);
     }
+
+    #[tokio::test]
+    async fn test_start_with_stream_transport() {
+        use crate::transport::StreamTransport;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+
+            let init = lines.next_line().await.unwrap().unwrap();
+            assert!(init.contains(r#""method":"initialize""#), "{}", init);
+            writer
+                .write_all(b"{\"id\":1,\"result\":{\"userAgent\":\"fake\"}}\n")
+                .await
+                .unwrap();
+
+            let initialized = lines.next_line().await.unwrap().unwrap();
+            assert!(initialized.contains(r#""method":"initialized""#));
+
+            let start = lines.next_line().await.unwrap().unwrap();
+            assert!(start.contains(r#""method":"thread/start""#));
+            writer
+                .write_all(b"{\"method\":\"thread/started\",\"params\":{}}\n")
+                .await
+                .unwrap();
+            writer
+                .write_all(b"{\"id\":2,\"result\":{\"thread\":{\"id\":\"th_1\"}}}\n")
+                .await
+                .unwrap();
+        });
+
+        let mut client = AsyncClient::start_with_transport(StreamTransport::from_stream(client_io))
+            .await
+            .unwrap();
+        assert_eq!(client.pid(), None);
+        assert!(client.is_alive());
+
+        let thread = client
+            .thread_start(&ThreadStartParams::default())
+            .await
+            .unwrap();
+        assert_eq!(thread.thread_id(), "th_1");
+
+        // The notification that arrived before the response was buffered
+        match client.next_message().await.unwrap() {
+            Some(ServerMessage::Notification { method, .. }) => {
+                assert_eq!(method, "thread/started")
+            }
+            other => panic!("expected buffered notification, got {:?}", other),
+        }
+
+        server.await.unwrap();
+        assert!(client.next_message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_thread_history_and_steer_requests() {
+        use crate::transport::StreamTransport;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+            let mut methods = Vec::new();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let method = request["method"].as_str().unwrap().to_string();
+                let result = match method.as_str() {
+                    "thread/resume" | "thread/fork" => {
+                        serde_json::json!({"thread": {"id": "th_2"}, "model": "gpt-5-codex"})
+                    }
+                    "thread/list" => serde_json::json!({
+                        "data": [{"id": "th_2", "preview": "fix it"}],
+                        "nextCursor": null
+                    }),
+                    "thread/read" => {
+                        assert_eq!(request["params"]["includeTurns"], true);
+                        serde_json::json!({"thread": {"id": "th_2", "turns": [{
+                            "id": "t_1",
+                            "status": "completed",
+                            "items": [{"type": "agentMessage", "id": "m_1", "text": "done"}]
+                        }]}})
+                    }
+                    "turn/steer" => {
+                        assert_eq!(request["params"]["input"][0]["text"], "also run tests");
+                        serde_json::json!({"turnId": "t_2"})
+                    }
+                    other => panic!("unexpected method {}", other),
+                };
+                let response = serde_json::json!({"id": request["id"], "result": result});
+                writer
+                    .write_all(format!("{}\n", response).as_bytes())
+                    .await
+                    .unwrap();
+                methods.push(method);
+            }
+            methods
+        });
+
+        let mut client =
+            AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        let resumed = client
+            .thread_resume(&ThreadResumeParams::new("th_2"))
+            .await
+            .unwrap();
+        assert_eq!(resumed.thread_id(), "th_2");
+        let forked = client
+            .thread_fork(&ThreadForkParams::new("th_2"))
+            .await
+            .unwrap();
+        assert_eq!(forked.model.as_deref(), Some("gpt-5-codex"));
+
+        let page = client
+            .thread_list(&ThreadListParams::default())
+            .await
+            .unwrap();
+        assert_eq!(page.data[0].id, "th_2");
+        assert_eq!(page.next_cursor, None);
+
+        let read = client
+            .thread_read(&ThreadReadParams {
+                thread_id: "th_2".to_string(),
+                include_turns: true,
+            })
+            .await
+            .unwrap();
+        let items: Vec<_> = read.thread.items().collect();
+        assert!(matches!(items[..], [crate::ThreadItem::AgentMessage(ref m)] if m.text == "done"));
+
+        let steered = client
+            .turn_steer(&TurnSteerParams {
+                thread_id: "th_2".to_string(),
+                input: vec![crate::protocol::UserInput::Text {
+                    text: "also run tests".to_string(),
+                }],
+                expected_turn_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(steered.turn_id.as_deref(), Some("t_2"));
+
+        drop(client);
+        assert_eq!(
+            server.await.unwrap(),
+            vec![
+                "thread/resume",
+                "thread/fork",
+                "thread/list",
+                "thread/read",
+                "turn/steer"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_approval_handler_answers_requests() {
+        use crate::approval::DeclineAll;
+        use crate::transport::StreamTransport;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+
+            writer
+                .write_all(
+                    concat!(
+                        r#"{"id":"a1","method":"item/commandExecution/requestApproval","params":"#,
+                        r#"{"threadId":"th_1","turnId":"t_1","callId":"c1","command":"rm -rf /","cwd":"/"}}"#,
+                        "\n"
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            let response = lines.next_line().await.unwrap().unwrap();
+            let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+            assert_eq!(response["id"], "a1");
+            assert_eq!(response["result"]["decision"], "decline");
+
+            writer
+                .write_all(b"{\"method\":\"turn/started\",\"params\":{\"threadId\":\"th_1\",\"turnId\":\"t_1\"}}\n")
+                .await
+                .unwrap();
+        });
+
+        let mut client =
+            AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        client.set_approval_handler(DeclineAll);
+
+        // The approval request is answered internally; the next message is the notification
+        match client.next_message().await.unwrap() {
+            Some(ServerMessage::Notification { method, .. }) => assert_eq!(method, "turn/started"),
+            other => panic!("expected notification, got {:?}", other),
+        }
+
+        server.await.unwrap();
+        assert!(client.clear_approval_handler().is_some());
+        assert!(client.next_message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_turn_aggregates_items() {
+        use crate::transport::StreamTransport;
+        use crate::ThreadItem;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+
+            for _ in 0..2 {
+                let request = lines.next_line().await.unwrap().unwrap();
+                let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+                assert_eq!(request["method"], "turn/start");
+                let messages = [
+                    serde_json::json!({"id": request["id"], "result": {}}),
+                    serde_json::json!({"method": "turn/completed", "params": {
+                        "threadId": "th_1", "turnId": "t_0",
+                        "turn": {"id": "t_0", "status": "interrupted"}
+                    }}),
+                    serde_json::json!({"method": "turn/started", "params": {
+                        "threadId": "th_1", "turnId": "t_1"
+                    }}),
+                    serde_json::json!({"method": "item/agentMessage/delta", "params": {
+                        "threadId": "th_1", "itemId": "msg_1", "delta": "Hel"
+                    }}),
+                    serde_json::json!({"method": "item/agentMessage/delta", "params": {
+                        "threadId": "th_1", "itemId": "msg_1", "delta": "lo"
+                    }}),
+                    serde_json::json!({"id": "a1", "method": "item/fileChange/requestApproval", "params": {
+                        "threadId": "th_1", "turnId": "t_1", "callId": "c1", "changes": {}
+                    }}),
+                ];
+                for line in messages {
+                    writer
+                        .write_all(format!("{}\n", line).as_bytes())
+                        .await
+                        .unwrap();
+                }
+
+                let response = lines.next_line().await.unwrap().unwrap();
+                let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+                assert_eq!(response["id"], "a1");
+                assert_eq!(response["result"]["decision"], "decline");
+
+                let completed = serde_json::json!({"method": "turn/completed", "params": {
+                    "threadId": "th_1", "turnId": "t_1",
+                    "turn": {"id": "t_1", "status": "completed", "items": [
+                        {"type": "agentMessage", "id": "msg_1", "text": "Hello"}
+                    ]}
+                }});
+                writer
+                    .write_all(format!("{}\n", completed).as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let mut client =
+            AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+
+        // Streaming: a stale turn/completed is skipped, deltas arrive as
+        // snapshots, the approval request is handed back
+        let mut stream = client
+            .stream_turn(&TurnStartParams::text("th_1", "Hi"))
+            .await
+            .unwrap();
+        match stream.next().await.unwrap().unwrap() {
+            TurnUpdate::TurnStarted { turn_id } => assert_eq!(turn_id, "t_1"),
+            other => panic!("expected turn start, got {:?}", other),
+        }
+        match stream.next().await.unwrap().unwrap() {
+            TurnUpdate::ItemUpdated {
+                item: ThreadItem::AgentMessage(message),
+                delta,
+            } => assert_eq!((message.text.as_str(), delta.as_str()), ("Hel", "Hel")),
+            other => panic!("expected agent message delta, got {:?}", other),
+        }
+        match stream.next().await.unwrap().unwrap() {
+            TurnUpdate::ItemUpdated { item, .. } => {
+                assert!(matches!(item, ThreadItem::AgentMessage(m) if m.text == "Hello"))
+            }
+            other => panic!("expected agent message delta, got {:?}", other),
+        }
+        match stream.next().await.unwrap().unwrap() {
+            TurnUpdate::Request(ServerRequest::FileChangeApproval { id, .. }) => {
+                let response = FileChangeApprovalResponse {
+                    decision: FileChangeApprovalDecision::Decline,
+                };
+                stream.respond(id, &response).await.unwrap();
+            }
+            other => panic!("expected approval request, got {:?}", other),
+        }
+        assert!(stream.next().await.is_none());
+        let outcome = stream.finish().await.unwrap();
+        assert_eq!(outcome.final_message(), Some("Hello"));
+
+        // Run to completion: the unanswered approval request is declined
+        let outcome = client
+            .run_turn("th_1", vec![UserInput::Text { text: "Hi".into() }])
+            .await
+            .unwrap();
+        assert!(outcome.success());
+        assert_eq!(outcome.turn.id, "t_1");
+        assert_eq!(outcome.items.len(), 1);
+        assert_eq!(outcome.agent_text(), "Hello");
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_turn_leaves_other_threads_requests_unanswered() {
+        use crate::transport::StreamTransport;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+
+            let request = lines.next_line().await.unwrap().unwrap();
+            let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "turn/start");
+            let messages = [
+                serde_json::json!({"id": request["id"], "result": {}}),
+                serde_json::json!({"method": "turn/started", "params": {
+                    "threadId": "th_1", "turnId": "t_1"
+                }}),
+                serde_json::json!({"id": "b1", "method": "item/commandExecution/requestApproval", "params": {
+                    "threadId": "th_2", "turnId": "t_9", "callId": "c9", "command": "ls", "cwd": "/"
+                }}),
+                serde_json::json!({"method": "item/agentMessage/delta", "params": {
+                    "threadId": "th_2", "itemId": "msg_9", "delta": "other"
+                }}),
+                serde_json::json!({"id": "a1", "method": "item/fileChange/requestApproval", "params": {
+                    "threadId": "th_1", "turnId": "t_1", "callId": "c1", "changes": {}
+                }}),
+            ];
+            for line in messages {
+                writer
+                    .write_all(format!("{}\n", line).as_bytes())
+                    .await
+                    .unwrap();
+            }
+
+            // Only this thread's request is declined
+            let response = lines.next_line().await.unwrap().unwrap();
+            let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+            assert_eq!(response["id"], "a1");
+            assert_eq!(response["result"]["decision"], "decline");
+
+            let completed = serde_json::json!({"method": "turn/completed", "params": {
+                "threadId": "th_1", "turnId": "t_1",
+                "turn": {"id": "t_1", "status": "completed"}
+            }});
+            writer
+                .write_all(format!("{}\n", completed).as_bytes())
+                .await
+                .unwrap();
+            lines.next_line().await.unwrap()
+        });
+
+        let mut client =
+            AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        let outcome = client
+            .run_turn("th_1", vec![UserInput::Text { text: "Hi".into() }])
+            .await
+            .unwrap();
+        assert!(outcome.success());
+        assert_eq!(outcome.agent_text(), "");
+
+        // The other thread's request is handed back to the client
+        match client.next_typed_message().await.unwrap() {
+            Some(TypedServerMessage::Request(ServerRequest::CommandExecutionApproval {
+                id,
+                params,
+            })) => {
+                assert_eq!(id, RequestId::String("b1".to_string()));
+                assert_eq!(params.thread_id, "th_2");
+            }
+            other => panic!("expected the held-back approval, got {:?}", other),
+        }
+
+        drop(client);
+        assert_eq!(server.await.unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    fn sh(script: &str) -> AsyncClient {
+        let child = tokio::process::Command::new("sh")
+            .args(["-c", script])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        AsyncClient::from_transport(ProcessTransport::new(child)).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_crash_is_reported_with_stderr_tail() {
+        let mut client = sh("echo 'thread main panicked' >&2; exit 101");
+        match client.next_message().await {
+            Err(Error::ServerCrashed(report)) => {
+                assert_eq!(report.code, Some(101));
+                assert_eq!(report.stderr_tail, vec!["thread main panicked"]);
+            }
+            other => panic!("expected ServerCrashed, got {:?}", other),
+        }
+
+        let mut client = sh("exit 0");
+        assert!(client.next_message().await.unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_captured_stderr_is_attached_to_crash() {
+        use crate::stderr::{Diagnostic, StderrPump};
+
+        let mut client = sh("echo 'Not logged in. Run codex login' >&2; exit 1");
+        let buffer = client.capture_stderr(StderrPump::new()).unwrap();
+        assert!(client.take_stderr().is_none());
+
+        match client.next_message().await {
+            Err(Error::ServerCrashed(report)) => {
+                assert_eq!(report.stderr_tail, vec!["Not logged in. Run codex login"]);
+                assert!(matches!(
+                    report.diagnostics()[..],
+                    [Diagnostic::AuthFailure { .. }]
+                ));
+            }
+            other => panic!("expected ServerCrashed, got {:?}", other),
+        }
+        assert_eq!(buffer.total_lines(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_captured_stderr_is_attached_to_parse_errors() {
+        use crate::stderr::StderrPump;
+
+        let mut client = sh("echo 'ERROR bad config' >&2; sleep 0.2; echo 'not json'; sleep 5");
+        client.capture_stderr(StderrPump::new()).unwrap();
+
+        let err = client.next_message().await.unwrap_err();
+        assert!(matches!(err.root(), Error::Deserialization(_)), "{:?}", err);
+        assert_eq!(err.stderr_tail(), ["ERROR bad config"]);
+        assert!(err.to_string().ends_with("(stderr: ERROR bad config)"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_close_waits_for_stdin_eof_then_kills() {
+        let report = sh("cat > /dev/null")
+            .close(Duration::from_secs(5))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(report.success());
+        assert!(!report.killed);
+
+        let report = sh("sleep 30")
+            .close(Duration::from_millis(100))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(report.killed);
+        assert_eq!(report.signal, Some(9));
+    }
+
+    #[tokio::test]
+    async fn test_request_and_idle_timeouts() {
+        use crate::transport::StreamTransport;
+
+        // The server reads requests but never answers
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+            // Half a line, so the idle timeout hits mid-message
+            writer.write_all(br#"{"method":"turn/sta"#).await.unwrap();
+            let request = lines.next_line().await.unwrap().unwrap();
+            assert!(request.contains("thread/start"));
+            writer
+                .write_all(b"rted\",\"params\":{\"threadId\":\"th_1\"}}\n")
+                .await
+                .unwrap();
+            lines.next_line().await.unwrap();
+        });
+
+        let mut client =
+            AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        client.set_request_timeout(Some(Duration::from_millis(100)));
+        client.set_idle_timeout(Some(Duration::from_millis(100)));
+
+        assert!(matches!(client.next_message().await, Err(Error::Timeout)));
+        let err = client
+            .thread_start(&ThreadStartParams::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Timeout), "{:?}", err);
+
+        // The partial line read before the timeout was kept
+        match client.next_message_timeout(Duration::from_secs(5)).await {
+            Ok(Some(ServerMessage::Notification { method, .. })) => {
+                assert_eq!(method, "turn/started")
+            }
+            other => panic!("expected notification, got {:?}", other),
+        }
+        drop(client);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_interrupts_and_drains_turn() {
+        use crate::transport::StreamTransport;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+
+            let request = lines.next_line().await.unwrap().unwrap();
+            let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "turn/start");
+            writer
+                .write_all(
+                    format!(
+                        "{{\"id\":{},\"result\":{{\"turn\":{{\"id\":\"t_1\",\"items\":[],\"status\":\"inProgress\"}}}}}}\n",
+                        request["id"]
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+
+            let request = lines.next_line().await.unwrap().unwrap();
+            let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "turn/interrupt");
+            assert_eq!(request["params"]["threadId"], "th_1");
+            writer
+                .write_all(format!("{{\"id\":{},\"result\":{{}}}}\n", request["id"]).as_bytes())
+                .await
+                .unwrap();
+            writer
+                .write_all(
+                    concat!(
+                        r#"{"method":"turn/completed","params":{"threadId":"th_1","turnId":"t_1","#,
+                        r#""turn":{"id":"t_1","items":[],"status":"interrupted"}}}"#,
+                        "\n"
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+        });
+
+        let mut client =
+            AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        client
+            .turn_start(&TurnStartParams {
+                thread_id: "th_1".to_string(),
+                input: vec![],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+        assert!(matches!(
+            client.next_message_cancellable(&cancel).await,
+            Err(Error::Cancelled)
+        ));
+
+        // The turn/completed notification was consumed while draining
+        server.await.unwrap();
+        assert!(client.active_turns.is_empty());
+        assert!(client.next_message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_usage_budget_interrupts_turn() {
+        use crate::accounting::{Budget, UsageLedger};
+        use crate::transport::StreamTransport;
+
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(server_io);
+            let mut lines = BufReader::new(reader).lines();
+
+            writer
+                .write_all(
+                    concat!(
+                        r#"{"method":"thread/tokenUsage/updated","params":"#,
+                        r#"{"threadId":"th_1","usage":{"inputTokens":900,"outputTokens":200}}}"#,
+                        "\n"
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+            let request = lines.next_line().await.unwrap().unwrap();
+            let request: serde_json::Value = serde_json::from_str(&request).unwrap();
+            assert_eq!(request["method"], "turn/interrupt");
+            assert_eq!(request["params"]["threadId"], "th_1");
+            writer
+                .write_all(format!("{{\"id\":{},\"result\":{{}}}}\n", request["id"]).as_bytes())
+                .await
+                .unwrap();
+        });
+
+        let mut client =
+            AsyncClient::from_transport(StreamTransport::from_stream(client_io)).unwrap();
+        client.set_usage_ledger(UsageLedger::new().budget(Budget::total_tokens(1000).interrupt()));
+
+        match client.next_message().await.unwrap() {
+            Some(ServerMessage::Notification { method, .. }) => {
+                assert_eq!(method, "thread/tokenUsage/updated")
+            }
+            other => panic!("expected notification, got {:?}", other),
+        }
+
+        server.await.unwrap();
+        let ledger = client.take_usage_ledger().unwrap();
+        assert!(ledger.budgets()[0].is_exceeded());
+        assert_eq!(ledger.report().threads["th_1"].output_tokens, 200);
+    }
+
+    #[tokio::test]
+    async fn test_transcript_record_and_replay() {
+        use crate::transcript::{Direction, ReplayTransport, Transcript, TranscriptRecorder};
+
+        let transcript = Transcript::from_jsonl(concat!(
+            r#"{"transcript_version":1,"provider":"codex","crate_version":"0","started_at_ms":0}"#,
+            "\n",
+            r#"{"timestamp_ms":0,"direction":"sent","line":"{\"id\":1,\"method\":\"thread/start\"}"}"#,
+            "\n",
+            r#"{"timestamp_ms":1,"direction":"received","line":"{\"method\":\"thread/started\",\"params\":{}}"}"#,
+            "\n",
+            r#"{"timestamp_ms":2,"direction":"received","line":"{\"id\":1,\"result\":{\"thread\":{\"id\":\"th_1\"}}}"}"#,
+        ))
+        .unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "codex-transcript-{}-{}.jsonl",
+            std::process::id(),
+            transcript.records.len()
+        ));
+        let mut client = AsyncClient::from_transport(ReplayTransport::new(transcript)).unwrap();
+        client.record_transcript(TranscriptRecorder::create(&path).unwrap());
+
+        let thread = client
+            .thread_start(&ThreadStartParams::default())
+            .await
+            .unwrap();
+        assert_eq!(thread.thread_id(), "th_1");
+        assert!(client.next_message().await.unwrap().is_some());
+        assert!(client.next_message().await.unwrap().is_none());
+        client.stop_recording();
+
+        // Re-recording the replay reproduces the original wire traffic
+        let recorded = Transcript::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let directions: Vec<_> = recorded.records.iter().map(|r| r.direction).collect();
+        assert_eq!(
+            directions,
+            vec![Direction::Sent, Direction::Received, Direction::Received]
+        );
+        assert_eq!(
+            recorded.sent_messages().next().unwrap()["method"],
+            "thread/start"
+        );
+    }
 }
@@ -27,9 +27,7 @@
 //! client.turn_start(&TurnStartParams {
 //!     thread_id: thread.thread_id().to_string(),
 //!     input: vec![UserInput::Text { text: "Hello!".into() }],
-//!     model: None,
-//!     reasoning_effort: None,
-//!     sandbox_policy: None,
+//!     ..Default::default()
 //! })?;
 //!
 //! for result in client.events() {
@@ -42,26 +40,42 @@
 //! }
 //! ```
 
+use crate::approval::ApprovalHandler;
 use crate::cli::AppServerBuilder;
-use crate::error::{Error, Result};
+use crate::error::{Error, ExitReport, Result};
 use crate::jsonrpc::{
     JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, RequestId,
 };
 use crate::protocol::{
     ClientInfo, InitializeParams, InitializeResponse, ServerMessage, ThreadArchiveParams,
-    ThreadArchiveResponse, ThreadStartParams, ThreadStartResponse, TurnInterruptParams,
-    TurnInterruptResponse, TurnStartParams, TurnStartResponse,
+    ThreadArchiveResponse, ThreadForkParams, ThreadForkResponse, ThreadListParams,
+    ThreadListResponse, ThreadReadParams, ThreadReadResponse, ThreadResumeParams,
+    ThreadResumeResponse, ThreadRollbackParams, ThreadRollbackResponse, ThreadStartParams,
+    ThreadStartResponse, TurnInterruptParams, TurnInterruptResponse, TurnStartParams,
+    TurnStartResponse, TurnSteerParams, TurnSteerResponse, TypedServerMessage,
 };
+#[cfg(doc)]
+use crate::protocol::{ServerNotification, ServerRequest};
 use log::{debug, warn};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::collections::VecDeque;
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::process::Child;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, Instant};
 
 /// Buffer size for reading stdout (10MB).
 const STDOUT_BUFFER_SIZE: usize = 10 * 1024 * 1024;
 
+/// How long to wait for the process to exit once its stdout has closed.
+const EXIT_WAIT: Duration = Duration::from_secs(2);
+
+/// How long to wait for stderr to drain once the process has exited.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How often to poll a child process for exit.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Synchronous multi-turn client for the Codex app-server.
 ///
 /// Communicates with a long-lived `codex app-server` process via
@@ -72,10 +86,13 @@ const STDOUT_BUFFER_SIZE: usize = 10 * 1024 * 1024;
 /// The client automatically kills the app-server process when dropped.
 pub struct SyncClient {
     child: Child,
-    writer: BufWriter<std::process::ChildStdin>,
+    /// `None` once [`SyncClient::close`] has closed stdin.
+    writer: Option<BufWriter<std::process::ChildStdin>>,
     reader: BufReader<std::process::ChildStdout>,
     next_id: i64,
     buffered: VecDeque<ServerMessage>,
+    /// Answers approval requests automatically when set.
+    approval_handler: Option<Box<dyn ApprovalHandler>>,
 }
 
 impl SyncClient {
@@ -124,8 +141,15 @@ impl SyncClient {
     pub fn spawn(builder: AppServerBuilder) -> Result<Self> {
         crate::version::check_codex_version()?;
 
-        let mut child = builder.spawn_sync().map_err(Error::Io)?;
+        let child = builder.spawn_sync().map_err(Error::Io)?;
+        Self::new(child)
+    }
 
+    /// Wrap an already spawned app-server process.
+    ///
+    /// The child must have piped stdin and stdout; piped stderr is used for
+    /// the [`ExitReport`] when the process exits. No handshake is performed.
+    pub fn new(mut child: Child) -> Result<Self> {
         let stdin = child
             .stdin
             .take()
@@ -137,10 +161,11 @@ impl SyncClient {
 
         Ok(Self {
             child,
-            writer: BufWriter::new(stdin),
+            writer: Some(BufWriter::new(stdin)),
             reader: BufReader::with_capacity(STDOUT_BUFFER_SIZE, stdout),
             next_id: 1,
             buffered: VecDeque::new(),
+            approval_handler: None,
         })
     }
 
@@ -190,11 +215,14 @@ impl SyncClient {
                     });
                 }
                 JsonRpcMessage::Request(req) => {
-                    self.buffered.push_back(ServerMessage::Request {
+                    let msg = ServerMessage::Request {
                         id: req.id,
                         method: req.method,
                         params: req.params,
-                    });
+                    };
+                    if let Some(msg) = self.auto_approve(msg)? {
+                        self.buffered.push_back(msg);
+                    }
                 }
                 JsonRpcMessage::Response(resp) => {
                     warn!(
@@ -236,6 +264,11 @@ impl SyncClient {
         self.request(crate::protocol::methods::TURN_INTERRUPT, params)
     }
 
+    /// Add input to the turn in progress without interrupting it.
+    pub fn turn_steer(&mut self, params: &TurnSteerParams) -> Result<TurnSteerResponse> {
+        self.request(crate::protocol::methods::TURN_STEER, params)
+    }
+
     /// Archive a thread.
     pub fn thread_archive(
         &mut self,
@@ -244,6 +277,36 @@ impl SyncClient {
         self.request(crate::protocol::methods::THREAD_ARCHIVE, params)
     }
 
+    /// Resume a persisted thread so new turns can be started on it.
+    pub fn thread_resume(&mut self, params: &ThreadResumeParams) -> Result<ThreadResumeResponse> {
+        self.request(crate::protocol::methods::THREAD_RESUME, params)
+    }
+
+    /// Copy a thread's history into a new thread.
+    ///
+    /// The response describes the new thread; the original is left untouched.
+    pub fn thread_fork(&mut self, params: &ThreadForkParams) -> Result<ThreadForkResponse> {
+        self.request(crate::protocol::methods::THREAD_FORK, params)
+    }
+
+    /// Drop the most recent turns from a thread.
+    pub fn thread_rollback(
+        &mut self,
+        params: &ThreadRollbackParams,
+    ) -> Result<ThreadRollbackResponse> {
+        self.request(crate::protocol::methods::THREAD_ROLLBACK, params)
+    }
+
+    /// List stored threads, a page at a time.
+    pub fn thread_list(&mut self, params: &ThreadListParams) -> Result<ThreadListResponse> {
+        self.request(crate::protocol::methods::THREAD_LIST, params)
+    }
+
+    /// Read a stored thread, optionally with its turns and items.
+    pub fn thread_read(&mut self, params: &ThreadReadParams) -> Result<ThreadReadResponse> {
+        self.request(crate::protocol::methods::THREAD_READ, params)
+    }
+
     /// Perform the `initialize` handshake with the app-server.
     ///
     /// Sends `initialize` with the given params and then sends the
@@ -288,10 +351,13 @@ impl SyncClient {
     /// Returns buffered messages first (from notifications that arrived during
     /// a [`SyncClient::request`] call), then reads from the wire.
     ///
-    /// Returns `Ok(None)` when the app-server closes the connection (EOF).
+    /// Returns `Ok(None)` when the app-server closes the connection (EOF),
+    /// or [`Error::ServerCrashed`] if it exited with an error.
     pub fn next_message(&mut self) -> Result<Option<ServerMessage>> {
-        if let Some(msg) = self.buffered.pop_front() {
-            return Ok(Some(msg));
+        while let Some(msg) = self.buffered.pop_front() {
+            if let Some(msg) = self.auto_approve(msg)? {
+                return Ok(Some(msg));
+            }
         }
 
         loop {
@@ -308,11 +374,14 @@ impl SyncClient {
                     }));
                 }
                 JsonRpcMessage::Request(req) => {
-                    return Ok(Some(ServerMessage::Request {
+                    let msg = ServerMessage::Request {
                         id: req.id,
                         method: req.method,
                         params: req.params,
-                    }));
+                    };
+                    if let Some(msg) = self.auto_approve(msg)? {
+                        return Ok(Some(msg));
+                    }
                 }
                 JsonRpcMessage::Response(resp) => {
                     warn!(
@@ -330,6 +399,31 @@ impl SyncClient {
         }
     }
 
+    /// Answer approval requests automatically with `handler`.
+    ///
+    /// `item/commandExecution/requestApproval` and `item/fileChange/requestApproval`
+    /// requests are then decided by the handler and responded to by the client,
+    /// including those that arrive while waiting for an RPC response; they are
+    /// no longer returned by [`SyncClient::next_message`]. Replaces any handler
+    /// already set. See [`crate::approval`].
+    pub fn set_approval_handler<H: ApprovalHandler + 'static>(&mut self, handler: H) {
+        self.approval_handler = Some(Box::new(handler));
+    }
+
+    /// Remove the approval handler, returning approval requests to the caller.
+    pub fn clear_approval_handler(&mut self) -> Option<Box<dyn ApprovalHandler>> {
+        self.approval_handler.take()
+    }
+
+    /// Read the next incoming server message, decoded by method.
+    ///
+    /// Like [`SyncClient::next_message`], but yields a [`TypedServerMessage`] so
+    /// callers can match on [`ServerNotification`] and
+    /// [`ServerRequest`] variants instead of method strings.
+    pub fn next_typed_message(&mut self) -> Result<Option<TypedServerMessage>> {
+        Ok(self.next_message()?.map(ServerMessage::into_typed))
+    }
+
     /// Return an iterator over [`ServerMessage`]s.
     ///
     /// The iterator yields `Result<ServerMessage>` and terminates when the
@@ -339,9 +433,75 @@ impl SyncClient {
         EventIterator { client: self }
     }
 
-    /// Shut down the child process.
+    /// Close stdin and wait for the app-server to exit, killing it after `grace`.
+    ///
+    /// Closing stdin lets the app-server finish up and exit on its own.
+    /// Returns how the process exited.
+    pub fn close(mut self, grace: Duration) -> Result<ExitReport> {
+        debug!("[CLIENT] Closing");
+        // Dropping the writer closes the app-server's stdin.
+        if let Some(mut writer) = self.writer.take() {
+            if let Err(e) = writer.flush() {
+                debug!("[CLIENT] Failed to flush stdin: {}", e);
+            }
+        }
+        let deadline = Instant::now() + grace;
+        let (status, killed) = loop {
+            if let Some(status) = self.child.try_wait().map_err(Error::Io)? {
+                break (status, false);
+            }
+            if Instant::now() >= deadline {
+                warn!(
+                    "[CLIENT] App-server {} did not exit within {:?}, killing it",
+                    self.child.id(),
+                    grace
+                );
+                self.child.kill().map_err(Error::Io)?;
+                break (self.child.wait().map_err(Error::Io)?, true);
+            }
+            std::thread::sleep(EXIT_POLL_INTERVAL);
+        };
+        let report = self.report(status, killed);
+        debug!("[CLIENT] App-server exited: {}", report);
+        Ok(report)
+    }
+
+    /// Build an exit report, draining whatever is left on stderr.
+    fn report(&mut self, status: ExitStatus, killed: bool) -> ExitReport {
+        let report = ExitReport {
+            killed,
+            ..ExitReport::from_status(&status)
+        };
+        let Some(mut pipe) = self.child.stderr.take() else {
+            return report;
+        };
+        // A grandchild may hold the pipe open, so read on a thread we can abandon.
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut stderr = Vec::new();
+            let _ = pipe.read_to_end(&mut stderr);
+            let _ = tx.send(stderr);
+        });
+        let stderr = rx.recv_timeout(STDERR_DRAIN_TIMEOUT).unwrap_or_default();
+        report.with_stderr(&stderr)
+    }
+
+    /// How the process exited, if it does so within `wait`.
+    fn exit_report(&mut self, wait: Duration) -> Option<ExitReport> {
+        let deadline = Instant::now() + wait;
+        loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => return Some(self.report(status, false)),
+                Ok(None) if Instant::now() < deadline => std::thread::sleep(EXIT_POLL_INTERVAL),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Kill the child process.
     ///
     /// Kills the process if it's still running. Called automatically on [`Drop`].
+    /// Use [`close`](Self::close) to let it exit on its own first.
     pub fn shutdown(&mut self) -> Result<()> {
         debug!("[CLIENT] Shutting down");
         match self.child.try_wait() {
@@ -365,12 +525,37 @@ impl SyncClient {
         self.send_raw(&notif)
     }
 
+    /// Answer `msg` with the approval handler if it is an approval request.
+    ///
+    /// Returns the message back when it was not handled.
+    fn auto_approve(&mut self, msg: ServerMessage) -> Result<Option<ServerMessage>> {
+        let handler = match self.approval_handler.as_mut() {
+            Some(handler) => handler,
+            None => return Ok(Some(msg)),
+        };
+        match crate::approval::dispatch(handler.as_mut(), msg) {
+            Ok((id, response)) => {
+                debug!(
+                    "[CLIENT] Approval handler answered request {}: {}",
+                    id, response
+                );
+                self.respond(id, &response)?;
+                Ok(None)
+            }
+            Err(msg) => Ok(Some(msg)),
+        }
+    }
+
     fn send_raw<T: Serialize>(&mut self, msg: &T) -> Result<()> {
         let json = serde_json::to_string(msg).map_err(Error::Json)?;
         debug!("[CLIENT] Sending: {}", json);
-        self.writer.write_all(json.as_bytes()).map_err(Error::Io)?;
-        self.writer.write_all(b"\n").map_err(Error::Io)?;
-        self.writer.flush().map_err(Error::Io)?;
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| Error::Protocol("stdin is closed".to_string()))?;
+        writer.write_all(json.as_bytes()).map_err(Error::Io)?;
+        writer.write_all(b"\n").map_err(Error::Io)?;
+        writer.flush().map_err(Error::Io)?;
         Ok(())
     }
 
@@ -384,7 +569,13 @@ impl SyncClient {
             match self.reader.read_line(&mut line) {
                 Ok(0) => {
                     debug!("[CLIENT] Stream closed (EOF)");
-                    return Ok(None);
+                    return match self.exit_report(EXIT_WAIT) {
+                        Some(report) if !report.success() => {
+                            warn!("[CLIENT] App-server crashed: {}", report);
+                            Err(Error::ServerCrashed(report))
+                        }
+                        _ => Ok(None),
+                    };
                 }
                 Ok(_) => {
                     let trimmed = line.trim();
@@ -432,6 +623,13 @@ pub struct EventIterator<'a> {
     client: &'a mut SyncClient,
 }
 
+impl<'a> EventIterator<'a> {
+    /// Adapt the iterator to yield messages decoded by method.
+    pub fn typed(self) -> impl Iterator<Item = Result<TypedServerMessage>> + 'a {
+        self.map(|result| result.map(ServerMessage::into_typed))
+    }
+}
+
 impl Iterator for EventIterator<'_> {
     type Item = Result<ServerMessage>;
 
@@ -452,4 +650,41 @@ mod tests {
     fn test_buffer_size() {
         assert_eq!(STDOUT_BUFFER_SIZE, 10 * 1024 * 1024);
     }
+
+    #[cfg(unix)]
+    fn sh(script: &str) -> SyncClient {
+        let child = std::process::Command::new("sh")
+            .args(["-c", script])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        SyncClient::new(child).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crash_is_reported_with_stderr_tail() {
+        let mut client = sh("echo 'thread main panicked' >&2; exit 101");
+        match client.next_message() {
+            Err(Error::ServerCrashed(report)) => {
+                assert_eq!(report.code, Some(101));
+                assert_eq!(report.stderr_tail, vec!["thread main panicked"]);
+            }
+            other => panic!("expected ServerCrashed, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_close_waits_for_stdin_eof_then_kills() {
+        let report = sh("cat > /dev/null").close(Duration::from_secs(5)).unwrap();
+        assert!(report.success());
+        assert!(!report.killed);
+
+        let report = sh("sleep 30").close(Duration::from_millis(100)).unwrap();
+        assert!(report.killed);
+        assert_eq!(report.signal, Some(9));
+    }
 }
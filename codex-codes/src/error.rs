@@ -6,6 +6,8 @@
 
 use thiserror::Error;
 
+pub use codes_core::exit::{ExitReport, STDERR_TAIL_LINES};
+
 /// All possible errors from codex-codes operations.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -54,10 +56,95 @@ pub enum Error {
     #[error("Server closed connection")]
     ServerClosed,
 
+    /// The app-server (or `codex exec`) process exited with a non-zero
+    /// status or was killed by a signal.
+    ///
+    /// Returned instead of a normal end of stream, with the exit status and
+    /// the last lines the process wrote to stderr.
+    #[error("App-server crashed: {0}")]
+    ServerCrashed(ExitReport),
+
+    /// Nothing arrived within a configured timeout.
+    ///
+    /// The connection is still usable; the app-server may just be slow.
+    #[error("Timeout occurred")]
+    Timeout,
+
+    /// The operation was cancelled through a cancellation token.
+    #[error("Operation cancelled")]
+    Cancelled,
+
     /// An unclassified error.
     #[error("Unknown error: {0}")]
     Unknown(String),
+
+    /// A read failed after the app-server had written to stderr.
+    ///
+    /// Only produced when stderr is captured; wraps the original error with
+    /// the last lines read. Use [`Error::root`] to match on the cause.
+    #[error("{source} (stderr: {})", stderr_tail.last().map_or("", String::as_str))]
+    WithStderr {
+        source: Box<Error>,
+        stderr_tail: Vec<String>,
+    },
 }
 
 /// A `Result` type alias using [`enum@Error`].
 pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The underlying error, looking through any attached stderr.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::WithStderr { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// The stderr lines attached to this error, oldest first.
+    pub fn stderr_tail(&self) -> &[String] {
+        match self {
+            Error::WithStderr { stderr_tail, .. } => stderr_tail,
+            Error::ServerCrashed(report) => &report.stderr_tail,
+            _ => &[],
+        }
+    }
+
+    /// Attach `stderr_tail` to a failed read.
+    ///
+    /// Timeouts, cancellations, normal ends of stream and errors that already
+    /// carry stderr are returned unchanged.
+    #[cfg(feature = "async-client")]
+    pub(crate) fn with_stderr(self, stderr_tail: Vec<String>) -> Error {
+        match self {
+            Error::Timeout
+            | Error::Cancelled
+            | Error::ServerClosed
+            | Error::ServerCrashed(_)
+            | Error::WithStderr { .. } => self,
+            _ if stderr_tail.is_empty() => self,
+            source => Error::WithStderr {
+                source: Box::new(source),
+                stderr_tail,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "async-client")]
+impl From<codes_core::limits::ReadError> for Error {
+    fn from(error: codes_core::limits::ReadError) -> Self {
+        match error {
+            codes_core::limits::ReadError::Timeout => Error::Timeout,
+            codes_core::limits::ReadError::Cancelled => Error::Cancelled,
+            codes_core::limits::ReadError::Io(e) => Error::Io(e),
+        }
+    }
+}
+
+#[cfg(feature = "async-client")]
+impl From<codes_core::pool::PoolError> for Error {
+    fn from(error: codes_core::pool::PoolError) -> Self {
+        Error::Protocol(error.to_string())
+    }
+}
@@ -0,0 +1,558 @@
+//! One-shot runs of `codex exec --json`.
+//!
+//! For a single prompt — a CI job, a pre-commit check, a scripted edit — a
+//! long-lived app-server is overkill. `codex exec --json` runs one turn and
+//! prints [`ThreadEvent`]s as JSON lines, then exits. An [`ExecBuilder`]
+//! configures the run and an [`ExecClient`] streams its events, folding them
+//! into an [`ExecSummary`].
+//!
+//! The prompt is written to the process's stdin, so it may be arbitrarily
+//! long. Stderr is drained in the background with a [`StderrPump`], and its
+//! tail is attached to the report if the process crashes.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use codex_codes::exec::ExecBuilder;
+//! use codex_codes::{SandboxMode, ThreadEvent};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut run = ExecBuilder::new("Fix the failing test in src/lib.rs")
+//!     .sandbox(SandboxMode::WorkspaceWrite)
+//!     .spawn()
+//!     .await?;
+//!
+//! while let Some(event) = run.next_event().await? {
+//!     if let ThreadEvent::ItemCompleted(done) = &event {
+//!         println!("{:?}", done.item);
+//!     }
+//! }
+//!
+//! let summary = run.finish().await?;
+//! println!("{}", summary.final_message().unwrap_or("(no answer)"));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, ExitReport, Result, STDERR_TAIL_LINES};
+use crate::io::events::{ThreadEvent, Usage};
+use crate::io::items::ThreadItem;
+use crate::io::options::SandboxMode;
+use crate::stderr::{StderrBuffer, StderrPump};
+use crate::transport::{ProcessTransport, Transport, TransportReader, STDERR_DRAIN_TIMEOUT};
+use log::{debug, error, warn};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// How long to wait for the process to exit once its stdout has closed.
+const EXIT_WAIT: Duration = Duration::from_secs(2);
+
+// ---------------------------------------------------------------------------
+// Builder
+// ---------------------------------------------------------------------------
+
+/// Which earlier session an exec run continues.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Resume {
+    Thread(String),
+    Last,
+}
+
+/// Builder for a `codex exec --json` run.
+///
+/// Produces commands of the form:
+/// `codex exec --json [options] [resume <thread-id>] -`, with the prompt
+/// written to stdin.
+#[derive(Debug, Clone)]
+pub struct ExecBuilder {
+    command: PathBuf,
+    prompt: String,
+    model: Option<String>,
+    sandbox: Option<SandboxMode>,
+    images: Vec<PathBuf>,
+    output_schema: Option<PathBuf>,
+    working_directory: Option<PathBuf>,
+    additional_directories: Vec<PathBuf>,
+    skip_git_repo_check: bool,
+    config: Vec<(String, String)>,
+    resume: Option<Resume>,
+}
+
+impl ExecBuilder {
+    /// Create a builder that runs `prompt`.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            command: PathBuf::from("codex"),
+            prompt: prompt.into(),
+            model: None,
+            sandbox: None,
+            images: Vec::new(),
+            output_schema: None,
+            working_directory: None,
+            additional_directories: Vec::new(),
+            skip_git_repo_check: false,
+            config: Vec::new(),
+            resume: None,
+        }
+    }
+
+    /// Set custom path to the codex binary.
+    pub fn command<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.command = path.into();
+        self
+    }
+
+    /// Set the model (`--model`).
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Set the sandbox mode for commands the agent runs (`--sandbox`).
+    pub fn sandbox(mut self, sandbox: SandboxMode) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Attach an image to the prompt (`--image`). May be called repeatedly.
+    pub fn image<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.images.push(path.into());
+        self
+    }
+
+    /// Constrain the final message to a JSON Schema read from `path` (`--output-schema`).
+    pub fn output_schema<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.output_schema = Some(path.into());
+        self
+    }
+
+    /// Set the agent's working directory (`--cd`).
+    pub fn working_directory<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.working_directory = Some(dir.into());
+        self
+    }
+
+    /// Make another directory writable alongside the working directory (`--add-dir`).
+    pub fn add_directory<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.additional_directories.push(dir.into());
+        self
+    }
+
+    /// Allow running outside a Git repository (`--skip-git-repo-check`).
+    pub fn skip_git_repo_check(mut self, skip: bool) -> Self {
+        self.skip_git_repo_check = skip;
+        self
+    }
+
+    /// Override a `config.toml` value for this run (`-c key=value`).
+    ///
+    /// The value is parsed as TOML by the CLI, falling back to a string.
+    pub fn config(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.push((key.into(), value.into()));
+        self
+    }
+
+    /// Continue an earlier thread instead of starting a new one.
+    ///
+    /// Use the thread ID reported by [`ExecSummary::thread_id`].
+    pub fn resume(mut self, thread_id: impl Into<String>) -> Self {
+        self.resume = Some(Resume::Thread(thread_id.into()));
+        self
+    }
+
+    /// Continue the most recent thread in the working directory.
+    pub fn resume_last(mut self) -> Self {
+        self.resume = Some(Resume::Last);
+        self
+    }
+
+    /// Build the command arguments.
+    fn build_args(&self) -> Vec<String> {
+        let mut args = vec!["exec".to_string(), "--json".to_string()];
+        if let Some(ref model) = self.model {
+            args.push("--model".to_string());
+            args.push(model.clone());
+        }
+        if let Some(ref sandbox) = self.sandbox {
+            args.push("--sandbox".to_string());
+            args.push(sandbox.as_cli_str().to_string());
+        }
+        for image in &self.images {
+            args.push("--image".to_string());
+            args.push(image.display().to_string());
+        }
+        if let Some(ref schema) = self.output_schema {
+            args.push("--output-schema".to_string());
+            args.push(schema.display().to_string());
+        }
+        if let Some(ref dir) = self.working_directory {
+            args.push("--cd".to_string());
+            args.push(dir.display().to_string());
+        }
+        for dir in &self.additional_directories {
+            args.push("--add-dir".to_string());
+            args.push(dir.display().to_string());
+        }
+        if self.skip_git_repo_check {
+            args.push("--skip-git-repo-check".to_string());
+        }
+        for (key, value) in &self.config {
+            args.push("-c".to_string());
+            args.push(format!("{}={}", key, value));
+        }
+        match &self.resume {
+            Some(Resume::Thread(thread_id)) => {
+                args.push("resume".to_string());
+                args.push(thread_id.clone());
+            }
+            Some(Resume::Last) => {
+                args.push("resume".to_string());
+                args.push("--last".to_string());
+            }
+            None => {}
+        }
+        // Read the prompt from stdin.
+        args.push("-".to_string());
+        args
+    }
+
+    /// Spawn `codex exec` and send it the prompt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `codex` CLI cannot be started or the prompt
+    /// cannot be written to its stdin.
+    pub async fn spawn(self) -> Result<ExecClient> {
+        crate::version::check_codex_version_async().await?;
+
+        let args = self.build_args();
+        debug!(
+            "[CLI] Spawning exec: {} {}",
+            self.command.display(),
+            args.join(" ")
+        );
+
+        let child = tokio::process::Command::new(&self.command)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(Error::Io)?;
+        ExecClient::from_transport(ProcessTransport::new(child), &self.prompt).await
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Summary
+// ---------------------------------------------------------------------------
+
+/// What an exec run produced, folded from its events.
+#[derive(Debug, Clone, Default)]
+pub struct ExecSummary {
+    /// The thread's ID, from `thread.started`; pass it to
+    /// [`ExecBuilder::resume`] to continue the conversation.
+    pub thread_id: Option<String>,
+    /// Completed items, in completion order.
+    pub items: Vec<ThreadItem>,
+    /// Token usage, from `turn.completed`.
+    pub usage: Option<Usage>,
+    /// The failure message, from `turn.failed` or a thread-level `error` event.
+    pub error: Option<String>,
+    /// How the process exited, once it has (`None` for transports that cannot tell).
+    pub exit: Option<ExitReport>,
+}
+
+impl ExecSummary {
+    /// Fold one event into the summary.
+    pub fn observe(&mut self, event: &ThreadEvent) {
+        match event {
+            ThreadEvent::ThreadStarted(started) => {
+                self.thread_id = Some(started.thread_id.clone());
+            }
+            ThreadEvent::TurnCompleted(completed) => {
+                self.usage = Some(completed.usage.clone());
+            }
+            ThreadEvent::TurnFailed(failed) => {
+                self.error = Some(failed.error.message.clone());
+            }
+            ThreadEvent::Error(error) => {
+                self.error = Some(error.message.clone());
+            }
+            ThreadEvent::ItemCompleted(completed) => {
+                self.items.push(completed.item.clone());
+            }
+            ThreadEvent::TurnStarted(_)
+            | ThreadEvent::ItemStarted(_)
+            | ThreadEvent::ItemUpdated(_) => {}
+        }
+    }
+
+    /// The last agent message — the run's answer.
+    pub fn final_message(&self) -> Option<&str> {
+        self.items.iter().rev().find_map(|item| match item {
+            ThreadItem::AgentMessage(message) => Some(message.text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Whether the turn completed without an error.
+    pub fn success(&self) -> bool {
+        self.usage.is_some() && self.error.is_none()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Client
+// ---------------------------------------------------------------------------
+
+/// A running `codex exec --json` process.
+///
+/// Created by [`ExecBuilder::spawn`]. Read events with
+/// [`next_event`](Self::next_event), or skip straight to the result with
+/// [`finish`](Self::finish). The process is killed if the client is dropped
+/// before it exits.
+pub struct ExecClient {
+    transport: Box<dyn Transport>,
+    reader: BufReader<TransportReader>,
+    stderr: Option<StderrBuffer>,
+    summary: ExecSummary,
+    finished: bool,
+}
+
+impl ExecClient {
+    /// Run over a custom [`Transport`], writing `prompt` and closing the write half.
+    ///
+    /// Stderr, if the transport has one, is drained by a [`StderrPump`].
+    /// Must be called from within a Tokio runtime.
+    pub async fn from_transport<T: Transport + 'static>(
+        mut transport: T,
+        prompt: &str,
+    ) -> Result<Self> {
+        let (reader, mut writer) = transport.connect().map_err(Error::Io)?;
+        let stderr = transport
+            .take_stderr()
+            .map(|stderr| StderrPump::new().spawn(stderr));
+
+        writer
+            .write_all(prompt.as_bytes())
+            .await
+            .map_err(Error::Io)?;
+        // Closing stdin marks the end of the prompt.
+        writer.shutdown().await.map_err(Error::Io)?;
+        drop(writer);
+
+        Ok(Self {
+            transport: Box::new(transport),
+            reader: BufReader::new(reader),
+            stderr,
+            summary: ExecSummary::default(),
+            finished: false,
+        })
+    }
+
+    /// Read the next event, or `None` once the run is over.
+    ///
+    /// Every event is also folded into the [summary](Self::summary).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ServerCrashed`] if the process exits with an error
+    /// without reporting a failed turn, and [`Error::Deserialization`] for a
+    /// line that is not a [`ThreadEvent`]. Failures other than a crash carry
+    /// the stderr tail as [`Error::WithStderr`].
+    pub async fn next_event(&mut self) -> Result<Option<ThreadEvent>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let result = self.read_event().await;
+        result.map_err(|error| match &self.stderr {
+            Some(buffer) => error.with_stderr(buffer.tail(STDERR_TAIL_LINES)),
+            None => error,
+        })
+    }
+
+    /// Read and parse the next event line.
+    async fn read_event(&mut self) -> Result<Option<ThreadEvent>> {
+        loop {
+            let mut line = String::new();
+            let bytes_read = self.reader.read_line(&mut line).await.map_err(Error::Io)?;
+            if bytes_read == 0 {
+                self.finished = true;
+                return self.finish_stream().await.map(|()| None);
+            }
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            debug!("[EXEC] Received: {}", trimmed);
+
+            match serde_json::from_str::<ThreadEvent>(trimmed) {
+                Ok(event) => {
+                    self.summary.observe(&event);
+                    return Ok(Some(event));
+                }
+                Err(e) => {
+                    warn!(
+                        "[EXEC] Failed to deserialize event. \
+                         Please report this at https://github.com/meawoppl/rust-code-agent-sdks/issues"
+                    );
+                    warn!("[EXEC] Parse error: {}", e);
+                    warn!("[EXEC] Raw: {}", trimmed);
+                    return Err(Error::Deserialization(format!("{} (raw: {})", e, trimmed)));
+                }
+            }
+        }
+    }
+
+    /// Read the remaining events and return the summary.
+    pub async fn finish(mut self) -> Result<ExecSummary> {
+        while self.next_event().await?.is_some() {}
+        Ok(std::mem::take(&mut self.summary))
+    }
+
+    /// The summary of the events read so far.
+    pub fn summary(&self) -> &ExecSummary {
+        &self.summary
+    }
+
+    /// Whether the process has exited and every event has been read.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Recent stderr lines, if the transport has a stderr stream.
+    pub fn stderr(&self) -> Option<&StderrBuffer> {
+        self.stderr.as_ref()
+    }
+
+    /// Get the process ID, if the transport is a local process.
+    pub fn pid(&self) -> Option<u32> {
+        self.transport.pid()
+    }
+
+    /// Kill the process and wait for it to exit.
+    pub async fn shutdown(mut self) -> Result<()> {
+        debug!("[EXEC] Shutting down exec process");
+        self.transport.shutdown().await.map_err(Error::Io)
+    }
+
+    /// Record how the process exited once its stdout has closed.
+    async fn finish_stream(&mut self) -> Result<()> {
+        let Some(mut report) = self.transport.exit_report(EXIT_WAIT).await else {
+            return Ok(());
+        };
+        if let Some(buffer) = &self.stderr {
+            if report.stderr_tail.is_empty() {
+                // The pipe closes with the process; give the pump a moment to catch up.
+                let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, buffer.closed()).await;
+                report.stderr_tail = buffer.tail(STDERR_TAIL_LINES);
+            }
+        }
+        self.summary.exit = Some(report.clone());
+        // A failed turn also exits with an error, but is reported in the summary.
+        if report.success() || self.summary.error.is_some() {
+            debug!("[EXEC] Process exited: {}", report);
+            return Ok(());
+        }
+        error!("[EXEC] Process crashed: {}", report);
+        Err(Error::ServerCrashed(report))
+    }
+}
+
+impl Drop for ExecClient {
+    fn drop(&mut self) {
+        if !self.finished && self.transport.is_alive() {
+            if let Err(e) = self.transport.start_kill() {
+                error!("[EXEC] Failed to kill exec process on drop: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::StreamTransport;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_build_args() {
+        let args = ExecBuilder::new("fix it")
+            .model("gpt-5-codex")
+            .sandbox(SandboxMode::WorkspaceWrite)
+            .image("shot.png")
+            .output_schema("schema.json")
+            .skip_git_repo_check(true)
+            .config("model_reasoning_effort", "high")
+            .resume("th_1")
+            .build_args();
+        assert_eq!(
+            args,
+            vec![
+                "exec",
+                "--json",
+                "--model",
+                "gpt-5-codex",
+                "--sandbox",
+                "workspace-write",
+                "--image",
+                "shot.png",
+                "--output-schema",
+                "schema.json",
+                "--skip-git-repo-check",
+                "-c",
+                "model_reasoning_effort=high",
+                "resume",
+                "th_1",
+                "-",
+            ]
+        );
+        assert_eq!(
+            ExecBuilder::new("go").resume_last().build_args(),
+            vec!["exec", "--json", "resume", "--last", "-"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streams_events_into_summary() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (mut reader, mut writer) = tokio::io::split(server_io);
+            let mut prompt = String::new();
+            reader.read_to_string(&mut prompt).await.unwrap();
+            let events = [
+                r#"{"type":"thread.started","thread_id":"th_1"}"#,
+                r#"{"type":"turn.started"}"#,
+                r#"{"type":"item.completed","item":{"type":"agent_message","id":"m1","text":"Looking"}}"#,
+                r#"{"type":"item.completed","item":{"type":"agent_message","id":"m2","text":"Fixed"}}"#,
+                r#"{"type":"turn.completed","usage":{"input_tokens":10,"cached_input_tokens":0,"output_tokens":5}}"#,
+            ];
+            for event in events {
+                writer
+                    .write_all(format!("{}\n", event).as_bytes())
+                    .await
+                    .unwrap();
+            }
+            prompt
+        });
+
+        let mut client =
+            ExecClient::from_transport(StreamTransport::from_stream(client_io), "fix it")
+                .await
+                .unwrap();
+        let first = client.next_event().await.unwrap().unwrap();
+        assert_eq!(first.event_type(), "thread.started");
+        assert_eq!(client.summary().thread_id.as_deref(), Some("th_1"));
+
+        let summary = client.finish().await.unwrap();
+        assert_eq!(server.await.unwrap(), "fix it");
+        assert!(summary.success());
+        assert_eq!(summary.items.len(), 2);
+        assert_eq!(summary.final_message(), Some("Fixed"));
+        assert_eq!(summary.usage.unwrap().output_tokens, 5);
+    }
+}
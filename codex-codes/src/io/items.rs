@@ -199,6 +199,22 @@ pub enum ThreadItem {
     Error(ErrorItem),
 }
 
+impl ThreadItem {
+    /// The item's ID, unique within its thread.
+    pub fn id(&self) -> &str {
+        match self {
+            Self::AgentMessage(item) => &item.id,
+            Self::Reasoning(item) => &item.id,
+            Self::CommandExecution(item) => &item.id,
+            Self::FileChange(item) => &item.id,
+            Self::McpToolCall(item) => &item.id,
+            Self::WebSearch(item) => &item.id,
+            Self::TodoList(item) => &item.id,
+            Self::Error(item) => &item.id,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -14,7 +14,10 @@
 //! ## Using the Async Client (Recommended)
 //!
 //! ```ignore
-//! use codex_codes::{AsyncClient, ThreadStartParams, TurnStartParams, UserInput, ServerMessage};
+//! use codex_codes::{
+//!     AsyncClient, ServerNotification, ThreadStartParams, TurnStartParams, TypedServerMessage,
+//!     UserInput,
+//! };
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -28,20 +31,17 @@
 //!     client.turn_start(&TurnStartParams {
 //!         thread_id: thread.thread_id().to_string(),
 //!         input: vec![UserInput::Text { text: "What is 2 + 2?".into() }],
-//!         model: None,
-//!         reasoning_effort: None,
-//!         sandbox_policy: None,
+//!         ..Default::default()
 //!     }).await?;
 //!
 //!     // Stream notifications until the turn completes
-//!     while let Some(msg) = client.next_message().await? {
+//!     while let Some(msg) = client.next_typed_message().await? {
 //!         match msg {
-//!             ServerMessage::Notification { method, params } => {
-//!                 if method == "turn/completed" { break; }
-//!             }
-//!             ServerMessage::Request { id, .. } => {
+//!             TypedServerMessage::Notification(ServerNotification::TurnCompleted(_)) => break,
+//!             TypedServerMessage::Notification(_) => {}
+//!             TypedServerMessage::Request(req) => {
 //!                 // Approval request — auto-accept for this example
-//!                 client.respond(id, &serde_json::json!({"decision": "accept"})).await?;
+//!                 client.respond(req.id().clone(), &serde_json::json!({"decision": "accept"})).await?;
 //!             }
 //!         }
 //!     }
@@ -63,9 +63,7 @@
 //!     client.turn_start(&TurnStartParams {
 //!         thread_id: thread.thread_id().to_string(),
 //!         input: vec![UserInput::Text { text: "What is 2 + 2?".into() }],
-//!         model: None,
-//!         reasoning_effort: None,
-//!         sandbox_policy: None,
+//!         ..Default::default()
 //!     })?;
 //!
 //!     for result in client.events() {
@@ -91,6 +89,14 @@
 //! - [`jsonrpc`] — Low-level JSON-RPC message types (request, response, error,
 //!   notification) matching the app-server's wire format
 //! - [`cli`] — Builder for spawning `codex app-server --listen stdio://`
+//! - [`exec`] — One-shot `codex exec --json` runs, streamed as [`ThreadEvent`]s
+//! - [`accounting`] — Token totals per thread and model, with budgets
+//! - [`approval`] — Approval handlers the clients answer requests with automatically
+//! - [`pool`] — Supervised pool of app-server clients with crash recovery
+//! - [`transport`] — Byte-stream transports the async client runs over
+//! - [`stderr`] — Background stderr capture and app-server diagnostics
+//! - [`transcript`] — Wire-level session recording and replay
+//! - [`turn`] — Folding a turn's notifications into items and a final outcome
 //! - [`error`] — Error types and result aliases
 //! - [`version`] — Version compatibility checking against the installed CLI
 //!
@@ -155,10 +161,16 @@
 
 mod io;
 
+pub mod accounting;
 pub mod error;
 pub mod jsonrpc;
 pub mod protocol;
+pub mod stderr;
+pub mod transcript;
+pub mod turn;
 
+#[cfg(any(feature = "sync-client", feature = "async-client"))]
+pub mod approval;
 #[cfg(any(feature = "sync-client", feature = "async-client"))]
 pub mod cli;
 
@@ -170,6 +182,12 @@ pub mod client_sync;
 
 #[cfg(feature = "async-client")]
 pub mod client_async;
+#[cfg(feature = "async-client")]
+pub mod exec;
+#[cfg(feature = "async-client")]
+pub mod pool;
+#[cfg(feature = "async-client")]
+pub mod transport;
 
 // Exec-level event types (JSONL protocol)
 pub use io::events::{
@@ -191,7 +209,7 @@ pub use io::options::{
 };
 
 // Error types (always available)
-pub use error::{Error, Result};
+pub use error::{Error, ExitReport, Result};
 
 // JSON-RPC types (always available)
 pub use jsonrpc::{
@@ -206,14 +224,23 @@ pub use protocol::{
     FileChangeApprovalDecision, FileChangeApprovalParams, FileChangeApprovalResponse,
     FileChangeOutputDeltaNotification, InitializeCapabilities, InitializeParams,
     InitializeResponse, ItemCompletedNotification, ItemStartedNotification,
-    ReasoningDeltaNotification, ServerMessage, ThreadArchiveParams, ThreadArchiveResponse,
-    ThreadInfo, ThreadStartParams, ThreadStartResponse, ThreadStartedNotification, ThreadStatus,
-    ThreadStatusChangedNotification, ThreadTokenUsageUpdatedNotification, TokenUsage, Turn,
-    TurnCompletedNotification, TurnError, TurnInterruptParams, TurnInterruptResponse,
-    TurnStartParams, TurnStartResponse, TurnStartedNotification, TurnStatus, UserInput,
+    ReasoningDeltaNotification, SandboxPolicy, ServerMessage, ServerNotification, ServerRequest,
+    ThreadArchiveParams, ThreadArchiveResponse, ThreadForkParams, ThreadForkResponse, ThreadInfo,
+    ThreadListParams, ThreadListResponse, ThreadReadParams, ThreadReadResponse, ThreadResumeParams,
+    ThreadResumeResponse, ThreadRollbackParams, ThreadRollbackResponse, ThreadStartParams,
+    ThreadStartResponse, ThreadStartedNotification, ThreadStatus, ThreadStatusChangedNotification,
+    ThreadTokenUsageUpdatedNotification, TokenUsage, Turn, TurnCompletedNotification, TurnError,
+    TurnInterruptParams, TurnInterruptResponse, TurnStartParams, TurnStartResponse,
+    TurnStartedNotification, TurnStatus, TurnSteerParams, TurnSteerResponse, TypedServerMessage,
+    UserInput, WorkspaceWritePolicy,
 };
 
-// CLI builder (feature-gated)
+// Turn aggregation (always available)
+pub use turn::{TurnAggregator, TurnOutcome, TurnUpdate};
+
+// CLI builder and approval handlers (feature-gated)
+#[cfg(any(feature = "sync-client", feature = "async-client"))]
+pub use approval::{ApprovalHandler, ApproveAll, DeclineAll};
 #[cfg(any(feature = "sync-client", feature = "async-client"))]
 pub use cli::AppServerBuilder;
 
@@ -223,4 +250,10 @@ pub use client_sync::{EventIterator, SyncClient};
 
 // Async client
 #[cfg(feature = "async-client")]
-pub use client_async::{AsyncClient, EventStream};
+pub use client_async::{AsyncClient, EventStream, TurnStream};
+#[cfg(feature = "async-client")]
+pub use exec::{ExecBuilder, ExecClient, ExecSummary};
+#[cfg(feature = "async-client")]
+pub use tokio_util::sync::CancellationToken;
+#[cfg(feature = "async-client")]
+pub use transport::{ProcessTransport, StreamTransport, Transport};
@@ -0,0 +1,137 @@
+//! Pool of app-server clients for running many Codex sessions concurrently.
+//!
+//! A [`SessionPool`] spawns [`AsyncClient`]s from an [`AppServerBuilder`]
+//! template — one app-server process per worker — and hands them out with
+//! [`SessionPool::checkout`]. At most [`max_workers`](SessionPool::max_workers)
+//! clients are checked out at once; further callers wait for one to be
+//! returned. The supervisor itself is [`codes_core::pool::Pool`], shared with
+//! `claude_codes::pool`.
+//!
+//! Workers are health-checked without sending a request: a worker whose
+//! process has exited is replaced by a freshly started and initialized
+//! app-server. Threads live inside the app-server, so a restarted worker
+//! starts with no threads — callers holding thread IDs must start new ones.
+//! Per-client state such as approval handlers is not carried over, so set it
+//! in [`SessionPool::on_spawn`], which runs for every spawned and restarted
+//! client.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use codex_codes::pool::SessionPool;
+//! use codex_codes::{AppServerBuilder, ThreadStartParams};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let pool = SessionPool::new(AppServerBuilder::new()).max_workers(8);
+//!
+//! let mut client = pool.checkout().await?;
+//! let thread = client.thread_start(&ThreadStartParams::default()).await?;
+//! println!("worker {} runs thread {}", client.worker_id(), thread.thread_id());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cli::AppServerBuilder;
+use crate::client_async::AsyncClient;
+use crate::error::Error;
+use crate::transport::BoxFuture;
+use codes_core::pool::{Pool, PoolClient};
+use std::convert::Infallible;
+
+pub use codes_core::pool::{PoolError, PoolStats, WorkerStatus};
+
+/// A supervisor for many concurrent app-server clients.
+pub type SessionPool = Pool<AsyncClient>;
+
+/// A client checked out of a [`SessionPool`].
+///
+/// Dereferences to [`AsyncClient`]. Returned to the pool on drop.
+pub type PooledClient<'a> = codes_core::pool::PooledClient<'a, AsyncClient>;
+
+/// Health of one idle worker, from [`SessionPool::health_check`].
+///
+/// App-server workers have no session to resume, so `session` is always `None`.
+pub type HealthReport = codes_core::pool::HealthReport<Infallible>;
+
+/// Workers are started with [`AsyncClient::start_with`], so each is
+/// initialized before it is handed out. Restarts start a fresh app-server.
+impl PoolClient for AsyncClient {
+    type Builder = AppServerBuilder;
+    type Session = Infallible;
+    type Error = Error;
+
+    fn spawn(
+        builder: &AppServerBuilder,
+        _session: Option<Infallible>,
+    ) -> BoxFuture<'static, Result<Self, Error>> {
+        Box::pin(AsyncClient::start_with(builder.clone()))
+    }
+
+    fn is_alive(&mut self) -> bool {
+        AsyncClient::is_alive(self)
+    }
+
+    fn shutdown(self) -> BoxFuture<'static, Result<(), Error>> {
+        Box::pin(AsyncClient::shutdown(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{StreamTransport, Transport, TransportReader, TransportWriter};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// A stream transport whose liveness the test controls.
+    struct Switchable {
+        inner: StreamTransport,
+        alive: Arc<AtomicBool>,
+    }
+
+    impl Transport for Switchable {
+        fn connect(&mut self) -> std::io::Result<(TransportReader, TransportWriter)> {
+            self.inner.connect()
+        }
+
+        fn is_alive(&mut self) -> bool {
+            self.alive.load(Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_check_restarts_exited_workers() {
+        let alive = Arc::new(Mutex::new(Vec::new()));
+        let flags = alive.clone();
+        let pool = SessionPool::with_spawner(move |_| {
+            let flag = Arc::new(AtomicBool::new(true));
+            flags.lock().unwrap().push(flag.clone());
+            let (client_io, _server_io) = tokio::io::duplex(64);
+            let transport = Switchable {
+                inner: StreamTransport::from_stream(client_io),
+                alive: flag,
+            };
+            Box::pin(async move { AsyncClient::from_transport(transport) })
+        })
+        .max_restarts(1);
+
+        drop(pool.checkout().await.unwrap());
+        alive.lock().unwrap()[0].store(false, Ordering::SeqCst);
+
+        let reports = pool.health_check().await;
+        assert_eq!(
+            reports,
+            vec![HealthReport {
+                worker: 0,
+                session: None,
+                status: WorkerStatus::Restarted
+            }]
+        );
+        assert_eq!(pool.stats().restarts, 1);
+
+        alive.lock().unwrap()[1].store(false, Ordering::SeqCst);
+        let reports = pool.health_check().await;
+        assert!(matches!(reports[0].status, WorkerStatus::Failed(_)));
+        assert_eq!(pool.stats().idle, 0);
+    }
+}
@@ -13,6 +13,8 @@
 //!   field of a [`ServerMessage::Notification`]
 //! - **Approval flow types** — [`CommandExecutionApprovalParams`] and
 //!   [`FileChangeApprovalParams`] for server-to-client requests that need a response
+//! - **Typed server messages** — [`TypedServerMessage`], [`ServerNotification`] and
+//!   [`ServerRequest`] decode a [`ServerMessage`] by method, with `Unknown` fallbacks
 //! - **Method constants** — The [`methods`] module contains all JSON-RPC method
 //!   name strings
 //!
@@ -33,9 +35,10 @@
 //! ```
 
 use crate::io::items::ThreadItem;
+use crate::io::options::{ApprovalMode, ModelReasoningEffort, SandboxMode, ThreadOptions};
 use crate::jsonrpc::RequestId;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 // ---------------------------------------------------------------------------
 // User input
@@ -113,13 +116,103 @@ pub struct InitializeResponse {
     pub user_agent: String,
 }
 
+// ---------------------------------------------------------------------------
+// Sandbox policy
+// ---------------------------------------------------------------------------
+
+/// Sandbox policy for commands the agent runs, sent with [`TurnStartParams`].
+///
+/// Where [`SandboxMode`] only names a level, a policy also carries the
+/// settings of a workspace-write sandbox.
+///
+/// # Example
+///
+/// ```
+/// use codex_codes::{SandboxPolicy, WorkspaceWritePolicy};
+///
+/// let policy = SandboxPolicy::WorkspaceWrite(WorkspaceWritePolicy {
+///     writable_roots: vec!["/tmp/cache".into()],
+///     network_access: true,
+///     ..Default::default()
+/// });
+/// let json = serde_json::to_value(&policy).unwrap();
+/// assert_eq!(json["type"], "workspaceWrite");
+/// assert_eq!(json["networkAccess"], true);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SandboxPolicy {
+    /// Commands may read anything but write nothing.
+    ReadOnly,
+    /// Commands may write inside the working directory and the listed roots.
+    WorkspaceWrite(WorkspaceWritePolicy),
+    /// No sandbox at all.
+    DangerFullAccess,
+}
+
+/// Settings of a [`SandboxPolicy::WorkspaceWrite`] sandbox.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceWritePolicy {
+    /// Directories writable in addition to the working directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writable_roots: Vec<String>,
+    /// Whether commands may use the network.
+    #[serde(default)]
+    pub network_access: bool,
+    /// Keep the directory named by `$TMPDIR` read-only.
+    #[serde(default)]
+    pub exclude_tmpdir_env_var: bool,
+    /// Keep `/tmp` read-only.
+    #[serde(default)]
+    pub exclude_slash_tmp: bool,
+}
+
+impl SandboxPolicy {
+    /// The policy for a sandbox mode, with default settings.
+    pub fn from_mode(mode: &SandboxMode) -> Self {
+        match mode {
+            SandboxMode::ReadOnly => SandboxPolicy::ReadOnly,
+            SandboxMode::WorkspaceWrite => {
+                SandboxPolicy::WorkspaceWrite(WorkspaceWritePolicy::default())
+            }
+            SandboxMode::DangerFullAccess => SandboxPolicy::DangerFullAccess,
+        }
+    }
+
+    /// The policy described by `options`, or `None` if it sets no sandbox mode.
+    ///
+    /// A workspace-write sandbox takes its writable roots from
+    /// `additional_directories` and its network access from
+    /// `network_access_enabled`.
+    pub fn from_options(options: &ThreadOptions) -> Option<Self> {
+        let mut policy = Self::from_mode(options.sandbox_mode.as_ref()?);
+        if let SandboxPolicy::WorkspaceWrite(settings) = &mut policy {
+            settings.writable_roots = options.additional_directories.clone();
+            settings.network_access = options.network_access_enabled.unwrap_or(false);
+        }
+        Some(policy)
+    }
+
+    /// The sandbox mode this policy corresponds to.
+    pub fn mode(&self) -> SandboxMode {
+        match self {
+            SandboxPolicy::ReadOnly => SandboxMode::ReadOnly,
+            SandboxPolicy::WorkspaceWrite(_) => SandboxMode::WorkspaceWrite,
+            SandboxPolicy::DangerFullAccess => SandboxMode::DangerFullAccess,
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Thread lifecycle requests
 // ---------------------------------------------------------------------------
 
 /// Parameters for `thread/start`.
 ///
-/// Use `ThreadStartParams::default()` for a basic thread with no custom instructions.
+/// Use `ThreadStartParams::default()` for a basic thread with no custom
+/// instructions, or [`ThreadStartParams::from_options`] to configure it from
+/// [`ThreadOptions`].
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ThreadStartParams {
@@ -129,19 +222,101 @@ pub struct ThreadStartParams {
     /// Optional tool definitions to make available to the agent.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Value>>,
+    /// Model for the thread (e.g., `"gpt-5-codex"`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Working directory for the thread.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// When the agent asks before running commands.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalMode>,
+    /// Sandbox level for commands the agent runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxMode>,
+    /// `config.toml` overrides for the thread, keyed by dotted path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<Map<String, Value>>,
 }
 
-/// Thread metadata returned inside a [`ThreadStartResponse`].
+impl ThreadStartParams {
+    /// Parameters for a thread configured by `options`.
+    ///
+    /// Settings without a dedicated field — reasoning effort, network
+    /// access, web search and additional writable directories — are sent as
+    /// [`config`](Self::config) overrides.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use codex_codes::{ModelReasoningEffort, SandboxMode, ThreadOptions, ThreadStartParams};
+    ///
+    /// let params = ThreadStartParams::from_options(&ThreadOptions {
+    ///     model: Some("gpt-5-codex".into()),
+    ///     sandbox_mode: Some(SandboxMode::WorkspaceWrite),
+    ///     model_reasoning_effort: Some(ModelReasoningEffort::High),
+    ///     ..Default::default()
+    /// });
+    /// let json = serde_json::to_value(&params).unwrap();
+    /// assert_eq!(json["sandbox"], "workspace-write");
+    /// assert_eq!(json["config"]["model_reasoning_effort"], "high");
+    /// ```
+    pub fn from_options(options: &ThreadOptions) -> Self {
+        let mut config = Map::new();
+        let mut set = |key: &str, value: Value| {
+            config.insert(key.to_string(), value);
+        };
+        if let Some(ref effort) = options.model_reasoning_effort {
+            set("model_reasoning_effort", serde_json::json!(effort));
+        }
+        if let Some(network) = options.network_access_enabled {
+            set("sandbox_workspace_write.network_access", network.into());
+        }
+        if !options.additional_directories.is_empty() {
+            set(
+                "sandbox_workspace_write.writable_roots",
+                serde_json::json!(options.additional_directories),
+            );
+        }
+        if let Some(ref mode) = options.web_search_mode {
+            set("web_search", serde_json::json!(mode));
+        }
+        if let Some(enabled) = options.web_search_enabled {
+            set("tools.web_search", enabled.into());
+        }
+
+        Self {
+            model: options.model.clone(),
+            cwd: options.working_directory.clone(),
+            approval_policy: options.approval_policy.clone(),
+            sandbox: options.sandbox_mode.clone(),
+            config: (!config.is_empty()).then_some(config),
+            ..Self::default()
+        }
+    }
+}
+
+/// Thread metadata returned by `thread/start`, `thread/list`, `thread/read`, etc.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ThreadInfo {
     /// Unique thread identifier.
     pub id: String,
+    /// The thread's turns, when requested with [`ThreadReadParams::include_turns`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turns: Vec<Turn>,
     /// All other fields are captured but not typed.
     #[serde(flatten)]
     pub extra: Value,
 }
 
+impl ThreadInfo {
+    /// All stored items, across turns in order.
+    pub fn items(&self) -> impl Iterator<Item = &ThreadItem> {
+        self.turns.iter().flat_map(|turn| turn.items.iter())
+    }
+}
+
 /// Response from `thread/start`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -175,6 +350,139 @@ pub struct ThreadArchiveParams {
 #[serde(rename_all = "camelCase")]
 pub struct ThreadArchiveResponse {}
 
+/// Parameters for `thread/resume`.
+///
+/// Loads a persisted thread so new turns can be started on it. The optional
+/// fields override the settings it was created with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadResumeParams {
+    pub thread_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxMode>,
+    /// `config.toml` overrides, keyed by dotted path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<Map<String, Value>>,
+}
+
+impl ThreadResumeParams {
+    /// Resume `thread_id` with its original settings.
+    pub fn new(thread_id: impl Into<String>) -> Self {
+        Self {
+            thread_id: thread_id.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Response from `thread/resume`; the same shape as [`ThreadStartResponse`].
+pub type ThreadResumeResponse = ThreadStartResponse;
+
+/// Parameters for `thread/fork`.
+///
+/// Copies a thread's history into a new thread, leaving the original
+/// untouched. To fork at an earlier point, roll the new thread back with
+/// `thread/rollback` ([`ThreadRollbackParams`]).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadForkParams {
+    /// The thread to copy.
+    pub thread_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxMode>,
+    /// `config.toml` overrides, keyed by dotted path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<Map<String, Value>>,
+}
+
+impl ThreadForkParams {
+    /// Fork `thread_id` with its original settings.
+    pub fn new(thread_id: impl Into<String>) -> Self {
+        Self {
+            thread_id: thread_id.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Response from `thread/fork`, describing the new thread; the same shape as
+/// [`ThreadStartResponse`].
+pub type ThreadForkResponse = ThreadStartResponse;
+
+/// Parameters for `thread/rollback`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadRollbackParams {
+    pub thread_id: String,
+    /// How many of the most recent turns to drop.
+    pub num_turns: u32,
+}
+
+/// Response from `thread/rollback`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadRollbackResponse {
+    /// The thread after the rollback.
+    pub thread: ThreadInfo,
+}
+
+/// Parameters for `thread/list`.
+///
+/// Threads are returned newest first, a page at a time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadListParams {
+    /// The `next_cursor` of the previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Page size; the server picks one if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// List archived threads instead of active ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+}
+
+/// Response from `thread/list`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadListResponse {
+    /// Threads on this page.
+    pub data: Vec<ThreadInfo>,
+    /// Cursor for the next page, or `None` on the last one.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// Parameters for `thread/read`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadReadParams {
+    pub thread_id: String,
+    /// Include the thread's turns and their items.
+    #[serde(default)]
+    pub include_turns: bool,
+}
+
+/// Response from `thread/read`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadReadResponse {
+    pub thread: ThreadInfo,
+}
+
 // ---------------------------------------------------------------------------
 // Turn lifecycle requests
 // ---------------------------------------------------------------------------
@@ -182,8 +490,9 @@ pub struct ThreadArchiveResponse {}
 /// Parameters for `turn/start`.
 ///
 /// Starts a new agent turn within an existing thread. The agent processes the
-/// input and streams notifications until the turn completes.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// input and streams notifications until the turn completes. The optional
+/// fields override the thread's settings for this and later turns.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TurnStartParams {
     /// The thread ID from [`ThreadStartResponse`].
@@ -193,12 +502,61 @@ pub struct TurnStartParams {
     /// Override the model for this turn (e.g., `"o4-mini"`).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
-    /// Override reasoning effort for this turn (e.g., `"low"`, `"medium"`, `"high"`).
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub reasoning_effort: Option<String>,
+    /// Override reasoning effort for this turn.
+    #[serde(
+        rename = "effort",
+        alias = "reasoningEffort",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reasoning_effort: Option<ModelReasoningEffort>,
     /// Override sandbox policy for this turn.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub sandbox_policy: Option<Value>,
+    pub sandbox_policy: Option<SandboxPolicy>,
+    /// Override the working directory for this turn.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Override when the agent asks before running commands.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<ApprovalMode>,
+}
+
+impl TurnStartParams {
+    /// Parameters for a turn sending `input` to a thread, with no overrides.
+    pub fn new(thread_id: impl Into<String>, input: Vec<UserInput>) -> Self {
+        Self {
+            thread_id: thread_id.into(),
+            input,
+            ..Self::default()
+        }
+    }
+
+    /// Parameters for a turn sending a single text message.
+    pub fn text(thread_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::new(thread_id, vec![UserInput::Text { text: text.into() }])
+    }
+
+    /// Override this turn's settings with those set in `options`.
+    ///
+    /// The sandbox policy is built with [`SandboxPolicy::from_options`].
+    /// Web search settings apply to whole threads only and are ignored.
+    pub fn with_options(mut self, options: &ThreadOptions) -> Self {
+        if options.model.is_some() {
+            self.model = options.model.clone();
+        }
+        if options.model_reasoning_effort.is_some() {
+            self.reasoning_effort = options.model_reasoning_effort.clone();
+        }
+        if let Some(policy) = SandboxPolicy::from_options(options) {
+            self.sandbox_policy = Some(policy);
+        }
+        if options.working_directory.is_some() {
+            self.cwd = options.working_directory.clone();
+        }
+        if options.approval_policy.is_some() {
+            self.approval_policy = options.approval_policy.clone();
+        }
+        self
+    }
 }
 
 /// Response from `turn/start`.
@@ -218,6 +576,30 @@ pub struct TurnInterruptParams {
 #[serde(rename_all = "camelCase")]
 pub struct TurnInterruptResponse {}
 
+/// Parameters for `turn/steer`.
+///
+/// Adds input to the turn in progress without interrupting it; the agent
+/// picks it up at its next step.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnSteerParams {
+    pub thread_id: String,
+    /// Additional user input for the running turn.
+    pub input: Vec<UserInput>,
+    /// Only steer if this turn is the one running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_turn_id: Option<String>,
+}
+
+/// Response from `turn/steer`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnSteerResponse {
+    /// The turn that received the input.
+    #[serde(default)]
+    pub turn_id: Option<String>,
+}
+
 // ---------------------------------------------------------------------------
 // Turn status & data types
 // ---------------------------------------------------------------------------
@@ -270,7 +652,7 @@ pub struct Turn {
 /// Cumulative token usage for a thread.
 ///
 /// Sent via [`ThreadTokenUsageUpdatedNotification`] after each turn.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TokenUsage {
     /// Total input tokens consumed.
@@ -535,6 +917,242 @@ pub enum ServerMessage {
     },
 }
 
+impl ServerMessage {
+    /// The JSON-RPC method name.
+    pub fn method(&self) -> &str {
+        match self {
+            ServerMessage::Notification { method, .. } | ServerMessage::Request { method, .. } => {
+                method
+            }
+        }
+    }
+
+    /// Decode into a [`TypedServerMessage`] keyed by method.
+    pub fn into_typed(self) -> TypedServerMessage {
+        match self {
+            ServerMessage::Notification { method, params } => {
+                TypedServerMessage::Notification(ServerNotification::from_parts(method, params))
+            }
+            ServerMessage::Request { id, method, params } => {
+                TypedServerMessage::Request(ServerRequest::from_parts(id, method, params))
+            }
+        }
+    }
+}
+
+impl From<ServerMessage> for TypedServerMessage {
+    fn from(msg: ServerMessage) -> Self {
+        msg.into_typed()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Typed server messages
+// ---------------------------------------------------------------------------
+
+/// A [`ServerMessage`] decoded into typed notification or request bodies.
+///
+/// Returned by [`AsyncClient::next_typed_message`](crate::AsyncClient::next_typed_message)
+/// and [`SyncClient::next_typed_message`](crate::SyncClient::next_typed_message).
+///
+/// # Example
+///
+/// ```
+/// use codex_codes::{ServerMessage, ServerNotification, TypedServerMessage};
+/// use serde_json::json;
+///
+/// let raw = ServerMessage::Notification {
+///     method: "item/agentMessage/delta".into(),
+///     params: Some(json!({"threadId": "th_1", "itemId": "msg_1", "delta": "Hi"})),
+/// };
+///
+/// match raw.into_typed() {
+///     TypedServerMessage::Notification(ServerNotification::AgentMessageDelta(d)) => {
+///         assert_eq!(d.delta, "Hi");
+///     }
+///     other => panic!("unexpected: {:?}", other),
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub enum TypedServerMessage {
+    /// A notification (no response needed).
+    Notification(ServerNotification),
+    /// A request that needs a response via the client's `respond()` method.
+    Request(ServerRequest),
+}
+
+/// A server notification decoded by method.
+///
+/// Methods this crate does not know — and known methods whose params fail to
+/// parse — are preserved as [`ServerNotification::Unknown`].
+#[derive(Debug, Clone)]
+pub enum ServerNotification {
+    /// `thread/started`
+    ThreadStarted(ThreadStartedNotification),
+    /// `thread/status/changed`
+    ThreadStatusChanged(ThreadStatusChangedNotification),
+    /// `thread/tokenUsage/updated`
+    ThreadTokenUsageUpdated(ThreadTokenUsageUpdatedNotification),
+    /// `turn/started`
+    TurnStarted(TurnStartedNotification),
+    /// `turn/completed`
+    TurnCompleted(TurnCompletedNotification),
+    /// `item/started`
+    ItemStarted(ItemStartedNotification),
+    /// `item/completed`
+    ItemCompleted(ItemCompletedNotification),
+    /// `item/agentMessage/delta`
+    AgentMessageDelta(AgentMessageDeltaNotification),
+    /// `item/commandExecution/outputDelta`
+    CmdOutputDelta(CmdOutputDeltaNotification),
+    /// `item/fileChange/outputDelta`
+    FileChangeOutputDelta(FileChangeOutputDeltaNotification),
+    /// `item/reasoning/summaryTextDelta`
+    ReasoningDelta(ReasoningDeltaNotification),
+    /// `error`
+    Error(ErrorNotification),
+    /// Any other notification, left undecoded.
+    Unknown {
+        method: String,
+        params: Option<Value>,
+    },
+}
+
+impl ServerNotification {
+    /// Decode a notification from its method and params.
+    pub fn from_parts(method: String, params: Option<Value>) -> Self {
+        fn parse<T: serde::de::DeserializeOwned>(params: &Option<Value>) -> Option<T> {
+            serde_json::from_value(params.clone().unwrap_or(Value::Null)).ok()
+        }
+
+        let typed = match method.as_str() {
+            methods::THREAD_STARTED => parse(&params).map(Self::ThreadStarted),
+            methods::THREAD_STATUS_CHANGED => parse(&params).map(Self::ThreadStatusChanged),
+            methods::THREAD_TOKEN_USAGE_UPDATED => {
+                parse(&params).map(Self::ThreadTokenUsageUpdated)
+            }
+            methods::TURN_STARTED => parse(&params).map(Self::TurnStarted),
+            methods::TURN_COMPLETED => parse(&params).map(Self::TurnCompleted),
+            methods::ITEM_STARTED => parse(&params).map(Self::ItemStarted),
+            methods::ITEM_COMPLETED => parse(&params).map(Self::ItemCompleted),
+            methods::AGENT_MESSAGE_DELTA => parse(&params).map(Self::AgentMessageDelta),
+            methods::CMD_OUTPUT_DELTA => parse(&params).map(Self::CmdOutputDelta),
+            methods::FILE_CHANGE_OUTPUT_DELTA => parse(&params).map(Self::FileChangeOutputDelta),
+            methods::REASONING_DELTA => parse(&params).map(Self::ReasoningDelta),
+            methods::ERROR => parse(&params).map(Self::Error),
+            _ => None,
+        };
+        typed.unwrap_or(Self::Unknown { method, params })
+    }
+
+    /// The JSON-RPC method name.
+    pub fn method(&self) -> &str {
+        match self {
+            Self::ThreadStarted(_) => methods::THREAD_STARTED,
+            Self::ThreadStatusChanged(_) => methods::THREAD_STATUS_CHANGED,
+            Self::ThreadTokenUsageUpdated(_) => methods::THREAD_TOKEN_USAGE_UPDATED,
+            Self::TurnStarted(_) => methods::TURN_STARTED,
+            Self::TurnCompleted(_) => methods::TURN_COMPLETED,
+            Self::ItemStarted(_) => methods::ITEM_STARTED,
+            Self::ItemCompleted(_) => methods::ITEM_COMPLETED,
+            Self::AgentMessageDelta(_) => methods::AGENT_MESSAGE_DELTA,
+            Self::CmdOutputDelta(_) => methods::CMD_OUTPUT_DELTA,
+            Self::FileChangeOutputDelta(_) => methods::FILE_CHANGE_OUTPUT_DELTA,
+            Self::ReasoningDelta(_) => methods::REASONING_DELTA,
+            Self::Error(_) => methods::ERROR,
+            Self::Unknown { method, .. } => method,
+        }
+    }
+
+    /// The thread this notification belongs to, when it carries one.
+    pub fn thread_id(&self) -> Option<&str> {
+        match self {
+            Self::ThreadStarted(n) => Some(&n.thread_id),
+            Self::ThreadStatusChanged(n) => Some(&n.thread_id),
+            Self::ThreadTokenUsageUpdated(n) => Some(&n.thread_id),
+            Self::TurnStarted(n) => Some(&n.thread_id),
+            Self::TurnCompleted(n) => Some(&n.thread_id),
+            Self::ItemStarted(n) => Some(&n.thread_id),
+            Self::ItemCompleted(n) => Some(&n.thread_id),
+            Self::AgentMessageDelta(n) => Some(&n.thread_id),
+            Self::CmdOutputDelta(n) => Some(&n.thread_id),
+            Self::FileChangeOutputDelta(n) => Some(&n.thread_id),
+            Self::ReasoningDelta(n) => Some(&n.thread_id),
+            Self::Error(n) => n.thread_id.as_deref(),
+            Self::Unknown { params, .. } => params
+                .as_ref()
+                .and_then(|p| p.get("threadId"))
+                .and_then(Value::as_str),
+        }
+    }
+}
+
+/// A server-to-client request decoded by method.
+///
+/// Every variant carries the request `id` to pass to the client's `respond()`.
+/// Unknown methods — and known methods whose params fail to parse — are
+/// preserved as [`ServerRequest::Unknown`].
+#[derive(Debug, Clone)]
+pub enum ServerRequest {
+    /// `item/commandExecution/requestApproval` — respond with
+    /// [`CommandExecutionApprovalResponse`].
+    CommandExecutionApproval {
+        id: RequestId,
+        params: CommandExecutionApprovalParams,
+    },
+    /// `item/fileChange/requestApproval` — respond with
+    /// [`FileChangeApprovalResponse`].
+    FileChangeApproval {
+        id: RequestId,
+        params: FileChangeApprovalParams,
+    },
+    /// Any other request, left undecoded.
+    Unknown {
+        id: RequestId,
+        method: String,
+        params: Option<Value>,
+    },
+}
+
+impl ServerRequest {
+    /// Decode a request from its id, method and params.
+    pub fn from_parts(id: RequestId, method: String, params: Option<Value>) -> Self {
+        let value = params.clone().unwrap_or(Value::Null);
+        match method.as_str() {
+            methods::CMD_EXEC_APPROVAL => {
+                if let Ok(params) = serde_json::from_value(value) {
+                    return Self::CommandExecutionApproval { id, params };
+                }
+            }
+            methods::FILE_CHANGE_APPROVAL => {
+                if let Ok(params) = serde_json::from_value(value) {
+                    return Self::FileChangeApproval { id, params };
+                }
+            }
+            _ => {}
+        }
+        Self::Unknown { id, method, params }
+    }
+
+    /// The request id to respond to.
+    pub fn id(&self) -> &RequestId {
+        match self {
+            Self::CommandExecutionApproval { id, .. }
+            | Self::FileChangeApproval { id, .. }
+            | Self::Unknown { id, .. } => id,
+        }
+    }
+
+    /// The JSON-RPC method name.
+    pub fn method(&self) -> &str {
+        match self {
+            Self::CommandExecutionApproval { .. } => methods::CMD_EXEC_APPROVAL,
+            Self::FileChangeApproval { .. } => methods::FILE_CHANGE_APPROVAL,
+            Self::Unknown { method, .. } => method,
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Method name constants
 // ---------------------------------------------------------------------------
@@ -549,6 +1167,11 @@ pub mod methods {
     pub const INITIALIZED: &str = "initialized";
     pub const THREAD_START: &str = "thread/start";
     pub const THREAD_ARCHIVE: &str = "thread/archive";
+    pub const THREAD_RESUME: &str = "thread/resume";
+    pub const THREAD_FORK: &str = "thread/fork";
+    pub const THREAD_ROLLBACK: &str = "thread/rollback";
+    pub const THREAD_LIST: &str = "thread/list";
+    pub const THREAD_READ: &str = "thread/read";
     pub const TURN_START: &str = "turn/start";
     pub const TURN_INTERRUPT: &str = "turn/interrupt";
     pub const TURN_STEER: &str = "turn/steer";
@@ -633,6 +1256,7 @@ mod tests {
         let params = ThreadStartParams {
             instructions: Some("Be helpful".to_string()),
             tools: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&params).unwrap();
         assert!(json.contains("instructions"));
@@ -654,15 +1278,66 @@ mod tests {
             input: vec![UserInput::Text {
                 text: "What is 2+2?".to_string(),
             }],
-            model: None,
-            reasoning_effort: None,
-            sandbox_policy: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&params).unwrap();
         assert!(json.contains("threadId"));
         assert!(json.contains("input"));
     }
 
+    #[test]
+    fn test_options_map_to_thread_and_turn_params() {
+        let options = ThreadOptions {
+            model: Some("gpt-5-codex".to_string()),
+            sandbox_mode: Some(SandboxMode::WorkspaceWrite),
+            working_directory: Some("/work".to_string()),
+            model_reasoning_effort: Some(ModelReasoningEffort::Low),
+            network_access_enabled: Some(true),
+            approval_policy: Some(ApprovalMode::OnRequest),
+            additional_directories: vec!["/cache".to_string()],
+            ..Default::default()
+        };
+
+        let thread = serde_json::to_value(ThreadStartParams::from_options(&options)).unwrap();
+        assert_eq!(
+            thread,
+            serde_json::json!({
+                "model": "gpt-5-codex",
+                "cwd": "/work",
+                "approvalPolicy": "on-request",
+                "sandbox": "workspace-write",
+                "config": {
+                    "model_reasoning_effort": "low",
+                    "sandbox_workspace_write.network_access": true,
+                    "sandbox_workspace_write.writable_roots": ["/cache"]
+                }
+            })
+        );
+
+        let turn = TurnStartParams::text("th_1", "hi").with_options(&options);
+        let json = serde_json::to_value(&turn).unwrap();
+        assert_eq!(json["effort"], "low");
+        assert_eq!(
+            json["sandboxPolicy"],
+            serde_json::json!({
+                "type": "workspaceWrite",
+                "writableRoots": ["/cache"],
+                "networkAccess": true,
+                "excludeTmpdirEnvVar": false,
+                "excludeSlashTmp": false
+            })
+        );
+        let parsed: TurnStartParams = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            parsed.sandbox_policy.unwrap().mode(),
+            SandboxMode::WorkspaceWrite
+        );
+        assert_eq!(
+            serde_json::to_value(SandboxPolicy::ReadOnly).unwrap(),
+            serde_json::json!({"type": "readOnly"})
+        );
+    }
+
     #[test]
     fn test_turn_status() {
         let json = r#""completed""#;
@@ -732,6 +1407,77 @@ mod tests {
         assert_eq!(status, ThreadStatus::Idle);
     }
 
+    #[test]
+    fn test_typed_notification() {
+        let msg = ServerMessage::Notification {
+            method: methods::TURN_COMPLETED.to_string(),
+            params: Some(serde_json::json!({
+                "threadId": "th_1",
+                "turnId": "t_1",
+                "turn": {"id": "t_1", "items": [], "status": "completed"}
+            })),
+        };
+        match msg.into_typed() {
+            TypedServerMessage::Notification(notif) => {
+                assert_eq!(notif.method(), methods::TURN_COMPLETED);
+                assert_eq!(notif.thread_id(), Some("th_1"));
+                assert!(
+                    matches!(notif, ServerNotification::TurnCompleted(ref n) if n.turn_id == "t_1")
+                );
+            }
+            other => panic!("expected notification, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_typed_notification_unknown_fallback() {
+        // Unknown method
+        let notif = ServerNotification::from_parts(
+            "thread/somethingNew".to_string(),
+            Some(serde_json::json!({"threadId": "th_2"})),
+        );
+        assert!(matches!(notif, ServerNotification::Unknown { .. }));
+        assert_eq!(notif.method(), "thread/somethingNew");
+        assert_eq!(notif.thread_id(), Some("th_2"));
+
+        // Known method, unparseable params
+        let notif = ServerNotification::from_parts(
+            methods::AGENT_MESSAGE_DELTA.to_string(),
+            Some(serde_json::json!({"unexpected": true})),
+        );
+        assert!(matches!(notif, ServerNotification::Unknown { .. }));
+    }
+
+    #[test]
+    fn test_typed_request() {
+        let msg = ServerMessage::Request {
+            id: RequestId::Integer(7),
+            method: methods::CMD_EXEC_APPROVAL.to_string(),
+            params: Some(serde_json::json!({
+                "threadId": "th_1",
+                "turnId": "t_1",
+                "callId": "call_1",
+                "command": "ls",
+                "cwd": "/tmp"
+            })),
+        };
+        match msg.into_typed() {
+            TypedServerMessage::Request(ServerRequest::CommandExecutionApproval { id, params }) => {
+                assert_eq!(id, RequestId::Integer(7));
+                assert_eq!(params.command, "ls");
+            }
+            other => panic!("expected approval request, got {:?}", other),
+        }
+
+        let req = ServerRequest::from_parts(
+            RequestId::String("r1".to_string()),
+            "item/tool/requestUserInput".to_string(),
+            None,
+        );
+        assert_eq!(req.method(), "item/tool/requestUserInput");
+        assert_eq!(req.id(), &RequestId::String("r1".to_string()));
+    }
+
     #[test]
     fn test_token_usage() {
         let json = r#"{"inputTokens":100,"outputTokens":200,"cachedInputTokens":50}"#;
@@ -0,0 +1,34 @@
+//! Background stderr capture and diagnostics.
+//!
+//! The app-server writes its `tracing` log, update notices and failures to
+//! stderr. If nobody reads the pipe it eventually fills up and the server
+//! blocks, so long-running clients should drain it. A [`StderrPump`] does
+//! this on a background task: it keeps a bounded ring buffer of recent lines
+//! in a [`StderrBuffer`], forwards every line to `log` and an optional
+//! callback, and recognizes a few well-known shapes as [`Diagnostic`]s.
+//! These types live in the shared `codes-core` crate, so they are the same
+//! ones `claude_codes::stderr` exports.
+//!
+//! Attach a pump with `AsyncClient::capture_stderr`; the client then fills
+//! the stderr tail of [`Error::ServerCrashed`](crate::Error::ServerCrashed)
+//! reports from the buffer.
+//!
+//! # Example
+//!
+//! ```
+//! use codex_codes::stderr::Diagnostic;
+//!
+//! let line = "2026-01-01T00:00:00Z ERROR codex_core::mcp: MCP client for `github` failed to start";
+//! assert!(matches!(
+//!     Diagnostic::parse(line),
+//!     Some(Diagnostic::McpServerFailed { server: Some(ref name), .. }) if name == "github"
+//! ));
+//! assert!(Diagnostic::parse("2026-01-01T00:00:00Z  INFO codex_core: ready").is_none());
+//! ```
+
+pub use codes_core::stderr::Diagnostic;
+
+#[cfg(feature = "async-client")]
+pub use codes_core::stderr::{
+    StderrBuffer, StderrLine, StderrPump, DEFAULT_STDERR_CAPACITY, MAX_STDERR_LINE_BYTES,
+};
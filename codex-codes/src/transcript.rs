@@ -0,0 +1,46 @@
+//! Wire-level session transcripts: recording and deterministic replay.
+//!
+//! A transcript is a versioned JSONL file. The first line is a
+//! [`TranscriptHeader`]; every following line is a [`TranscriptRecord`] holding
+//! one raw JSON-RPC line, the direction it travelled and when. The format is
+//! shared with the `claude-codes` crate, with `provider` set to `"codex"`.
+//!
+//! ```text
+//! {"transcript_version":1,"provider":"codex","crate_version":"0.101.0","started_at_ms":1767225600000}
+//! {"timestamp_ms":1767225600010,"direction":"sent","line":"{\"id\":1,\"method\":\"initialize\",...}"}
+//! {"timestamp_ms":1767225600050,"direction":"received","line":"{\"id\":1,\"result\":{...}}"}
+//! ```
+//!
+//! Attach a [`TranscriptRecorder`] with `AsyncClient::record_transcript` to
+//! capture a session, then feed the file back through a [`ReplayTransport`] to
+//! reproduce it without the app-server.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use codex_codes::transcript::{ReplayTransport, Transcript, TranscriptRecorder};
+//! use codex_codes::{AsyncClient, ThreadStartParams};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! // Record a live session (the recorder is attached after the handshake)
+//! let mut client = AsyncClient::start().await?;
+//! client.record_transcript(TranscriptRecorder::create("session.jsonl")?);
+//! client.thread_start(&ThreadStartParams::default()).await?;
+//!
+//! // Replay it later, e.g. in a test
+//! let transcript = Transcript::load("session.jsonl")?;
+//! let mut replayed = AsyncClient::from_transport(ReplayTransport::new(transcript))?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use codes_core::transcript::{
+    Direction, Transcript, TranscriptHeader, TranscriptRecord, TranscriptRecorder,
+    TRANSCRIPT_VERSION,
+};
+
+#[cfg(feature = "async-client")]
+pub use codes_core::transcript::ReplayTransport;
+
+/// Provider name written to transcripts recorded by this crate.
+pub const TRANSCRIPT_PROVIDER: &str = "codex";
@@ -0,0 +1,37 @@
+//! Byte-stream transports for the asynchronous client.
+//!
+//! [`AsyncClient`](crate::AsyncClient) handles newline-delimited JSON-RPC
+//! framing and request correlation, and delegates everything else to a
+//! [`Transport`]: where the bytes come from, where they go, and how the other
+//! end is started and stopped.
+//!
+//! Two implementations are provided:
+//!
+//! - [`ProcessTransport`] — a spawned `codex app-server` process, talking over
+//!   its stdio pipes. This is what [`AsyncClient::start`](crate::AsyncClient::start) uses.
+//! - [`StreamTransport`] — any [`AsyncRead`](tokio::io::AsyncRead) /
+//!   [`AsyncWrite`](tokio::io::AsyncWrite) pair, such as a Unix socket, an SSH
+//!   channel, a container exec stream or a [`tokio::io::duplex`] pipe.
+//!
+//! The transports live in the shared `codes-core` crate and are re-exported
+//! here, so they are the same ones `claude_codes::transport` exports.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use codex_codes::transport::StreamTransport;
+//! use codex_codes::{AsyncClient, ThreadStartParams};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let socket = tokio::net::UnixStream::connect("/tmp/codex.sock").await?;
+//! let mut client = AsyncClient::start_with_transport(StreamTransport::from_stream(socket)).await?;
+//! let thread = client.thread_start(&ThreadStartParams::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub use codes_core::transport::{
+    BoxFuture, ProcessTransport, StreamTransport, Transport, TransportReader, TransportWriter,
+};
+
+pub(crate) use codes_core::transport::STDERR_DRAIN_TIMEOUT;
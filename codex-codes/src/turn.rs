@@ -0,0 +1,554 @@
+//! Folding a turn's notifications into items and a final outcome.
+//!
+//! While a turn runs, the app-server announces each item with `item/started`,
+//! streams its text in delta notifications (`item/agentMessage/delta`,
+//! `item/reasoning/summaryTextDelta`, `item/commandExecution/outputDelta`,
+//! `item/fileChange/outputDelta`), and sends the finished item with
+//! `item/completed`. A [`TurnAggregator`] stitches these together per item,
+//! reporting each change as a [`TurnUpdate`], and produces a [`TurnOutcome`]
+//! once the turn's `turn/completed` arrives.
+//!
+//! `AsyncClient::run_turn` and `AsyncClient::stream_turn` drive an aggregator
+//! for you; use one directly when reading messages yourself.
+//!
+//! # Example
+//!
+//! ```
+//! use codex_codes::turn::{TurnAggregator, TurnUpdate};
+//! use codex_codes::ServerNotification;
+//! use serde_json::json;
+//!
+//! let mut turn = TurnAggregator::new("th_1");
+//! let notifications = [
+//!     ("turn/started", json!({"threadId": "th_1", "turnId": "turn_1"})),
+//!     ("item/agentMessage/delta", json!({"threadId": "th_1", "itemId": "msg_1", "delta": "4"})),
+//!     ("item/agentMessage/delta", json!({"threadId": "th_1", "itemId": "msg_1", "delta": "2"})),
+//!     ("turn/completed", json!({
+//!         "threadId": "th_1",
+//!         "turnId": "turn_1",
+//!         "turn": {"id": "turn_1", "status": "completed"}
+//!     })),
+//! ];
+//! for (method, params) in notifications {
+//!     let notification = ServerNotification::from_parts(method.to_string(), Some(params));
+//!     if let Some(TurnUpdate::ItemUpdated { delta, .. }) = turn.observe(&notification) {
+//!         print!("{}", delta);
+//!     }
+//! }
+//!
+//! let outcome = turn.into_outcome().unwrap();
+//! assert!(outcome.success());
+//! assert_eq!(outcome.agent_text(), "42");
+//! ```
+
+use crate::io::items::{
+    AgentMessageItem, CommandExecutionItem, CommandExecutionStatus, FileChangeItem, ReasoningItem,
+    ThreadItem,
+};
+use crate::protocol::{ServerNotification, ServerRequest, TokenUsage, Turn, TurnStatus};
+
+/// One change to a turn in progress.
+#[derive(Debug, Clone)]
+pub enum TurnUpdate {
+    /// `turn/started` — the server assigned the turn its ID.
+    TurnStarted { turn_id: String },
+    /// An item was announced with `item/started`.
+    ItemStarted(ThreadItem),
+    /// A delta notification extended an item.
+    ///
+    /// `item` is a snapshot with every delta so far applied. File change
+    /// deltas carry patch output the item has no field for, so only `delta`
+    /// reports it.
+    ItemUpdated { item: ThreadItem, delta: String },
+    /// An item finished with `item/completed`.
+    ItemCompleted(ThreadItem),
+    /// The thread's cumulative token usage, from `thread/tokenUsage/updated`.
+    Usage(TokenUsage),
+    /// A server request the approval handler did not answer.
+    ///
+    /// Respond with `TurnStream::respond`, or the turn will wait for it.
+    Request(ServerRequest),
+}
+
+/// Everything a completed turn produced.
+#[derive(Debug, Clone)]
+pub struct TurnOutcome {
+    pub thread_id: String,
+    /// The turn as sent in `turn/completed`.
+    pub turn: Turn,
+    /// Every item in the order it started, in its final form.
+    pub items: Vec<ThreadItem>,
+    /// The thread's cumulative token usage, if the server reported it during the turn.
+    pub usage: Option<TokenUsage>,
+}
+
+impl TurnOutcome {
+    /// Whether the turn finished with [`TurnStatus::Completed`].
+    pub fn success(&self) -> bool {
+        self.turn.status == TurnStatus::Completed
+    }
+
+    /// All agent message text, one message per line.
+    pub fn agent_text(&self) -> String {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                ThreadItem::AgentMessage(message) => Some(message.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The last agent message — the turn's answer.
+    pub fn final_message(&self) -> Option<&str> {
+        self.items.iter().rev().find_map(|item| match item {
+            ThreadItem::AgentMessage(message) => Some(message.text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The reasoning summaries, in order.
+    pub fn reasoning(&self) -> Vec<&str> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                ThreadItem::Reasoning(reasoning) => Some(reasoning.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The commands the agent ran, with their output.
+    pub fn commands(&self) -> Vec<&CommandExecutionItem> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                ThreadItem::CommandExecution(command) => Some(command),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The file changes the agent applied.
+    pub fn file_changes(&self) -> Vec<&FileChangeItem> {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                ThreadItem::FileChange(change) => Some(change),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Builds the items of one turn from its notifications.
+///
+/// Notifications for other threads are ignored, so one aggregator per
+/// running turn can share a message stream. The turn's ID is taken from
+/// `turn/started`, or from the first item if that arrives first; item and
+/// `turn/completed` notifications for any other turn on the thread — such as
+/// the tail of an interrupted turn — are ignored, as is a `turn/completed`
+/// that arrives before the turn has reported its ID.
+#[derive(Debug, Clone)]
+pub struct TurnAggregator {
+    thread_id: String,
+    turn_id: Option<String>,
+    items: Vec<ThreadItem>,
+    usage: Option<TokenUsage>,
+    completed: Option<Turn>,
+}
+
+impl TurnAggregator {
+    /// Aggregate the next turn on `thread_id`.
+    pub fn new(thread_id: impl Into<String>) -> Self {
+        Self {
+            thread_id: thread_id.into(),
+            turn_id: None,
+            items: Vec::new(),
+            usage: None,
+            completed: None,
+        }
+    }
+
+    /// The thread being aggregated.
+    pub fn thread_id(&self) -> &str {
+        &self.thread_id
+    }
+
+    /// The turn's ID, once `turn/started` or an item has reported it.
+    pub fn turn_id(&self) -> Option<&str> {
+        self.turn_id.as_deref()
+    }
+
+    /// The items seen so far, in the order they started.
+    pub fn items(&self) -> &[ThreadItem] {
+        &self.items
+    }
+
+    /// Whether `turn/completed` has arrived.
+    pub fn is_complete(&self) -> bool {
+        self.completed.is_some()
+    }
+
+    /// Fold one notification into the turn, returning what changed.
+    ///
+    /// `turn/completed` returns `None`; check [`is_complete`](Self::is_complete).
+    pub fn observe(&mut self, notification: &ServerNotification) -> Option<TurnUpdate> {
+        match notification {
+            ServerNotification::TurnStarted(started) if self.is_own(&started.thread_id) => {
+                if self
+                    .turn_id
+                    .as_ref()
+                    .is_some_and(|id| *id != started.turn_id)
+                {
+                    // The items seen so far belonged to an earlier turn.
+                    self.items.clear();
+                }
+                self.turn_id = Some(started.turn_id.clone());
+                Some(TurnUpdate::TurnStarted {
+                    turn_id: started.turn_id.clone(),
+                })
+            }
+            ServerNotification::ItemStarted(started)
+                if self.is_own(&started.thread_id) && self.note_turn(&started.turn_id) =>
+            {
+                self.upsert(started.item.clone());
+                Some(TurnUpdate::ItemStarted(started.item.clone()))
+            }
+            ServerNotification::ItemCompleted(completed)
+                if self.is_own(&completed.thread_id) && self.note_turn(&completed.turn_id) =>
+            {
+                self.upsert(completed.item.clone());
+                Some(TurnUpdate::ItemCompleted(completed.item.clone()))
+            }
+            ServerNotification::AgentMessageDelta(delta) if self.is_own(&delta.thread_id) => self
+                .apply_delta(&delta.item_id, &delta.delta, |id| {
+                    Some(ThreadItem::AgentMessage(AgentMessageItem {
+                        id,
+                        text: String::new(),
+                    }))
+                }),
+            ServerNotification::ReasoningDelta(delta) if self.is_own(&delta.thread_id) => self
+                .apply_delta(&delta.item_id, &delta.delta, |id| {
+                    Some(ThreadItem::Reasoning(ReasoningItem {
+                        id,
+                        text: String::new(),
+                    }))
+                }),
+            ServerNotification::CmdOutputDelta(delta) if self.is_own(&delta.thread_id) => self
+                .apply_delta(&delta.item_id, &delta.delta, |id| {
+                    Some(ThreadItem::CommandExecution(CommandExecutionItem {
+                        id,
+                        command: String::new(),
+                        aggregated_output: String::new(),
+                        exit_code: None,
+                        status: CommandExecutionStatus::InProgress,
+                    }))
+                }),
+            ServerNotification::FileChangeOutputDelta(delta) if self.is_own(&delta.thread_id) => {
+                // A file change item cannot be made up before `item/started`.
+                self.apply_delta(&delta.item_id, &delta.delta, |_| None)
+            }
+            ServerNotification::ThreadTokenUsageUpdated(updated)
+                if self.is_own(&updated.thread_id) =>
+            {
+                self.usage = Some(updated.usage);
+                Some(TurnUpdate::Usage(updated.usage))
+            }
+            ServerNotification::TurnCompleted(completed)
+                if self.is_own(&completed.thread_id)
+                    && self.turn_id.as_deref() == Some(completed.turn_id.as_str()) =>
+            {
+                self.completed = Some(completed.turn.clone());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// The outcome, or `None` if the turn has not completed.
+    ///
+    /// Items the final [`Turn`] lists replace those built from deltas, and
+    /// any the stream never mentioned are appended.
+    pub fn into_outcome(self) -> Option<TurnOutcome> {
+        let turn = self.completed?;
+        let mut items = self.items;
+        for item in &turn.items {
+            match items.iter_mut().find(|seen| seen.id() == item.id()) {
+                Some(seen) => *seen = item.clone(),
+                None => items.push(item.clone()),
+            }
+        }
+        Some(TurnOutcome {
+            thread_id: self.thread_id,
+            turn,
+            items,
+            usage: self.usage,
+        })
+    }
+
+    fn is_own(&self, thread_id: &str) -> bool {
+        self.completed.is_none() && thread_id == self.thread_id
+    }
+
+    /// Adopt `turn_id` if no turn has reported yet; whether it is this turn.
+    fn note_turn(&mut self, turn_id: &str) -> bool {
+        match &self.turn_id {
+            Some(own) => own == turn_id,
+            None => {
+                self.turn_id = Some(turn_id.to_string());
+                true
+            }
+        }
+    }
+
+    fn upsert(&mut self, item: ThreadItem) {
+        match self.items.iter_mut().find(|seen| seen.id() == item.id()) {
+            Some(seen) => *seen = item,
+            None => self.items.push(item),
+        }
+    }
+
+    fn apply_delta(
+        &mut self,
+        item_id: &str,
+        delta: &str,
+        placeholder: impl FnOnce(String) -> Option<ThreadItem>,
+    ) -> Option<TurnUpdate> {
+        let index = match self.items.iter().position(|item| item.id() == item_id) {
+            Some(index) => index,
+            None => {
+                self.items.push(placeholder(item_id.to_string())?);
+                self.items.len() - 1
+            }
+        };
+        let item = &mut self.items[index];
+        match item {
+            ThreadItem::AgentMessage(message) => message.text.push_str(delta),
+            ThreadItem::Reasoning(reasoning) => reasoning.text.push_str(delta),
+            ThreadItem::CommandExecution(command) => command.aggregated_output.push_str(delta),
+            _ => {}
+        }
+        Some(TurnUpdate::ItemUpdated {
+            item: item.clone(),
+            delta: delta.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn notification(method: &str, params: serde_json::Value) -> ServerNotification {
+        ServerNotification::from_parts(method.to_string(), Some(params))
+    }
+
+    #[test]
+    fn test_aggregates_deltas_and_completed_items() {
+        let mut turn = TurnAggregator::new("th_1");
+        let stream = [
+            notification(
+                "turn/started",
+                json!({"threadId": "th_1", "turnId": "turn_1"}),
+            ),
+            notification(
+                "item/started",
+                json!({"threadId": "th_1", "turnId": "turn_1", "item": {
+                    "type": "commandExecution", "id": "cmd_1", "command": "ls",
+                    "aggregated_output": "", "status": "inProgress"
+                }}),
+            ),
+            notification(
+                "item/commandExecution/outputDelta",
+                json!({"threadId": "th_1", "itemId": "cmd_1", "delta": "a.txt\n"}),
+            ),
+            notification(
+                "item/reasoning/summaryTextDelta",
+                json!({"threadId": "th_1", "itemId": "rs_1", "delta": "Listing files"}),
+            ),
+            notification(
+                "item/agentMessage/delta",
+                json!({"threadId": "th_2", "itemId": "msg_9", "delta": "other thread"}),
+            ),
+            notification(
+                "item/agentMessage/delta",
+                json!({"threadId": "th_1", "itemId": "msg_1", "delta": "One "}),
+            ),
+            notification(
+                "item/agentMessage/delta",
+                json!({"threadId": "th_1", "itemId": "msg_1", "delta": "file."}),
+            ),
+            notification(
+                "thread/tokenUsage/updated",
+                json!({"threadId": "th_1", "usage": {"inputTokens": 10, "outputTokens": 3}}),
+            ),
+        ];
+        let updates: Vec<TurnUpdate> = stream.iter().filter_map(|n| turn.observe(n)).collect();
+
+        assert_eq!(updates.len(), 7);
+        assert_eq!(turn.turn_id(), Some("turn_1"));
+        assert!(matches!(
+            &updates[2],
+            TurnUpdate::ItemUpdated { item: ThreadItem::CommandExecution(cmd), delta }
+                if cmd.aggregated_output == "a.txt\n" && delta == "a.txt\n"
+        ));
+        assert!(matches!(
+            &updates[5],
+            TurnUpdate::ItemUpdated { item: ThreadItem::AgentMessage(msg), .. }
+                if msg.text == "One file."
+        ));
+        assert!(!turn.is_complete());
+
+        turn.observe(&notification(
+            "item/completed",
+            json!({"threadId": "th_1", "turnId": "turn_1", "item": {
+                "type": "commandExecution", "id": "cmd_1", "command": "ls",
+                "aggregated_output": "a.txt\n", "exit_code": 0, "status": "completed"
+            }}),
+        ));
+        turn.observe(&notification(
+            "turn/completed",
+            json!({"threadId": "th_1", "turnId": "turn_1", "turn": {
+                "id": "turn_1", "status": "completed",
+                "items": [{"type": "agentMessage", "id": "msg_1", "text": "One file."}]
+            }}),
+        ));
+        assert!(turn.is_complete());
+
+        let outcome = turn.into_outcome().unwrap();
+        assert!(outcome.success());
+        let ids: Vec<&str> = outcome.items.iter().map(ThreadItem::id).collect();
+        assert_eq!(ids, vec!["cmd_1", "rs_1", "msg_1"]);
+        assert_eq!(outcome.agent_text(), "One file.");
+        assert_eq!(outcome.final_message(), Some("One file."));
+        assert_eq!(outcome.reasoning(), vec!["Listing files"]);
+        assert_eq!(outcome.commands()[0].exit_code, Some(0));
+        assert!(outcome.file_changes().is_empty());
+        assert_eq!(outcome.usage.map(|usage| usage.input_tokens), Some(10));
+    }
+
+    #[test]
+    fn test_ignores_notifications_from_an_earlier_turn() {
+        let mut turn = TurnAggregator::new("th_1");
+        let completed = |turn_id: &str| {
+            notification(
+                "turn/completed",
+                json!({"threadId": "th_1", "turnId": turn_id, "turn": {
+                    "id": turn_id, "status": "interrupted"
+                }}),
+            )
+        };
+
+        // The tail of an interrupted turn arrives before the new turn starts
+        assert!(turn.observe(&completed("turn_0")).is_none());
+        assert!(!turn.is_complete());
+
+        turn.observe(&notification(
+            "turn/started",
+            json!({"threadId": "th_1", "turnId": "turn_1"}),
+        ));
+        let stale_item = notification(
+            "item/completed",
+            json!({"threadId": "th_1", "turnId": "turn_0", "item": {
+                "type": "agentMessage", "id": "msg_0", "text": "stale"
+            }}),
+        );
+        assert!(turn.observe(&stale_item).is_none());
+        turn.observe(&completed("turn_0"));
+        assert!(!turn.is_complete());
+
+        turn.observe(&notification(
+            "item/agentMessage/delta",
+            json!({"threadId": "th_1", "itemId": "msg_1", "delta": "fresh"}),
+        ));
+        turn.observe(&completed("turn_1"));
+        assert!(turn.is_complete());
+
+        let outcome = turn.into_outcome().unwrap();
+        assert_eq!(outcome.turn.id, "turn_1");
+        assert_eq!(outcome.agent_text(), "fresh");
+    }
+
+    #[test]
+    fn test_ignores_other_threads() {
+        let mut turn = TurnAggregator::new("th_1");
+        let other = [
+            notification(
+                "turn/started",
+                json!({"threadId": "th_2", "turnId": "turn_9"}),
+            ),
+            notification(
+                "item/started",
+                json!({"threadId": "th_2", "turnId": "turn_9", "item": {
+                    "type": "agentMessage", "id": "msg_9", "text": ""
+                }}),
+            ),
+            notification(
+                "item/reasoning/summaryTextDelta",
+                json!({"threadId": "th_2", "itemId": "rs_9", "delta": "elsewhere"}),
+            ),
+            notification(
+                "thread/tokenUsage/updated",
+                json!({"threadId": "th_2", "usage": {"inputTokens": 5, "outputTokens": 1}}),
+            ),
+            notification(
+                "turn/completed",
+                json!({"threadId": "th_2", "turnId": "turn_9", "turn": {
+                    "id": "turn_9", "status": "completed"
+                }}),
+            ),
+        ];
+        for n in &other {
+            assert!(turn.observe(n).is_none());
+        }
+        assert_eq!(turn.turn_id(), None);
+        assert!(turn.items().is_empty());
+        assert!(!turn.is_complete());
+    }
+
+    #[test]
+    fn test_ignores_an_earlier_turns_late_item() {
+        let mut turn = TurnAggregator::new("th_1");
+
+        // No turn/started yet: the first item reports the turn
+        let started = notification(
+            "item/started",
+            json!({"threadId": "th_1", "turnId": "turn_1", "item": {
+                "type": "agentMessage", "id": "msg_1", "text": ""
+            }}),
+        );
+        assert!(matches!(
+            turn.observe(&started),
+            Some(TurnUpdate::ItemStarted(_))
+        ));
+        assert_eq!(turn.turn_id(), Some("turn_1"));
+
+        let late = notification(
+            "item/completed",
+            json!({"threadId": "th_1", "turnId": "turn_0", "item": {
+                "type": "agentMessage", "id": "msg_0", "text": "late"
+            }}),
+        );
+        assert!(turn.observe(&late).is_none());
+
+        turn.observe(&notification(
+            "item/completed",
+            json!({"threadId": "th_1", "turnId": "turn_1", "item": {
+                "type": "agentMessage", "id": "msg_1", "text": "current"
+            }}),
+        ));
+        turn.observe(&notification(
+            "turn/completed",
+            json!({"threadId": "th_1", "turnId": "turn_1", "turn": {
+                "id": "turn_1", "status": "completed"
+            }}),
+        ));
+        let outcome = turn.into_outcome().unwrap();
+        let ids: Vec<&str> = outcome.items.iter().map(ThreadItem::id).collect();
+        assert_eq!(ids, vec!["msg_1"]);
+        assert_eq!(outcome.agent_text(), "current");
+    }
+}
@@ -62,9 +62,7 @@ async fn test_async_client_basic_turn() {
             input: vec![UserInput::Text {
                 text: "What is 2 + 2? Reply with just the number.".to_string(),
             }],
-            model: None,
-            reasoning_effort: None,
-            sandbox_policy: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to start turn");
@@ -185,9 +183,7 @@ fn test_sync_client_basic_turn() {
             input: vec![UserInput::Text {
                 text: "What is 2 + 2? Reply with just the number.".to_string(),
             }],
-            model: None,
-            reasoning_effort: None,
-            sandbox_policy: None,
+            ..Default::default()
         })
         .expect("Failed to start turn");
 
@@ -247,9 +243,7 @@ async fn test_async_client_multi_turn() {
             input: vec![UserInput::Text {
                 text: "Remember the number 42. Just say OK.".to_string(),
             }],
-            model: None,
-            reasoning_effort: None,
-            sandbox_policy: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to start first turn");
@@ -282,9 +276,7 @@ async fn test_async_client_multi_turn() {
                 text: "What number did I ask you to remember? Reply with just the number."
                     .to_string(),
             }],
-            model: None,
-            reasoning_effort: None,
-            sandbox_policy: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to start second turn");
@@ -344,9 +336,7 @@ async fn test_async_client_event_stream() {
             input: vec![UserInput::Text {
                 text: "Say hello.".to_string(),
             }],
-            model: None,
-            reasoning_effort: None,
-            sandbox_policy: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to start turn");